@@ -0,0 +1,100 @@
+//! MCP tool annotations, the hints clients use to decide which calls to confirm with the user.
+//! The same hints decide which tools a server started with `--read-only` offers.
+
+use crate::config::Config;
+use serde::Serialize;
+
+/// What a tool may do to its environment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Annotations {
+    /// The tool changes no files. Tools that only change session state, such as the working
+    /// directory, count as read-only
+    #[serde(rename = "readOnlyHint")]
+    pub read_only: bool,
+
+    /// The tool may overwrite or remove existing data, rather than only adding to it
+    #[serde(rename = "destructiveHint")]
+    pub destructive: bool,
+
+    /// Repeating a call with the same arguments has no further effect
+    #[serde(rename = "idempotentHint")]
+    pub idempotent: bool,
+}
+
+impl Annotations {
+    pub const READ_ONLY: Self = Self {
+        read_only: true,
+        destructive: false,
+        idempotent: true,
+    };
+
+    pub const ADDITIVE: Self = Self {
+        read_only: false,
+        destructive: false,
+        idempotent: false,
+    };
+
+    pub const DESTRUCTIVE: Self = Self {
+        read_only: false,
+        destructive: true,
+        idempotent: false,
+    };
+
+    pub const fn idempotent(self) -> Self {
+        Self {
+            idempotent: true,
+            ..self
+        }
+    }
+
+    /// The annotations of the built-in or custom tool named `name`. Unknown tools are assumed to
+    /// be destructive, which is also what MCP clients assume without annotations
+    pub fn for_tool(name: &str) -> Self {
+        match name {
+            "add_bookmark"
+            | "configure_session"
+            | "create_child_session"
+            | "define_alias"
+            | "detect_project_root"
+            | "get_working_directory"
+            | "previous_working_directory"
+            | "set_path_alias"
+            | "set_working_directory" => Self::READ_ONLY,
+            "blame"
+            | "code_stats"
+            | "compare_directories"
+            | "diff_vs_head"
+            | "disk_usage"
+            | "exists"
+            | "extract_matches"
+            | "file_stats"
+            | "goto_bookmark"
+            | "list"
+            | "list_bookmarks"
+            | "preview"
+            | "read"
+            | "read_structured"
+            | "recent_files"
+            | "search"
+            | "self_check"
+            | "session_activity"
+            | "simulated_changes"
+            | "tail_log"
+            | "usage_stats"
+            | "vcs_status" => Self::READ_ONLY,
+            // Reports usage, and only ever tightens limits when called by an agent
+            "quota_status" => Self::READ_ONLY,
+            "archive" | "restore_deleted" | "unstage" => Self::ADDITIVE,
+            "new_rust_module" | "touch" => Self::ADDITIVE.idempotent(),
+            "edit_structured" | "scaffold" | "set_permissions" | "write" => {
+                Self::DESTRUCTIVE.idempotent()
+            }
+            "apply_patch" | "confirm_operation" | "delete" | "extract" | "move"
+            | "purge_staged" | "rename_symbol" => Self::DESTRUCTIVE,
+            _ => match Config::global().custom_tool(name) {
+                Some(tool) => tool.annotations(),
+                None => Self::DESTRUCTIVE,
+            },
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Default limits that adapt to how large a session's responses have been.
+//!
+//! Every response uses up some of the agent's context. When several recent responses were too
+//! large, the defaults for how much `search`, `list`, and `read` return are halved, down to an
+//! eighth, recursive listings stop descending as deep, and when responses have been small for a
+//! while they are raised again. Limits passed explicitly are never changed.
+
+use crate::config::Config;
+use serde::{Deserialize, Serialize};
+
+/// A response larger than this counts as too large, around 10k tokens
+pub const RESPONSE_BUDGET: u64 = 40 * 1024;
+
+/// Number of recent responses considered
+const WINDOW: usize = 5;
+
+/// How many of the recent responses must be too large for the defaults to be lowered
+const TOO_LARGE_TO_LOWER: usize = 3;
+
+/// The defaults are lowered at most this many times, each halving them
+const MAX_LEVEL: u32 = 3;
+
+/// How deep recursive listings without a depth limit go once the defaults are first lowered,
+/// halved again at each further level
+const LOWERED_MAX_DEPTH: usize = 8;
+
+/// The default limits of the tools whose responses grow with what they find
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// search's `max_results`
+    pub max_results: usize,
+    /// list's `max_entries`
+    pub max_entries: usize,
+    /// read's `max_total_bytes`
+    pub max_total_bytes: u64,
+    /// list's `max_depth` when listing recursively, or None for no limit
+    pub max_depth: Option<usize>,
+}
+
+impl Limits {
+    pub const DEFAULT: Self = Self {
+        max_results: 50,
+        max_entries: 500,
+        max_total_bytes: 256 * 1024,
+        max_depth: None,
+    };
+}
+
+/// Recent response sizes of a session, and how far its defaults have been lowered because of
+/// them
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ResponseBudget {
+    /// Sizes of the most recent responses, oldest first, since the level last changed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    recent: Vec<u64>,
+
+    /// How many times the defaults have been halved
+    #[serde(default)]
+    level: u32,
+}
+
+impl ResponseBudget {
+    /// Record the size of a response, lowering or raising the defaults if recent responses call
+    /// for it
+    pub fn record(&mut self, response_bytes: u64) {
+        self.recent.push(response_bytes);
+        let excess = self.recent.len().saturating_sub(WINDOW);
+        self.recent.drain(..excess);
+
+        let too_large = self
+            .recent
+            .iter()
+            .filter(|bytes| **bytes > RESPONSE_BUDGET)
+            .count();
+        if too_large >= TOO_LARGE_TO_LOWER && self.level < MAX_LEVEL {
+            self.level += 1;
+            self.recent.clear();
+        } else if self.level > 0
+            && self.recent.len() == WINDOW
+            && self
+                .recent
+                .iter()
+                .all(|bytes| *bytes <= RESPONSE_BUDGET / 4)
+        {
+            self.level -= 1;
+            self.recent.clear();
+        }
+    }
+
+    pub fn is_lowered(&self) -> bool {
+        self.level > 0
+    }
+
+    /// The current default limits
+    pub fn limits(&self) -> Limits {
+        let Limits {
+            max_results,
+            max_entries,
+            max_total_bytes,
+            max_depth,
+        } = Config::global().limits();
+        let max_depth = match (max_depth, self.level) {
+            (max_depth, 0) => max_depth,
+            (Some(max_depth), level) => Some(max_depth >> level),
+            (None, level) => Some(LOWERED_MAX_DEPTH >> (level - 1)),
+        };
+        Limits {
+            max_results: (max_results >> self.level).max(1),
+            max_entries: (max_entries >> self.level).max(1),
+            max_total_bytes: (max_total_bytes >> self.level).max(1),
+            max_depth: max_depth.map(|max_depth| max_depth.max(1)),
+        }
+    }
+
+    /// A note of the lowered defaults for the end of a response, or None if they are the usual
+    /// ones
+    pub fn footer(&self, format_size: impl Fn(u64) -> String) -> Option<String> {
+        if !self.is_lowered() {
+            return None;
+        }
+        let limits = self.limits();
+        let max_depth = limits
+            .max_depth
+            .map(|max_depth| format!(", max_depth {max_depth} for recursive listings"))
+            .unwrap_or_default();
+        Some(format!(
+            "Default limits are lowered to 1/{} because recent responses were large: \
+             max_results {}, max_entries {}, max_total_bytes {}{max_depth}. Pass a limit \
+             explicitly to override it",
+            1 << self.level,
+            limits.max_results,
+            limits.max_entries,
+            format_size(limits.max_total_bytes)
+        ))
+    }
+}
@@ -0,0 +1,205 @@
+//! The config file, `~/.config/fs-mcp/config.toml` or the one passed with `--config`, read once
+//! at startup.
+
+use crate::{
+    budget::Limits,
+    custom_tools::CustomTool,
+    format_hooks::FormatHook,
+    hints::Hinted,
+    tools::{HighlightStyle, Tools},
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Project-specific commands offered as tools alongside the built-in ones
+    #[serde(default)]
+    pub tools: Vec<CustomTool>,
+
+    /// Formatters run on files after `write` changes them
+    #[serde(default)]
+    pub format_on_write: Vec<FormatHook>,
+
+    /// Built-in tools to leave out of the tool list and refuse to run
+    #[serde(default)]
+    pub disabled_tools: Vec<String>,
+
+    /// Defaults of tool parameters, used when a call leaves them out
+    #[serde(default)]
+    pub defaults: Defaults,
+
+    /// Directories that every path has to be inside of. Any path can be used when empty
+    #[serde(default)]
+    pub sandbox_roots: Vec<PathBuf>,
+}
+
+/// The `[defaults]` table of the config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Defaults {
+    /// search's `max_results`
+    pub max_results: Option<usize>,
+    /// search's `highlight_style`
+    pub highlight_style: Option<HighlightStyle>,
+    /// read's `max_total_bytes`
+    pub max_total_bytes: Option<u64>,
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".config").join("fs-mcp").join("config.toml"))
+    }
+
+    /// Read the config file at `path`, or at [`Config::path`] if there is one there, and make it
+    /// available from [`Config::global`]
+    pub fn init(path: Option<PathBuf>) -> Result<&'static Self> {
+        if let Some(config) = CONFIG.get() {
+            return Ok(config);
+        }
+        let config = match path.or_else(|| Self::path().filter(|path| path.exists())) {
+            Some(path) => Self::load(&path)?,
+            None => Self::default(),
+        };
+        Ok(CONFIG.get_or_init(|| config))
+    }
+
+    /// The config read by [`Config::init`], or the defaults if it hasn't been called, as when
+    /// fs-mcp is embedded
+    pub fn global() -> &'static Self {
+        CONFIG.get_or_init(Self::default)
+    }
+
+    fn load(path: &PathBuf) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        let mut config: Self = toml::from_str(&contents).map_err(|error| {
+            Hinted::new(
+                format!("{} is not a valid config file: {error}", path.display()),
+                None,
+            )
+        })?;
+        for tool in &config.tools {
+            tool.validate().map_err(|problem| {
+                Hinted::new(
+                    format!(
+                        "{} declares an invalid tool {}: {problem}",
+                        path.display(),
+                        tool.name
+                    ),
+                    None,
+                )
+            })?;
+        }
+        for hook in &config.format_on_write {
+            hook.validate().map_err(|problem| {
+                Hinted::new(
+                    format!(
+                        "{} declares an invalid format_on_write hook: {problem}",
+                        path.display()
+                    ),
+                    None,
+                )
+            })?;
+        }
+        if let Some(name) = config
+            .disabled_tools
+            .iter()
+            .find(|name| !Tools::has_tool(name))
+        {
+            return Err(Hinted::new(
+                format!(
+                    "{} disables {name}, which is not a built-in tool",
+                    path.display()
+                ),
+                None,
+            )
+            .into());
+        }
+        for root in &mut config.sandbox_roots {
+            *root = PathBuf::from(&*shellexpand::tilde(&root.to_string_lossy()));
+            if !root.is_absolute() {
+                return Err(Hinted::new(
+                    format!(
+                        "{} has a sandbox root that is not absolute: {}",
+                        path.display(),
+                        root.display()
+                    ),
+                    None,
+                )
+                .into());
+            }
+        }
+        Ok(config)
+    }
+
+    /// Whether the config disables the built-in tool `name`
+    pub fn is_disabled(&self, name: &str) -> bool {
+        self.disabled_tools.iter().any(|disabled| disabled == name)
+    }
+
+    /// The default limits, before any lowering for large responses
+    pub(crate) fn limits(&self) -> Limits {
+        Limits {
+            max_results: self
+                .defaults
+                .max_results
+                .unwrap_or(Limits::DEFAULT.max_results),
+            max_total_bytes: self
+                .defaults
+                .max_total_bytes
+                .unwrap_or(Limits::DEFAULT.max_total_bytes),
+            ..Limits::DEFAULT
+        }
+    }
+
+    /// Whether `path` is inside one of the sandbox roots, or there are none
+    pub fn in_sandbox(&self, path: &Path) -> bool {
+        self.sandbox_roots.is_empty()
+            || self.sandbox_roots.iter().any(|root| {
+                path.starts_with(root)
+                    || root
+                        .canonicalize()
+                        .is_ok_and(|canonical| path.starts_with(canonical))
+            })
+    }
+
+    /// Whether `path` is in the sandbox both as written and with symlinks resolved, since a link
+    /// inside a root can point out of it, also for files a call is about to create
+    pub fn allows(&self, path: &Path) -> bool {
+        self.in_sandbox(path)
+            && canonicalize_existing(path).is_none_or(|canonical| self.in_sandbox(&canonical))
+    }
+
+    /// The custom tool named `name`, if the config declares one
+    pub fn custom_tool(&self, name: &str) -> Option<&CustomTool> {
+        self.tools.iter().find(|tool| tool.name == name)
+    }
+
+    /// The formatter to run after writing `path`, if the config declares one for its extension
+    pub fn format_hook(&self, path: &Path) -> Option<&FormatHook> {
+        self.format_on_write
+            .iter()
+            .find(|hook| hook.applies_to(path))
+    }
+}
+
+/// `path` with symlinks resolved, for paths that may not exist yet: the deepest existing
+/// ancestor is canonicalized, and the rest of the path appended to it
+fn canonicalize_existing(path: &Path) -> Option<PathBuf> {
+    path.ancestors().find_map(|ancestor| {
+        let canonical = ancestor.canonicalize().ok()?;
+        let rest = path.strip_prefix(ancestor).ok()?;
+        Some(match rest.as_os_str().is_empty() {
+            true => canonical,
+            false => canonical.join(rest),
+        })
+    })
+}
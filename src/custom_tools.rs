@@ -0,0 +1,378 @@
+//! Tools declared in the config file that run a command, so that project-specific scripts such
+//! as formatters and code generators can be offered through the same server.
+//!
+//! ```toml
+//! [[tools]]
+//! name = "format_rust"
+//! description = "Format Rust files with rustfmt"
+//! command = ["rustfmt", "--edition", "2024", "{files}"]
+//! path_arguments = ["files"]
+//!
+//! [tools.input_schema]
+//! type = "object"
+//! required = ["files"]
+//! properties.files = { type = "array", items = { type = "string" } }
+//! ```
+//!
+//! Commands are run directly rather than through a shell, in the session's working directory.
+//! A command element that is exactly `{name}` is replaced by that argument, one element per item
+//! for arrays, and dropped if the argument wasn't given. Elsewhere `{name}` is replaced by the
+//! argument's text. Arguments are checked against `input_schema` before the command runs, and
+//! the command is stopped once it has run for `time_limit_ms`. Commands are presented to clients
+//! as changing files unless declared with `read_only = true`.
+
+use crate::{
+    annotations::Annotations,
+    deadline::DEFAULT_TIME_LIMIT,
+    hints::{ErrorCode, Hinted},
+    state::FsTools,
+};
+use anyhow::Result;
+use mcplease::types::{InputSchema, Tagged, ToolSchema};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use std::{
+    io::Read,
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+/// How often a running command is checked for having finished
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomTool {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// JSON schema of the arguments. Defaults to no arguments
+    #[serde(default = "CustomTool::no_arguments")]
+    pub input_schema: Value,
+
+    /// The program followed by its arguments
+    pub command: Vec<String>,
+
+    /// Arguments that are paths, resolved like the paths of built-in tools before being passed to
+    /// the command
+    #[serde(default)]
+    pub path_arguments: Vec<String>,
+
+    /// Whether the command leaves files alone, so that clients needn't confirm it and it is
+    /// offered by servers started with `--read-only`
+    #[serde(default)]
+    pub read_only: bool,
+
+    /// Whether running the command again with the same arguments has no further effect
+    #[serde(default)]
+    pub idempotent: bool,
+
+    /// How long the command may run before it is stopped, capped by `--timeout-ms`.
+    /// Defaults to 30 seconds
+    #[serde(default)]
+    pub time_limit_ms: Option<u64>,
+}
+
+impl CustomTool {
+    fn no_arguments() -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    /// Check the declaration, so that mistakes show up at startup rather than on first use
+    pub fn validate(&self) -> Result<(), String> {
+        let is_identifier = !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier {
+            return Err("names may only contain letters, digits, and underscores".into());
+        }
+        if crate::tools::Tools::has_tool(&self.name) {
+            return Err("a built-in tool already has that name".into());
+        }
+        if self.command.is_empty() {
+            return Err("`command` needs at least the program to run".into());
+        }
+        self.input_schema()?;
+        Ok(())
+    }
+
+    fn input_schema(&self) -> Result<InputSchema, String> {
+        serde_json::from_value(self.input_schema.clone())
+            .map_err(|error| format!("`input_schema` is not a supported JSON schema: {error}"))
+    }
+
+    pub fn schema(&self) -> Result<ToolSchema, String> {
+        Ok(ToolSchema {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            input_schema: self.input_schema()?,
+        })
+    }
+
+    /// Commands are assumed to change files unless declared `read_only`
+    pub fn annotations(&self) -> Annotations {
+        let annotations = if self.read_only {
+            Annotations::READ_ONLY
+        } else {
+            Annotations::DESTRUCTIVE
+        };
+        Annotations {
+            idempotent: self.read_only || self.idempotent,
+            ..annotations
+        }
+    }
+
+    /// Run the command with `arguments`, returning its output, or an error if it fails
+    pub fn execute(&self, state: &mut FsTools, arguments: &Map<String, Value>) -> Result<String> {
+        // What a command writes can't be known before it runs, so it can't be charged
+        if !self.read_only && state.quota()?.limits.limits_amounts() {
+            return Err(Hinted::new(
+                format!(
+                    "{} is not available: this session has a quota, and what the command changes \
+                     can't be counted against it",
+                    self.name
+                ),
+                Some(
+                    "use the built-in tools, or declare the command `read_only = true` in the \
+                     fs-mcp config file if it leaves files alone"
+                        .into(),
+                ),
+            )
+            .with_code(ErrorCode::QuotaExceeded)
+            .into());
+        }
+
+        let schema = self.input_schema().map_err(|error| {
+            Hinted::new(format!("{} is misconfigured: {error}", self.name), None)
+        })?;
+        if let Err(error) = check_value(&schema, &Value::Object(arguments.clone()), "arguments") {
+            return Err(Hinted::new(
+                format!("Invalid arguments for {}: {error}", self.name),
+                Some(format!("check the input schema of {}", self.name)),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into());
+        }
+
+        let mut command_line = vec![];
+        for element in &self.command {
+            match element
+                .strip_prefix('{')
+                .and_then(|element| element.strip_suffix('}'))
+                .filter(|name| !name.contains(['{', '}']))
+            {
+                Some(name) => match arguments.get(name) {
+                    Some(Value::Array(items)) => {
+                        for item in items {
+                            command_line.push(self.argument(state, name, item)?);
+                        }
+                    }
+                    Some(Value::Null) | None => {}
+                    Some(value) => command_line.push(self.argument(state, name, value)?),
+                },
+                None => {
+                    let mut element = element.clone();
+                    for (name, value) in arguments {
+                        let placeholder = format!("{{{name}}}");
+                        if element.contains(&placeholder) {
+                            let value = self.argument(state, name, value)?;
+                            element = element.replace(&placeholder, &value);
+                        }
+                    }
+                    command_line.push(element);
+                }
+            }
+        }
+
+        let working_directory = state.resolve_path(".", None)?;
+        let deadline = state.deadline(self.time_limit_ms, DEFAULT_TIME_LIMIT);
+        let program = &command_line[0];
+        let mut child = Command::new(program)
+            .args(&command_line[1..])
+            .current_dir(&working_directory)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| {
+                Hinted::new(
+                    format!("{} could not run {program}: {error}", self.name),
+                    Some(format!(
+                        "check the `command` of {} in the fs-mcp config file",
+                        self.name
+                    )),
+                )
+            })?;
+
+        // Read while waiting, so that a command with a lot of output doesn't block on a full pipe
+        let read_all = |mut pipe: Box<dyn Read + Send>| {
+            thread::spawn(move || {
+                let mut bytes = vec![];
+                let _ = pipe.read_to_end(&mut bytes);
+                bytes
+            })
+        };
+        let stdout = child.stdout.take().map(|pipe| read_all(Box::new(pipe)));
+        let stderr = child.stderr.take().map(|pipe| read_all(Box::new(pipe)));
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if deadline.is_reached() {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(Hinted::new(
+                    format!(
+                        "{} was stopped after running for its time limit of {}ms",
+                        self.name,
+                        deadline.limit().as_millis()
+                    ),
+                    Some(format!(
+                        "raise `time_limit_ms` of {} in the fs-mcp config file, or the server's \
+                         --timeout-ms",
+                        self.name
+                    )),
+                )
+                .into());
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+        let collect = |reader: Option<thread::JoinHandle<Vec<u8>>>| {
+            reader
+                .and_then(|reader| reader.join().ok())
+                .unwrap_or_default()
+        };
+        let (stdout, stderr) = (collect(stdout), collect(stderr));
+
+        let mut text = String::from_utf8_lossy(&stdout).into_owned();
+        let stderr = String::from_utf8_lossy(&stderr);
+        if !stderr.trim().is_empty() {
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.push_str(&stderr);
+        }
+
+        if status.success() {
+            if text.trim().is_empty() {
+                Ok(format!("{} completed without output", self.name))
+            } else {
+                Ok(text)
+            }
+        } else {
+            Err(Hinted::new(format!("{} failed ({status}):\n{text}", self.name), None).into())
+        }
+    }
+
+    /// The text of one argument value, resolved if it is a path
+    fn argument(&self, state: &mut FsTools, name: &str, value: &Value) -> Result<String> {
+        let text = match value {
+            Value::String(text) => text.clone(),
+            Value::Array(_) | Value::Object(_) => {
+                return Err(Hinted::new(
+                    format!("{name} must be a single value here"),
+                    Some(format!("check the input schema of {}", self.name)),
+                )
+                .into());
+            }
+            other => other.to_string(),
+        };
+
+        if self
+            .path_arguments
+            .iter()
+            .any(|path_argument| path_argument == name)
+        {
+            let path = state.resolve_path(&text, None)?;
+            Ok(path.to_string_lossy().into_owned())
+        } else {
+            Ok(text)
+        }
+    }
+}
+
+/// Check `value` against the subset of JSON schema that tool schemas use, naming what doesn't
+/// match by its place `at` in the arguments
+fn check_value(schema: &InputSchema, value: &Value, at: &str) -> Result<(), String> {
+    let tagged = match schema {
+        InputSchema::AnyOf { any_of, .. } => {
+            return match any_of
+                .iter()
+                .any(|schema| check_value(schema, value, at).is_ok())
+            {
+                true => Ok(()),
+                false => Err(format!("{at} matches none of the allowed schemas")),
+            };
+        }
+        InputSchema::OneOf { one_of, .. } => {
+            let matching = one_of
+                .iter()
+                .filter(|schema| check_value(schema, value, at).is_ok())
+                .count();
+            return match matching {
+                1 => Ok(()),
+                0 => Err(format!("{at} matches none of the allowed schemas")),
+                _ => Err(format!("{at} matches more than one of the allowed schemas")),
+            };
+        }
+        InputSchema::Tagged(tagged) => tagged,
+    };
+
+    match (tagged, value) {
+        (
+            Tagged::Object {
+                properties,
+                required,
+                additional_properties,
+                ..
+            },
+            Value::Object(object),
+        ) => {
+            for name in required.iter().flatten() {
+                if object.get(name).is_none_or(Value::is_null) {
+                    return Err(format!("{at}.{name} is required"));
+                }
+            }
+            // Null arguments count as not given, as when the command line is built
+            for (name, value) in object.iter().filter(|(_, value)| !value.is_null()) {
+                let at = format!("{at}.{name}");
+                match (properties.get(name), additional_properties) {
+                    (Some(schema), _) | (None, Some(schema)) => check_value(schema, value, &at)?,
+                    (None, None) => {}
+                }
+            }
+            Ok(())
+        }
+        (Tagged::String { r#enum, .. }, Value::String(text)) => match r#enum {
+            Some(allowed) if !allowed.contains(text) => Err(format!(
+                "{at} must be one of {}, not {text:?}",
+                allowed.join(", ")
+            )),
+            _ => Ok(()),
+        },
+        (Tagged::Boolean { .. }, Value::Bool(_)) | (Tagged::Null, Value::Null) => Ok(()),
+        (Tagged::Integer { .. }, Value::Number(number)) if number.is_i64() || number.is_u64() => {
+            Ok(())
+        }
+        (Tagged::Array { items, .. }, Value::Array(values)) => values
+            .iter()
+            .enumerate()
+            .try_for_each(|(index, value)| check_value(items, value, &format!("{at}[{index}]"))),
+        (tagged, _) => {
+            let expected = match tagged {
+                Tagged::Object { .. } => "an object",
+                Tagged::String { .. } => "a string",
+                Tagged::Boolean { .. } => "a boolean",
+                Tagged::Integer { .. } => "an integer",
+                Tagged::Array { .. } => "an array",
+                Tagged::Null => "null",
+            };
+            Err(format!("{at} must be {expected}"))
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Time limits for tools that walk directory trees, so that a walk over a slow network share or
+//! an unexpectedly huge tree returns partial results instead of holding up the MCP connection.
+//!
+//! Calls pass `time_limit_ms`. Serving with `--timeout-ms N` makes N the default for calls that
+//! don't, and caps the limit of those that do.
+
+use std::time::{Duration, Instant};
+
+/// The default limit of the walking tools other than search
+pub const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(30);
+
+/// When a call has to stop and return what it has
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    limit: Duration,
+    at: Instant,
+}
+
+impl Deadline {
+    /// A deadline `time_limit_ms` from now, or `default` if not given, capped at `cap`
+    pub fn new(time_limit_ms: Option<u64>, default: Duration, cap: Option<Duration>) -> Self {
+        let limit = match (time_limit_ms.map(Duration::from_millis), cap) {
+            (Some(limit), Some(cap)) => limit.min(cap),
+            (Some(limit), None) => limit,
+            (None, Some(cap)) => cap,
+            (None, None) => default,
+        };
+        Self {
+            limit,
+            at: Instant::now() + limit,
+        }
+    }
+
+    pub fn limit(&self) -> Duration {
+        self.limit
+    }
+
+    pub fn is_reached(&self) -> bool {
+        Instant::now() >= self.at
+    }
+
+    /// A note for the end of results cut short by the deadline
+    pub fn note(&self) -> String {
+        format!(
+            "\n\nThe time limit of {}ms was reached, so these results are partial. Pass a larger \
+             `time_limit_ms` or a narrower path for complete results",
+            self.limit.as_millis()
+        )
+    }
+}
@@ -0,0 +1,75 @@
+//! The `exclude` globs of list and search, for leaving out e.g. `**/generated/**` or
+//! `**/*.min.js` in a single call. Unlike [`crate::project_ignore`], they only apply to the
+//! call they are passed to.
+
+use crate::{
+    hints::{ErrorCode, Hinted},
+    state::FsTools,
+};
+use anyhow::Result;
+use glob::{MatchOptions, Pattern};
+use std::path::{Path, PathBuf};
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Compiled `exclude` globs, matched against paths relative to the working directory
+#[derive(Debug, Clone, Default)]
+pub struct Exclude {
+    patterns: Vec<Pattern>,
+    root: Option<PathBuf>,
+}
+
+impl Exclude {
+    /// Compile `patterns`. Nothing is excluded if there are none
+    pub fn new(state: &mut FsTools, patterns: Option<&[String]>) -> Result<Self> {
+        let Some(patterns) = patterns.filter(|patterns| !patterns.is_empty()) else {
+            return Ok(Self::default());
+        };
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                Pattern::new(pattern).map_err(|error| {
+                    Hinted::new(
+                        format!("Invalid exclude pattern \"{pattern}\": {error}"),
+                        Some("use glob syntax, such as `**/generated/**` or `*.min.js`".into()),
+                    )
+                    .with_code(ErrorCode::InvalidArgument)
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            patterns,
+            root: state.get_context(None)?,
+        })
+    }
+
+    /// Whether `path` matches one of the patterns. Patterns without a `/` match the name of a
+    /// file or directory at any depth, and the others match its path relative to the working
+    /// directory. Walks skip excluded directories without looking inside
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+        let relative = self
+            .root
+            .as_deref()
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path);
+        let name = path.file_name().map(Path::new);
+        self.patterns.iter().any(|pattern| {
+            match pattern.as_str().contains('/') {
+                true => {
+                    // `dir/**` also excludes `dir` itself, so that it isn't listed
+                    pattern.matches_path_with(relative, MATCH_OPTIONS)
+                        || (pattern.as_str().ends_with("/**")
+                            && pattern.matches_path_with(&relative.join("_"), MATCH_OPTIONS))
+                }
+                false => name.is_some_and(|name| pattern.matches_path_with(name, MATCH_OPTIONS)),
+            }
+        })
+    }
+}
@@ -0,0 +1,108 @@
+//! Friendly names for groups of file extensions, like ripgrep's `--type`.
+
+use crate::hints::Hinted;
+use std::path::Path;
+
+/// Each file type and the extensions it covers
+pub const FILE_TYPES: &[(&str, &[&str])] = &[
+    ("c", &["c", "h"]),
+    (
+        "config",
+        &["toml", "yaml", "yml", "json", "ini", "cfg", "conf"],
+    ),
+    ("cpp", &["cpp", "cc", "cxx", "hpp", "hh", "hxx", "h"]),
+    ("csharp", &["cs", "csx"]),
+    ("css", &["css", "scss", "sass", "less"]),
+    ("docs", &["md", "markdown", "rst", "txt", "adoc"]),
+    ("go", &["go"]),
+    ("html", &["html", "htm", "xhtml"]),
+    ("java", &["java"]),
+    ("js", &["js", "mjs", "cjs", "jsx"]),
+    ("json", &["json", "jsonc", "json5"]),
+    ("kotlin", &["kt", "kts"]),
+    ("markdown", &["md", "markdown", "mdx"]),
+    ("php", &["php"]),
+    ("python", &["py", "pyi", "pyw"]),
+    ("ruby", &["rb", "rake", "gemspec"]),
+    ("rust", &["rs"]),
+    ("shell", &["sh", "bash", "zsh", "fish"]),
+    ("sql", &["sql"]),
+    ("swift", &["swift"]),
+    ("toml", &["toml"]),
+    ("ts", &["ts", "mts", "cts", "tsx"]),
+    (
+        "web",
+        &[
+            "html", "htm", "css", "scss", "sass", "less", "js", "mjs", "cjs", "jsx", "ts", "mts",
+            "cts", "tsx", "vue", "svelte",
+        ],
+    ),
+    ("yaml", &["yaml", "yml"]),
+];
+
+/// The extensions of every named file type, or an error naming the known types
+pub fn extensions(names: &[String]) -> Result<Vec<String>, Hinted> {
+    let mut extensions = Vec::new();
+    for name in names {
+        let Some((_, type_extensions)) = FILE_TYPES
+            .iter()
+            .find(|(type_name, _)| type_name.eq_ignore_ascii_case(name))
+        else {
+            return Err(Hinted::new(
+                format!("Unknown file type {name}"),
+                Some(format!(
+                    "use one of {}, or list extensions with `include_extensions`",
+                    FILE_TYPES
+                        .iter()
+                        .map(|(type_name, _)| *type_name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+            ));
+        };
+        for extension in *type_extensions {
+            if !extensions.iter().any(|existing| existing == extension) {
+                extensions.push(extension.to_string());
+            }
+        }
+    }
+    Ok(extensions)
+}
+
+/// Types that group several languages, and so don't name the language of a file
+const GROUPS: &[&str] = &["config", "docs", "web"];
+
+/// The language of a file, by its extension, if it is one of the named file types
+pub fn language(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    FILE_TYPES
+        .iter()
+        .filter(|(name, _)| !GROUPS.contains(name))
+        .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+        .map(|(name, _)| *name)
+}
+
+/// How comments are written in a language
+#[derive(Debug, Clone, Copy)]
+pub struct CommentSyntax {
+    /// Markers that start a comment running to the end of the line
+    pub line: &'static [&'static str],
+    /// The delimiters of block comments
+    pub block: Option<(&'static str, &'static str)>,
+}
+
+/// The comment syntax of one of the languages returned by [`language`]
+pub fn comment_syntax(language: &str) -> CommentSyntax {
+    let (line, block): (&'static [&'static str], _) = match language {
+        "c" | "cpp" | "csharp" | "go" | "java" | "js" | "kotlin" | "rust" | "swift" | "ts" => {
+            (&["//"], Some(("/*", "*/")))
+        }
+        "php" => (&["//", "#"], Some(("/*", "*/"))),
+        "css" => (&[], Some(("/*", "*/"))),
+        "sql" => (&["--"], Some(("/*", "*/"))),
+        "html" | "markdown" => (&[], Some(("<!--", "-->"))),
+        "python" | "ruby" | "shell" | "toml" | "yaml" => (&["#"], None),
+        _ => (&[], None),
+    };
+    CommentSyntax { line, block }
+}
@@ -0,0 +1,117 @@
+//! Formatters run on files after `write` changes them, declared in the config file per
+//! extension, so that slightly misformatted code doesn't churn in review.
+//!
+//! ```toml
+//! [[format_on_write]]
+//! extensions = ["rs"]
+//! command = ["rustfmt", "--edition", "2024", "{path}"]
+//!
+//! [[format_on_write]]
+//! extensions = ["ts", "tsx", "json"]
+//! command = ["npx", "prettier", "--write"]
+//! ```
+//!
+//! Commands are run directly rather than through a shell, in the session's working directory. A
+//! command element that is exactly `{path}` is replaced by the written file's path, which is
+//! otherwise passed as the last argument. The first hook whose extensions match is used.
+
+use serde::Deserialize;
+use similar::TextDiff;
+use std::{borrow::Cow, fs, path::Path, process::Command};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatHook {
+    /// Extensions of the files to format, without the leading `.`
+    pub extensions: Vec<String>,
+
+    /// The program followed by its arguments
+    pub command: Vec<String>,
+}
+
+impl FormatHook {
+    /// Check the declaration, so that mistakes show up at startup rather than on first write
+    pub fn validate(&self) -> Result<(), String> {
+        if self.extensions.is_empty() {
+            return Err("`extensions` needs at least one extension".into());
+        }
+        if self.command.is_empty() {
+            return Err("`command` needs at least the program to run".into());
+        }
+        Ok(())
+    }
+
+    pub fn applies_to(&self, path: &Path) -> bool {
+        path.extension().is_some_and(|extension| {
+            self.extensions
+                .iter()
+                .any(|wanted| extension.eq_ignore_ascii_case(wanted.trim_start_matches('.')))
+        })
+    }
+
+    /// The program's name without its directory, for responses
+    fn program(&self) -> Cow<'_, str> {
+        let program = Path::new(&self.command[0]);
+        program
+            .file_name()
+            .map_or_else(|| program.to_string_lossy(), |name| name.to_string_lossy())
+    }
+
+    /// Format `path`, returning a note for the write's response: what the formatter changed,
+    /// unless `detailed` is false, or why it couldn't format. The write has already happened,
+    /// so failing to format is not an error
+    pub fn run(&self, path: &Path, working_directory: &Path, detailed: bool) -> String {
+        let program = self.program();
+        let before = fs::read(path).unwrap_or_default();
+
+        let path_text = path.to_string_lossy();
+        let mut arguments = self.command[1..]
+            .iter()
+            .map(|element| match element.as_str() {
+                "{path}" => path_text.to_string(),
+                _ => element.clone(),
+            })
+            .collect::<Vec<_>>();
+        if !self.command.iter().any(|element| element == "{path}") {
+            arguments.push(path_text.to_string());
+        }
+
+        let output = match Command::new(&self.command[0])
+            .args(&arguments)
+            .current_dir(working_directory)
+            .output()
+        {
+            Ok(output) => output,
+            Err(error) => {
+                return format!(
+                    "\nCould not run the formatter {program}: {error}. The file was written \
+                     unformatted"
+                );
+            }
+        };
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return format!(
+                "\nThe formatter {program} failed ({}), so the file was written unformatted:\n{}",
+                output.status,
+                stderr.trim_end()
+            );
+        }
+
+        let after = fs::read(path).unwrap_or_default();
+        if after == before {
+            return format!("\nAlready formatted according to {program}");
+        }
+        if !detailed {
+            return format!("\nFormatted with {program}");
+        }
+        let before = String::from_utf8_lossy(&before);
+        let after = String::from_utf8_lossy(&after);
+        let diff = TextDiff::from_lines(&before, &after)
+            .unified_diff()
+            .context_radius(1)
+            .header("written", "formatted")
+            .to_string();
+        format!("\nFormatted with {program}:\n\n{diff}")
+    }
+}
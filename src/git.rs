@@ -0,0 +1,52 @@
+//! Running the `git` command line for the tools that report on version control, so that they
+//! see the same repository state, config, and ignore rules as the user's own git.
+
+use crate::hints::Hinted;
+use anyhow::Result;
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Run git in `directory` and return its stdout, or an error with git's stderr
+pub fn run(directory: &Path, args: &[impl AsRef<OsStr>]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(args)
+        .output()
+        .map_err(|error| {
+            Hinted::new(
+                format!("Could not run git: {error}"),
+                Some("check that git is installed and on the server's PATH".into()),
+            )
+        })?;
+
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("not a git repository") {
+        return Err(Hinted::new(
+            format!("{} is not inside a git repository", directory.display()),
+            Some("set_working_directory to a directory inside the repository".into()),
+        )
+        .into());
+    }
+    Err(Hinted::new(format!("git failed: {}", stderr.trim()), None).into())
+}
+
+/// The root of the repository containing `directory`
+pub fn toplevel(directory: &Path) -> Result<PathBuf> {
+    let root = run(directory, &["rev-parse", "--show-toplevel"])?;
+    Ok(PathBuf::from(root.trim_end_matches(['\n', '\r'])))
+}
+
+/// The directory to run git in for `path`, which need not exist: the closest existing
+/// directory containing it
+pub fn directory_for(path: &Path) -> &Path {
+    path.ancestors()
+        .find(|ancestor| ancestor.is_dir())
+        .unwrap_or(path)
+}
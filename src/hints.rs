@@ -0,0 +1,447 @@
+//! Actionable next steps attached to tool errors.
+//!
+//! Errors that carry a suggestion render it on a final line starting with `suggestion: `, so
+//! an agent can pick it out of the error text and self-correct without another round trip.
+//! Before it, tool errors carry a `code: ` line with an [`ErrorCode`], and a `path: ` line with
+//! the resolved path the error is about, if there is one.
+
+use crate::{config::Config, tools::DetectProjectRoot};
+use std::{
+    fmt::{self, Display, Formatter},
+    io,
+    path::{Path, PathBuf},
+};
+
+/// What kind of failure an error is, for agents to decide how to recover without parsing the
+/// message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    NotFound,
+    PermissionDenied,
+    /// The path is outside of the directory the operation is confined to
+    OutsideSandbox,
+    AlreadyExists,
+    TooLarge,
+    IsADirectory,
+    NotADirectory,
+    /// The call's arguments are invalid or contradict each other
+    InvalidArgument,
+    /// The file changed, or is being changed, underneath the call
+    Conflict,
+    QuotaExceeded,
+    /// The tool can't be used while serving with `--read-only`
+    ReadOnly,
+    /// Any other error from the filesystem
+    Io,
+    Other,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::NotFound => "NOT_FOUND",
+            Self::PermissionDenied => "PERMISSION_DENIED",
+            Self::OutsideSandbox => "OUTSIDE_SANDBOX",
+            Self::AlreadyExists => "ALREADY_EXISTS",
+            Self::TooLarge => "TOO_LARGE",
+            Self::IsADirectory => "IS_A_DIRECTORY",
+            Self::NotADirectory => "NOT_A_DIRECTORY",
+            Self::InvalidArgument => "INVALID_ARGUMENT",
+            Self::Conflict => "CONFLICT",
+            Self::QuotaExceeded => "QUOTA_EXCEEDED",
+            Self::ReadOnly => "READ_ONLY",
+            Self::Io => "IO_ERROR",
+            Self::Other => "ERROR",
+        }
+    }
+
+    fn from_io(kind: io::ErrorKind) -> Self {
+        match kind {
+            io::ErrorKind::NotFound => Self::NotFound,
+            io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => {
+                Self::PermissionDenied
+            }
+            io::ErrorKind::AlreadyExists | io::ErrorKind::DirectoryNotEmpty => Self::AlreadyExists,
+            io::ErrorKind::IsADirectory => Self::IsADirectory,
+            io::ErrorKind::NotADirectory => Self::NotADirectory,
+            io::ErrorKind::FileTooLarge | io::ErrorKind::StorageFull => Self::TooLarge,
+            io::ErrorKind::InvalidInput | io::ErrorKind::InvalidFilename => Self::InvalidArgument,
+            _ => Self::Io,
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An error message with an optional suggested next step
+#[derive(Debug)]
+pub struct Hinted {
+    message: String,
+    suggestion: Option<String>,
+    code: Option<ErrorCode>,
+    path: Option<PathBuf>,
+}
+
+impl Hinted {
+    pub fn new(message: impl Into<String>, suggestion: Option<String>) -> Self {
+        Self {
+            message: message.into(),
+            suggestion,
+            code: None,
+            path: None,
+        }
+    }
+
+    /// Classify the error, which otherwise is classified by any io error it came from
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// The resolved path the error is about
+    pub fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_path_buf());
+        self
+    }
+}
+
+impl Display for Hinted {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "\nsuggestion: {suggestion}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Hinted {}
+
+/// Render a tool error with its `code: ` line, and `path: ` line if it names one, placed before
+/// any suggestion so that the suggestion stays last
+pub fn render(error: &anyhow::Error) -> String {
+    let hinted = error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<Hinted>());
+    let code = hinted
+        .and_then(|hinted| hinted.code)
+        .or_else(|| {
+            error
+                .chain()
+                .find_map(|cause| cause.downcast_ref::<io::Error>())
+                .map(|io_error| ErrorCode::from_io(io_error.kind()))
+        })
+        .unwrap_or(ErrorCode::Other);
+
+    let text = error.to_string();
+    let (message, suggestion) = text.split_at(text.rfind("\nsuggestion: ").unwrap_or(text.len()));
+    let mut rendered = format!("{message}\ncode: {code}");
+    if let Some(path) = hinted.and_then(|hinted| hinted.path.as_ref()) {
+        rendered.push_str(&format!("\npath: {}", path.display()));
+    }
+    rendered.push_str(suggestion);
+    rendered
+}
+
+/// Maximum number of alternative paths listed in a "does not exist" suggestion
+const MAX_CANDIDATES: usize = 5;
+
+/// How many entries of the project are looked at for files with the missing file's name, to
+/// keep the error quick in large trees
+const MAX_SEARCHED_ENTRIES: usize = 10_000;
+
+/// Build a "does not exist" error, suggesting the closest existing paths if there are any
+pub fn not_found(path: &Path) -> anyhow::Error {
+    if let Some(matched) = case_insensitive_match(path) {
+        return Hinted::new(
+            format!("{} does not exist", path.display()),
+            Some(format!(
+                "did you mean {}? configure_session with `case_insensitive_paths: true` uses \
+                 paths like this automatically",
+                matched.display()
+            )),
+        )
+        .with_code(ErrorCode::NotFound)
+        .with_path(path)
+        .into();
+    }
+
+    let mut candidates = closest_existing_paths(path);
+    for elsewhere in same_name_elsewhere(path, Config::global()) {
+        if candidates.len() >= MAX_CANDIDATES {
+            break;
+        }
+        if !candidates.contains(&elsewhere) {
+            candidates.push(elsewhere);
+        }
+    }
+
+    let suggestion = match &candidates[..] {
+        [] => existing_ancestor(path).map(|ancestor| {
+            format!(
+                "{} exists; use list to see what it contains",
+                ancestor.display()
+            )
+        }),
+        [candidate] => Some(format!("did you mean {}?", candidate.display())),
+        candidates => Some(format!(
+            "did you mean one of: {}?",
+            candidates
+                .iter()
+                .map(|candidate| candidate.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    };
+
+    Hinted::new(format!("{} does not exist", path.display()), suggestion)
+        .with_code(ErrorCode::NotFound)
+        .with_path(path)
+        .into()
+}
+
+/// The deepest ancestor of `path` that exists
+fn existing_ancestor(path: &Path) -> Option<&Path> {
+    path.ancestors().skip(1).find(|ancestor| ancestor.is_dir())
+}
+
+/// How closely `name` resembles the `missing` path component, lower being closer. Differences
+/// in case rank first, then the same name with a different extension, then typos by edit
+/// distance. Returns None if the names are unrelated
+pub(crate) fn name_distance(missing: &str, name: &str) -> Option<usize> {
+    let missing_lower = missing.to_lowercase();
+    let name_lower = name.to_lowercase();
+    if missing_lower == name_lower {
+        return Some(0);
+    }
+
+    let stem = |name: &str| Path::new(name).file_stem().map(|stem| stem.to_os_string());
+    if stem(&missing_lower) == stem(&name_lower) {
+        return Some(1);
+    }
+
+    let distance = strsim::damerau_levenshtein(&missing_lower, &name_lower);
+    let threshold = (missing.chars().count() / 3).max(2);
+    (distance <= threshold).then_some(distance + 1)
+}
+
+/// The one existing path that matches `path` when compared case-insensitively, component by
+/// component from its deepest existing ancestor. None if any component matches no entry or
+/// several
+pub(crate) fn case_insensitive_match(path: &Path) -> Option<PathBuf> {
+    let ancestor = path
+        .ancestors()
+        .skip(1)
+        .find(|ancestor| ancestor.is_dir())?;
+    let mut matched = ancestor.to_path_buf();
+    for component in path.strip_prefix(ancestor).ok()?.components() {
+        let wanted = component.as_os_str().to_string_lossy().to_lowercase();
+        let mut entries = std::fs::read_dir(&matched)
+            .ok()?
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().to_lowercase() == wanted);
+        let entry = entries.next()?;
+        if entries.next().is_some() {
+            return None;
+        }
+        matched.push(entry.file_name());
+    }
+    Some(matched)
+}
+
+/// Find the existing paths closest to `path`, correcting the first missing component
+fn closest_existing_paths(path: &Path) -> Vec<PathBuf> {
+    let Some(ancestor) = existing_ancestor(path) else {
+        return vec![];
+    };
+    let Ok(relative) = path.strip_prefix(ancestor) else {
+        return vec![];
+    };
+    let mut components = relative.components();
+    let Some(missing) = components.next() else {
+        return vec![];
+    };
+    let missing = missing.as_os_str().to_string_lossy();
+    let rest = components.as_path();
+
+    let Ok(entries) = std::fs::read_dir(ancestor) else {
+        return vec![];
+    };
+
+    let mut candidates = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let distance = name_distance(&missing, &name)?;
+            let mut candidate = ancestor.join(name);
+            if !rest.as_os_str().is_empty() {
+                candidate.push(rest);
+            }
+            candidate.exists().then_some((distance, candidate))
+        })
+        .collect::<Vec<_>>();
+
+    candidates.sort();
+    candidates
+        .into_iter()
+        .take(MAX_CANDIDATES)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// Files elsewhere in the project with the same name as `path`, nearest to it first, for when
+/// a file was moved or its directory misremembered. The project is the root detected from the
+/// deepest existing ancestor, and gitignored paths are skipped. A project reaching above the
+/// sandbox roots of `config` is only looked through from the sandbox root holding `path`
+pub(crate) fn same_name_elsewhere(path: &Path, config: &Config) -> Vec<PathBuf> {
+    let (Some(ancestor), Some(file_name)) = (existing_ancestor(path), path.file_name()) else {
+        return vec![];
+    };
+    let root = match DetectProjectRoot::detect(ancestor).root() {
+        Some(project) => project.directory.clone(),
+        // Outside of any project, only look beneath the ancestor, and never the whole disk
+        None if ancestor.parent().is_some() => ancestor.to_path_buf(),
+        None => return vec![],
+    };
+    let root = if config.allows(&root) {
+        root
+    } else {
+        match config
+            .sandbox_roots
+            .iter()
+            .find(|sandbox_root| path.starts_with(sandbox_root))
+        {
+            Some(sandbox_root) => sandbox_root.clone(),
+            None => return vec![],
+        }
+    };
+
+    let mut found = ignore::WalkBuilder::new(&root)
+        .build()
+        .take(MAX_SEARCHED_ENTRIES)
+        .flatten()
+        .filter(|entry| entry.file_name() == file_name && entry.path() != path)
+        .filter(|entry| config.allows(entry.path()))
+        .map(|entry| entry.into_path())
+        .collect::<Vec<_>>();
+
+    // Nearest first: the most path components shared with the missing path, then the shallowest
+    let shared = |candidate: &Path| {
+        candidate
+            .components()
+            .zip(path.components())
+            .take_while(|(a, b)| a == b)
+            .count()
+    };
+    found.sort_by_key(|candidate| {
+        (
+            std::cmp::Reverse(shared(candidate)),
+            candidate.components().count(),
+        )
+    });
+    found
+}
+
+/// Attach a suggestion to an io error, based on its kind and the path involved
+pub fn io_error(error: io::Error, path: &Path) -> anyhow::Error {
+    let code = ErrorCode::from_io(error.kind());
+    let hinted = match error.kind() {
+        io::ErrorKind::NotFound => return not_found(path),
+        io::ErrorKind::PermissionDenied => Hinted::new(
+            format!("Permission denied for {}", path.display()),
+            Some(
+                "check the current mode with list include_metadata, or change it with \
+                 set_permissions if appropriate"
+                    .into(),
+            ),
+        ),
+        io::ErrorKind::AlreadyExists => Hinted::new(
+            format!("{} already exists", path.display()),
+            Some("use `overwrite` if you intend to replace it".into()),
+        ),
+        io::ErrorKind::IsADirectory => Hinted::new(
+            format!("{} is a directory", path.display()),
+            Some("use list to see its contents".into()),
+        ),
+        io::ErrorKind::NotADirectory => Hinted::new(
+            format!("A component of {} is not a directory", path.display()),
+            existing_ancestor(path)
+                .map(|ancestor| format!("use list on {} to check the path", ancestor.display())),
+        ),
+        _ => Hinted::new(format!("{}: {error}", path.display()), None),
+    };
+    hinted.with_code(code).with_path(path).into()
+}
+
+/// Extension for results whose error should be enriched with a suggestion about `path`
+pub trait Hint<T> {
+    fn hint_path(self, path: &Path) -> anyhow::Result<T>;
+}
+
+impl<T> Hint<T> for io::Result<T> {
+    fn hint_path(self, path: &Path) -> anyhow::Result<T> {
+        self.map_err(|error| io_error(error, path))
+    }
+}
+
+/// Unreadable paths listed at the end of a report
+const MAX_UNREADABLE_SHOWN: usize = 10;
+
+/// Paths that a walk could not read, reported after the results instead of failing the call
+#[derive(Debug, Default)]
+pub struct Unreadable(Vec<String>);
+
+impl Unreadable {
+    /// Record an error for `path`
+    pub fn record(&mut self, path: &Path, error: impl Display) {
+        self.0.push(format!("{}: {error}", path.display()));
+    }
+
+    /// Record an error from walking with `ignore`, which names its path in its own way
+    pub fn record_walk_error(&mut self, error: &ignore::Error) {
+        let mut inner = error;
+        loop {
+            match inner {
+                ignore::Error::WithDepth { err, .. }
+                | ignore::Error::WithLineNumber { err, .. } => inner = err,
+                ignore::Error::WithPath { path, err } => {
+                    // Only the kind is kept, since the message from walkdir repeats the path
+                    match err.io_error() {
+                        Some(io_error) => self.record(path, io_error.kind()),
+                        None => self.record(path, err),
+                    }
+                    return;
+                }
+                _ => {
+                    self.0.push(error.to_string());
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Append a section listing the unreadable paths to `output`, if there were any
+    pub fn append_to(&self, output: &mut String) {
+        if self.0.is_empty() {
+            return;
+        }
+        output.truncate(output.trim_end().len());
+        match self.0.len() {
+            1 => output.push_str("\n\nSkipped 1 unreadable path:"),
+            count => output.push_str(&format!("\n\nSkipped {count} unreadable paths:")),
+        }
+        for error in self.0.iter().take(MAX_UNREADABLE_SHOWN) {
+            output.push_str(&format!("\n{error}"));
+        }
+        if self.0.len() > MAX_UNREADABLE_SHOWN {
+            output.push_str(&format!(
+                "\n... and {} more",
+                self.0.len() - MAX_UNREADABLE_SHOWN
+            ));
+        }
+    }
+}
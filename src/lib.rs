@@ -0,0 +1,72 @@
+#![allow(clippy::collapsible_if)]
+//! Filesystem tools for AI agents, served over MCP by the `fs-mcp` binary.
+//!
+//! The same tools can be embedded in other programs without the MCP transport:
+//!
+//! ```no_run
+//! use fs_mcp::FsTools;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let mut fs_tools = FsTools::with_dir("/tmp/my-agent")?;
+//! fs_tools.call("set_working_directory", serde_json::json!({ "path": "/tmp" }))?;
+//! println!("{}", fs_tools.call("list", serde_json::json!({}))?);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Tools can also be built as values, such as [`tools::Read`], and run with
+//! [`Tool::execute`], which skips the session's activity log. Building them from JSON keeps
+//! working as tools gain optional parameters:
+//!
+//! ```
+//! use fs_mcp::{FsTools, Tool, tools::Search};
+//! use serde_json::json;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! # let dir = tempfile::tempdir()?;
+//! # let project = dir.path().join("project");
+//! # std::fs::create_dir_all(&project)?;
+//! # std::fs::write(project.join("notes.txt"), "TODO: embed fs-mcp\n")?;
+//! let mut fs_tools = FsTools::with_dir(dir.path().join("data"))?;
+//! fs_tools.call("set_working_directory", json!({ "path": project }))?;
+//! let search: Search = serde_json::from_value(json!({ "pattern": "TODO" }))?;
+//! assert!(search.execute(&mut fs_tools)?.contains("notes.txt"));
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod annotations;
+mod budget;
+pub mod config;
+pub mod custom_tools;
+mod deadline;
+mod exclude;
+mod file_types;
+mod format_hooks;
+mod git;
+pub mod hints;
+mod path_locks;
+mod project_ignore;
+mod quota;
+mod read_cache;
+mod resources;
+mod self_check;
+pub mod server;
+pub mod session_store;
+pub mod settings;
+mod simulation;
+pub mod state;
+pub mod tools;
+mod trash;
+mod visibility;
+
+#[cfg(test)]
+mod tests;
+
+pub use mcplease::traits::Tool;
+pub use state::FsTools;
+pub use tools::Tools;
+
+/// Instructions sent to MCP clients when they connect
+pub const INSTRUCTIONS: &str =
+    "Filesystem operations. Use set_working_directory to use relative paths";
@@ -1,19 +1,13 @@
-#![allow(clippy::collapsible_if)]
-
-mod state;
-mod tools;
-
-#[cfg(test)]
-mod tests;
-
 use anyhow::Result;
+use fs_mcp::{FsTools, INSTRUCTIONS, tools::Audited};
 use mcplease::server_info;
-use state::FsTools;
-
-const INSTRUCTIONS: &str = "Filesystem operations. Use set_working_directory to use relative paths";
 
 fn main() -> Result<()> {
     let mut state = FsTools::new()?;
 
-    mcplease::run::<tools::Tools, _>(&mut state, server_info!(), Some(INSTRUCTIONS))
+    if state.serving() {
+        fs_mcp::server::serve(&mut state, server_info!(), Some(INSTRUCTIONS))
+    } else {
+        mcplease::run::<Audited, _>(&mut state, server_info!(), Some(INSTRUCTIONS))
+    }
 }
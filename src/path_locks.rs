@@ -0,0 +1,98 @@
+//! Advisory locks that keep fs-mcp processes from changing the same path at the same time.
+//!
+//! Two MCP clients pointed at one repository each run their own fs-mcp. Without these locks,
+//! an append in one could read a file, and a write in the other replace it, before the append
+//! writes its result, silently losing one of the edits. The locks are sidecar files in the
+//! data directory, named by a hash of the path, so nothing is added to the project. They only
+//! coordinate processes that share a data directory, and don't stop other programs.
+
+use crate::hints::{ErrorCode, Hinted};
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File, TryLockError},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// How long to wait for another process to finish with a path before giving up
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Locks on paths, released when dropped
+#[derive(Debug)]
+#[must_use = "the locks are released when dropped"]
+pub(crate) struct PathLocks {
+    _files: Vec<File>,
+}
+
+/// Lock every path in `paths` with a sidecar file in `lock_dir`, waiting for other processes
+/// that hold any of them
+pub(crate) fn lock(lock_dir: &Path, paths: &[&Path]) -> Result<PathLocks> {
+    lock_with_timeout(lock_dir, paths, LOCK_TIMEOUT)
+}
+
+pub(crate) fn lock_with_timeout(
+    lock_dir: &Path,
+    paths: &[&Path],
+    timeout: Duration,
+) -> Result<PathLocks> {
+    fs::create_dir_all(lock_dir)
+        .with_context(|| format!("could not create {}", lock_dir.display()))?;
+
+    // Always taken in the same order, so that two processes locking the same paths can't each
+    // wait for the other
+    let mut named = paths
+        .iter()
+        .map(|path| {
+            let hash = Sha256::digest(path.as_os_str().as_encoded_bytes());
+            let name = hash[..16]
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>();
+            (name, *path)
+        })
+        .collect::<Vec<_>>();
+    named.sort();
+    named.dedup_by(|a, b| a.0 == b.0);
+
+    let deadline = Instant::now() + timeout;
+    let mut files = Vec::with_capacity(named.len());
+    for (name, path) in named {
+        let lock_path = lock_dir.join(format!("{name}.lock"));
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("could not open {}", lock_path.display()))?;
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+                Err(TryLockError::WouldBlock) => {
+                    return Err(Hinted::new(
+                        format!(
+                            "{} is being changed by another fs-mcp process. No filesystem \
+                             operation has been performed",
+                            path.display()
+                        ),
+                        Some(
+                            "read the file again once the other change is done, then retry".into(),
+                        ),
+                    )
+                    .with_code(ErrorCode::Conflict)
+                    .with_path(path)
+                    .into());
+                }
+                Err(TryLockError::Error(error)) => {
+                    return Err(error)
+                        .with_context(|| format!("could not lock {}", lock_path.display()));
+                }
+            }
+        }
+        files.push(file);
+    }
+    Ok(PathLocks { _files: files })
+}
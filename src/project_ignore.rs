@@ -0,0 +1,54 @@
+//! `.fs-mcp-ignore`, a file in gitignore syntax at the root of the working directory listing
+//! paths that list, search, extract_matches, and recent_files leave out. Teams use it to keep
+//! e.g. giant fixture directories out of an agent's view without touching `.gitignore`. Hidden
+//! and gitignored paths are handled separately, by [`crate::visibility`].
+
+use crate::{hints::Hinted, state::FsTools};
+use anyhow::Result;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+pub const IGNORE_FILE: &str = ".fs-mcp-ignore";
+
+/// The patterns of the working directory's ignore file, if it has one
+#[derive(Debug, Clone, Default)]
+pub struct ProjectIgnore(Option<Gitignore>);
+
+impl ProjectIgnore {
+    /// Load the ignore file at the root of the session's working directory. Nothing is ignored
+    /// with `bypass`, or if there is no working directory or no ignore file
+    pub fn load(state: &mut FsTools, bypass: bool) -> Result<Self> {
+        if bypass || state.get_context(None)?.is_none() {
+            return Ok(Self::default());
+        }
+
+        let path = state.resolve_path(IGNORE_FILE, None)?;
+        let Some(root) = path.parent().filter(|_| path.is_file()) else {
+            return Ok(Self::default());
+        };
+
+        let mut builder = GitignoreBuilder::new(root);
+        if let Some(error) = builder.add(&path) {
+            return Err(Hinted::new(
+                format!("{} could not be read: {error}", path.display()),
+                Some(format!(
+                    "fix {IGNORE_FILE}, or pass `bypass_ignore_file: true`"
+                )),
+            )
+            .into());
+        }
+        Ok(Self(Some(builder.build()?)))
+    }
+
+    /// Whether `path` matches the ignore file. Only the path itself is checked, since walks
+    /// skip ignored directories without looking inside, and a path given explicitly inside an
+    /// ignored directory should still be walked
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        match &self.0 {
+            Some(gitignore) => {
+                path.starts_with(gitignore.path()) && gitignore.matched(path, is_dir).is_ignore()
+            }
+            None => false,
+        }
+    }
+}
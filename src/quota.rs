@@ -0,0 +1,232 @@
+//! Per-session limits on how much a session may change the filesystem.
+//!
+//! Usage only ever grows until a person resets it from the command line, so an agent stuck in a
+//! loop generating files runs into the limit instead of filling the disk. The number of calls
+//! to tools that change files can also be limited per minute, checked before each such call.
+
+use crate::hints::{ErrorCode, Hinted};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// The window that `max_changes_per_minute` counts calls in
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Amounts of filesystem change, used both for limits and for what has been used
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuotaUsage {
+    #[serde(default)]
+    pub bytes_written: u64,
+    #[serde(default)]
+    pub files_created: u64,
+    #[serde(default)]
+    pub files_deleted: u64,
+}
+
+impl QuotaUsage {
+    pub fn written(bytes: u64, created: bool) -> Self {
+        Self {
+            bytes_written: bytes,
+            files_created: created.into(),
+            files_deleted: 0,
+        }
+    }
+
+    pub fn created(files: u64) -> Self {
+        Self {
+            files_created: files,
+            ..Self::default()
+        }
+    }
+
+    pub fn deleted(files: u64) -> Self {
+        Self {
+            files_deleted: files,
+            ..Self::default()
+        }
+    }
+
+    fn fields(&self) -> [(&'static str, u64); 3] {
+        [
+            ("bytes written", self.bytes_written),
+            ("files created", self.files_created),
+            ("files deleted", self.files_deleted),
+        ]
+    }
+}
+
+/// Limits for a session, each unlimited if None
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuotaLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes_written: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_files_created: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_files_deleted: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_changes_per_minute: Option<u64>,
+}
+
+impl QuotaLimits {
+    /// Each limit, in the order of [`QuotaUsage`]'s fields followed by the rate
+    fn fields(&self) -> [Option<u64>; 4] {
+        [
+            self.max_bytes_written,
+            self.max_files_created,
+            self.max_files_deleted,
+            self.max_changes_per_minute,
+        ]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields().iter().all(Option::is_none)
+    }
+
+    /// Whether any limit applies to the amount changed, as opposed to only the rate of changes
+    pub fn limits_amounts(&self) -> bool {
+        self.fields()[..3].iter().any(Option::is_some)
+    }
+
+    /// Whether `self` allows more than `other` in any respect
+    pub fn loosens(&self, other: &Self) -> bool {
+        self.fields()
+            .iter()
+            .zip(other.fields())
+            .any(|(new, old)| match (new, old) {
+                (_, None) => false,
+                (None, Some(_)) => true,
+                (Some(new), Some(old)) => *new > old,
+            })
+    }
+}
+
+/// A session's limits and what it has used so far
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Quota {
+    #[serde(default)]
+    pub limits: QuotaLimits,
+    #[serde(default)]
+    pub usage: QuotaUsage,
+}
+
+impl Quota {
+    /// Add `charge` to the usage, unless that would exceed a limit. `session_id` is only used in
+    /// the error
+    pub fn charge(&mut self, charge: QuotaUsage, session_id: &str) -> Result<(), Hinted> {
+        let usage = self.usage.fields();
+        for (((name, used), (_, amount)), limit) in
+            usage.iter().zip(charge.fields()).zip(self.limits.fields())
+        {
+            if let Some(limit) = limit {
+                if amount > 0 && used + amount > limit {
+                    return Err(Hinted::new(
+                        format!(
+                            "Quota exceeded: this session's quota of {limit} {name} would be \
+                             exceeded ({used} used, {amount} more needed). No filesystem \
+                             operation has been performed"
+                        ),
+                        Some(format!(
+                            "stop and ask a person to review; only they can reset the quota, by \
+                             running `{}` in a terminal",
+                            reset_command(session_id, "--reset")
+                        )),
+                    )
+                    .with_code(ErrorCode::QuotaExceeded));
+                }
+            }
+        }
+
+        self.usage.bytes_written += charge.bytes_written;
+        self.usage.files_created += charge.files_created;
+        self.usage.files_deleted += charge.files_deleted;
+        Ok(())
+    }
+
+    /// e.g. "bytes written: 1200 of 1000000", one line per kind of change, then the number of
+    /// `recent_changes` made in the last minute
+    pub fn describe(&self, recent_changes: usize) -> String {
+        let usage = self.usage.fields();
+        let rate = ("changes in the last minute", recent_changes as u64);
+        usage
+            .iter()
+            .chain([&rate])
+            .zip(self.limits.fields())
+            .map(|((name, used), limit)| match limit {
+                Some(limit) => format!("{name}: {used} of {limit}\n"),
+                None => format!("{name}: {used} (no limit)\n"),
+            })
+            .collect()
+    }
+}
+
+/// The command a person runs to change a session's quota, e.g. with `--reset`
+fn reset_command(session_id: &str, arguments: &str) -> String {
+    match session_id {
+        "default" => format!("fs-mcp quota-status {arguments}"),
+        session_id => format!("FS_MCP_SESSION={session_id} fs-mcp quota-status {arguments}"),
+    }
+}
+
+/// When the calls that changed files in the last minute were made, to enforce
+/// `max_changes_per_minute`. This is kept in memory, as a runaway loop is within one process
+#[derive(Debug, Default)]
+pub struct ChangeRate {
+    recent: VecDeque<Instant>,
+}
+
+impl ChangeRate {
+    /// How many changes were made in the minute before `now`
+    pub fn count(&mut self, now: Instant) -> usize {
+        self.expire(now);
+        self.recent.len()
+    }
+
+    /// Record a change at `now`, unless `limit` changes were already made in the minute before
+    /// it. `session_id` is only used in the error
+    pub fn record(
+        &mut self,
+        limit: Option<u64>,
+        now: Instant,
+        session_id: &str,
+    ) -> Result<(), Hinted> {
+        self.expire(now);
+        if let Some(limit) = limit {
+            let count = self.recent.len();
+            if count as u64 >= limit {
+                let wait = self.recent.front().map_or(RATE_WINDOW, |oldest| {
+                    (*oldest + RATE_WINDOW).saturating_duration_since(now)
+                });
+                return Err(Hinted::new(
+                    format!(
+                        "Quota exceeded: this session may make {limit} changes per minute, and \
+                         {count} were made in the last minute. No filesystem operation has been \
+                         performed"
+                    ),
+                    Some(format!(
+                        "wait {}s before changing files again; if the same changes keep \
+                         repeating, stop and ask a person to review. Only they can raise the \
+                         limit, by running `{}` in a terminal",
+                        wait.as_secs().max(1),
+                        reset_command(session_id, "--max-changes-per-minute <higher limit>")
+                    )),
+                )
+                .with_code(ErrorCode::QuotaExceeded));
+            }
+        }
+        self.recent.push_back(now);
+        Ok(())
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while self
+            .recent
+            .front()
+            .is_some_and(|at| now.saturating_duration_since(*at) >= RATE_WINDOW)
+        {
+            self.recent.pop_front();
+        }
+    }
+}
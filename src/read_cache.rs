@@ -0,0 +1,105 @@
+//! Contents of the files `read` has returned, so that reading an unchanged file again is served
+//! from memory and the response can say it is unchanged, which lets an agent skip re-reading it.
+//!
+//! Entries are keyed by path and checked against the file's modification time and size. When
+//! those differ, the file is read again, and a matching sha256 still counts as unchanged, as
+//! after a `touch` or a write of the same contents. The cache lives as long as the server.
+
+use crate::tools::Delete;
+use anyhow::{Context, Result};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+/// The most file contents kept, least recently read files being dropped first
+const MAX_CACHED_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Default)]
+pub struct ReadCache {
+    files: HashMap<PathBuf, Cached>,
+    bytes: usize,
+    /// Incremented on every lookup, to find the least recently read file
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct Cached {
+    modified: Option<SystemTime>,
+    len: u64,
+    sha256: String,
+    contents: Arc<str>,
+    last_read: u64,
+}
+
+/// A file's contents, and whether they are the same as when it was last read
+#[derive(Debug)]
+pub struct CachedRead {
+    pub contents: Arc<str>,
+    pub unchanged: bool,
+}
+
+impl ReadCache {
+    /// Read `path` as utf8, lossily, from the cache if it hasn't changed
+    pub fn read(&mut self, path: &Path) -> Result<CachedRead> {
+        self.clock += 1;
+        let metadata = fs::metadata(path)
+            .with_context(|| format!("Unable to open metadata for {}", path.display()))?;
+        let modified = metadata.modified().ok();
+
+        if let Some(cached) = self.files.get_mut(path) {
+            if modified.is_some() && cached.modified == modified && cached.len == metadata.len() {
+                cached.last_read = self.clock;
+                return Ok(CachedRead {
+                    contents: cached.contents.clone(),
+                    unchanged: true,
+                });
+            }
+        }
+
+        let bytes = fs::read(path).with_context(|| format!("Unable to read {}", path.display()))?;
+        let sha256 = Delete::sha256_hex(&bytes);
+        let contents: Arc<str> = String::from_utf8_lossy(&bytes).into();
+        let unchanged = self
+            .files
+            .get(path)
+            .is_some_and(|cached| cached.sha256 == sha256);
+
+        let cached = Cached {
+            modified,
+            len: bytes.len() as u64,
+            sha256,
+            contents: contents.clone(),
+            last_read: self.clock,
+        };
+        self.bytes += cached.contents.len();
+        if let Some(replaced) = self.files.insert(path.to_path_buf(), cached) {
+            self.bytes -= replaced.contents.len();
+        }
+        self.evict();
+
+        Ok(CachedRead {
+            contents,
+            unchanged,
+        })
+    }
+
+    fn evict(&mut self) {
+        while self.bytes > MAX_CACHED_BYTES {
+            let Some(oldest) = self
+                .files
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_read)
+                .map(|(path, _)| path.clone())
+            else {
+                break;
+            };
+            if let Some(removed) = self.files.remove(&oldest) {
+                self.bytes -= removed.contents.len();
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! The files under the working directory as MCP resources, for clients that attach context
+//! through `resources/list` and `resources/read` rather than tool calls.
+//!
+//! Resources are `file://` URIs of absolute paths. Listings skip the same paths as `list` does:
+//! hidden and gitignored files, and those in `.fs-mcp-ignore`.
+
+use crate::{
+    hints::{self, ErrorCode, Hinted},
+    project_ignore::ProjectIgnore,
+    state::FsTools,
+};
+use anyhow::Result;
+use base64::Engine;
+use ignore::WalkBuilder;
+use serde_json::{Value, json};
+use std::path::{Component, Path, PathBuf};
+
+/// Number of resources in each page of `resources/list`
+const PAGE_SIZE: usize = 500;
+
+/// Files larger than this are not offered for reading
+const MAX_RESOURCE_BYTES: u64 = 1024 * 1024;
+
+/// One page of the files under the working directory, starting at the offset in `cursor`.
+/// Without a working directory there are no resources
+pub fn list(state: &mut FsTools, cursor: Option<&str>) -> Result<Value> {
+    let Some(root) = state.get_context(None)? else {
+        return Ok(json!({ "resources": [] }));
+    };
+    let offset = match cursor {
+        Some(cursor) => cursor
+            .parse::<usize>()
+            .map_err(|_| Hinted::new(format!("{cursor} is not a resources/list cursor"), None))?,
+        None => 0,
+    };
+
+    let ignore = ProjectIgnore::load(state, false)?;
+    let walk = WalkBuilder::new(&root)
+        .filter_entry(move |entry| {
+            let is_dir = entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_dir());
+            !ignore.is_ignored(entry.path(), is_dir)
+        })
+        .build();
+    let mut files = walk
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+        })
+        .map(|entry| entry.into_path())
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let resources = files
+        .iter()
+        .skip(offset)
+        .take(PAGE_SIZE)
+        .map(|path| {
+            let name = path.strip_prefix(&root).unwrap_or(path);
+            let mut resource = json!({
+                "uri": uri(path),
+                "name": name.display().to_string(),
+            });
+            if let Some(mime_type) = mime_type(path) {
+                resource["mimeType"] = mime_type.into();
+            }
+            if let Ok(metadata) = path.metadata() {
+                resource["size"] = metadata.len().into();
+            }
+            resource
+        })
+        .collect::<Vec<_>>();
+
+    let mut response = json!({ "resources": resources });
+    if offset + PAGE_SIZE < files.len() {
+        response["nextCursor"] = (offset + PAGE_SIZE).to_string().into();
+    }
+    Ok(response)
+}
+
+/// The contents of the resource at `uri`, as text if it is utf8 and base64 otherwise
+pub fn read(state: &mut FsTools, uri: &str) -> Result<Value> {
+    let path = path(uri)?;
+    let root = state.get_context(None)?;
+    let escapes = path
+        .components()
+        .any(|component| component == Component::ParentDir);
+    if escapes || !root.as_ref().is_some_and(|root| path.starts_with(root)) {
+        return Err(Hinted::new(
+            format!("{uri} is not under the working directory"),
+            Some("only files under the working directory are resources".into()),
+        )
+        .with_code(ErrorCode::OutsideSandbox)
+        .with_path(&path)
+        .into());
+    }
+
+    // Resolved like a tool's path, so that a simulation's overlay is read
+    let resolved = state.resolve_path(&path.to_string_lossy(), None)?;
+    if !resolved.is_file() {
+        return Err(hints::not_found(&path));
+    }
+    let size = resolved.metadata()?.len();
+    if size > MAX_RESOURCE_BYTES {
+        return Err(Hinted::new(
+            format!(
+                "{} is too large to read as a resource ({size} bytes)",
+                path.display()
+            ),
+            Some("use the read tool, which can read part of a file".into()),
+        )
+        .with_code(ErrorCode::TooLarge)
+        .with_path(&path)
+        .into());
+    }
+
+    let bytes = std::fs::read(&resolved)?;
+    let mut contents = json!({ "uri": uri });
+    // NUL bytes are valid utf8, but a sure sign of a binary file
+    match String::from_utf8(bytes) {
+        Ok(text) if !text.contains('\0') => {
+            contents["mimeType"] = mime_type(&path).unwrap_or("text/plain").into();
+            contents["text"] = text.into();
+        }
+        binary => {
+            let bytes = binary.map_or_else(|error| error.into_bytes(), String::into_bytes);
+            contents["mimeType"] = mime_type(&path)
+                .unwrap_or("application/octet-stream")
+                .into();
+            contents["blob"] = base64::engine::general_purpose::STANDARD
+                .encode(bytes)
+                .into();
+        }
+    }
+    Ok(json!({ "contents": [contents] }))
+}
+
+/// The `file://` URI of an absolute path, percent-encoding everything but unreserved
+/// characters and separators
+pub(crate) fn uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
+
+/// The path of a `file://` URI
+pub(crate) fn path(uri: &str) -> Result<PathBuf> {
+    let Some(encoded) = uri.strip_prefix("file://") else {
+        return Err(Hinted::new(
+            format!("{uri} is not a file:// URI"),
+            Some("use a URI from resources/list".into()),
+        )
+        .into());
+    };
+
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    let decoded = String::from_utf8(bytes)
+        .map_err(|_| Hinted::new(format!("{uri} does not name a utf8 path"), None))?;
+
+    // `file:///C:/dir` names `C:/dir` on Windows
+    let decoded = match decoded.strip_prefix('/') {
+        Some(rest) if rest.as_bytes().get(1) == Some(&b':') => rest.to_string(),
+        _ => decoded,
+    };
+    Ok(PathBuf::from(decoded))
+}
+
+/// The MIME type of common formats, by extension
+fn mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "md" | "markdown" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "js" | "mjs" | "cjs" => "text/javascript",
+        "json" => "application/json",
+        "toml" => "application/toml",
+        "yaml" | "yml" => "application/yaml",
+        "xml" => "application/xml",
+        "rs" => "text/x-rust",
+        "py" => "text/x-python",
+        "sh" => "application/x-sh",
+        "txt" | "log" => "text/plain",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        _ => return None,
+    })
+}
@@ -0,0 +1,293 @@
+//! Checks of the environment fs-mcp runs in, so that a misconfiguration is reported up front
+//! instead of surfacing later as a confusing tool failure.
+
+use crate::state::FsTools;
+use std::{
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::Path,
+};
+
+/// Below this many inotify watches per user, servers that watch files may stop noticing changes
+#[cfg(target_os = "linux")]
+const MIN_INOTIFY_WATCHES: u64 = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    /// Something works differently than it might, which some tools may notice
+    Warning,
+    /// Something fs-mcp needs is broken
+    Problem,
+    /// Not checked on this platform
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    Unchecked,
+}
+
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+
+    fn from_result(name: &'static str, result: io::Result<()>, ok: &str, failed: &str) -> Self {
+        match result {
+            Ok(()) => Self::new(name, Status::Ok, ok),
+            Err(error) => Self::new(name, Status::Warning, format!("{failed}: {error}")),
+        }
+    }
+}
+
+impl Display for Check {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let status = match self.status {
+            Status::Ok => "ok",
+            Status::Warning => "warning",
+            Status::Problem => "PROBLEM",
+            Status::Unchecked => "-",
+        };
+        write!(f, "{status:<8} {}: {}", self.name, self.detail)
+    }
+}
+
+/// Run every check. `probe_dir` is where filesystem capabilities are tried out, since they
+/// depend on the filesystem
+pub fn run(state: &FsTools, probe_dir: &Path) -> Vec<Check> {
+    let mut checks = vec![];
+
+    let sessions_dir = state.session_store().path().parent().map(Path::to_path_buf);
+    for (name, dir) in [
+        ("session directory", sessions_dir),
+        ("data directory", Some(state.data_dir().to_path_buf())),
+    ] {
+        let Some(dir) = dir else { continue };
+        checks.push(match writable(&dir) {
+            Ok(()) => Check::new(name, Status::Ok, format!("{} is writable", dir.display())),
+            Err(error) => Check::new(
+                name,
+                Status::Problem,
+                format!("{} is not writable: {error}", dir.display()),
+            ),
+        });
+    }
+
+    for (path, verified) in [
+        (
+            state.shared_context_store().path(),
+            state.shared_context_store().verify(),
+        ),
+        (state.session_store().path(), state.session_store().verify()),
+    ] {
+        checks.push(match verified {
+            Ok(unreadable) if unreadable.is_empty() => Check::new(
+                "session file",
+                Status::Ok,
+                format!("{} parses", path.display()),
+            ),
+            Ok(unreadable) => Check::new(
+                "session file",
+                Status::Warning,
+                format!(
+                    "{} has sessions fs-mcp can't read, which it treats as empty: {}",
+                    path.display(),
+                    unreadable.join(", ")
+                ),
+            ),
+            Err(error) => Check::new(
+                "session file",
+                Status::Problem,
+                format!(
+                    "{} does not parse, and would be replaced on the next change: {error}",
+                    path.display()
+                ),
+            ),
+        });
+    }
+
+    checks.push(inotify_limits());
+
+    let probe = tempfile::Builder::new()
+        .prefix(".fs-mcp-check")
+        .tempdir_in(probe_dir);
+    match probe {
+        Ok(probe) => {
+            checks.push(symlinks(probe.path()));
+            checks.push(xattrs(probe.path()));
+            checks.push(reflinks(probe.path()));
+        }
+        Err(error) => checks.push(Check::new(
+            "filesystem capabilities",
+            Status::Warning,
+            format!(
+                "could not create a probe in {}: {error}",
+                probe_dir.display()
+            ),
+        )),
+    }
+
+    checks
+}
+
+fn writable(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    tempfile::NamedTempFile::new_in(dir)?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn inotify_limits() -> Check {
+    let read = |name: &str| -> Option<u64> {
+        fs::read_to_string(format!("/proc/sys/fs/inotify/{name}"))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+    match (read("max_user_watches"), read("max_user_instances")) {
+        (Some(watches), Some(instances)) if watches < MIN_INOTIFY_WATCHES => Check::new(
+            "inotify limits",
+            Status::Warning,
+            format!(
+                "{watches} watches and {instances} instances per user; other MCP servers that \
+                 watch session files may miss changes. Raise fs.inotify.max_user_watches"
+            ),
+        ),
+        (Some(watches), Some(instances)) => Check::new(
+            "inotify limits",
+            Status::Ok,
+            format!("{watches} watches and {instances} instances per user"),
+        ),
+        _ => Check::new(
+            "inotify limits",
+            Status::Warning,
+            "could not read /proc/sys/fs/inotify",
+        ),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn inotify_limits() -> Check {
+    Check::new(
+        "inotify limits",
+        Status::Unchecked,
+        "only relevant on linux",
+    )
+}
+
+fn symlinks(probe: &Path) -> Check {
+    let target = probe.join("target");
+    let link = probe.join("link");
+    let result = fs::write(&target, "").and_then(|()| create_symlink(&target, &link));
+    Check::from_result(
+        "symlinks",
+        result,
+        "can be created",
+        "can't be created, which on Windows needs developer mode or administrator rights",
+    )
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+#[cfg(target_os = "linux")]
+fn c_path(path: &Path) -> io::Result<std::ffi::CString> {
+    use std::os::unix::ffi::OsStrExt;
+    std::ffi::CString::new(path.as_os_str().as_bytes()).map_err(io::Error::other)
+}
+
+#[cfg(target_os = "linux")]
+fn xattrs(probe: &Path) -> Check {
+    let path = probe.join("xattr");
+    let result = fs::write(&path, "").and_then(|()| {
+        let path = c_path(&path)?;
+        // SAFETY: both strings are nul-terminated, and the value is one readable byte
+        let status = unsafe {
+            libc::setxattr(
+                path.as_ptr(),
+                c"user.fs_mcp_check".as_ptr(),
+                b"1".as_ptr().cast(),
+                1,
+                0,
+            )
+        };
+        match status {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    });
+    Check::from_result(
+        "extended attributes",
+        result,
+        "supported",
+        "not supported here",
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn xattrs(_probe: &Path) -> Check {
+    Check::new(
+        "extended attributes",
+        Status::Unchecked,
+        "only checked on linux",
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn reflinks(probe: &Path) -> Check {
+    use std::os::fd::AsRawFd;
+    let source = probe.join("reflink-source");
+    let result = fs::write(&source, "fs-mcp").and_then(|()| {
+        let source = fs::File::open(&source)?;
+        let destination = fs::File::create(probe.join("reflink-destination"))?;
+        // SAFETY: both descriptors are open for the duration of the call
+        let status =
+            unsafe { libc::ioctl(destination.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) };
+        match status {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    });
+    Check::from_result(
+        "reflinks",
+        result,
+        "supported, so copies can share storage",
+        "not supported here, so copies take their full size",
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflinks(_probe: &Path) -> Check {
+    Check::new("reflinks", Status::Unchecked, "only checked on linux")
+}
+
+/// Run the checks when the server starts, logging anything wrong so that it is noticed without
+/// keeping the server from starting
+pub fn at_startup(state: &FsTools) {
+    let probe_dir = state.data_dir().to_path_buf();
+    for check in run(state, &probe_dir) {
+        match check.status {
+            Status::Problem => {
+                log::error!("{check}\nRun `fs-mcp self-check` for details");
+            }
+            Status::Warning => log::warn!("{check}"),
+            Status::Ok | Status::Unchecked => {}
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! The MCP server loop. It is mcplease's, except that each tool in `tools/list` carries its
+//! [`Annotations`], which mcplease's tool schemas have no field for, and that files are also
+//! offered as resources (see `crate::resources`).
+
+use crate::{annotations::Annotations, resources, state::FsTools, tools::Audited};
+use anyhow::Result;
+use env_logger::{Builder, Target};
+use mcplease::{
+    traits::AsToolsList,
+    types::{Info, McpMessage, McpResponse},
+};
+use std::{
+    fs::OpenOptions,
+    io::{BufRead, Write},
+    path::PathBuf,
+};
+
+/// Serve MCP on stdin and stdout until stdin is closed
+pub fn serve(
+    state: &mut FsTools,
+    server_info: Info,
+    instructions: Option<&'static str>,
+) -> Result<()> {
+    init_logging()?;
+    // After logging is set up, so that what the checks find reaches the log
+    crate::self_check::at_startup(state);
+
+    let mut stdout = std::io::stdout();
+    for line in std::io::stdin().lock().lines() {
+        let line = line?;
+        log::trace!("<- {line}");
+        let request = match serde_json::from_str(&line) {
+            Ok(McpMessage::Request(request)) => request,
+            Ok(McpMessage::Notification(notification)) => {
+                log::trace!("received {notification:?}, ignoring");
+                continue;
+            }
+            Err(error) => {
+                log::error!("{error:?}");
+                continue;
+            }
+        };
+
+        let param = |name: &str| {
+            request
+                .params
+                .as_ref()
+                .and_then(|params| params.get(name))
+                .and_then(|value| value.as_str())
+                .map(str::to_string)
+        };
+        let response = match request.method.as_str() {
+            "tools/list" => McpResponse::success(
+                request.id,
+                serde_json::json!({ "tools": tools_list(state) }),
+            ),
+            "resources/list" => {
+                let cursor = param("cursor");
+                respond(request.id, resources::list(state, cursor.as_deref()))
+            }
+            "resources/read" => match param("uri") {
+                Some(uri) => respond(request.id, resources::read(state, &uri)),
+                None => McpResponse::error(request.id, "resources/read needs a uri".into()),
+            },
+            "initialize" => {
+                let mut response =
+                    request.execute::<FsTools, Audited>(state, instructions, &server_info);
+                if let Some(capabilities) = response
+                    .result
+                    .as_mut()
+                    .and_then(|result| result.get_mut("capabilities"))
+                {
+                    capabilities["resources"] = serde_json::json!({});
+                }
+                response
+            }
+            _ => request.execute::<FsTools, Audited>(state, instructions, &server_info),
+        };
+
+        let response = serde_json::to_string(&response)?;
+        log::trace!("-> {response}");
+        writeln!(stdout, "{response}")?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+fn respond(id: serde_json::Value, result: Result<serde_json::Value>) -> McpResponse {
+    match result {
+        Ok(result) => McpResponse::success(id, result),
+        Err(error) => {
+            log::error!("{error}");
+            McpResponse::error(id, error.to_string())
+        }
+    }
+}
+
+/// The tool schemas with their annotations, leaving out tools a read-only server refuses
+fn tools_list(state: &FsTools) -> Vec<serde_json::Value> {
+    Audited::tools_list()
+        .into_iter()
+        .filter_map(|tool| {
+            let annotations = Annotations::for_tool(&tool.name);
+            if state.read_only() && !annotations.read_only {
+                return None;
+            }
+            let mut tool = serde_json::to_value(tool).ok()?;
+            tool["annotations"] = serde_json::to_value(annotations).ok()?;
+            Some(tool)
+        })
+        .collect()
+}
+
+/// Log to the file named by `MCP_LOG_LOCATION`, as mcplease does, since stdout is the transport
+fn init_logging() -> Result<()> {
+    let Ok(log_location) = std::env::var("MCP_LOG_LOCATION") else {
+        return Ok(());
+    };
+    let path = PathBuf::from(&*shellexpand::tilde(&log_location));
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    Builder::from_default_env()
+        .target(Target::Pipe(Box::new(file)))
+        .init();
+    Ok(())
+}
@@ -0,0 +1,247 @@
+//! Session persistence that is safe to share between processes.
+//!
+//! Several fs-mcp processes, and other MCP servers using `shared-context.json`, read and rewrite
+//! the same files. Each change here is made under an exclusive lock on a sibling `.lock` file,
+//! against a fresh read of the file, and replaces only its own session, so concurrent writers
+//! neither interleave their writes nor drop each other's sessions. The format is the one
+//! mcplease's `SessionStore` uses, so servers still using that can share the files.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionMetadata {
+    created_at: SystemTime,
+    last_used: SystemTime,
+}
+
+impl Default for SessionMetadata {
+    fn default() -> Self {
+        let now = SystemTime::now();
+        Self {
+            created_at: now,
+            last_used: now,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SessionEntry<T> {
+    data: T,
+    metadata: SessionMetadata,
+}
+
+/// Sessions stored in a JSON file, keyed by session id
+#[derive(Debug)]
+pub struct SessionStore<T> {
+    path: PathBuf,
+    lock_path: PathBuf,
+    /// Every session in the file as it was read, including any this type can't parse
+    raw: Map<String, Value>,
+    sessions: HashMap<String, SessionEntry<T>>,
+    /// Modification time and length of the file when it was read, to notice other writers
+    loaded: Option<(SystemTime, u64)>,
+}
+
+impl<T> SessionStore<T>
+where
+    T: Serialize + DeserializeOwned + Clone + Default + PartialEq,
+{
+    pub fn new(path: PathBuf) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut store = Self {
+            lock_path: path.with_extension("lock"),
+            path,
+            raw: Map::new(),
+            sessions: HashMap::new(),
+            loaded: None,
+        };
+        store.load(false)?;
+        Ok(store)
+    }
+
+    /// Get a session's data, creating the session if it doesn't exist
+    pub fn get_or_create(&mut self, session_id: &str) -> Result<&T> {
+        self.reload_if_changed()?;
+        if !self.sessions.contains_key(session_id) {
+            self.update(session_id, |_| {})?;
+        }
+        Ok(&self.sessions[session_id].data)
+    }
+
+    /// Get a session's data, or None if the session doesn't exist
+    pub fn get(&mut self, session_id: &str) -> Result<Option<&T>> {
+        self.reload_if_changed()?;
+        Ok(self.sessions.get(session_id).map(|entry| &entry.data))
+    }
+
+    /// Change a session's data, creating the session first if it doesn't exist
+    pub fn update(&mut self, session_id: &str, fun: impl FnOnce(&mut T)) -> Result<()> {
+        let _lock = self.lock()?;
+
+        // Another process may have written since the last read, and this change must not
+        // discard what it wrote
+        self.load(true)?;
+
+        let is_new = !self.sessions.contains_key(session_id);
+        let entry = self.sessions.entry(session_id.to_string()).or_default();
+        let before = entry.data.clone();
+        fun(&mut entry.data);
+        if !is_new && before == entry.data {
+            return Ok(());
+        }
+        entry.metadata.last_used = SystemTime::now();
+        self.raw
+            .insert(session_id.to_string(), serde_json::to_value(&*entry)?);
+
+        // Written to a file of our own first, so that readers never see a partial write
+        let temp_path = self
+            .path
+            .with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&temp_path, serde_json::to_string_pretty(&self.raw)?)?;
+        fs::rename(&temp_path, &self.path)?;
+        self.loaded = Self::stamp(&fs::metadata(&self.path)?);
+        Ok(())
+    }
+
+    /// Every session's id, data, and when it was last changed, most recently changed first
+    pub fn recent(&mut self) -> Result<Vec<(String, SystemTime, T)>> {
+        self.reload_if_changed()?;
+        let mut sessions = self
+            .sessions
+            .iter()
+            .map(|(id, entry)| (id.clone(), entry.metadata.last_used, entry.data.clone()))
+            .collect::<Vec<_>>();
+        sessions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(sessions)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Check that the file parses, returning the ids of any sessions in it that this store
+    /// can't read. Those are kept as they are, but are empty as far as fs-mcp is concerned
+    pub fn verify(&self) -> Result<Vec<String>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(error.into()),
+        };
+        if contents.trim().is_empty() {
+            return Ok(vec![]);
+        }
+        let raw: Map<String, Value> = serde_json::from_str(&contents)?;
+        Ok(raw
+            .into_iter()
+            .filter(|(_, value)| serde_json::from_value::<SessionEntry<T>>(value.clone()).is_err())
+            .map(|(session_id, _)| session_id)
+            .collect())
+    }
+
+    /// Replace a session's data
+    pub fn set(&mut self, session_id: &str, data: T) -> Result<()> {
+        self.update(session_id, |existing| *existing = data)
+    }
+
+    /// Take the exclusive lock that every change to the file is made under, released when the
+    /// returned file is dropped
+    fn lock(&self) -> Result<File> {
+        let lock = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.lock_path)
+            .with_context(|| format!("could not open {}", self.lock_path.display()))?;
+        lock.lock()?;
+        Ok(lock)
+    }
+
+    fn stamp(metadata: &fs::Metadata) -> Option<(SystemTime, u64)> {
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+
+    fn reload_if_changed(&mut self) -> Result<()> {
+        let stamp = match fs::metadata(&self.path) {
+            Ok(metadata) => Self::stamp(&metadata),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => return Err(error.into()),
+        };
+        if stamp.is_none() || stamp != self.loaded {
+            self.load(false)?;
+        }
+        Ok(())
+    }
+
+    /// Read the file, `locked` saying whether the caller holds the lock
+    fn load(&mut self, locked: bool) -> Result<()> {
+        // Stamped before reading, so that a write in between is picked up by the next reload
+        // rather than missed
+        self.loaded = fs::metadata(&self.path)
+            .ok()
+            .and_then(|metadata| Self::stamp(&metadata));
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error.into()),
+        };
+
+        self.raw = if contents.trim().is_empty() {
+            Map::new()
+        } else {
+            match serde_json::from_str(&contents) {
+                Ok(raw) => raw,
+                // Checked again under the lock, in case another process was replacing the file
+                Err(_) if !locked => {
+                    let _lock = self.lock()?;
+                    return self.load(true);
+                }
+                // A file that doesn't parse, e.g. one edited by hand, is moved aside rather than
+                // making every tool fail, or being overwritten along with every session in it
+                Err(error) => {
+                    let aside = self.set_aside()?;
+                    log::warn!(
+                        "{} does not parse ({error}), so fs-mcp moved it to {} and started \
+                         with no sessions",
+                        self.path.display(),
+                        aside.display()
+                    );
+                    Map::new()
+                }
+            }
+        };
+        self.sessions = self
+            .raw
+            .iter()
+            .filter_map(|(session_id, value)| {
+                let entry = serde_json::from_value(value.clone()).ok()?;
+                Some((session_id.clone(), entry))
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Move the file out of the way, to a sibling named after it and the current time
+    fn set_aside(&mut self) -> Result<PathBuf> {
+        let seconds = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".corrupt-{seconds}"));
+        let aside = self.path.with_file_name(name);
+        fs::rename(&self.path, &aside)
+            .with_context(|| format!("could not move {} aside", self.path.display()))?;
+        self.loaded = None;
+        Ok(aside)
+    }
+}
@@ -0,0 +1,255 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use size::{Size, fmt::Base};
+use std::{
+    fmt::{self, Display, Formatter as FmtFormatter},
+    path::Path,
+    time::SystemTime,
+};
+use timeago::{BoxedLanguage, Formatter, languages::IsolangLanguage};
+
+/// Units used when displaying file sizes
+#[derive(
+    Debug, Default, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeUnits {
+    /// Powers of 1024: KiB, MiB, GiB
+    #[default]
+    Binary,
+    /// Powers of 1000: KB, MB, GB
+    Decimal,
+    /// Exact byte counts
+    Bytes,
+}
+
+impl Display for SizeUnits {
+    fn fmt(&self, f: &mut FmtFormatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Binary => "binary",
+            Self::Decimal => "decimal",
+            Self::Bytes => "bytes",
+        })
+    }
+}
+
+/// How points in time are displayed
+#[derive(
+    Debug, Default, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Timestamps {
+    /// Relative to now, like "3 hours ago"
+    #[default]
+    Relative,
+    /// Absolute UTC time, like "2024-01-15T09:30:00Z"
+    Iso8601,
+    /// Relative followed by absolute, like "3 hours ago (2024-01-15T09:30:00Z)"
+    Both,
+}
+
+impl Display for Timestamps {
+    fn fmt(&self, f: &mut FmtFormatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Relative => "relative",
+            Self::Iso8601 => "iso8601",
+            Self::Both => "both",
+        })
+    }
+}
+
+/// How much detail tool responses include
+#[derive(
+    Debug, Default, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Verbosity {
+    /// Minimal confirmation
+    Quiet,
+    /// Confirmation with sizes and context
+    #[default]
+    Normal,
+    /// Everything in normal, plus diffs, metadata, and hints
+    Detailed,
+}
+
+impl Display for Verbosity {
+    fn fmt(&self, f: &mut FmtFormatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Quiet => "quiet",
+            Self::Normal => "normal",
+            Self::Detailed => "detailed",
+        })
+    }
+}
+
+/// Formats points in time according to session preferences
+pub struct TimestampFormatter {
+    style: Timestamps,
+    age: Formatter<BoxedLanguage>,
+}
+
+impl TimestampFormatter {
+    pub fn format(&self, time: SystemTime) -> String {
+        let relative = || self.age.convert(time.elapsed().unwrap_or_default());
+        let absolute = || humantime::format_rfc3339_seconds(time).to_string();
+        match self.style {
+            Timestamps::Relative => relative(),
+            Timestamps::Iso8601 => absolute(),
+            Timestamps::Both => format!("{} ({})", relative(), absolute()),
+        }
+    }
+}
+
+/// Session-level preferences, persisted across calls
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct SessionSettings {
+    /// ISO 639-1 language code for relative times
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+
+    /// Units for file sizes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_units: Option<SizeUnits>,
+
+    /// How points in time are displayed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamps: Option<Timestamps>,
+
+    /// Default response detail for tools that accept `verbosity`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verbosity: Option<Verbosity>,
+
+    /// Whether tools that accept `create_directories` create missing parent directories
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_create_directories: Option<bool>,
+
+    /// Glob patterns for generated files that write refuses to change without
+    /// `allow_generated`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated_patterns: Option<Vec<String>>,
+
+    /// Whether `$VAR` and `${VAR}` in paths expand to environment variables when no path alias
+    /// has that name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expand_env_vars: Option<bool>,
+
+    /// Whether a path that doesn't exist resolves to the one existing path that matches it
+    /// case-insensitively, as it would on a case-insensitive filesystem
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case_insensitive_paths: Option<bool>,
+
+    /// Whether high-risk calls are held as pending operations until confirm_operation is
+    /// called with their token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmation_mode: Option<bool>,
+
+    /// In confirmation mode, overwrites of files larger than this many KiB are held
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirm_overwrite_above_kb: Option<u64>,
+}
+
+/// Files larger than this many KiB are only overwritten after confirmation, in confirmation mode
+pub const DEFAULT_CONFIRM_OVERWRITE_ABOVE_KB: u64 = 64;
+
+/// Files that are almost always generated by a tool rather than edited by hand
+pub const DEFAULT_GENERATED_PATTERNS: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "*.pb.go",
+    "*/generated/*",
+];
+
+/// Look up a timeago language by ISO 639-1 code
+pub fn language(locale: &str) -> Option<BoxedLanguage> {
+    IsolangLanguage::from_639_1(&locale.to_lowercase()).and_then(timeago::from_isolang)
+}
+
+impl SessionSettings {
+    pub fn size_units(&self) -> SizeUnits {
+        self.size_units.unwrap_or_default()
+    }
+
+    pub fn timestamps(&self) -> Timestamps {
+        self.timestamps.unwrap_or_default()
+    }
+
+    /// The per-call verbosity if provided, otherwise the session default
+    pub fn verbosity(&self, per_call: Option<Verbosity>) -> Verbosity {
+        per_call.or(self.verbosity).unwrap_or_default()
+    }
+
+    /// The per-call `create_directories` if provided, otherwise the session default
+    pub fn create_directories(&self, per_call: Option<bool>) -> bool {
+        per_call.or(self.auto_create_directories).unwrap_or(true)
+    }
+
+    pub fn expand_env_vars(&self) -> bool {
+        self.expand_env_vars.unwrap_or_default()
+    }
+
+    pub fn case_insensitive_paths(&self) -> bool {
+        self.case_insensitive_paths.unwrap_or_default()
+    }
+
+    pub fn confirmation_mode(&self) -> bool {
+        self.confirmation_mode.unwrap_or_default()
+    }
+
+    pub fn confirm_overwrite_above_kb(&self) -> u64 {
+        self.confirm_overwrite_above_kb
+            .unwrap_or(DEFAULT_CONFIRM_OVERWRITE_ABOVE_KB)
+    }
+
+    pub fn generated_patterns(&self) -> Vec<String> {
+        self.generated_patterns.clone().unwrap_or_else(|| {
+            DEFAULT_GENERATED_PATTERNS
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect()
+        })
+    }
+
+    /// The first generated-file pattern that `path` matches. Patterns containing a `/` are
+    /// matched against the whole path, others against the file name
+    pub fn generated_pattern(&self, path: &Path) -> Option<String> {
+        let file_name = path.file_name()?;
+        self.generated_patterns().into_iter().find(|pattern| {
+            let Ok(glob) = glob::Pattern::new(pattern) else {
+                return false;
+            };
+            if pattern.contains('/') {
+                glob.matches_path(path)
+            } else {
+                glob.matches_path(Path::new(file_name))
+            }
+        })
+    }
+
+    pub fn locale(&self) -> &str {
+        self.locale.as_deref().unwrap_or("en")
+    }
+
+    /// Format a byte count according to the configured size units
+    pub fn format_size(&self, bytes: u64) -> String {
+        let size = Size::from_bytes(bytes);
+        match self.size_units() {
+            SizeUnits::Binary => size.format().with_base(Base::Base2).to_string(),
+            SizeUnits::Decimal => size.format().with_base(Base::Base10).to_string(),
+            SizeUnits::Bytes => format!("{bytes} bytes"),
+        }
+    }
+
+    /// Build a timestamp formatter in the configured style and locale
+    pub fn timestamp_formatter(&self) -> TimestampFormatter {
+        TimestampFormatter {
+            style: self.timestamps(),
+            age: Formatter::with_language(
+                language(self.locale())
+                    .unwrap_or_else(|| timeago::languages::boxup(timeago::English)),
+            ),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Copy-on-write overlay used by `fs-mcp serve --simulate`.
+//!
+//! Tools that change files get paths in a scratch directory instead of the real ones, and what
+//! they are about to change is copied there first, one file at a time, so the real tree is never
+//! modified. Tools that only read get the real path, unless the simulation has changed something
+//! at or beneath it. The overlay keeps an index in memory of the real paths it has taken over:
+//! its copy is authoritative for them and everything beneath them, and a taken-over path missing
+//! from the scratch directory is a whiteout, which is how simulated deletions stay deleted.
+
+use ignore::{DirEntry, WalkBuilder};
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+/// A change the simulation made relative to the real tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimulatedChange {
+    Added(PathBuf),
+    Modified(PathBuf),
+    Deleted(PathBuf),
+}
+
+/// How the tool resolving a path is going to use it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// A directory is only copied into the overlay if it has at most this many files
+const MAX_COPIED_FILES: usize = 10_000;
+
+/// A directory is only copied into the overlay if its files total at most this many bytes
+const MAX_COPIED_BYTES: u64 = 512 * 1024 * 1024;
+
+#[derive(Debug)]
+pub struct Overlay {
+    root: PathBuf,
+    /// Real paths whose overlay copy is authoritative, along with everything beneath them
+    materialized: BTreeSet<PathBuf>,
+}
+
+impl Overlay {
+    pub fn new() -> io::Result<Self> {
+        let root = std::env::temp_dir().join(format!("fs-mcp-simulation-{}", std::process::id()));
+        if root.exists() {
+            fs::remove_dir_all(&root)?;
+        }
+        fs::create_dir_all(&root)?;
+        Ok(Self {
+            root,
+            materialized: BTreeSet::new(),
+        })
+    }
+
+    /// Where the overlay keeps its copy of the absolute path `real`
+    fn overlay_path(&self, real: &Path) -> PathBuf {
+        let mut overlay = self.root.clone();
+        for component in real.components() {
+            if let Component::Normal(name) = component {
+                overlay.push(name);
+            }
+        }
+        overlay
+    }
+
+    /// The real path an overlay path stands for, or `path` itself if it isn't in the overlay
+    pub fn real_path(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(&self.root) {
+            Ok(relative) => Path::new("/").join(relative),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+
+    fn is_materialized(&self, real: &Path) -> bool {
+        real.ancestors()
+            .any(|ancestor| self.materialized.contains(ancestor))
+    }
+
+    /// Whether the simulation has changed anything beneath the directory `real`
+    fn has_changes_beneath(&self, real: &Path) -> bool {
+        self.materialized
+            .range(real.to_path_buf()..)
+            .next()
+            .is_some_and(|materialized| materialized.starts_with(real))
+    }
+
+    /// Map an absolute path, real or already in the overlay, to where a tool should find it,
+    /// along with a note when simulated changes can't be shown there.
+    ///
+    /// Writes always go to the overlay, copying what is on disk at the path first. Reads go to
+    /// the real tree unless the overlay has taken the path over, or has changes beneath it, in
+    /// which case the directory is copied over so that the tool sees them
+    pub fn map(&mut self, path: &Path, access: Access) -> io::Result<(PathBuf, Option<String>)> {
+        let real = self.real_path(path);
+        let overlay = self.overlay_path(&real);
+        if self.is_materialized(&real) {
+            return Ok((overlay, None));
+        }
+        if access == Access::Read && !self.has_changes_beneath(&real) {
+            return Ok((real, None));
+        }
+
+        match self.materialize(&real) {
+            Ok(()) => Ok((overlay, None)),
+            Err(error) if access == Access::Read && error.kind() == io::ErrorKind::FileTooLarge => {
+                let note = format!(
+                    "{} is too large to merge with the simulated changes beneath it, so this call \
+                     sees it as it is on disk",
+                    real.display()
+                );
+                Ok((real, Some(note)))
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Copy what is on disk at `real` into the overlay and take the path over
+    fn materialize(&mut self, real: &Path) -> io::Result<()> {
+        if real.is_dir() {
+            self.check_size(real)?;
+        }
+
+        // Recreate the ancestors that exist on disk, so that tools see the same missing
+        // directories as they would without the simulation
+        for ancestor in real.ancestors().skip(1) {
+            if ancestor.is_dir() {
+                fs::create_dir_all(self.overlay_path(ancestor))?;
+                break;
+            }
+        }
+
+        if real.is_dir() {
+            self.copy_directory(real)?;
+        } else if real.is_file() {
+            fs::copy(real, self.overlay_path(real))?;
+        }
+        self.materialized.insert(real.to_path_buf());
+        Ok(())
+    }
+
+    /// Fail with `FileTooLarge` if the directory `real` is too large to copy
+    fn check_size(&self, real: &Path) -> io::Result<()> {
+        let (mut files, mut bytes) = (0, 0);
+        for entry in walk(real, self.skipped()) {
+            if entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+            {
+                files += 1;
+                bytes += entry.metadata().map_or(0, |metadata| metadata.len());
+            }
+            if files > MAX_COPIED_FILES || bytes > MAX_COPIED_BYTES {
+                return Err(io::Error::new(
+                    io::ErrorKind::FileTooLarge,
+                    format!(
+                        "{} has more than {MAX_COPIED_FILES} files or 512 MiB, too much to copy \
+                         into the simulation",
+                        real.display()
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Paths a copy leaves alone: the overlay itself, and what it has already taken over
+    fn skipped(&self) -> Vec<PathBuf> {
+        let mut skipped = self.materialized.iter().cloned().collect::<Vec<_>>();
+        skipped.push(self.root.clone());
+        skipped
+    }
+
+    /// Copy a directory into the overlay, except for anything the overlay already owns
+    fn copy_directory(&self, real: &Path) -> io::Result<()> {
+        fs::create_dir_all(self.overlay_path(real))?;
+        for entry in walk(real, self.skipped()) {
+            let overlay = self.overlay_path(entry.path());
+            match entry.file_type() {
+                Some(file_type) if file_type.is_dir() => fs::create_dir_all(&overlay)?,
+                Some(file_type) if file_type.is_file() => {
+                    fs::copy(entry.path(), &overlay)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the overlay's location from `text`, so that responses name real paths
+    pub fn scrub(&self, text: &str) -> String {
+        text.replace(&*self.root.to_string_lossy(), "")
+    }
+
+    /// Every file the simulation added, modified, or deleted, in path order
+    pub fn changes(&self) -> io::Result<Vec<SimulatedChange>> {
+        let mut changes = vec![];
+        for materialized in &self.materialized {
+            let overlay = self.overlay_path(materialized);
+            let mut real_files = files_under(materialized, vec![self.root.clone()]);
+            let mut overlay_files = files_under(&overlay, vec![])
+                .into_iter()
+                .map(|path| self.real_path(&path))
+                .collect::<Vec<_>>();
+            real_files.sort();
+            overlay_files.sort();
+
+            for file in &overlay_files {
+                if real_files.binary_search(file).is_err() {
+                    changes.push(SimulatedChange::Added(file.clone()));
+                } else if fs::read(file)? != fs::read(self.overlay_path(file))? {
+                    changes.push(SimulatedChange::Modified(file.clone()));
+                }
+            }
+            for file in real_files {
+                if overlay_files.binary_search(&file).is_err() {
+                    changes.push(SimulatedChange::Deleted(file));
+                }
+            }
+        }
+
+        changes.sort_by(|a, b| change_path(a).cmp(change_path(b)));
+        changes.dedup();
+        Ok(changes)
+    }
+
+    /// The overlay copy of a real file
+    pub fn simulated(&self, real: &Path) -> PathBuf {
+        self.overlay_path(real)
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn change_path(change: &SimulatedChange) -> &Path {
+    match change {
+        SimulatedChange::Added(path)
+        | SimulatedChange::Modified(path)
+        | SimulatedChange::Deleted(path) => path,
+    }
+}
+
+/// Everything under `path`, including ignored and hidden files, skipping the `skipped` paths
+fn walk(path: &Path, skipped: Vec<PathBuf>) -> impl Iterator<Item = DirEntry> {
+    WalkBuilder::new(path)
+        .standard_filters(false)
+        .filter_entry(move |entry| !skipped.iter().any(|skip| entry.path().starts_with(skip)))
+        .build()
+        .flatten()
+}
+
+/// The files under `path`, or `path` itself if it is a file
+fn files_under(path: &Path, skipped: Vec<PathBuf>) -> Vec<PathBuf> {
+    walk(path, skipped)
+        .filter(|entry| {
+            entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+        })
+        .map(|entry| entry.into_path())
+        .collect()
+}
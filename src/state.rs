@@ -1,9 +1,29 @@
-use std::path::{Component, Path, PathBuf};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    convert::Infallible,
+    path::{Component, Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
 
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use mcplease::session::SessionStore;
+use mcplease::traits::Tool;
+
+use crate::{
+    budget::{Limits, ResponseBudget},
+    config::Config,
+    deadline::Deadline,
+    hints::{self, ErrorCode, Hint, Hinted},
+    path_locks::{self, PathLocks},
+    quota::{ChangeRate, Quota, QuotaUsage},
+    read_cache::ReadCache,
+    session_store::SessionStore,
+    settings::SessionSettings,
+    simulation::{Access, Overlay},
+    tools::Audited,
+};
 
 /// Shared context data that can be used across multiple MCP servers
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
@@ -12,12 +32,321 @@ pub struct SharedContextData {
     pub context_path: Option<PathBuf>,
 }
 
+/// Session data specific to fs-mcp, kept apart from the shared context so that other servers
+/// rewriting the shared file cannot drop fields they don't know about
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct FsSessionData {
+    /// Session-level preferences
+    #[serde(default)]
+    pub settings: SessionSettings,
+
+    /// The session this one was created from with create_child_session, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+
+    /// Recent searches, most recent last, so that a follow-up search can be refined to the files
+    /// they matched
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub searches: Vec<SearchRecord>,
+
+    /// Named absolute paths, expanded as `$NAME`, `${NAME}`, or a leading `@NAME` in any path
+    /// argument
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub path_aliases: BTreeMap<String, PathBuf>,
+
+    /// Files moved into the staging area by `delete` with `stage`, awaiting purge or unstage
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub staged: Vec<StagedEntry>,
+
+    /// Limits on filesystem changes and how much of them has been used
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub quota: Quota,
+
+    /// Recent tool calls, most recent last, for session_activity
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub activity: Vec<ActivityEntry>,
+
+    /// Snapshots of small files deleted permanently, most recent last, for restore_deleted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub journal: Vec<JournalEntry>,
+
+    /// Calls and response sizes per tool, for usage_stats
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub usage: BTreeMap<String, ToolUsage>,
+
+    /// Recent response sizes, which the default limits of some tools adapt to
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub response_budget: ResponseBudget,
+
+    /// Previous working directories, most recent last, for previous_working_directory
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub directory_history: Vec<PathBuf>,
+
+    /// Named places in files to come back to, from add_bookmark
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bookmarks: BTreeMap<String, Bookmark>,
+
+    /// High-risk calls held in confirmation mode, oldest first, awaiting confirm_operation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pending_operations: Vec<PendingOperation>,
+}
+
+/// How much one tool has been used in a session
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct ToolUsage {
+    pub calls: u64,
+    pub errors: u64,
+    /// Total bytes of responses, including error messages
+    pub response_bytes: u64,
+    pub largest_response: u64,
+}
+
+/// A file held in the staging area instead of being deleted
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StagedEntry {
+    pub id: String,
+    /// Where the file was before it was staged
+    pub original: PathBuf,
+    /// Where the file is now
+    pub staged: PathBuf,
+    pub staged_at: SystemTime,
+}
+
+/// The files matched by a previous search
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SearchRecord {
+    pub id: String,
+    pub pattern: String,
+    pub files: Vec<PathBuf>,
+    /// Paths not yet searched because the search ran out of time or reached `max_results`, in
+    /// the order they would have been visited
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pending: Vec<PathBuf>,
+    /// The options that decide what matches, which resuming the search has to repeat
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub options: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A tool call recorded in a session's activity log
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ActivityEntry {
+    pub at: SystemTime,
+    pub tool: String,
+    /// The call's arguments as JSON, shortened if long
+    pub arguments: String,
+    /// The first line of the error, if the call failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A file, or a line in one, saved under a name with add_bookmark
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Bookmark {
+    pub path: PathBuf,
+    /// 1-based
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// The text of `line` when bookmarked, to find it again after edits above it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub created_at: SystemTime,
+}
+
+impl Bookmark {
+    /// `path:line`, or the path of a whole-file bookmark
+    pub fn location(&self) -> String {
+        match self.line {
+            Some(line) => format!("{}:{line}", self.path.display()),
+            None => self.path.display().to_string(),
+        }
+    }
+}
+
+/// A copy of a deleted file kept in the undo journal
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub id: String,
+    /// Where the file was deleted from
+    pub original: PathBuf,
+    /// Where the copy is kept
+    pub snapshot: PathBuf,
+    /// Hex-encoded SHA-256 of the contents
+    pub sha256: String,
+    pub size: u64,
+    pub deleted_at: SystemTime,
+}
+
+/// A high-risk call held in confirmation mode until confirm_operation is called with its token
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PendingOperation {
+    pub token: String,
+    /// The held call, as `{"name": ..., "arguments": ...}`
+    pub call: serde_json::Value,
+    /// What the call would do, e.g. "overwrite /project/data.json (120 KiB)"
+    pub description: String,
+    /// The working directory when the call was made, which its relative paths depend on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_path: Option<PathBuf>,
+    pub created_at: SystemTime,
+}
+
+/// How many searches are kept for refinement
+const MAX_SEARCH_RECORDS: usize = 10;
+
+/// Number of tool calls kept in each session's activity log
+const MAX_ACTIVITY_ENTRIES: usize = 200;
+
+/// Number of previous working directories kept for each session
+const MAX_DIRECTORY_HISTORY: usize = 20;
+
+/// Number of pending operations kept for each session before the oldest are discarded
+const MAX_PENDING_OPERATIONS: usize = 20;
+
 /// Filesystem tools with session support
 #[derive(Debug, fieldwork::Fieldwork)]
 pub struct FsTools {
     /// Shared context store for cross-server communication
     #[fieldwork(get, get_mut)]
     shared_context_store: SessionStore<SharedContextData>,
+
+    /// Store for fs-mcp specific session data
+    #[fieldwork(get, get_mut)]
+    session_store: SessionStore<FsSessionData>,
+
+    /// Directory for files fs-mcp keeps on behalf of sessions, such as staged deletions
+    #[fieldwork(get)]
+    data_dir: PathBuf,
+
+    /// The session this server acts for, from `FS_MCP_SESSION`, or "default"
+    session_id: &'static str,
+
+    /// Whether this process is serving MCP, as opposed to running one tool from a terminal
+    #[fieldwork(get)]
+    serving: bool,
+
+    /// The overlay every path is redirected into when serving with `--simulate`
+    #[fieldwork(get)]
+    simulation: Option<Overlay>,
+
+    /// Whether only read-only tools may be called, when serving with `--read-only`
+    #[fieldwork(get)]
+    read_only: bool,
+
+    /// The longest any call may walk the filesystem, when serving with `--timeout-ms`
+    #[fieldwork(get)]
+    timeout: Option<Duration>,
+
+    /// Contents of the files read so far, to notice when a file is read again unchanged
+    #[fieldwork(get_mut)]
+    read_cache: ReadCache,
+
+    /// When the recent calls to tools that change files were made, for `max_changes_per_minute`
+    #[fieldwork(get_mut)]
+    change_rate: ChangeRate,
+
+    /// Paths resolved differently than written during the current call, e.g. by
+    /// `case_insensitive_paths`, as notes for its response
+    resolution_notes: Vec<String>,
+
+    /// Working directory of the current call, from its `cwd` argument, used in place of the
+    /// session's
+    call_cwd: Option<PathBuf>,
+
+    /// Whether the current call is to a tool that changes files, which decides whether the
+    /// simulation copies what it resolves into the overlay
+    call_changes_files: bool,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// Replace a leading `@name` component with the alias it names. Unknown names are left as-is,
+/// since `@` is also valid at the start of a file name
+fn expand_short_alias<'a>(path_str: &'a str, aliases: &BTreeMap<String, PathBuf>) -> Cow<'a, str> {
+    let Some(rest) = path_str.strip_prefix('@') else {
+        return Cow::Borrowed(path_str);
+    };
+    let (name, remainder) = rest.split_at(rest.find(['/', '\\']).unwrap_or(rest.len()));
+    match aliases.get(name) {
+        Some(path) => Cow::Owned(format!("{}{remainder}", path.display())),
+        None => Cow::Borrowed(path_str),
+    }
+}
+
+/// Tidy a path the way Windows clients send them: `/` and `\` mixed, lowercase drive letters,
+/// and `\\?\` extended-length prefixes, which would otherwise end up in joined and displayed
+/// paths. The standard library adds the prefix back where a path is long enough to need it
+pub(crate) fn normalize_windows_path(path_str: &str) -> Cow<'_, str> {
+    let mut path = path_str.replace('/', "\\");
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        path = format!(r"\\{rest}");
+    } else if let Some(rest) = path.strip_prefix(r"\\?\") {
+        path = rest.to_string();
+    }
+    if path.as_bytes().get(1) == Some(&b':') && path.as_bytes()[0].is_ascii_alphabetic() {
+        path[..1].make_ascii_uppercase();
+    }
+    if path == path_str {
+        Cow::Borrowed(path_str)
+    } else {
+        Cow::Owned(path)
+    }
+}
+
+/// Replace a leading `~user` with that user's home directory. Unknown users are left as-is
+fn expand_other_user(path_str: &str) -> Cow<'_, str> {
+    let Some(rest) = path_str.strip_prefix('~') else {
+        return Cow::Borrowed(path_str);
+    };
+    let (user, remainder) = rest.split_at(rest.find(['/', '\\']).unwrap_or(rest.len()));
+    if user.is_empty() {
+        return Cow::Borrowed(path_str);
+    }
+    match user_home(user) {
+        Some(home) => Cow::Owned(format!("{}{remainder}", home.display())),
+        None => Cow::Borrowed(path_str),
+    }
+}
+
+/// The home directory of `user`, from the user database
+#[cfg(unix)]
+fn user_home(user: &str) -> Option<PathBuf> {
+    use std::{
+        ffi::{CStr, CString, OsStr},
+        os::unix::ffi::OsStrExt,
+    };
+    let name = CString::new(user).ok()?;
+    // SAFETY: passwd is plain data, and getpwnam_r fills it in before it is read
+    let mut passwd = unsafe { std::mem::zeroed::<libc::passwd>() };
+    let mut buffer = vec![0 as libc::c_char; 16 * 1024];
+    let mut result = std::ptr::null_mut();
+    // SAFETY: every pointer is valid for the duration of the call, and the strings in `passwd`
+    // point into `buffer`, which outlives their use below
+    let status = unsafe {
+        libc::getpwnam_r(
+            name.as_ptr(),
+            &mut passwd,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        )
+    };
+    if status != 0 || result.is_null() || passwd.pw_dir.is_null() {
+        return None;
+    }
+    // SAFETY: checked for null above, and getpwnam_r nul-terminates it
+    let home = unsafe { CStr::from_ptr(passwd.pw_dir) };
+    Some(PathBuf::from(OsStr::from_bytes(home.to_bytes())))
+}
+
+/// The home directory of `user`, assumed to be beside the current user's
+#[cfg(not(unix))]
+fn user_home(user: &str) -> Option<PathBuf> {
+    let home = dirs::home_dir()?.parent()?.join(user);
+    home.is_dir().then_some(home)
 }
 
 fn normalize_path(path: &Path) -> PathBuf {
@@ -48,65 +377,777 @@ fn normalize_path(path: &Path) -> PathBuf {
 }
 
 impl FsTools {
-    /// Create a new FsTools instance
+    /// Create a new FsTools instance for the `fs-mcp` binary, keeping sessions in
+    /// `~/.ai-tools`, where they are shared with other servers
     pub fn new() -> Result<Self> {
+        let ai_tools_dir = dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(".ai-tools");
+
+        // The same check mcplease uses to decide whether to serve
+        let serving = std::env::args().nth(1).as_deref() == Some("serve");
+
+        let config = match serving {
+            true => Self::flag_value(std::env::args().skip(2), "--config")
+                .map(|path| PathBuf::from(&*shellexpand::tilde(&path))),
+            false => None,
+        };
+        Config::init(config)?;
+        let mut fs_tools = Self::build(ai_tools_dir, serving)?;
+
+        // mcplease ignores arguments after `serve`, so the flag is ours to read
+        if serving && std::env::args().skip(2).any(|arg| arg == "--simulate") {
+            fs_tools.start_simulation()?;
+        }
+        fs_tools.read_only = serving && std::env::args().skip(2).any(|arg| arg == "--read-only");
+        if serving {
+            fs_tools.timeout = Self::timeout_arg(std::env::args().skip(2))?;
+        }
+
+        Ok(fs_tools)
+    }
+
+    /// Create an FsTools instance for embedding, keeping sessions and staged files in `dir`.
+    /// Calls are treated as coming from an agent, so e.g. quotas can only be tightened
+    pub fn with_dir(dir: impl Into<PathBuf>) -> Result<Self> {
+        Self::build(dir.into(), true)
+    }
+
+    fn build(ai_tools_dir: PathBuf, serving: bool) -> Result<Self> {
+        let sessions_dir = ai_tools_dir.join("sessions");
+
         // Use shared context store for cross-server communication
-        let mut shared_path = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-        shared_path.push(".ai-tools");
-        shared_path.push("sessions");
-        shared_path.push("shared-context.json");
+        let shared_context_store = SessionStore::new(sessions_dir.join("shared-context.json"))?;
+        let session_store = SessionStore::new(sessions_dir.join("fs-mcp.json"))?;
 
-        let shared_context_store = SessionStore::new(Some(shared_path))?;
+        // Leaked once per process, so that the id can be borrowed alongside the stores
+        let session_id = match std::env::var("FS_MCP_SESSION") {
+            Ok(session_id) if !session_id.is_empty() => Box::leak(session_id.into_boxed_str()),
+            _ => "default",
+        };
 
         Ok(Self {
             shared_context_store,
+            session_store,
+            data_dir: ai_tools_dir.join("fs-mcp"),
+            session_id,
+            serving,
+            simulation: None,
+            read_only: false,
+            timeout: None,
+            read_cache: ReadCache::default(),
+            change_rate: ChangeRate::default(),
+            resolution_notes: vec![],
+            call_cwd: None,
+            call_changes_files: false,
         })
     }
 
-    fn default_session_id(&self) -> &'static str {
-        "default"
+    /// The value of the last `--flag value` or `--flag=value` in `args`
+    fn flag_value(mut args: impl Iterator<Item = String>, flag: &str) -> Option<String> {
+        let mut value = None;
+        while let Some(arg) = args.next() {
+            if arg == flag {
+                value = args.next();
+            } else if let Some(rest) = arg
+                .strip_prefix(flag)
+                .and_then(|rest| rest.strip_prefix('='))
+            {
+                value = Some(rest.to_string());
+            }
+        }
+        value
+    }
+
+    /// The value of `--timeout-ms N` or `--timeout-ms=N`
+    fn timeout_arg(args: impl Iterator<Item = String>) -> Result<Option<Duration>> {
+        let Some(value) = Self::flag_value(args, "--timeout-ms") else {
+            return Ok(None);
+        };
+        match value.parse::<u64>() {
+            Ok(millis) if millis > 0 => Ok(Some(Duration::from_millis(millis))),
+            _ => Err(Hinted::new(
+                format!("--timeout-ms needs a positive number of milliseconds, not {value:?}"),
+                Some("e.g. `fs-mcp serve --timeout-ms 20000`".into()),
+            )
+            .into()),
+        }
+    }
+
+    /// The deadline of a call that walks the filesystem, from its `time_limit_ms` and the
+    /// server's `--timeout-ms`
+    pub(crate) fn deadline(&self, time_limit_ms: Option<u64>, default: Duration) -> Deadline {
+        Deadline::new(time_limit_ms, default, self.timeout)
+    }
+
+    /// Run a tool by name with JSON arguments, exactly as an MCP `tools/call` would, including
+    /// recording it in the session's activity log
+    pub fn call(&mut self, name: &str, arguments: serde_json::Value) -> Result<String> {
+        let tool: Audited = serde_json::from_value(serde_json::json!({
+            "name": name,
+            "arguments": arguments,
+        }))?;
+        tool.execute(self)
+    }
+
+    /// Redirect every path into a fresh overlay, and move to a child session so that nothing the
+    /// simulation does is remembered by the real one
+    pub(crate) fn start_simulation(&mut self) -> Result<()> {
+        let child: &'static str =
+            Box::leak(format!("{}-simulation", self.session_id).into_boxed_str());
+        self.create_child_session(child, None, BTreeMap::new())?;
+        self.session_id = child;
+        self.simulation = Some(Overlay::new()?);
+        Ok(())
+    }
+
+    /// Map a resolved path into the simulation's overlay, if simulating
+    fn simulate(&mut self, path: PathBuf) -> Result<PathBuf> {
+        let Some(overlay) = &mut self.simulation else {
+            return Ok(path);
+        };
+        let access = match self.call_changes_files {
+            true => Access::Write,
+            false => Access::Read,
+        };
+        let (path, note) = overlay.map(&path, access).hint_path(&path)?;
+        self.resolution_notes.extend(note);
+        Ok(path)
+    }
+
+    /// Whether a session with this id has been used or created
+    pub(crate) fn session_exists(&mut self, session_id: &str) -> Result<bool> {
+        Ok(self.session_store.get(session_id)?.is_some()
+            || self.shared_context_store.get(session_id)?.is_some())
+    }
+
+    /// Create `child` from the current session, copying its working directory, preferences, and
+    /// aliases, then applying `context_path` and `aliases` as overrides. Anything already stored
+    /// for `child` is replaced
+    pub(crate) fn create_child_session(
+        &mut self,
+        child: &str,
+        context_path: Option<PathBuf>,
+        aliases: BTreeMap<String, PathBuf>,
+    ) -> Result<FsSessionData> {
+        let parent = self.default_session_id();
+        let parent_data = self.session_store.get_or_create(parent)?.clone();
+        let mut path_aliases = parent_data.path_aliases;
+        path_aliases.extend(aliases);
+
+        let child_data = FsSessionData {
+            settings: parent_data.settings,
+            // Limits and what has been used carry over, so a child can't be used to escape them
+            quota: parent_data.quota,
+            parent: Some(parent.to_string()),
+            path_aliases,
+            ..FsSessionData::default()
+        };
+        self.session_store.set(child, child_data.clone())?;
+
+        let context_path = match context_path {
+            Some(context_path) => Some(context_path),
+            None => self.get_context(None)?,
+        };
+        self.shared_context_store
+            .update(child, |shared_data| shared_data.context_path = context_path)?;
+
+        Ok(child_data)
+    }
+
+    /// The session this instance acts for, from `FS_MCP_SESSION`, or "default"
+    pub fn default_session_id(&self) -> &'static str {
+        self.session_id
+    }
+
+    /// Resolve a path relative to session context if needed, expanding aliases, `~`, `~user`,
+    /// and environment variables if the session's `expand_env_vars` is on. With
+    /// `case_insensitive_paths`, a path that doesn't exist resolves to one that matches it
+    /// ignoring case
+    pub fn resolve_path(&mut self, path_str: &str, session_id: Option<&str>) -> Result<PathBuf> {
+        self.resolve(path_str, session_id, true)
+    }
+
+    /// Resolve a path that a tool may create, such as a destination, like [`Self::resolve_path`]
+    /// but never swapping it for an existing path that matches it ignoring case
+    pub(crate) fn resolve_new_path(
+        &mut self,
+        path_str: &str,
+        session_id: Option<&str>,
+    ) -> Result<PathBuf> {
+        self.resolve(path_str, session_id, false)
     }
 
-    /// Resolve a path relative to session context if needed
-    pub(crate) fn resolve_path(
+    fn resolve(
         &mut self,
         path_str: &str,
         session_id: Option<&str>,
+        must_exist: bool,
     ) -> Result<PathBuf> {
-        let path = PathBuf::from(&*shellexpand::tilde(path_str));
+        let session_id = session_id.unwrap_or_else(|| self.default_session_id());
 
-        if path.is_absolute() {
-            return Ok(normalize_path(&path));
+        let aliases = self.path_aliases(Some(session_id))?;
+        let settings = self.settings(Some(session_id))?;
+        let expand_env_vars = settings.expand_env_vars();
+        let path_str = match cfg!(windows) {
+            true => normalize_windows_path(path_str),
+            false => Cow::Borrowed(path_str),
+        };
+        let path_str = &*expand_short_alias(&path_str, &aliases);
+        let path_str = &*expand_other_user(path_str);
+        let home_dir = || dirs::home_dir().map(|home| home.to_string_lossy().into_owned());
+        // A path naming an unknown variable is used literally, since `$` is valid in file names
+        let expanded = shellexpand::full_with_context(path_str, home_dir, |name| {
+            Ok::<_, Infallible>(match aliases.get(name) {
+                Some(path) => Some(path.to_string_lossy().into_owned()),
+                None if expand_env_vars => std::env::var(name).ok(),
+                None => None,
+            })
+        })
+        .unwrap_or(path_str.into());
+        let path = PathBuf::from(&*expanded);
+
+        let path = if path.is_absolute() {
+            normalize_path(&path)
+        } else {
+            match self.get_context(Some(session_id))? {
+                Some(context) => normalize_path(&context.join(path)),
+                None => {
+                    let mut hint = "call set_working_directory with the project root, or \
+                                    provide an absolute path"
+                        .to_string();
+                    let suggestions = self.context_suggestions(session_id)?;
+                    if !suggestions.is_empty() {
+                        hint.push_str(". Likely project roots:");
+                        for (directory, reason) in suggestions {
+                            hint.push_str(&format!("\n  {} ({reason})", directory.display()));
+                        }
+                    }
+                    return Err(Hinted::new(
+                        format!("No working directory is set, so {path_str} cannot be resolved"),
+                        Some(hint),
+                    )
+                    .into());
+                }
+            }
+        };
+
+        let config = Config::global();
+        if !config.allows(&path) {
+            return Err(Hinted::new(
+                format!(
+                    "{} is outside of the sandbox roots set in the config file",
+                    path.display()
+                ),
+                Some(format!(
+                    "use a path under {}",
+                    config
+                        .sandbox_roots
+                        .iter()
+                        .map(|root| root.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(" or ")
+                )),
+            )
+            .with_code(ErrorCode::OutsideSandbox)
+            .with_path(&path)
+            .into());
         }
 
-        let session_id = session_id.unwrap_or_else(|| self.default_session_id());
+        let path = self.simulate(path)?;
+        if must_exist
+            && settings.case_insensitive_paths()
+            && std::fs::symlink_metadata(&path).is_err()
+        {
+            if let Some(matched) = hints::case_insensitive_match(&path) {
+                self.resolution_notes.push(format!(
+                    "{path_str} does not exist; using {}, which matches it ignoring case",
+                    matched.display()
+                ));
+                return Ok(matched);
+            }
+        }
+        Ok(path)
+    }
 
-        match self.get_context(Some(session_id))? {
-            Some(context) => Ok(normalize_path(&context.join(path_str))),
-            None => Err(anyhow!(
-                "Use set_working_directory first or provide an absolute path.",
-            )),
+    /// Use `cwd`, resolved against the session's working directory, as the working directory
+    /// until this is called again, or stop overriding it with None
+    pub(crate) fn set_call_cwd(&mut self, cwd: Option<&str>) -> Result<()> {
+        self.call_cwd = None;
+        let Some(cwd) = cwd else {
+            return Ok(());
+        };
+        let path = self.resolve_path(cwd, None)?;
+        if !path.is_dir() {
+            return Err(match path.exists() {
+                true => Hinted::new(
+                    format!("cwd is not a directory: {}", path.display()),
+                    Some("pass the directory the file is in".into()),
+                )
+                .with_code(ErrorCode::NotADirectory)
+                .with_path(&path)
+                .into(),
+                false => hints::not_found(&path),
+            });
         }
+        self.call_cwd = Some(path);
+        Ok(())
     }
 
-    /// Get context for a session
+    /// Set whether the current call is to a tool that changes files
+    pub(crate) fn set_call_changes_files(&mut self, changes_files: bool) {
+        self.call_changes_files = changes_files;
+    }
+
+    /// Take the notes on paths resolved differently than written since this was last called
+    pub(crate) fn take_resolution_notes(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.resolution_notes)
+    }
+
+    /// Directories a session without a working directory probably means, each with why it is
+    /// suggested: the process's current directory and its git repository, this session's
+    /// previous working directories, and the working directories of recently used sessions
+    pub(crate) fn context_suggestions(
+        &mut self,
+        session_id: &str,
+    ) -> Result<Vec<(PathBuf, String)>> {
+        const MAX_SUGGESTIONS: usize = 6;
+        let mut suggestions: Vec<(PathBuf, String)> = vec![];
+        let mut suggest = |directory: PathBuf, reason: String| {
+            if directory.is_dir()
+                && directory.parent().is_some()
+                && suggestions.len() < MAX_SUGGESTIONS
+                && !suggestions
+                    .iter()
+                    .any(|(existing, _)| *existing == directory)
+            {
+                suggestions.push((directory, reason));
+            }
+        };
+
+        if let Ok(current) = std::env::current_dir() {
+            if let Ok(root) = crate::git::toplevel(&current) {
+                suggest(
+                    root,
+                    "git repository of the server's current directory".into(),
+                );
+            }
+            suggest(current, "the server's current directory".into());
+        }
+        for directory in self.directory_history(Some(session_id))?.into_iter().rev() {
+            suggest(
+                directory,
+                "a previous working directory of this session".into(),
+            );
+        }
+        let formatter = self.settings(Some(session_id))?.timestamp_formatter();
+        for (id, last_used, shared) in self.shared_context_store.recent()? {
+            if let Some(directory) = shared.context_path {
+                let used = formatter.format(last_used);
+                suggest(
+                    directory,
+                    format!("working directory of session {id}, used {used}"),
+                );
+            }
+        }
+        Ok(suggestions)
+    }
+
+    /// Get context for a session. A call's `cwd` overrides the default session's
     pub fn get_context(&mut self, session_id: Option<&str>) -> Result<Option<PathBuf>> {
+        if let Some(cwd) = &self.call_cwd {
+            if session_id.is_none_or(|session_id| session_id == self.default_session_id()) {
+                return Ok(Some(cwd.clone()));
+            }
+        }
         let session_data = self
             .shared_context_store
             .get_or_create(session_id.unwrap_or_else(|| self.default_session_id()))?;
         Ok(session_data.context_path.clone())
     }
 
-    pub(crate) fn set_working_directory(
+    /// Set the working directory that relative paths are resolved against, adding the previous
+    /// one to the session's directory history
+    pub fn set_working_directory(&mut self, path: PathBuf, session_id: Option<&str>) -> Result<()> {
+        let session_id = session_id.unwrap_or_else(|| self.default_session_id());
+        if let Some(previous) = self
+            .get_context(Some(session_id))?
+            .filter(|previous| *previous != path)
+        {
+            self.session_store.update(session_id, |session_data| {
+                let history = &mut session_data.directory_history;
+                history.retain(|directory| *directory != previous && *directory != path);
+                history.push(previous);
+                let excess = history.len().saturating_sub(MAX_DIRECTORY_HISTORY);
+                history.drain(..excess);
+            })?;
+        }
+        self.shared_context_store.update(session_id, |shared_data| {
+            shared_data.context_path = Some(path)
+        })
+    }
+
+    /// Previous working directories of a session, most recent last
+    pub fn directory_history(&mut self, session_id: Option<&str>) -> Result<Vec<PathBuf>> {
+        let session_data = self
+            .session_store
+            .get_or_create(session_id.unwrap_or_else(|| self.default_session_id()))?;
+        Ok(session_data.directory_history.clone())
+    }
+
+    /// Bookmarks of a session, by name
+    pub fn bookmarks(&mut self, session_id: Option<&str>) -> Result<BTreeMap<String, Bookmark>> {
+        let session_data = self
+            .session_store
+            .get_or_create(session_id.unwrap_or_else(|| self.default_session_id()))?;
+        Ok(session_data.bookmarks.clone())
+    }
+
+    /// Save or, with `None`, remove a bookmark
+    pub(crate) fn set_bookmark(
+        &mut self,
+        session_id: Option<&str>,
+        name: &str,
+        bookmark: Option<Bookmark>,
+    ) -> Result<()> {
+        self.session_store.update(
+            session_id.unwrap_or_else(|| self.default_session_id()),
+            |session_data| match bookmark {
+                Some(bookmark) => {
+                    session_data.bookmarks.insert(name.to_string(), bookmark);
+                }
+                None => {
+                    session_data.bookmarks.remove(name);
+                }
+            },
+        )
+    }
+
+    /// Get the preferences for a session
+    pub fn settings(&mut self, session_id: Option<&str>) -> Result<SessionSettings> {
+        let session_data = self
+            .session_store
+            .get_or_create(session_id.unwrap_or_else(|| self.default_session_id()))?;
+        Ok(session_data.settings.clone())
+    }
+
+    /// Update the preferences for a session
+    pub(crate) fn update_settings(
+        &mut self,
+        session_id: Option<&str>,
+        fun: impl FnOnce(&mut SessionSettings),
+    ) -> Result<()> {
+        self.session_store.update(
+            session_id.unwrap_or_else(|| self.default_session_id()),
+            |session_data| fun(&mut session_data.settings),
+        )
+    }
+
+    /// The path aliases defined for a session
+    pub fn path_aliases(&mut self, session_id: Option<&str>) -> Result<BTreeMap<String, PathBuf>> {
+        let session_data = self
+            .session_store
+            .get_or_create(session_id.unwrap_or_else(|| self.default_session_id()))?;
+        Ok(session_data.path_aliases.clone())
+    }
+
+    /// Define or, with `None`, remove a path alias
+    pub(crate) fn set_path_alias(
         &mut self,
-        path: PathBuf,
         session_id: Option<&str>,
+        name: &str,
+        path: Option<PathBuf>,
     ) -> Result<()> {
-        self.shared_context_store.update(
+        self.session_store.update(
             session_id.unwrap_or_else(|| self.default_session_id()),
-            |shared_data| {
-                shared_data.context_path = Some(path);
+            |session_data| match path {
+                Some(path) => {
+                    session_data.path_aliases.insert(name.to_string(), path);
+                }
+                None => {
+                    session_data.path_aliases.remove(name);
+                }
             },
         )
     }
+
+    /// Directory that staged files for a session are moved into
+    pub(crate) fn staging_dir(&self, session_id: Option<&str>) -> PathBuf {
+        self.data_dir
+            .join("staged")
+            .join(session_id.unwrap_or_else(|| self.default_session_id()))
+    }
+
+    /// Files currently held in the staging area, oldest first
+    pub(crate) fn staged(&mut self, session_id: Option<&str>) -> Result<Vec<StagedEntry>> {
+        let session_data = self
+            .session_store
+            .get_or_create(session_id.unwrap_or_else(|| self.default_session_id()))?;
+        Ok(session_data.staged.clone())
+    }
+
+    /// Change the list of staged files
+    pub(crate) fn update_staged(
+        &mut self,
+        session_id: Option<&str>,
+        fun: impl FnOnce(&mut Vec<StagedEntry>),
+    ) -> Result<()> {
+        self.session_store.update(
+            session_id.unwrap_or_else(|| self.default_session_id()),
+            |session_data| fun(&mut session_data.staged),
+        )
+    }
+
+    /// Lock `paths` against changes by other fs-mcp processes sharing this data directory,
+    /// until the returned locks are dropped
+    pub(crate) fn lock_paths(&self, paths: &[&Path]) -> Result<PathLocks> {
+        path_locks::lock(&self.data_dir.join("locks"), paths)
+    }
+
+    /// Refuse a path under the working directory that goes through a symlink to somewhere outside
+    /// of it, so that changes can't unknowingly land in e.g. system directories. The last
+    /// component is only checked if `through_last`, for operations that follow it rather than
+    /// act on the link itself. Paths outside of the working directory were asked for explicitly,
+    /// and are not checked
+    pub(crate) fn check_symlinks(&mut self, path: &Path, through_last: bool) -> Result<()> {
+        let Some(context) = self.get_context(None)? else {
+            return Ok(());
+        };
+        let Ok(relative) = path.strip_prefix(&context) else {
+            return Ok(());
+        };
+        let root = context.canonicalize().unwrap_or_else(|_| context.clone());
+
+        let mut components = relative.components().collect::<Vec<_>>();
+        if !through_last {
+            components.pop();
+        }
+        let mut current = context.clone();
+        for component in components {
+            current.push(component);
+            let Ok(link) = std::fs::read_link(&current) else {
+                continue;
+            };
+            // A dangling link is judged by where it points, since writing through it would
+            // create its target
+            let target = current.canonicalize().unwrap_or_else(|_| {
+                normalize_path(&current.parent().unwrap_or(&context).join(link))
+            });
+            if !target.starts_with(&root) && !target.starts_with(&context) {
+                return Err(Hinted::new(
+                    format!(
+                        "{} is a symlink to {}, outside of the working directory. No filesystem \
+                         operation has been performed",
+                        current.display(),
+                        target.display()
+                    ),
+                    Some(
+                        "pass `follow_symlinks: true` if changing files through the link is \
+                         intended"
+                            .into(),
+                    ),
+                )
+                .with_code(ErrorCode::OutsideSandbox)
+                .with_path(path)
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Directory that undo journal snapshots for a session are kept in
+    pub(crate) fn journal_dir(&self, session_id: Option<&str>) -> PathBuf {
+        self.data_dir
+            .join("journal")
+            .join(session_id.unwrap_or_else(|| self.default_session_id()))
+    }
+
+    /// Snapshots in the undo journal, oldest first
+    pub(crate) fn journal(&mut self, session_id: Option<&str>) -> Result<Vec<JournalEntry>> {
+        let session_data = self
+            .session_store
+            .get_or_create(session_id.unwrap_or_else(|| self.default_session_id()))?;
+        Ok(session_data.journal.clone())
+    }
+
+    /// Change the undo journal
+    pub(crate) fn update_journal(
+        &mut self,
+        session_id: Option<&str>,
+        fun: impl FnOnce(&mut Vec<JournalEntry>),
+    ) -> Result<()> {
+        self.session_store.update(
+            session_id.unwrap_or_else(|| self.default_session_id()),
+            |session_data| fun(&mut session_data.journal),
+        )
+    }
+
+    /// Operations held in confirmation mode, oldest first
+    pub(crate) fn pending_operations(&mut self) -> Result<Vec<PendingOperation>> {
+        let session_data = self
+            .session_store
+            .get_or_create(self.default_session_id())?;
+        Ok(session_data.pending_operations.clone())
+    }
+
+    /// Hold an operation until it is confirmed, discarding the oldest beyond the limit
+    pub(crate) fn add_pending_operation(&mut self, operation: PendingOperation) -> Result<()> {
+        self.session_store
+            .update(self.default_session_id(), |session_data| {
+                let pending = &mut session_data.pending_operations;
+                pending.push(operation);
+                let excess = pending.len().saturating_sub(MAX_PENDING_OPERATIONS);
+                pending.drain(..excess);
+            })
+    }
+
+    /// Remove and return the pending operation with this token, if there is one
+    pub(crate) fn take_pending_operation(
+        &mut self,
+        token: &str,
+    ) -> Result<Option<PendingOperation>> {
+        let mut taken = None;
+        self.session_store
+            .update(self.default_session_id(), |session_data| {
+                let pending = &mut session_data.pending_operations;
+                if let Some(index) = pending.iter().position(|op| op.token == token) {
+                    taken = Some(pending.remove(index));
+                }
+            })?;
+        Ok(taken)
+    }
+
+    /// Remember the files matched by a search, evicting the oldest record if necessary
+    pub(crate) fn record_search(&mut self, record: SearchRecord) -> Result<()> {
+        self.session_store
+            .update(self.default_session_id(), |session_data| {
+                session_data.searches.push(record);
+                let excess = session_data
+                    .searches
+                    .len()
+                    .saturating_sub(MAX_SEARCH_RECORDS);
+                session_data.searches.drain(..excess);
+            })
+    }
+
+    /// The current session's quota
+    pub(crate) fn quota(&mut self) -> Result<Quota> {
+        let session_data = self
+            .session_store
+            .get_or_create(self.default_session_id())?;
+        Ok(session_data.quota)
+    }
+
+    /// Change the current session's quota
+    pub(crate) fn update_quota(&mut self, fun: impl FnOnce(&mut Quota)) -> Result<()> {
+        self.session_store
+            .update(self.default_session_id(), |session_data| {
+                fun(&mut session_data.quota)
+            })
+    }
+
+    /// Count a filesystem change against the current session's quota, failing without
+    /// counting it if that would exceed a limit. Call before making the change
+    pub(crate) fn charge_quota(&mut self, charge: QuotaUsage) -> Result<()> {
+        let mut quota = self.quota()?;
+        if quota.limits.is_empty() {
+            return Ok(());
+        }
+        quota.charge(charge, self.default_session_id())?;
+        self.update_quota(|stored| *stored = quota)
+    }
+
+    /// Count a call to a tool that changes files against the session's `max_changes_per_minute`,
+    /// failing without counting it if the limit has been reached. Call before running the tool
+    pub(crate) fn charge_change_rate(&mut self) -> Result<()> {
+        let limit = self.quota()?.limits.max_changes_per_minute;
+        let session_id = self.default_session_id();
+        self.change_rate.record(limit, Instant::now(), session_id)?;
+        Ok(())
+    }
+
+    /// Append a tool call to the current session's activity log
+    pub(crate) fn record_activity(
+        &mut self,
+        entry: ActivityEntry,
+        response_bytes: u64,
+    ) -> Result<()> {
+        self.session_store
+            .update(self.default_session_id(), |session_data| {
+                let usage = session_data.usage.entry(entry.tool.clone()).or_default();
+                usage.calls += 1;
+                usage.errors += u64::from(entry.error.is_some());
+                usage.response_bytes += response_bytes;
+                usage.largest_response = usage.largest_response.max(response_bytes);
+                session_data.response_budget.record(response_bytes);
+
+                session_data.activity.push(entry);
+                let excess = session_data
+                    .activity
+                    .len()
+                    .saturating_sub(MAX_ACTIVITY_ENTRIES);
+                session_data.activity.drain(..excess);
+            })
+    }
+
+    /// The activity log of any session, or None if there is no such session
+    pub(crate) fn activity(&mut self, session_id: &str) -> Result<Option<Vec<ActivityEntry>>> {
+        Ok(self
+            .session_store
+            .get(session_id)?
+            .map(|session_data| session_data.activity.clone()))
+    }
+
+    /// The session's response budget, which sets the default limits of tools whose responses
+    /// grow with what they find
+    pub(crate) fn response_budget(&mut self) -> Result<ResponseBudget> {
+        let session_data = self
+            .session_store
+            .get_or_create(self.default_session_id())?;
+        Ok(session_data.response_budget.clone())
+    }
+
+    /// The default limits of tools whose responses grow with what they find
+    pub(crate) fn limits(&mut self) -> Result<Limits> {
+        Ok(self.response_budget()?.limits())
+    }
+
+    /// The tool usage of any session, or None if there is no such session
+    pub(crate) fn usage(
+        &mut self,
+        session_id: &str,
+    ) -> Result<Option<BTreeMap<String, ToolUsage>>> {
+        Ok(self
+            .session_store
+            .get(session_id)?
+            .map(|session_data| session_data.usage.clone()))
+    }
+
+    pub(crate) fn reset_usage(&mut self, session_id: &str) -> Result<()> {
+        self.session_store
+            .update(session_id, |session_data| session_data.usage.clear())
+    }
+
+    /// Look up a previous search by its id
+    pub(crate) fn search_record(&mut self, id: &str) -> Result<SearchRecord> {
+        let session_data = self
+            .session_store
+            .get_or_create(self.default_session_id())?;
+        if let Some(record) = session_data.searches.iter().find(|record| record.id == id) {
+            return Ok(record.clone());
+        }
+
+        let recent = session_data
+            .searches
+            .iter()
+            .rev()
+            .map(|record| format!("{} (\"{}\")", record.id, record.pattern))
+            .collect::<Vec<_>>();
+        let suggestion = if recent.is_empty() {
+            "run a search without `refine` first".to_string()
+        } else {
+            format!("recent search ids are {}", recent.join(", "))
+        };
+        Err(Hinted::new(format!("Unknown search_id {id}"), Some(suggestion)).into())
+    }
 }
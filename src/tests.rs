@@ -1,7 +1,2474 @@
-use crate::tools::Tools;
+use crate::tools::{SetPermissions, Tools};
 use mcplease::traits::AsToolsList;
 
+/// A session whose working directory is a fresh `project` directory, keeping its data beside
+/// it in the returned temporary directory
+fn project_session() -> (tempfile::TempDir, std::path::PathBuf, crate::FsTools) {
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join("project");
+    std::fs::create_dir_all(&project).unwrap();
+    let mut fs_tools = crate::FsTools::with_dir(dir.path().join("data")).unwrap();
+    fs_tools
+        .call(
+            "set_working_directory",
+            serde_json::json!({ "path": project }),
+        )
+        .unwrap();
+    (dir, project, fs_tools)
+}
+
 #[test]
 fn schemars_dont_panic() {
     Tools::tools_list();
 }
+
+#[test]
+fn octal_modes() {
+    assert_eq!(
+        SetPermissions::apply_mode_spec("755", 0o644, false).unwrap(),
+        0o755
+    );
+    assert_eq!(
+        SetPermissions::apply_mode_spec("0644", 0o777, false).unwrap(),
+        0o644
+    );
+    assert!(SetPermissions::apply_mode_spec("789", 0o644, false).is_err());
+    assert!(SetPermissions::apply_mode_spec("17777", 0o644, false).is_err());
+}
+
+#[test]
+fn symbolic_modes() {
+    let apply = |spec, mode, is_dir| SetPermissions::apply_mode_spec(spec, mode, is_dir).unwrap();
+    assert_eq!(apply("u+x", 0o644, false), 0o744);
+    assert_eq!(apply("+x", 0o644, false), 0o755);
+    assert_eq!(apply("go-w", 0o666, false), 0o644);
+    assert_eq!(apply("a=r,u+w", 0o777, false), 0o644);
+    assert_eq!(apply("u=rwx,g=rx,o=", 0o000, false), 0o750);
+    assert_eq!(apply("a+X", 0o644, false), 0o644);
+    assert_eq!(apply("a+X", 0o644, true), 0o755);
+    assert_eq!(apply("u+x-w", 0o644, false), 0o544);
+    assert!(SetPermissions::apply_mode_spec("z+x", 0o644, false).is_err());
+    assert!(SetPermissions::apply_mode_spec("u+q", 0o644, false).is_err());
+    assert!(SetPermissions::apply_mode_spec("ux", 0o644, false).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn set_permissions_reports_modes_that_leave_a_file_unreadable() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::write(project.join("run.sh"), "echo hi\n").unwrap();
+
+    let output = fs_tools
+        .call(
+            "set_permissions",
+            json!({ "path": "run.sh", "mode": "100", "verbosity": "detailed" }),
+        )
+        .unwrap();
+    assert!(output.contains("Changed permissions"), "{output}");
+}
+
+#[test]
+fn not_found_suggests_closest_path() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("src/main.rs"), "").unwrap();
+
+    let error = crate::hints::not_found(&dir.path().join("src/mian.rs")).to_string();
+    assert!(error.ends_with(&format!(
+        "suggestion: did you mean {}?",
+        dir.path().join("src/main.rs").display()
+    )));
+
+    let error = crate::hints::not_found(&dir.path().join("src/unrelated/file.rs")).to_string();
+    assert!(error.contains("use list to see what it contains"));
+}
+
+#[test]
+fn name_distance_ranking() {
+    use crate::hints::name_distance;
+    assert_eq!(name_distance("README.MD", "README.md"), Some(0));
+    assert_eq!(name_distance("config.yml", "config.yaml"), Some(1));
+    assert_eq!(name_distance("lib.ts", "lib.rs"), Some(1));
+    assert_eq!(name_distance("mian.rs", "main.rs"), Some(2));
+    assert_eq!(name_distance("main.rs", "Cargo.toml"), None);
+}
+
+#[test]
+fn rotation_shifts_previous_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("out.log");
+    std::fs::write(&log, "third").unwrap();
+    std::fs::write(dir.path().join("out.log.1"), "second").unwrap();
+    std::fs::write(dir.path().join("out.log.2"), "first").unwrap();
+
+    let rotated = crate::tools::Write::rotate(&log, None).unwrap();
+    assert_eq!(rotated, dir.path().join("out.log.1"));
+    assert!(!log.exists());
+    let read = |name: &str| std::fs::read_to_string(dir.path().join(name)).unwrap();
+    assert_eq!(read("out.log.1"), "third");
+    assert_eq!(read("out.log.2"), "second");
+    assert_eq!(read("out.log.3"), "first");
+
+    // Only the newest rotations are kept
+    std::fs::write(&log, "fourth").unwrap();
+    crate::tools::Write::rotate(&log, Some(2)).unwrap();
+    assert_eq!(read("out.log.1"), "fourth");
+    assert_eq!(read("out.log.2"), "third");
+    assert!(!dir.path().join("out.log.3").exists());
+    assert!(!dir.path().join("out.log.4").exists());
+}
+
+#[test]
+fn line_numbers_are_aligned() {
+    let content = (1..=10).map(|n| format!("line {n}\n")).collect::<String>();
+    let numbered = crate::tools::Read::number_lines(&content, 1);
+    let lines = numbered.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 10);
+    assert_eq!(lines[0], " 1\tline 1");
+    assert_eq!(lines[9], "10\tline 10");
+}
+
+#[test]
+fn line_numbers_continue_from_offset() {
+    let numbered = crate::tools::Read::number_lines("a\nb\n", 99);
+    assert_eq!(numbered, " 99\ta\n100\tb");
+}
+
+#[test]
+fn dedent_removes_common_indentation() {
+    let dedent = crate::tools::Write::dedent_contents;
+    assert_eq!(
+        dedent("    fn main() {\n        todo!()\n\n    }\n"),
+        ("fn main() {\n    todo!()\n\n}\n".to_string(), 4)
+    );
+    assert_eq!(dedent("\tone\n\t\ttwo"), ("one\n\ttwo".to_string(), 1));
+    assert_eq!(dedent("one\n  two\n"), ("one\n  two\n".to_string(), 0));
+}
+
+#[test]
+fn code_fences_are_stripped() {
+    let strip = crate::tools::Write::strip_code_fence;
+    assert_eq!(
+        strip("```rust\nfn main() {}\n```\n"),
+        Some(("fn main() {}\n".to_string(), Some("rust".to_string())))
+    );
+    assert_eq!(
+        strip("~~~~\n```\nnested\n```\n~~~~"),
+        Some(("```\nnested\n```\n".to_string(), None))
+    );
+    assert_eq!(strip("```rust\nfn main() {}\n```\nmore text"), None);
+    assert_eq!(strip("fn main() {}\n"), None);
+}
+
+#[test]
+fn insert_lines_at_positions() {
+    let insert = crate::tools::Write::insert_lines;
+    assert_eq!(insert("a\nb\n", "x\n", 1).unwrap(), "x\na\nb\n");
+    assert_eq!(insert("a\nb\n", "x", 2).unwrap(), "a\nx\nb\n");
+    assert_eq!(insert("a\nb\n", "x\n", 3).unwrap(), "a\nb\nx\n");
+    assert_eq!(insert("a\nb", "x\n", 3).unwrap(), "a\nb\nx\n");
+    assert!(insert("a\nb\n", "x\n", 4).is_err());
+    assert!(insert("a\nb\n", "x\n", 0).is_err());
+}
+
+#[test]
+fn tidy_trims_whitespace_and_final_newlines() {
+    let tidy = crate::tools::Write::tidy_contents;
+    assert_eq!(tidy("a  \nb\t\n\n\n"), ("a\nb\n".to_string(), 3));
+    assert_eq!(tidy("a\r\nb \r\n"), ("a\r\nb\r\n".to_string(), 1));
+    assert_eq!(tidy("a\nb"), ("a\nb\n".to_string(), 1));
+    assert_eq!(tidy("a\nb\n"), ("a\nb\n".to_string(), 0));
+}
+
+#[test]
+fn generated_file_patterns() {
+    use std::path::Path;
+    let settings = crate::settings::SessionSettings::default();
+    assert_eq!(
+        settings.generated_pattern(Path::new("/project/Cargo.lock")),
+        Some("Cargo.lock".into())
+    );
+    assert_eq!(
+        settings.generated_pattern(Path::new("/project/api/service.pb.go")),
+        Some("*.pb.go".into())
+    );
+    assert_eq!(
+        settings.generated_pattern(Path::new("/project/src/generated/types.ts")),
+        Some("*/generated/*".into())
+    );
+    assert_eq!(
+        settings.generated_pattern(Path::new("/project/src/main.rs")),
+        None
+    );
+
+    let settings = crate::settings::SessionSettings {
+        generated_patterns: Some(vec![]),
+        ..Default::default()
+    };
+    assert_eq!(
+        settings.generated_pattern(Path::new("/project/Cargo.lock")),
+        None
+    );
+}
+
+#[test]
+fn recursive_copy_preserves_times_and_permissions() {
+    use filetime::FileTime;
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("source");
+    std::fs::create_dir_all(source.join("nested")).unwrap();
+    std::fs::write(source.join("nested/file.txt"), "contents").unwrap();
+    let mtime = FileTime::from_unix_time(1_600_000_000, 0);
+    filetime::set_file_mtime(source.join("nested/file.txt"), mtime).unwrap();
+    filetime::set_file_mtime(source.join("nested"), mtime).unwrap();
+
+    let destination = dir.path().join("destination");
+    crate::tools::Move::copy_recursive(&source, &destination).unwrap();
+
+    let copied = destination.join("nested/file.txt");
+    assert_eq!(std::fs::read_to_string(&copied).unwrap(), "contents");
+    for path in [copied, destination.join("nested")] {
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+    }
+}
+
+#[test]
+fn snapshot_hashes() {
+    use crate::tools::Delete;
+    assert_eq!(
+        Delete::sha256_hex(b""),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+        Delete::sha256_hex(b"hello\n"),
+        "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03"
+    );
+}
+
+#[test]
+fn quota_limits() {
+    use crate::quota::{Quota, QuotaLimits, QuotaUsage};
+    let mut quota = Quota {
+        limits: QuotaLimits {
+            max_files_created: Some(2),
+            ..QuotaLimits::default()
+        },
+        ..Quota::default()
+    };
+
+    quota
+        .charge(QuotaUsage::written(100, true), "default")
+        .unwrap();
+    quota.charge(QuotaUsage::created(1), "default").unwrap();
+    assert!(quota.charge(QuotaUsage::created(1), "default").is_err());
+    // Changes without a limit are still counted
+    quota.charge(QuotaUsage::deleted(5), "default").unwrap();
+    assert_eq!(
+        quota.usage,
+        QuotaUsage {
+            bytes_written: 100,
+            files_created: 2,
+            files_deleted: 5
+        }
+    );
+
+    let tighter = QuotaLimits {
+        max_files_created: Some(1),
+        ..QuotaLimits::default()
+    };
+    assert!(!tighter.loosens(&quota.limits));
+    assert!(QuotaLimits::default().loosens(&quota.limits));
+}
+
+#[test]
+fn change_rate_limits() {
+    use crate::quota::ChangeRate;
+    use std::time::{Duration, Instant};
+    let start = Instant::now();
+    let mut rate = ChangeRate::default();
+    rate.record(Some(2), start, "default").unwrap();
+    rate.record(Some(2), start + Duration::from_secs(10), "default")
+        .unwrap();
+    let error = rate
+        .record(Some(2), start + Duration::from_secs(20), "default")
+        .unwrap_err();
+    assert!(error.to_string().starts_with("Quota exceeded"));
+    assert_eq!(rate.count(start + Duration::from_secs(20)), 2);
+
+    // A minute after the first change, there is room for another
+    rate.record(Some(2), start + Duration::from_secs(60), "default")
+        .unwrap();
+    assert_eq!(rate.count(start + Duration::from_secs(65)), 2);
+    // Changes are counted without a limit, so that a new limit applies right away
+    rate.record(None, start + Duration::from_secs(66), "default")
+        .unwrap();
+    assert!(
+        rate.record(Some(3), start + Duration::from_secs(67), "default")
+            .is_err()
+    );
+}
+
+#[test]
+fn session_stores_keep_each_others_sessions() {
+    use crate::session_store::SessionStore;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sessions.json");
+    std::fs::write(
+        &path,
+        r#"{"other-server": {"data": {"unknown": true}, "metadata": {}}}"#,
+    )
+    .unwrap();
+
+    let mut first = SessionStore::<Vec<String>>::new(path.clone()).unwrap();
+    let mut second = SessionStore::<Vec<String>>::new(path.clone()).unwrap();
+    first.set("a", vec!["from first".into()]).unwrap();
+    // `second` read the file before `first` wrote, and must not write over its session
+    second.set("b", vec!["from second".into()]).unwrap();
+
+    assert_eq!(first.get("b").unwrap().unwrap(), &["from second"]);
+    assert_eq!(second.get("a").unwrap().unwrap(), &["from first"]);
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.contains("other-server"));
+}
+
+#[test]
+fn session_stores_move_files_that_do_not_parse_aside() {
+    use crate::session_store::SessionStore;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sessions.json");
+    std::fs::write(&path, r#"{"other-server": {"data": "#).unwrap();
+
+    let mut store = SessionStore::<Vec<String>>::new(path.clone()).unwrap();
+    store.set("a", vec!["kept".into()]).unwrap();
+
+    let aside = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.to_string_lossy().contains("sessions.json.corrupt-"))
+        .expect("the corrupt file is kept");
+    assert_eq!(
+        std::fs::read_to_string(aside).unwrap(),
+        r#"{"other-server": {"data": "#
+    );
+    assert_eq!(store.get("a").unwrap().unwrap(), &["kept"]);
+}
+
+#[test]
+fn annotations_are_consistent() {
+    use crate::annotations::Annotations;
+    for tool in Tools::tools_list() {
+        let annotations = Annotations::for_tool(&tool.name);
+        assert!(
+            !(annotations.read_only && annotations.destructive),
+            "{}",
+            tool.name
+        );
+    }
+    assert!(Annotations::for_tool("read").read_only);
+    assert!(Annotations::for_tool("delete").destructive);
+    assert!(!Annotations::for_tool("write").read_only);
+}
+
+#[test]
+fn base64_contents() {
+    let decode = crate::tools::Write::decode_base64;
+    assert_eq!(decode("AAEC/w==").unwrap(), [0, 1, 2, 255]);
+    assert_eq!(decode("AAEC\n/w==\n").unwrap(), [0, 1, 2, 255]);
+    assert!(decode("not base64!").is_err());
+}
+
+#[test]
+fn compare_directories() {
+    use crate::{hints::Unreadable, tools::CompareDirectories};
+    use std::path::PathBuf;
+    let dir = tempfile::tempdir().unwrap();
+    let (left, right) = (dir.path().join("left"), dir.path().join("right"));
+    for root in [&left, &right] {
+        std::fs::create_dir_all(root.join("shared")).unwrap();
+        std::fs::write(root.join("shared/same.txt"), "same").unwrap();
+    }
+    std::fs::write(left.join("changed.txt"), "before").unwrap();
+    std::fs::write(right.join("changed.txt"), "after!").unwrap();
+    std::fs::create_dir_all(left.join("old/nested")).unwrap();
+    std::fs::write(left.join("old/nested/file.txt"), "").unwrap();
+    std::fs::write(right.join("new.txt"), "").unwrap();
+
+    let comparison = CompareDirectories::compare(&left, &right, false, &mut Unreadable::default());
+    assert_eq!(comparison.only_left, [PathBuf::from("old")]);
+    assert_eq!(comparison.only_right, [PathBuf::from("new.txt")]);
+    let differing = comparison.differing.iter().map(|(path, _)| path);
+    assert_eq!(
+        differing.collect::<Vec<_>>(),
+        [&PathBuf::from("changed.txt")]
+    );
+    assert_eq!(comparison.identical, 1);
+}
+
+#[test]
+fn response_budget_adapts() {
+    use crate::budget::{Limits, RESPONSE_BUDGET, ResponseBudget};
+    let mut budget = ResponseBudget::default();
+    for _ in 0..2 {
+        budget.record(RESPONSE_BUDGET + 1);
+    }
+    budget.record(100);
+    assert_eq!(budget.limits(), Limits::DEFAULT);
+
+    budget.record(RESPONSE_BUDGET + 1);
+    assert_eq!(budget.limits().max_results, Limits::DEFAULT.max_results / 2);
+    assert_eq!(budget.limits().max_depth, Some(8));
+    assert!(budget.footer(|bytes| bytes.to_string()).is_some());
+
+    for _ in 0..5 {
+        budget.record(100);
+    }
+    assert_eq!(budget.limits(), Limits::DEFAULT);
+    assert!(budget.footer(|bytes| bytes.to_string()).is_none());
+}
+
+#[test]
+fn recursive_listings_stop_sooner_after_large_responses() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::write(project.join("big.txt"), "line\n".repeat(20_000)).unwrap();
+    let deep = project.join("1/2/3/4/5/6/7/8/9");
+    std::fs::create_dir_all(&deep).unwrap();
+    std::fs::write(deep.join("deep.txt"), "").unwrap();
+
+    let output = fs_tools.call("list", json!({ "recursive": true })).unwrap();
+    assert!(output.contains("deep.txt"), "{output}");
+
+    for _ in 0..3 {
+        fs_tools
+            .call("read", json!({ "paths": ["big.txt"] }))
+            .unwrap();
+    }
+    let output = fs_tools.call("list", json!({ "recursive": true })).unwrap();
+    assert!(!output.contains("deep.txt"), "{output}");
+    assert!(
+        output.contains("1 directory at depth 8 was not expanded"),
+        "{output}"
+    );
+    assert!(
+        output.contains("max_depth 8 for recursive listings"),
+        "{output}"
+    );
+}
+
+#[test]
+fn resource_uris() {
+    use crate::resources::{path, uri};
+    use std::path::Path;
+    let file = Path::new("/tmp/a dir/ünï%code.rs");
+    assert_eq!(uri(file), "file:///tmp/a%20dir/%C3%BCn%C3%AF%25code.rs");
+    assert_eq!(path(&uri(file)).unwrap(), file);
+    assert!(path("https://example.com").is_err());
+}
+
+#[test]
+fn scaffold_substitution() {
+    use crate::tools::Scaffold;
+    use std::collections::{BTreeMap, BTreeSet};
+    assert_eq!(
+        Scaffold::convert_case("RateLimiter", "snake").unwrap(),
+        "rate_limiter"
+    );
+    assert_eq!(
+        Scaffold::convert_case("rate_limiter", "pascal").unwrap(),
+        "RateLimiter"
+    );
+    assert_eq!(
+        Scaffold::convert_case("rate-limiter", "camel").unwrap(),
+        "rateLimiter"
+    );
+    assert_eq!(
+        Scaffold::convert_case("HTTPClient", "kebab").unwrap(),
+        "http-client"
+    );
+    assert!(Scaffold::convert_case("x", "title").is_none());
+
+    let variables = BTreeMap::from([("name".to_string(), "RateLimiter".to_string())]);
+    let mut missing = BTreeSet::new();
+    assert_eq!(
+        Scaffold::substitute(
+            "mod {{name:snake}}; // {{ name }} {{other}}",
+            &variables,
+            &mut missing
+        ),
+        "mod rate_limiter; // RateLimiter "
+    );
+    assert_eq!(missing, BTreeSet::from(["other".to_string()]));
+}
+
+#[test]
+fn file_languages() {
+    use crate::file_types::language;
+    use std::path::Path;
+    assert_eq!(language(Path::new("src/main.rs")), Some("rust"));
+    assert_eq!(language(Path::new("App.TSX")), Some("ts"));
+    assert_eq!(language(Path::new("Cargo.lock")), None);
+    assert_eq!(language(Path::new("Makefile")), None);
+}
+
+#[test]
+fn exists_describes_paths() {
+    use crate::tools::Exists;
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    std::fs::write(dir.join("file"), "").unwrap();
+    assert_eq!(Exists::describe(dir), "directory");
+    assert_eq!(Exists::describe(&dir.join("file")), "file");
+    assert_eq!(Exists::describe(&dir.join("missing")), "does not exist");
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink("file", dir.join("link")).unwrap();
+        std::os::unix::fs::symlink("missing", dir.join("broken")).unwrap();
+        assert_eq!(
+            Exists::describe(&dir.join("link")),
+            "symlink to file -> file"
+        );
+        assert_eq!(
+            Exists::describe(&dir.join("broken")),
+            "broken symlink -> missing"
+        );
+    }
+}
+
+#[test]
+fn hidden_and_ignored_visibility() {
+    use crate::visibility::Visibility;
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    std::fs::create_dir(dir.join(".git")).unwrap();
+    std::fs::write(dir.join(".gitignore"), "ignored.txt\n").unwrap();
+    for file in [".hidden", "ignored.txt", "shown.txt"] {
+        std::fs::write(dir.join(file), "").unwrap();
+    }
+    let children = |visibility: Visibility| {
+        let mut names = visibility
+            .children(dir)
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+    assert_eq!(children(Visibility::new(None, None)), ["shown.txt"]);
+    assert_eq!(
+        children(Visibility::new(None, Some(true))),
+        ["ignored.txt", "shown.txt"]
+    );
+    assert_eq!(
+        children(Visibility::new(Some(true), None)),
+        [".git", ".gitignore", ".hidden", "shown.txt"]
+    );
+}
+
+#[test]
+fn multiline_search_spans() {
+    use crate::tools::Search;
+    use grep::regex::RegexMatcherBuilder;
+    let content = "fn new(\n    a: u8,\n) -> Result<Self> {\n}\nfn new() -> Result<()>\n";
+    let matcher = RegexMatcherBuilder::new()
+        .multi_line(true)
+        .build(r"fn new\([^)]*\)\s*->\s*Result")
+        .unwrap();
+    let lines = content.lines().count();
+    assert_eq!(
+        Search::multiline_spans(content, lines, &matcher).unwrap(),
+        [(0, 2), (4, 4)]
+    );
+    let matcher = RegexMatcherBuilder::new().build("$").unwrap();
+    assert_eq!(Search::multiline_spans("", 0, &matcher).unwrap(), []);
+}
+
+#[test]
+fn search_match_columns() {
+    use crate::tools::Search;
+    use grep::regex::RegexMatcherBuilder;
+    let matcher = RegexMatcherBuilder::new().build("foo").unwrap();
+    assert_eq!(
+        Search::match_columns("let foo = foo();", &matcher).unwrap(),
+        [(5, 4), (11, 10)]
+    );
+    // Columns count characters, and offsets count bytes
+    assert_eq!(
+        Search::match_columns("é → foo", &matcher).unwrap(),
+        [(5, 7)]
+    );
+    // Only matches starting on the first line of a multiline match are its columns
+    assert_eq!(
+        Search::match_columns("x foo\nfoo", &matcher).unwrap(),
+        [(3, 2)]
+    );
+}
+
+#[test]
+fn deadline_limits() {
+    use crate::deadline::Deadline;
+    use std::time::Duration;
+    let default = Duration::from_secs(30);
+    let limit = |time_limit_ms, cap| Deadline::new(time_limit_ms, default, cap).limit();
+    assert_eq!(limit(None, None), default);
+    assert_eq!(limit(Some(500), None), Duration::from_millis(500));
+    assert_eq!(
+        limit(None, Some(Duration::from_secs(5))),
+        Duration::from_secs(5)
+    );
+    assert_eq!(
+        limit(Some(60_000), Some(Duration::from_secs(5))),
+        Duration::from_secs(5)
+    );
+    assert!(Deadline::new(Some(0), default, None).is_reached());
+}
+
+#[test]
+fn format_hook_extensions() {
+    use crate::format_hooks::FormatHook;
+    let hook = FormatHook {
+        extensions: vec!["rs".into(), ".toml".into()],
+        command: vec!["rustfmt".into()],
+    };
+    assert!(hook.validate().is_ok());
+    assert!(hook.applies_to(std::path::Path::new("src/main.rs")));
+    assert!(hook.applies_to(std::path::Path::new("Cargo.TOML")));
+    assert!(!hook.applies_to(std::path::Path::new("README.md")));
+    assert!(!hook.applies_to(std::path::Path::new("rs")));
+    let no_command = FormatHook {
+        command: vec![],
+        ..hook
+    };
+    assert!(no_command.validate().is_err());
+}
+
+#[test]
+fn config_disables_tools_sets_defaults_and_sandboxes_paths() {
+    use crate::{config::Config, tools::HighlightStyle};
+    use std::path::Path;
+    let config: Config = toml::from_str(
+        r#"
+        disabled_tools = ["delete"]
+        sandbox_roots = ["/srv/projects"]
+
+        [defaults]
+        max_results = 20
+        highlight_style = "markdown"
+        "#,
+    )
+    .unwrap();
+    assert!(config.is_disabled("delete"));
+    assert!(!config.is_disabled("read"));
+    assert_eq!(config.limits().max_results, 20);
+    assert_eq!(
+        config.limits().max_total_bytes,
+        crate::budget::Limits::DEFAULT.max_total_bytes
+    );
+    assert!(matches!(
+        config.defaults.highlight_style,
+        Some(HighlightStyle::Markdown)
+    ));
+    assert!(config.in_sandbox(Path::new("/srv/projects/app/src")));
+    assert!(!config.in_sandbox(Path::new("/srv/projects-old")));
+    assert!(Config::default().in_sandbox(Path::new("/etc")));
+}
+
+#[cfg(unix)]
+#[test]
+fn sandbox_follows_links_for_files_not_yet_created() {
+    use crate::config::Config;
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("root");
+    let outside = dir.path().join("outside");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::create_dir_all(&outside).unwrap();
+    std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+    let config: Config = toml::from_str(&format!(
+        "sandbox_roots = [{:?}]",
+        root.display().to_string()
+    ))
+    .unwrap();
+
+    assert!(config.allows(&root.join("new.conf")));
+    assert!(config.allows(&root.join("missing/dir/new.conf")));
+    assert!(!config.allows(&root.join("link")));
+    assert!(!config.allows(&root.join("link/new.conf")));
+    assert!(!config.allows(&root.join("link/missing/new.conf")));
+}
+
+#[test]
+fn vcs_status_porcelain() {
+    use crate::tools::VcsStatus;
+    let (branch, entries) = VcsStatus::parse(
+        "## main...origin/main [ahead 1]\0M  src/lib.rs\0 D old.txt\0\
+         R  new.rs\0renamed.rs\0?? notes/\0UU both.rs\0",
+    );
+    assert_eq!(branch.as_deref(), Some("main...origin/main [ahead 1]"));
+    let summary = entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "{}{} {} {:?}",
+                entry.staged, entry.unstaged, entry.path, entry.from
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        [
+            "M  src/lib.rs None",
+            " D old.txt None",
+            "R  new.rs Some(\"renamed.rs\")",
+            "?? notes/ None",
+            "UU both.rs None",
+        ]
+    );
+}
+
+#[test]
+fn blame_porcelain() {
+    use crate::tools::Blame;
+    let porcelain = "\
+1111111111111111111111111111111111111111 1 1 2
+author Ada
+author-time 1700000000
+summary First
+filename a.txt
+\tone
+1111111111111111111111111111111111111111 2 2
+\ttwo
+0000000000000000000000000000000000000000 3 3 1
+author Not Committed Yet
+author-time 1700000100
+summary Version of a.txt from a.txt
+filename a.txt
+\tthree
+";
+    let (lines, commits) = Blame::parse(porcelain);
+    let lines = lines
+        .iter()
+        .map(|line| (&line.commit[..1], line.line, line.text.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(lines, [("1", 1, "one"), ("1", 2, "two"), ("0", 3, "three")]);
+    let first = &commits["1111111111111111111111111111111111111111"];
+    assert_eq!(
+        (first.author.as_str(), first.time, first.summary.as_str()),
+        ("Ada", 1_700_000_000, "First")
+    );
+}
+
+#[test]
+fn rust_module_declarations() {
+    use crate::tools::NewRustModule;
+    use std::path::Path;
+    assert_eq!(
+        NewRustModule::child_directory(Path::new("src/lib.rs")),
+        Path::new("src")
+    );
+    assert_eq!(
+        NewRustModule::child_directory(Path::new("src/tools.rs")),
+        Path::new("src/tools")
+    );
+
+    let source = "//! Docs\n\npub mod budget;\nmod state;\n#[cfg(test)]\nmod tests;\n\nuse x;\n";
+    assert_eq!(
+        NewRustModule::declare(source, "cache", "mod cache;"),
+        Some((
+            "//! Docs\n\npub mod budget;\nmod cache;\nmod state;\n#[cfg(test)]\nmod tests;\n\n\
+             use x;\n"
+                .into(),
+            4
+        ))
+    );
+    assert_eq!(
+        NewRustModule::declare(source, "store", "mod store;").map(|(_, line)| line),
+        Some(5)
+    );
+    assert_eq!(NewRustModule::declare(source, "state", "mod state;"), None);
+    assert_eq!(
+        NewRustModule::declare("//! Docs\nuse x;", "a", "pub mod a;"),
+        Some(("//! Docs\n\npub mod a;\n\nuse x;\n".into(), 3))
+    );
+    assert_eq!(
+        NewRustModule::declare("", "a", "mod a;"),
+        Some(("mod a;\n".into(), 1))
+    );
+}
+
+#[test]
+fn read_cache_notices_unchanged_files() {
+    use crate::read_cache::ReadCache;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.txt");
+    std::fs::write(&path, "first").unwrap();
+    let mut cache = ReadCache::default();
+    let read = cache.read(&path).unwrap();
+    assert_eq!((&*read.contents, read.unchanged), ("first", false));
+    assert!(cache.read(&path).unwrap().unchanged);
+
+    std::fs::write(&path, "second").unwrap();
+    let read = cache.read(&path).unwrap();
+    assert_eq!((&*read.contents, read.unchanged), ("second", false));
+
+    // A new modification time with the same contents still counts as unchanged
+    let later = filetime::FileTime::from_unix_time(2_000_000_000, 0);
+    filetime::set_file_mtime(&path, later).unwrap();
+    assert!(cache.read(&path).unwrap().unchanged);
+}
+
+#[test]
+fn bookmarks_follow_moved_lines() {
+    use crate::{state::Bookmark, tools::GotoBookmark};
+    let bookmark = Bookmark {
+        path: "src/lib.rs".into(),
+        line: Some(2),
+        text: Some("    connect_legacy();".into()),
+        note: None,
+        created_at: std::time::SystemTime::now(),
+    };
+    let unchanged = ["fn main() {", "    connect_legacy();", "}"];
+    assert_eq!(GotoBookmark::find_line(&bookmark, &unchanged), Some(2));
+    let moved = ["use x;", "", "fn main() {", "    connect_legacy();", "}"];
+    assert_eq!(GotoBookmark::find_line(&bookmark, &moved), Some(4));
+    let gone = ["fn main() {", "    connect();", "}"];
+    assert_eq!(GotoBookmark::find_line(&bookmark, &gone), None);
+}
+
+#[test]
+fn project_roots_prefer_workspaces() {
+    use crate::tools::DetectProjectRoot;
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let member = root.join("crates/billing");
+    std::fs::create_dir_all(member.join("src")).unwrap();
+    std::fs::create_dir(root.join(".git")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"crates/*\"]\n",
+    )
+    .unwrap();
+    std::fs::write(member.join("Cargo.toml"), "[package]\nname = \"billing\"\n").unwrap();
+    std::fs::write(member.join("src/lib.rs"), "").unwrap();
+
+    let detection = DetectProjectRoot::detect(&member.join("src/lib.rs"));
+    let nearest = detection.nearest.as_ref().unwrap();
+    assert_eq!(
+        (&*nearest.directory, nearest.kind()),
+        (&*member, "Rust crate".into())
+    );
+    let found = detection.root().unwrap();
+    assert_eq!(
+        (&*found.directory, found.kind()),
+        (root, "Rust workspace".into())
+    );
+    assert_eq!(found.markers, ["Cargo.toml", ".git"]);
+    assert_eq!(detection.repository.as_deref(), Some(root));
+
+    std::fs::write(member.join("pyproject.toml"), "").unwrap();
+    let detection = DetectProjectRoot::detect(&member);
+    assert_eq!(
+        detection.nearest.unwrap().kind(),
+        "Rust crate and Python project"
+    );
+}
+
+#[test]
+fn confirmation_mode_holds_recursive_deletes() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::create_dir_all(project.join("build/nested")).unwrap();
+    std::fs::write(project.join("build/nested/out.o"), "").unwrap();
+    fs_tools
+        .call("configure_session", json!({ "confirmation_mode": true }))
+        .unwrap();
+    for loosened in [
+        json!({ "confirmation_mode": false }),
+        json!({ "confirm_overwrite_above_kb": 1024 }),
+    ] {
+        let error = fs_tools
+            .call("configure_session", loosened)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("only be turned off or loosened by a person"),
+            "{error}"
+        );
+    }
+    fs_tools
+        .call("quota_status", json!({ "max_changes_per_minute": 1 }))
+        .unwrap();
+
+    let held = fs_tools
+        .call("delete", json!({ "path": "build", "permanent": true }))
+        .unwrap();
+    assert!(held.starts_with("Confirmation required"), "{held}");
+    assert!(project.join("build").exists());
+
+    let token = fs_tools.pending_operations().unwrap()[0].token.clone();
+    assert!(
+        fs_tools
+            .call("confirm_operation", json!({ "token": "unknown" }))
+            .is_err()
+    );
+    fs_tools
+        .call("confirm_operation", json!({ "token": token }))
+        .unwrap();
+    assert!(!project.join("build").exists());
+    assert!(fs_tools.pending_operations().unwrap().is_empty());
+}
+
+#[test]
+fn line_endings_follow_the_file() {
+    use crate::tools::Write;
+    assert_eq!(Write::detect_line_ending("a\r\nb\r\nc\n"), Some("\r\n"));
+    assert_eq!(Write::detect_line_ending("a\nb\r\n"), Some("\n"));
+    assert_eq!(Write::detect_line_ending("no breaks"), None);
+    assert_eq!(
+        Write::convert_line_endings("a\nb\r\nc\n", "\r\n"),
+        ("a\r\nb\r\nc\r\n".to_string(), 2)
+    );
+    assert_eq!(
+        Write::convert_line_endings("a\r\nb\n", "\n"),
+        ("a\nb\n".to_string(), 1)
+    );
+    assert_eq!(
+        Write::convert_line_endings("a\nb", "\n"),
+        ("a\nb".to_string(), 0)
+    );
+}
+
+#[test]
+fn invisible_bytes_are_reported() {
+    let invisible = crate::tools::Read::invisible_bytes;
+    assert_eq!(invisible("fn main() {}\n"), "");
+    assert_eq!(invisible(""), "");
+    assert_eq!(invisible("\u{feff}a,b\r\n"), ", BOM");
+    assert_eq!(invisible("\u{feff}a,b"), ", BOM, NO FINAL NEWLINE");
+}
+
+#[test]
+fn path_locks_exclude_each_other() {
+    use crate::path_locks::lock_with_timeout;
+    use std::{path::Path, time::Duration};
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = (Path::new("/project/a.txt"), Path::new("/project/b.txt"));
+    let held = lock_with_timeout(dir.path(), &[a], Duration::ZERO).unwrap();
+    let error = lock_with_timeout(dir.path(), &[b, a], Duration::from_millis(50)).unwrap_err();
+    assert!(error.to_string().contains("another fs-mcp process"));
+    let other = lock_with_timeout(dir.path(), &[b], Duration::ZERO).unwrap();
+    drop(held);
+    drop(other);
+    let _both = lock_with_timeout(dir.path(), &[a, b], Duration::ZERO).unwrap();
+}
+
+#[test]
+fn list_summarizes_directories() {
+    use serde_json::json;
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join("project");
+    std::fs::create_dir_all(project.join("src/tools")).unwrap();
+    std::fs::create_dir_all(project.join("empty")).unwrap();
+    std::fs::write(project.join("README.md"), "12345").unwrap();
+    std::fs::write(project.join("src/lib.rs"), "123").unwrap();
+    std::fs::write(project.join("src/tools/a.rs"), "12").unwrap();
+    std::fs::write(project.join("src/tools/b.rs"), "1").unwrap();
+    let mut fs_tools = crate::FsTools::with_dir(dir.path().join("data")).unwrap();
+    fs_tools
+        .call("configure_session", json!({ "size_units": "bytes" }))
+        .unwrap();
+
+    let list = |fs_tools: &mut crate::FsTools, arguments| {
+        let output = fs_tools.call("list", arguments).unwrap();
+        output
+            .lines()
+            .skip(2)
+            .take_while(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        list(
+            &mut fs_tools,
+            json!({ "path": project, "summarize_directories": true })
+        ),
+        [
+            "./  4 files, 11 bytes",
+            "empty/  0 files, 0 bytes",
+            "src/  3 files, 6 bytes",
+            "src/tools/  2 files, 3 bytes",
+        ]
+    );
+    assert_eq!(
+        list(
+            &mut fs_tools,
+            json!({ "path": project, "summarize_directories": true, "max_depth": 1 })
+        ),
+        ["./  4 files, 11 bytes"]
+    );
+}
+
+#[test]
+fn paths_expand_environment_variables_and_users() {
+    use serde_json::json;
+    let dir = tempfile::tempdir().unwrap();
+    let mut fs_tools = crate::FsTools::with_dir(dir.path().join("data")).unwrap();
+    assert_eq!(
+        fs_tools.resolve_path("/a/$PATH", None).unwrap(),
+        std::path::Path::new("/a/$PATH")
+    );
+    fs_tools
+        .call("configure_session", json!({ "expand_env_vars": true }))
+        .unwrap();
+    let path = std::env::var("PATH").unwrap();
+    assert_eq!(
+        fs_tools.resolve_path("/a/${PATH}", None).unwrap(),
+        std::path::Path::new("/a").join(path.trim_start_matches('/'))
+    );
+    assert_eq!(
+        fs_tools
+            .resolve_path("/a/$FS_MCP_SURELY_UNSET/b", None)
+            .unwrap(),
+        std::path::Path::new("/a/$FS_MCP_SURELY_UNSET/b")
+    );
+    fs_tools
+        .call("configure_session", json!({ "expand_env_vars": false }))
+        .unwrap();
+    assert_eq!(
+        fs_tools.resolve_path("/a/$PATH", None).unwrap(),
+        std::path::Path::new("/a/$PATH")
+    );
+    #[cfg(target_os = "linux")]
+    assert_eq!(
+        fs_tools.resolve_path("~root/notes", None).unwrap(),
+        std::path::Path::new("/root/notes")
+    );
+}
+
+#[test]
+fn windows_paths_are_normalized() {
+    use crate::state::normalize_windows_path;
+    assert_eq!(
+        normalize_windows_path(r"c:/Users\me/src"),
+        r"C:\Users\me\src"
+    );
+    assert_eq!(
+        normalize_windows_path(r"\\?\D:\very\long\path"),
+        r"D:\very\long\path"
+    );
+    assert_eq!(
+        normalize_windows_path(r"\\?\UNC\server\share\file.txt"),
+        r"\\server\share\file.txt"
+    );
+    assert_eq!(
+        normalize_windows_path("//server/share/dir"),
+        r"\\server\share\dir"
+    );
+    assert!(matches!(
+        normalize_windows_path(r"src\main.rs"),
+        std::borrow::Cow::Borrowed(_)
+    ));
+}
+
+#[test]
+fn case_insensitive_paths_resolve_to_unique_matches() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::create_dir_all(project.join("src")).unwrap();
+    std::fs::write(project.join("src/main.rs"), "fn main() {}\n").unwrap();
+    std::fs::write(project.join("README.md"), "a").unwrap();
+    std::fs::write(project.join("readme.md"), "b").unwrap();
+
+    let output = fs_tools
+        .call("read", json!({ "paths": ["SRC/Main.rs"] }))
+        .unwrap();
+    assert!(output.contains("does not exist"), "{output}");
+    assert!(output.contains("case_insensitive_paths"), "{output}");
+
+    fs_tools
+        .call(
+            "configure_session",
+            json!({ "case_insensitive_paths": true }),
+        )
+        .unwrap();
+    let output = fs_tools
+        .call("read", json!({ "paths": ["SRC/Main.rs"] }))
+        .unwrap();
+    assert!(output.contains("fn main() {}"), "{output}");
+    assert!(
+        output.contains("SRC/Main.rs does not exist; using"),
+        "{output}"
+    );
+
+    // Ambiguous matches are not guessed at
+    let output = fs_tools
+        .call("read", json!({ "paths": ["Readme.md"] }))
+        .unwrap();
+    assert!(output.contains("BEGIN ERROR"), "{output}");
+
+    // Paths that are being created are used as they are
+    fs_tools
+        .call(
+            "write",
+            json!({ "path": "SRC/Main.rs", "contents": "fn other() {}\n" }),
+        )
+        .unwrap();
+    assert!(project.join("SRC/Main.rs").exists());
+    assert_eq!(
+        std::fs::read_to_string(project.join("src/main.rs")).unwrap(),
+        "fn main() {}\n"
+    );
+    // src/main.rs would be the only match ignoring case
+    fs_tools
+        .call(
+            "move",
+            json!({ "source": "SRC/Main.rs", "destination": "src/MAIN.rs" }),
+        )
+        .unwrap();
+    assert!(project.join("src/MAIN.rs").exists());
+    assert!(project.join("src/main.rs").exists());
+}
+
+#[test]
+fn not_found_suggests_same_name_elsewhere() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join(".git")).unwrap();
+    std::fs::create_dir_all(dir.path().join("crates/core/src")).unwrap();
+    std::fs::create_dir_all(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("crates/core/src/config.rs"), "").unwrap();
+    std::fs::write(dir.path().join("src/confg.rs"), "").unwrap();
+
+    let error = crate::hints::not_found(&dir.path().join("src/config.rs")).to_string();
+    assert!(
+        error.ends_with(&format!(
+            "suggestion: did you mean one of: {}, {}?",
+            dir.path().join("src/confg.rs").display(),
+            dir.path().join("crates/core/src/config.rs").display()
+        )),
+        "{error}"
+    );
+}
+
+#[test]
+fn same_name_elsewhere_stays_in_the_sandbox() {
+    use crate::config::Config;
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join(".git")).unwrap();
+    std::fs::create_dir_all(dir.path().join("app/lib")).unwrap();
+    std::fs::create_dir_all(dir.path().join("secrets")).unwrap();
+    std::fs::write(dir.path().join("app/lib/config.rs"), "").unwrap();
+    std::fs::write(dir.path().join("secrets/config.rs"), "").unwrap();
+    let sandbox = dir.path().join("app");
+    let config: Config = toml::from_str(&format!(
+        "sandbox_roots = [{:?}]",
+        sandbox.display().to_string()
+    ))
+    .unwrap();
+
+    let found = crate::hints::same_name_elsewhere(&sandbox.join("src/config.rs"), &config);
+    assert_eq!(found, vec![sandbox.join("lib/config.rs")]);
+    let found =
+        crate::hints::same_name_elsewhere(&sandbox.join("src/config.rs"), &Config::default());
+    assert_eq!(found.len(), 2, "{found:?}");
+}
+
+#[test]
+fn errors_carry_codes_and_paths() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::write(project.join("a.txt"), "a").unwrap();
+
+    let error = fs_tools
+        .call("move", json!({ "source": "b.txt", "destination": "c.txt" }))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("\ncode: NOT_FOUND\n"), "{error}");
+    assert!(
+        error.contains(&format!("\npath: {}", project.join("b.txt").display())),
+        "{error}"
+    );
+
+    std::fs::write(project.join("c.txt"), "c").unwrap();
+    let error = fs_tools
+        .call("move", json!({ "source": "a.txt", "destination": "c.txt" }))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("\ncode: ALREADY_EXISTS\n"), "{error}");
+    assert!(error.lines().last().unwrap().starts_with("suggestion: "));
+
+    let output = fs_tools
+        .call("read", json!({ "paths": ["missing.txt"] }))
+        .unwrap();
+    assert!(output.contains("\ncode: NOT_FOUND\n"), "{output}");
+
+    std::fs::create_dir(project.join("dir")).unwrap();
+    let error = fs_tools
+        .call("delete", json!({ "path": "dir", "stage": true }))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("\ncode: IS_A_DIRECTORY\n"), "{error}");
+    assert!(project.join("dir").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_out_of_the_working_directory_need_follow_symlinks() {
+    use serde_json::json;
+    let (dir, project, mut fs_tools) = project_session();
+    let outside = dir.path().join("outside");
+    std::fs::create_dir_all(project.join("src")).unwrap();
+    std::fs::create_dir_all(&outside).unwrap();
+    std::os::unix::fs::symlink(&outside, project.join("escape")).unwrap();
+    std::os::unix::fs::symlink("src", project.join("inside")).unwrap();
+
+    let error = fs_tools
+        .call("write", json!({ "path": "escape/a.txt", "contents": "a" }))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("code: OUTSIDE_SANDBOX"), "{error}");
+    assert!(!outside.join("a.txt").exists());
+
+    fs_tools
+        .call("write", json!({ "path": "inside/a.txt", "contents": "a" }))
+        .unwrap();
+    assert!(
+        fs_tools
+            .call(
+                "move",
+                json!({ "source": "src/a.txt", "destination": "escape/a.txt" })
+            )
+            .is_err()
+    );
+
+    fs_tools
+        .call(
+            "move",
+            json!({ "source": "src/a.txt", "destination": "escape/a.txt", "follow_symlinks": true }),
+        )
+        .unwrap();
+    assert!(outside.join("a.txt").exists());
+
+    // Moving the link itself doesn't go through it
+    fs_tools
+        .call(
+            "move",
+            json!({ "source": "escape", "destination": "renamed" }),
+        )
+        .unwrap();
+}
+
+#[test]
+fn file_stats_counts_lines() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::create_dir_all(project.join("src")).unwrap();
+    std::fs::write(
+        project.join("src/lib.rs"),
+        "fn a() {}\n\n    // a longer line\n",
+    )
+    .unwrap();
+    std::fs::write(project.join("src/main.rs"), "fn main() {}\n").unwrap();
+    std::fs::write(project.join("notes.md"), "# Notes\n").unwrap();
+    std::fs::write(project.join("logo.png"), [0x89, 0, 1, 2]).unwrap();
+    fs_tools
+        .call("configure_session", json!({ "size_units": "bytes" }))
+        .unwrap();
+
+    let output = fs_tools
+        .call("file_stats", json!({ "path": "src/lib.rs" }))
+        .unwrap();
+    assert!(
+        output.ends_with(
+            "src/lib.rs: 3 lines (2 non-blank), longest line 20 characters (line 3), 32 bytes"
+        ),
+        "{output}"
+    );
+
+    let output = fs_tools.call("file_stats", json!({})).unwrap();
+    assert!(
+        output.contains("(4 files, 5 lines, 4 non-blank,"),
+        "{output}"
+    );
+    assert!(output.contains("rs        | 2 | 4 | 3 |"), "{output}");
+    assert!(
+        output.contains("(binary)  | 1 | 0 | 0 | 4 bytes"),
+        "{output}"
+    );
+    assert!(
+        output.contains("Most lines:\nsrc/lib.rs | 3 lines\n"),
+        "{output}"
+    );
+}
+
+#[test]
+fn code_stats_classifies_lines() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::write(
+        project.join("main.rs"),
+        "// header\n\nfn main() { /* inline */\n    /* a\n\n    b */ let x = 1;\n}\n/*\n*/\n",
+    )
+    .unwrap();
+    std::fs::write(
+        project.join("build.py"),
+        "# comment\nimport os\n\n  # indented\n",
+    )
+    .unwrap();
+    std::fs::write(project.join("data.bin"), [0, 1, 2]).unwrap();
+
+    let output = fs_tools.call("code_stats", json!({})).unwrap();
+    assert!(
+        output.contains("rust     | 1 | 3 | 5 | 1 | 9\n"),
+        "{output}"
+    );
+    assert!(
+        output.contains("python   | 1 | 1 | 2 | 1 | 4\n"),
+        "{output}"
+    );
+    assert!(
+        output.contains("total    | 2 | 4 | 7 | 2 | 13\n"),
+        "{output}"
+    );
+    assert!(output.contains("1 other files"), "{output}");
+}
+
+#[test]
+fn read_byte_ranges() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    // "é" is bytes 1-2 and "ö" bytes 8-9
+    std::fs::write(project.join("a.txt"), "héllo\nwörld\nend\n").unwrap();
+
+    let output = fs_tools
+        .call(
+            "read",
+            json!({ "paths": ["a.txt"], "offset": 2, "length": 7, "line_numbers": true }),
+        )
+        .unwrap();
+    assert!(
+        output.contains("FULL LENGTH: 18, OFFSET: 3, LENGTH: 5, NEXT OFFSET: 8 "),
+        "{output}"
+    );
+    assert!(output.contains("==\n1\tllo\n2\tw\n=="), "{output}");
+
+    let output = fs_tools
+        .call("read", json!({ "paths": ["a.txt"], "offset": 8 }))
+        .unwrap();
+    assert!(output.contains("OFFSET: 8, LENGTH: 10 "), "{output}");
+    assert!(output.contains("==\nörld\nend\n\n=="), "{output}");
+
+    let output = fs_tools
+        .call(
+            "read",
+            json!({ "paths": ["a.txt"], "offset": 14, "line_numbers": true }),
+        )
+        .unwrap();
+    assert!(output.contains("==\n3\tend\n=="), "{output}");
+
+    let output = fs_tools
+        .call("read", json!({ "paths": ["a.txt"], "offset": 100 }))
+        .unwrap();
+    assert!(output.contains("code: INVALID_ARGUMENT"), "{output}");
+}
+
+#[test]
+fn read_truncated_ends() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::write(project.join("a.log"), "one\ntwo\nthree\nfour\n").unwrap();
+
+    let output = fs_tools
+        .call(
+            "read",
+            json!({ "paths": ["a.log"], "max_length": 5, "truncate_strategy": "tail" }),
+        )
+        .unwrap();
+    assert!(
+        output.contains("==\n[... 14 bytes skipped ...]\nfour\n\n=="),
+        "{output}"
+    );
+
+    let output = fs_tools
+        .call(
+            "read",
+            json!({
+                "paths": ["a.log"],
+                "max_length": 8,
+                "truncate_strategy": "head_tail",
+                "line_numbers": true,
+            }),
+        )
+        .unwrap();
+    assert!(
+        output.contains("==\n1\tone\n[... 11 bytes, 2 lines, skipped ...]\n4\tour\n=="),
+        "{output}"
+    );
+}
+
+#[test]
+fn list_and_search_exclude_globs() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::create_dir_all(project.join("src/generated")).unwrap();
+    std::fs::write(project.join("src/app.js"), "fetchUser()\n").unwrap();
+    std::fs::write(project.join("src/app.min.js"), "fetchUser()\n").unwrap();
+    std::fs::write(project.join("src/generated/api.js"), "fetchUser()\n").unwrap();
+    let exclude = json!(["**/generated/**", "*.min.js"]);
+
+    let output = fs_tools
+        .call(
+            "list",
+            json!({ "path": "src", "recursive": true, "exclude": exclude }),
+        )
+        .unwrap();
+    assert!(output.contains("app.js"), "{output}");
+    assert!(!output.contains("app.min.js"), "{output}");
+    assert!(!output.contains("generated"), "{output}");
+
+    let output = fs_tools
+        .call(
+            "search",
+            json!({ "pattern": "fetchUser", "exclude": exclude }),
+        )
+        .unwrap();
+    assert!(output.contains("Found 1 matches"), "{output}");
+    assert!(output.contains("app.js"), "{output}");
+
+    let error = fs_tools
+        .call(
+            "search",
+            json!({ "pattern": "fetchUser", "exclude": ["a[b"] }),
+        )
+        .unwrap_err();
+    assert!(error.to_string().contains("INVALID_ARGUMENT"), "{error}");
+}
+
+#[test]
+fn search_results_are_sorted_by_path_and_line() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::create_dir_all(project.join("a")).unwrap();
+    for (file, contents) in [
+        ("b.txt", "x\nx\n"),
+        ("a.txt", "x\n"),
+        ("a/z.txt", "x\n"),
+        ("a-b.txt", "x\n"),
+    ] {
+        std::fs::write(project.join(file), contents).unwrap();
+    }
+
+    let output = fs_tools
+        .call(
+            "search",
+            json!({ "pattern": "x", "context_lines": 0, "highlight_style": "none" }),
+        )
+        .unwrap();
+    let files = output
+        .lines()
+        .filter_map(|line| line.split_once(':').map(|(file, _)| file))
+        .filter(|file| file.ends_with(".txt"))
+        .map(|file| file.rsplit_once("project/").map_or(file, |(_, file)| file))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        files,
+        ["a/z.txt", "a-b.txt", "a.txt", "b.txt", "b.txt"],
+        "{output}"
+    );
+}
+
+#[test]
+fn search_stops_at_max_results_unless_counting_every_match() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    for file in 1..=5 {
+        std::fs::write(project.join(format!("f{file}.txt")), "x\nx\n").unwrap();
+    }
+    let search = json!({ "pattern": "x", "max_results": 3, "context_lines": 0 });
+
+    let output = fs_tools.call("search", search.clone()).unwrap();
+    assert!(
+        output.contains("... and at least 1 more matches (limit 3)"),
+        "{output}"
+    );
+    assert!(output.contains("`max_results` was reached"), "{output}");
+    assert!(!output.contains("f3.txt"), "{output}");
+    let id = output
+        .split_once("search_id: ")
+        .and_then(|(_, rest)| rest.split_whitespace().next())
+        .unwrap();
+
+    let mut resume = search.clone();
+    resume["resume"] = id.into();
+    let output = fs_tools.call("search", resume).unwrap();
+    assert!(output.contains("f3.txt"), "{output}");
+    assert!(!output.contains("f1.txt"), "{output}");
+
+    let mut count_all = search;
+    count_all["count_all_matches"] = true.into();
+    let output = fs_tools.call("search", count_all).unwrap();
+    assert!(
+        output.contains("... and 7 more matches (limit 3)"),
+        "{output}"
+    );
+    assert!(!output.contains("resume"), "{output}");
+}
+
+#[test]
+fn search_resumes_only_with_the_same_options() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    for file in ["a.txt", "b.txt"] {
+        std::fs::write(project.join(file), "needle\n").unwrap();
+    }
+    let search_id = |output: &str| {
+        output
+            .split_once("search_id: ")
+            .and_then(|(_, rest)| rest.split_whitespace().next())
+            .map(str::to_string)
+    };
+
+    // With no time at all, each call searches one path: the directory, then each file
+    let search = json!({ "pattern": "needle", "time_limit_ms": 0, "include_extensions": ["txt"] });
+    let output = fs_tools.call("search", search.clone()).unwrap();
+    assert!(
+        output.contains("The time limit of 0ms was reached"),
+        "{output}"
+    );
+    let id = search_id(&output).unwrap();
+
+    let mut changed = search.clone();
+    changed["resume"] = id.clone().into();
+    changed["include_extensions"] = json!(["md"]);
+    let error = fs_tools.call("search", changed).unwrap_err().to_string();
+    assert!(
+        error.contains(r#"was run with `include_extensions` ["txt"], not ["md"]"#),
+        "{error}"
+    );
+
+    let mut found = vec![];
+    let mut id = Some(id);
+    while let Some(resumed) = id {
+        let mut resume = search.clone();
+        resume["resume"] = resumed.into();
+        let output = fs_tools.call("search", resume).unwrap();
+        found.extend(
+            ["a.txt", "b.txt"]
+                .into_iter()
+                .filter(|file| output.contains(*file)),
+        );
+        id = search_id(&output).filter(|_| output.contains("to continue"));
+    }
+    assert_eq!(found, ["a.txt", "b.txt"]);
+}
+
+#[test]
+fn cwd_overrides_the_working_directory_for_one_call() {
+    use serde_json::json;
+    let dir = tempfile::tempdir().unwrap();
+    for project in ["app", "lib"] {
+        std::fs::create_dir_all(dir.path().join(project)).unwrap();
+        std::fs::write(dir.path().join(project).join("name.txt"), project).unwrap();
+    }
+    let mut fs_tools = crate::FsTools::with_dir(dir.path().join("data")).unwrap();
+    fs_tools
+        .call(
+            "set_working_directory",
+            json!({ "path": dir.path().join("app") }),
+        )
+        .unwrap();
+
+    let output = fs_tools
+        .call("read", json!({ "paths": ["name.txt"], "cwd": "../lib" }))
+        .unwrap();
+    assert!(
+        output.contains("lib") && !output.contains("app"),
+        "{output}"
+    );
+    let output = fs_tools
+        .call("read", json!({ "paths": ["name.txt"] }))
+        .unwrap();
+    assert!(output.contains("app"), "{output}");
+
+    let error = fs_tools
+        .call("list", json!({ "cwd": "../missing" }))
+        .unwrap_err();
+    assert!(error.to_string().contains("code: NOT_FOUND"), "{error}");
+}
+
+#[test]
+fn preview_outlines_the_middle_of_a_file() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    let mut source = String::from("//! Parsing\nuse std::fmt;\n\n");
+    for name in ["parse", "lex"] {
+        source.push_str(&format!("pub fn {name}() {{\n    todo!()\n}}\n\n"));
+    }
+    source.push_str("struct Token;\n\nimpl fmt::Display for Token {}\n// end\n");
+    std::fs::write(project.join("parser.rs"), source).unwrap();
+    std::fs::write(
+        project.join("README.md"),
+        "# Title\nintro\n## Usage\n```\n# not a heading\n```\n## License\nMIT\n",
+    )
+    .unwrap();
+
+    let output = fs_tools
+        .call("preview", json!({ "path": "parser.rs", "lines": 2 }))
+        .unwrap();
+    assert!(
+        output.ends_with(
+            "1\t//! Parsing\n2\tuse std::fmt;\n\nOutline of lines 3 to 13:\n4\tpub fn parse() {\n\
+             8\tpub fn lex() {\n12\tstruct Token;\n\n14\timpl fmt::Display for Token {}\n\
+             15\t// end\n"
+        ),
+        "{output}"
+    );
+
+    let output = fs_tools
+        .call("preview", json!({ "path": "README.md", "lines": 1 }))
+        .unwrap();
+    assert!(
+        output.contains("Outline of lines 2 to 7:\n3\t## Usage\n7\t## License\n\n8\tMIT"),
+        "{output}"
+    );
+}
+
+#[test]
+fn read_structured_values_and_skeletons() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::write(
+        project.join("Cargo.toml"),
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = { version = \"1\", features = [\"derive\"] }\nanyhow = \"1\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        project.join("compose.yml"),
+        "services:\n  web:\n    image: nginx\n    ports: [\"80:80\"]\n",
+    )
+    .unwrap();
+
+    let output = fs_tools
+        .call(
+            "read_structured",
+            json!({ "path": "Cargo.toml", "key_path": "dependencies.serde.version" }),
+        )
+        .unwrap();
+    assert!(output.ends_with("\n\n\"1\""), "{output}");
+
+    let output = fs_tools
+        .call(
+            "read_structured",
+            json!({ "path": "Cargo.toml", "depth": 1 }),
+        )
+        .unwrap();
+    assert!(
+        output.ends_with("package: object (2 keys)\ndependencies: object (2 keys)\n"),
+        "{output}"
+    );
+
+    let output = fs_tools
+        .call(
+            "read_structured",
+            json!({ "path": "compose.yml", "key_path": "/services/web", "depth": 1 }),
+        )
+        .unwrap();
+    assert!(
+        output.ends_with("image: \"nginx\"\nports: array (1 item)\n"),
+        "{output}"
+    );
+
+    let error = fs_tools
+        .call(
+            "read_structured",
+            json!({ "path": "Cargo.toml", "key_path": "dependencies.tokio" }),
+        )
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("code: NOT_FOUND"), "{error}");
+    assert!(
+        error.contains("keys at dependencies: serde, anyhow"),
+        "{error}"
+    );
+}
+
+#[test]
+fn edit_structured_keeps_formatting() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::write(
+        project.join("Cargo.toml"),
+        "[package]\nname = \"demo\" # the crate\nversion = \"0.1.0\" # bumped on release\n\n[dependencies]\nanyhow = \"1\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        project.join("package.json"),
+        "{\n    \"name\": \"demo\",\n    \"scripts\": {\n        \"build\": \"tsc\",\n        \"prepublish\": \"tsc\"\n    }\n}\n",
+    )
+    .unwrap();
+
+    fs_tools
+        .call(
+            "edit_structured",
+            json!({ "path": "Cargo.toml", "key_path": "package.version", "value": "\"0.2.0\"" }),
+        )
+        .unwrap();
+    fs_tools
+        .call(
+            "edit_structured",
+            json!({ "path": "Cargo.toml", "key_path": "dependencies.serde", "value": "{\"version\": \"1\"}" }),
+        )
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(project.join("Cargo.toml")).unwrap(),
+        "[package]\nname = \"demo\" # the crate\nversion = \"0.2.0\" # bumped on release\n\n[dependencies]\nanyhow = \"1\"\nserde = { version = \"1\" }\n"
+    );
+
+    fs_tools
+        .call(
+            "edit_structured",
+            json!({ "path": "package.json", "key_path": "scripts.prepublish", "delete": true }),
+        )
+        .unwrap();
+    fs_tools
+        .call(
+            "edit_structured",
+            json!({ "path": "package.json", "key_path": "scripts.test", "value": "jest" }),
+        )
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(project.join("package.json")).unwrap(),
+        "{\n    \"name\": \"demo\",\n    \"scripts\": {\n        \"build\": \"tsc\",\n        \"test\": \"jest\"\n    }\n}\n"
+    );
+
+    let error = fs_tools
+        .call(
+            "edit_structured",
+            json!({ "path": "package.json", "key_path": "scripts.lint", "delete": true }),
+        )
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("scripts.lint in"), "{error}");
+    assert!(error.contains("does not exist"), "{error}");
+}
+
+#[test]
+fn edit_structured_splices_json_and_refuses_lossy_yaml() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::write(
+        project.join("data.json"),
+        "{\r\n  \"id\": 12345678901234567890,\r\n  \"tags\": [\"a\", \"b\"],\r\n  \"empty\": {}\r\n}\r\n",
+    )
+    .unwrap();
+    std::fs::write(project.join("ci.yml"), "# the pipeline\nname: build\n").unwrap();
+    std::fs::write(project.join("multi.yml"), "a: 1\n---\nb: 2\n").unwrap();
+    std::fs::write(
+        project.join("package-lock.json"),
+        "{\n  \"version\": 1\n}\n",
+    )
+    .unwrap();
+
+    for (key_path, value) in [
+        ("tags.1", "\"c\""),
+        ("empty.nested.flag", "true"),
+        ("tags.2", "\"d\""),
+    ] {
+        fs_tools
+            .call(
+                "edit_structured",
+                json!({ "path": "data.json", "key_path": key_path, "value": value }),
+            )
+            .unwrap();
+    }
+    fs_tools
+        .call(
+            "edit_structured",
+            json!({ "path": "data.json", "key_path": "tags.0", "delete": true }),
+        )
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(project.join("data.json")).unwrap(),
+        "{\r\n  \"id\": 12345678901234567890,\r\n  \"tags\": [\"c\", \"d\"],\r\n  \"empty\": {\r\n    \"nested\": {\r\n      \"flag\": true\r\n    }\r\n  }\r\n}\r\n"
+    );
+
+    let error = fs_tools
+        .call(
+            "edit_structured",
+            json!({ "path": "ci.yml", "key_path": "name", "value": "test" }),
+        )
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("rewrite_yaml"), "{error}");
+    fs_tools
+        .call(
+            "edit_structured",
+            json!({ "path": "ci.yml", "key_path": "name", "value": "test", "rewrite_yaml": true }),
+        )
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(project.join("ci.yml")).unwrap(),
+        "name: test\n"
+    );
+
+    let error = fs_tools
+        .call(
+            "edit_structured",
+            json!({ "path": "multi.yml", "key_path": "a", "value": "2" }),
+        )
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("2 YAML documents"), "{error}");
+
+    let error = fs_tools
+        .call(
+            "edit_structured",
+            json!({ "path": "package-lock.json", "key_path": "version", "value": "2" }),
+        )
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("generated file pattern"), "{error}");
+}
+
+#[test]
+fn tail_log_filters_the_end_of_a_log() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    let mut log = String::new();
+    for minute in 0..60 {
+        let level = if minute % 10 == 0 { "ERROR" } else { "INFO" };
+        log.push_str(&format!(
+            "2024-05-01 10:{minute:02}:00 {level} step {minute}\n"
+        ));
+    }
+    log.push_str("2024-05-01 11:00:00 ERROR boom\n  at frame one\n2024-05-01 11:00:01 INFO done\n");
+    std::fs::write(project.join("app.log"), log).unwrap();
+
+    let output = fs_tools
+        .call(
+            "tail_log",
+            json!({ "path": "app.log", "lines": 2, "pattern": "error", "case_sensitive": false }),
+        )
+        .unwrap();
+    assert!(
+        output.ends_with("\n\n2024-05-01 10:50:00 ERROR step 50\n2024-05-01 11:00:00 ERROR boom\n"),
+        "{output}"
+    );
+
+    let output = fs_tools
+        .call(
+            "tail_log",
+            json!({ "path": "app.log", "since": "2024-05-01T10:59:00" }),
+        )
+        .unwrap();
+    assert!(
+        output.ends_with(
+            "\n\n2024-05-01 10:59:00 INFO step 59\n2024-05-01 11:00:00 ERROR boom\n  at frame one\n2024-05-01 11:00:01 INFO done\n"
+        ),
+        "{output}"
+    );
+
+    let error = fs_tools
+        .call(
+            "tail_log",
+            json!({ "path": "app.log", "since": "yesterday" }),
+        )
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("code: INVALID_ARGUMENT"), "{error}");
+}
+
+#[test]
+fn tail_log_reads_timestamps_in_their_offset() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    // 08:00, 08:30, 09:00 and 09:30 UTC, followed by a long stack trace
+    let mut log = "2024-05-01T10:00:00+02:00 one\n2024-05-01T03:30:00-05:00 two\n\
+                   2024-05-01 10:00:00 +0100 three\n2024-05-01T09:30:00Z four\n"
+        .to_string();
+    for frame in 0..1000 {
+        log.push_str(&format!("  at frame {frame}\n"));
+    }
+    std::fs::write(project.join("app.log"), log).unwrap();
+
+    for since in ["2024-05-01T08:45:00Z", "2024-05-01T10:45:00+02:00"] {
+        let output = fs_tools
+            .call(
+                "tail_log",
+                json!({ "path": "app.log", "since": since, "pattern": "^2024" }),
+            )
+            .unwrap();
+        assert!(
+            output.ends_with("\n\n2024-05-01 10:00:00 +0100 three\n2024-05-01T09:30:00Z four\n"),
+            "{output}"
+        );
+    }
+
+    let output = fs_tools
+        .call(
+            "tail_log",
+            json!({ "path": "app.log", "since": "2024-05-01T09:15:00Z", "lines": 2 }),
+        )
+        .unwrap();
+    assert!(
+        output.ends_with("\n\n  at frame 998\n  at frame 999\n"),
+        "{output}"
+    );
+}
+
+#[test]
+fn apply_patch_reports_each_hunk() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    let original = (1..=30).map(|n| format!("line {n}\n")).collect::<String>();
+    // The file has moved on since the patch was made: three lines above it, and a changed line
+    // of context
+    std::fs::write(
+        project.join("a.txt"),
+        format!("x\ny\nz\n{}", original.replace("line 3\n", "line three\n")),
+    )
+    .unwrap();
+    let patch = "--- a/a.txt\n+++ b/a.txt\n@@ -2,7 +2,7 @@\n line 2\n line 3\n line 4\n-line 5\n+LINE FIVE\n line 6\n line 7\n line 8\n@@ -18,6 +18,7 @@\n line 18\n line 19\n line 20\n+inserted\n line 21\n line 22\n line 23\n--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1 @@\n+hello\n";
+
+    let output = fs_tools
+        .call("apply_patch", json!({ "patch": patch, "fuzz": 0 }))
+        .unwrap();
+    assert!(
+        output.starts_with("1 of 3 hunks failed, so no file was changed"),
+        "{output}"
+    );
+    assert!(
+        output.contains(
+            "hunk 1 FAILED @@ -2,7 +2,7 @@: line 6 is `line three`, where the hunk expects `line 3`"
+        ),
+        "{output}"
+    );
+    assert!(
+        output.contains("hunk 2 applied at line 21 (offset +3 lines)"),
+        "{output}"
+    );
+    assert!(!project.join("new.txt").exists());
+
+    let output = fs_tools
+        .call("apply_patch", json!({ "patch": patch, "dry_run": true }))
+        .unwrap();
+    assert!(
+        output.starts_with("The patch would apply, 3 hunks to 2 files."),
+        "{output}"
+    );
+    assert!(!project.join("new.txt").exists());
+
+    let output = fs_tools
+        .call("apply_patch", json!({ "patch": patch }))
+        .unwrap();
+    assert!(
+        output.contains("hunk 1 applied at line 5 (offset +3 lines, fuzz 2)"),
+        "{output}"
+    );
+    let contents = std::fs::read_to_string(project.join("a.txt")).unwrap();
+    assert!(
+        contents.contains("line 4\nLINE FIVE\nline 6\n"),
+        "{contents}"
+    );
+    assert!(
+        contents.contains("line 20\ninserted\nline 21\n"),
+        "{contents}"
+    );
+    assert_eq!(
+        std::fs::read_to_string(project.join("new.txt")).unwrap(),
+        "hello\n"
+    );
+
+    let output = fs_tools
+        .call(
+            "apply_patch",
+            json!({ "patch": patch.split("--- /dev/null").next().unwrap() }),
+        )
+        .unwrap();
+    assert!(
+        output.contains("hunk 2 FAILED @@ -18,6 +18,7 @@: already applied"),
+        "{output}"
+    );
+}
+
+#[test]
+fn apply_patch_refuses_hunks_without_changes_or_matching_context() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::write(project.join("f.txt"), "a\nb\nc\n").unwrap();
+
+    let output = fs_tools
+        .call(
+            "apply_patch",
+            json!({ "patch": "--- a/f.txt\n+++ b/f.txt\n@@ -1,3 +1,3 @@\n x\n y\n z\n" }),
+        )
+        .unwrap();
+    assert!(
+        output.contains("FAILED @@ -1,3 +1,3 @@: it has no added or removed lines"),
+        "{output}"
+    );
+
+    // With every context line fuzzed away, this would insert NEW at line 3
+    let output = fs_tools
+        .call(
+            "apply_patch",
+            json!({ "patch": "--- a/f.txt\n+++ b/f.txt\n@@ -2,2 +2,3 @@\n q\n+NEW\n r\n", "fuzz": 2 }),
+        )
+        .unwrap();
+    assert!(output.starts_with("1 of 1 hunk failed"), "{output}");
+    assert_eq!(
+        std::fs::read_to_string(project.join("f.txt")).unwrap(),
+        "a\nb\nc\n"
+    );
+}
+
+#[test]
+fn simulation_copies_lazily_and_keeps_deletions() {
+    use serde_json::json;
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join("project");
+    std::fs::create_dir_all(project.join("target")).unwrap();
+    std::fs::write(project.join(".gitignore"), "target\n").unwrap();
+    std::fs::write(project.join("target/build.log"), "built\n").unwrap();
+    std::fs::write(project.join("a.txt"), "real\n").unwrap();
+    std::fs::write(project.join("b.txt"), "keep\n").unwrap();
+    let mut fs_tools = crate::FsTools::with_dir(dir.path().join("data")).unwrap();
+    fs_tools.start_simulation().unwrap();
+    fs_tools
+        .call("set_working_directory", json!({ "path": project }))
+        .unwrap();
+
+    // Reading copies nothing, and ignored files are still there
+    let output = fs_tools
+        .call("read", json!({ "paths": ["target/build.log"] }))
+        .unwrap();
+    assert!(output.contains("built"), "{output}");
+    let overlay = fs_tools.simulation().unwrap();
+    assert!(!overlay.simulated(&project).exists());
+
+    fs_tools
+        .call(
+            "write",
+            json!({ "path": "a.txt", "contents": "simulated\n", "overwrite": true }),
+        )
+        .unwrap();
+    fs_tools.call("delete", json!({ "path": "b.txt" })).unwrap();
+    let overlay = fs_tools.simulation().unwrap();
+    assert!(!overlay.simulated(&project.join("target")).exists());
+
+    let output = fs_tools
+        .call("read", json!({ "paths": ["a.txt"] }))
+        .unwrap();
+    assert!(output.contains("simulated"), "{output}");
+    let output = fs_tools
+        .call("read", json!({ "paths": ["b.txt"] }))
+        .unwrap();
+    assert!(output.contains("\ncode: NOT_FOUND\n"), "{output}");
+    let output = fs_tools.call("list", json!({ "path": "." })).unwrap();
+    assert!(
+        output.contains("a.txt") && output.contains("target"),
+        "{output}"
+    );
+    assert!(!output.contains("b.txt"), "{output}");
+
+    assert_eq!(
+        std::fs::read_to_string(project.join("a.txt")).unwrap(),
+        "real\n"
+    );
+    assert!(project.join("b.txt").exists());
+    let output = fs_tools.call("simulated_changes", json!({})).unwrap();
+    assert!(
+        output.contains("a.txt") && output.contains("b.txt"),
+        "{output}"
+    );
+}
+
+#[test]
+fn rename_symbol_renames_across_files_but_not_in_generated_ones() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::create_dir_all(project.join("src/generated")).unwrap();
+    std::fs::write(project.join("src/a.rs"), "fn old_name() {}\n").unwrap();
+    std::fs::write(project.join("src/b.rs"), "old_name();\nold_name_too();\n").unwrap();
+    std::fs::write(project.join("src/generated/c.rs"), "old_name();\n").unwrap();
+
+    let rename = |files: &str| {
+        json!({
+            "symbol": "old_name",
+            "replacement": "new_name",
+            "files": files,
+            "confirm": true,
+        })
+    };
+    let error = fs_tools
+        .call("rename_symbol", rename("src/**/*.rs"))
+        .unwrap_err();
+    assert!(
+        error.to_string().contains("generated file pattern"),
+        "{error}"
+    );
+    assert_eq!(
+        std::fs::read_to_string(project.join("src/a.rs")).unwrap(),
+        "fn old_name() {}\n"
+    );
+
+    let output = fs_tools.call("rename_symbol", rename("src/*.rs")).unwrap();
+    assert!(
+        output.contains("2 occurrences of old_name in 2 files"),
+        "{output}"
+    );
+    assert_eq!(
+        std::fs::read_to_string(project.join("src/a.rs")).unwrap(),
+        "fn new_name() {}\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(project.join("src/b.rs")).unwrap(),
+        "new_name();\nold_name_too();\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(project.join("src/generated/c.rs")).unwrap(),
+        "old_name();\n"
+    );
+}
+
+#[test]
+fn quota_is_charged_by_every_tool_that_writes_and_inherited_by_children() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::write(project.join("big.txt"), "x".repeat(100)).unwrap();
+    fs_tools
+        .call(
+            "archive",
+            json!({ "source": "big.txt", "destination": "big.zip" }),
+        )
+        .unwrap();
+    fs_tools
+        .call("quota_status", json!({ "max_bytes_written": 50 }))
+        .unwrap();
+
+    let error = fs_tools
+        .call(
+            "archive",
+            json!({ "source": "big.txt", "destination": "again.zip" }),
+        )
+        .unwrap_err();
+    assert!(error.to_string().contains("Quota exceeded"), "{error}");
+    assert!(!project.join("again.zip").exists());
+    let error = fs_tools
+        .call(
+            "extract",
+            json!({ "archive": "big.zip", "destination": "out" }),
+        )
+        .unwrap_err();
+    assert!(error.to_string().contains("Quota exceeded"), "{error}");
+    assert!(!project.join("out/big.txt").exists());
+
+    fs_tools
+        .call("create_child_session", json!({ "name": "child" }))
+        .unwrap();
+    let child = fs_tools
+        .session_store_mut()
+        .get("child")
+        .unwrap()
+        .unwrap()
+        .clone();
+    assert_eq!(child.quota.limits.max_bytes_written, Some(50));
+}
+
+#[test]
+fn extract_keeps_entries_inside_the_destination() {
+    use serde_json::json;
+    use std::io::Write;
+    let (dir, project, mut fs_tools) = project_session();
+
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(project.join("a.zip")).unwrap());
+    for name in ["../evil.txt", "inner/good.txt"] {
+        zip.start_file(name, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"payload").unwrap();
+    }
+    zip.finish().unwrap();
+
+    let file = std::fs::File::create(project.join("a.tar.gz")).unwrap();
+    let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+        file,
+        flate2::Compression::default(),
+    ));
+    for name in ["../evil.txt", "inner/good.txt"] {
+        // `set_path` refuses `..`, so the name is written into the header directly
+        let mut header = tar::Header::new_gnu();
+        header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_size(7);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tar.append(&header, &b"payload"[..]).unwrap();
+    }
+    tar.into_inner().unwrap().finish().unwrap();
+
+    for (archive, destination) in [("a.zip", "from-zip"), ("a.tar.gz", "from-tar")] {
+        let output = fs_tools
+            .call(
+                "extract",
+                json!({ "archive": archive, "destination": destination }),
+            )
+            .unwrap();
+        assert!(output.contains("Extracted 1 files"), "{output}");
+        assert!(
+            output.contains("../evil.txt (outside destination)"),
+            "{output}"
+        );
+        assert!(project.join(destination).join("inner/good.txt").is_file());
+        assert!(!project.join("evil.txt").exists());
+    }
+
+    #[cfg(unix)]
+    {
+        let outside = dir.path().join("outside");
+        std::fs::create_dir_all(&outside).unwrap();
+        for (archive, destination) in [("a.zip", "linked-zip"), ("a.tar.gz", "linked-tar")] {
+            std::fs::create_dir_all(project.join(destination)).unwrap();
+            std::os::unix::fs::symlink(&outside, project.join(destination).join("inner")).unwrap();
+            let error = fs_tools
+                .call(
+                    "extract",
+                    json!({ "archive": archive, "destination": destination, "overwrite": true }),
+                )
+                .unwrap_err()
+                .to_string();
+            assert!(error.contains("is a symlink"), "{error}");
+            assert!(!outside.join("good.txt").exists());
+        }
+    }
+}
+
+#[test]
+fn deletions_count_toward_the_quota_wherever_the_files_go() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    for file in ["a.txt", "b.txt", "c.txt"] {
+        std::fs::write(project.join(file), "").unwrap();
+    }
+    fs_tools
+        .call("quota_status", json!({ "max_files_deleted": 1 }))
+        .unwrap();
+
+    fs_tools
+        .call("delete", json!({ "path": "a.txt", "stage": true }))
+        .unwrap();
+    for arguments in [
+        json!({ "path": "b.txt", "stage": true }),
+        json!({ "path": "b.txt" }),
+        json!({ "path": "*.txt", "stage": true, "confirm": true }),
+    ] {
+        let error = fs_tools.call("delete", arguments).unwrap_err().to_string();
+        assert!(error.contains("Quota exceeded"), "{error}");
+    }
+    assert!(project.join("b.txt").exists());
+    assert!(project.join("c.txt").exists());
+}
+
+#[test]
+fn delete_globs_report_progress_and_refund_the_quota_when_a_file_fails() {
+    use serde_json::json;
+    let (dir, project, mut fs_tools) = project_session();
+    std::fs::write(project.join("a.txt"), "").unwrap();
+    std::fs::write(project.join("b.txt"), "").unwrap();
+    fs_tools
+        .call("quota_status", json!({ "max_files_deleted": 2 }))
+        .unwrap();
+    // A file where the staging area belongs makes every file fail to stage
+    let staging = dir.path().join("data/fs-mcp/staged");
+    std::fs::create_dir_all(staging.parent().unwrap()).unwrap();
+    std::fs::write(&staging, "").unwrap();
+
+    let error = fs_tools
+        .call(
+            "delete",
+            json!({ "path": "*.txt", "stage": true, "confirm": true }),
+        )
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("Staged 0 of 2 files before stopping"),
+        "{error}"
+    );
+    assert!(project.join("a.txt").exists());
+
+    std::fs::remove_file(&staging).unwrap();
+    let output = fs_tools
+        .call(
+            "delete",
+            json!({ "path": "*.txt", "stage": true, "confirm": true }),
+        )
+        .unwrap();
+    assert!(output.contains("Staged 2 files"), "{output}");
+}
+
+#[test]
+fn paths_with_glob_characters_that_exist_are_literal() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::create_dir_all(project.join("app/[id]")).unwrap();
+    std::fs::create_dir_all(project.join("app/i")).unwrap();
+    std::fs::write(project.join("app/[id]/page.tsx"), "bracketed").unwrap();
+    std::fs::write(project.join("app/i/page.tsx"), "single letter").unwrap();
+
+    let output = fs_tools
+        .call("read", json!({ "paths": ["app/[id]/page.tsx"] }))
+        .unwrap();
+    assert!(output.contains("bracketed"), "{output}");
+    assert!(!output.contains("single letter"), "{output}");
+
+    fs_tools
+        .call(
+            "delete",
+            json!({ "path": "app/[id]/page.tsx", "permanent": true }),
+        )
+        .unwrap();
+    assert!(!project.join("app/[id]/page.tsx").exists());
+    assert!(project.join("app/i/page.tsx").exists());
+}
+
+#[test]
+#[cfg(windows)]
+fn reads_through_extended_length_paths() {
+    use serde_json::json;
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "extended").unwrap();
+    let mut fs_tools = crate::FsTools::with_dir(dir.path().join("data")).unwrap();
+
+    let path = format!(r"\\?\{}", dir.path().join("notes.txt").display());
+    let output = fs_tools.call("read", json!({ "paths": [path] })).unwrap();
+    assert!(output.contains("extended"), "{output}");
+    let missing = format!(r"\\?\{}", dir.path().join("missing.txt").display());
+    let output = fs_tools
+        .call("read", json!({ "paths": [missing] }))
+        .unwrap();
+    assert!(output.contains("\ncode: NOT_FOUND\n"), "{output}");
+}
+
+#[cfg(unix)]
+#[test]
+fn custom_tools_check_arguments_and_time_limit() {
+    use serde_json::json;
+    let (_dir, _, mut fs_tools) = project_session();
+    let tool: crate::custom_tools::CustomTool = serde_json::from_value(json!({
+        "name": "greet",
+        "command": ["echo", "hello", "{names}"],
+        "read_only": true,
+        "input_schema": {
+            "type": "object",
+            "required": ["names"],
+            "properties": { "names": { "type": "array", "items": { "type": "string" } } }
+        }
+    }))
+    .unwrap();
+    tool.validate().unwrap();
+    let arguments = |value: serde_json::Value| value.as_object().unwrap().clone();
+
+    let output = tool
+        .execute(&mut fs_tools, &arguments(json!({ "names": ["a", "b"] })))
+        .unwrap();
+    assert_eq!(output, "hello a b\n");
+
+    for (value, expected) in [
+        (json!({}), "arguments.names is required"),
+        (json!({ "names": "a" }), "arguments.names must be an array"),
+        (
+            json!({ "names": ["a", 1] }),
+            "arguments.names[1] must be a string",
+        ),
+    ] {
+        let error = tool
+            .execute(&mut fs_tools, &arguments(value))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains(expected), "{error}");
+    }
+
+    let tool: crate::custom_tools::CustomTool = serde_json::from_value(json!({
+        "name": "nap",
+        "command": ["sleep", "5"],
+        "read_only": true,
+        "time_limit_ms": 100
+    }))
+    .unwrap();
+    let started = std::time::Instant::now();
+    let error = tool
+        .execute(&mut fs_tools, &Default::default())
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("time limit of 100ms"), "{error}");
+    assert!(started.elapsed() < std::time::Duration::from_secs(4));
+}
+
+#[test]
+fn extract_matches_counts_what_json_leaves_out_inside_the_json() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+    std::fs::write(
+        project.join("Cargo.toml"),
+        "a = \"1.0\"\nb = \"2.0\"\nc = \"1.0\"\nd = \"3.0\"\n",
+    )
+    .unwrap();
+    let extract = |fs_tools: &mut crate::FsTools, arguments: serde_json::Value| {
+        let output = fs_tools.call("extract_matches", arguments).unwrap();
+        serde_json::from_str::<serde_json::Value>(&output)
+            .unwrap_or_else(|error| panic!("{error}: {output}"))
+    };
+
+    let output = extract(
+        &mut fs_tools,
+        json!({ "pattern": r#""([\d.]+)""#, "json": true, "max_results": 2 }),
+    );
+    assert_eq!(output["matches"].as_array().unwrap().len(), 2);
+    assert_eq!(output["matches"][1]["value"], "2.0");
+    assert_eq!(output["more"], 2);
+    assert_eq!(output["time_limit_reached"], false);
+
+    let output = extract(
+        &mut fs_tools,
+        json!({ "pattern": r#""([\d.]+)""#, "json": true, "unique": true, "max_results": 2 }),
+    );
+    assert_eq!(output["values"], json!(["1.0", "2.0"]));
+    assert_eq!(output["match_count"], 4);
+    assert_eq!(output["more"], 1);
+
+    let output = extract(&mut fs_tools, json!({ "pattern": "nowhere", "json": true }));
+    assert_eq!(output["matches"], json!([]));
+    assert_eq!(output["more"], 0);
+
+    let output = fs_tools
+        .call(
+            "extract_matches",
+            json!({ "pattern": r#""([\d.]+)""#, "unique": true, "max_results": 2 }),
+        )
+        .unwrap();
+    assert!(
+        output.ends_with("1.0\n2.0\n\n... and 1 more distinct values (limit 2)"),
+        "{output}"
+    );
+}
+
+#[test]
+fn rejected_writes_create_no_directories() {
+    use serde_json::json;
+    let (_dir, project, mut fs_tools) = project_session();
+
+    for arguments in [
+        json!({ "path": "new/a.txt", "contents": "a", "append": true, "overwrite": true }),
+        json!({ "path": "new/a.txt", "contents": "a", "keep_rotated": 2 }),
+        json!({ "path": "new/a.txt", "contents": "!", "content_encoding": "base64" }),
+    ] {
+        let _ = fs_tools.call("write", arguments);
+        assert!(!project.join("new").exists());
+    }
+
+    fs_tools
+        .call("quota_status", json!({ "max_bytes_written": 1 }))
+        .unwrap();
+    let error = fs_tools
+        .call("write", json!({ "path": "new/a.txt", "contents": "ab" }))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("Quota exceeded"), "{error}");
+    assert!(!project.join("new").exists());
+}
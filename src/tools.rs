@@ -1,16 +1,357 @@
-use crate::state::FsTools;
+use crate::{
+    annotations::Annotations,
+    config::Config,
+    hints::{self, ErrorCode, Hinted},
+    state::{ActivityEntry, FsTools},
+};
+use mcplease::types::{InputSchema, Tagged};
+use std::time::SystemTime;
+
+pub use search::HighlightStyle;
 
 mcplease::tools!(
     FsTools,
+    (AddBookmark, add_bookmark, "add_bookmark"),
+    (ApplyPatch, apply_patch, "apply_patch"),
+    (Archive, archive, "archive"),
+    (Blame, blame, "blame"),
+    (CodeStats, code_stats, "code_stats"),
+    (
+        CompareDirectories,
+        compare_directories,
+        "compare_directories"
+    ),
+    (ConfigureSession, configure_session, "configure_session"),
+    (ConfirmOperation, confirm_operation, "confirm_operation"),
+    (
+        CreateChildSession,
+        create_child_session,
+        "create_child_session"
+    ),
+    (DefineAlias, define_alias, "define_alias"),
     (Delete, delete, "delete"),
+    (
+        DetectProjectRoot,
+        detect_project_root,
+        "detect_project_root"
+    ),
+    (DiffVsHead, diff_vs_head, "diff_vs_head"),
+    (DiskUsage, disk_usage, "disk_usage"),
+    (EditStructured, edit_structured, "edit_structured"),
+    (Exists, exists, "exists"),
+    (Extract, extract, "extract"),
+    (ExtractMatches, extract_matches, "extract_matches"),
+    (FileStats, file_stats, "file_stats"),
+    (
+        GetWorkingDirectory,
+        get_working_directory,
+        "get_working_directory"
+    ),
+    (GotoBookmark, goto_bookmark, "goto_bookmark"),
     (List, list, "list"),
+    (ListBookmarks, list_bookmarks, "list_bookmarks"),
     (Move, r#move, "move"),
+    (NewRustModule, new_rust_module, "new_rust_module"),
+    (Preview, preview, "preview"),
+    (
+        PreviousWorkingDirectory,
+        previous_working_directory,
+        "previous_working_directory"
+    ),
+    (PurgeStaged, purge_staged, "purge_staged"),
+    (QuotaStatus, quota_status, "quota_status"),
+    (RecentFiles, recent_files, "recent_files"),
+    (RestoreDeleted, restore_deleted, "restore_deleted"),
+    (RenameSymbol, rename_symbol, "rename_symbol"),
+    (SetPathAlias, set_path_alias, "set_path_alias"),
     (
         SetWorkingDirectory,
         set_working_directory,
         "set_working_directory"
     ),
+    (Scaffold, scaffold, "scaffold"),
     (Search, search, "search"),
+    (SelfCheck, self_check, "self_check"),
+    (SessionActivity, session_activity, "session_activity"),
+    (SetPermissions, set_permissions, "set_permissions"),
+    (SimulatedChanges, simulated_changes, "simulated_changes"),
+    (TailLog, tail_log, "tail_log"),
+    (Touch, touch, "touch"),
+    (Unstage, unstage, "unstage"),
+    (UsageStats, usage_stats, "usage_stats"),
+    (VcsStatus, vcs_status, "vcs_status"),
     (Write, write, "write"),
-    (Read, read, "read")
+    (Read, read, "read"),
+    (ReadStructured, read_structured, "read_structured")
 );
+
+impl Tools {
+    /// Whether `name` is a built-in tool
+    pub fn has_tool(name: &str) -> bool {
+        <Self as mcplease::traits::AsToolsList>::tools_list()
+            .iter()
+            .any(|tool| tool.name == name)
+    }
+}
+
+/// A call to a built-in tool or to one declared in the config file
+#[derive(Debug)]
+pub enum Call {
+    Tool(Box<Tools>),
+    Custom {
+        name: String,
+        arguments: serde_json::Map<String, serde_json::Value>,
+    },
+}
+
+/// A [`Call`], recording every call in the session's activity log before returning its result
+#[derive(Debug)]
+pub struct Audited {
+    call: Call,
+    /// Working directory for this call only, from the `cwd` argument every tool takes
+    cwd: Option<String>,
+}
+
+impl<'de> serde::Deserialize<'de> for Audited {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let mut value = serde_json::Value::deserialize(deserializer)?;
+        let cwd = match value
+            .get_mut("arguments")
+            .and_then(|arguments| arguments.as_object_mut())
+            .and_then(|arguments| arguments.remove("cwd"))
+        {
+            None | Some(serde_json::Value::Null) => None,
+            Some(serde_json::Value::String(cwd)) => Some(cwd),
+            Some(_) => return Err(D::Error::custom("expected cwd to be a string")),
+        };
+        let name = value.get("name").and_then(|name| name.as_str());
+        match name.and_then(|name| Config::global().custom_tool(name)) {
+            Some(tool) => {
+                let arguments = match value.get("arguments") {
+                    Some(serde_json::Value::Object(arguments)) => arguments.clone(),
+                    None | Some(serde_json::Value::Null) => Default::default(),
+                    Some(_) => return Err(D::Error::custom("expected arguments to be an object")),
+                };
+                Ok(Self {
+                    call: Call::Custom {
+                        name: tool.name.clone(),
+                        arguments,
+                    },
+                    cwd,
+                })
+            }
+            None => serde_json::from_value(value)
+                .map(|tool| Self {
+                    call: Call::Tool(Box::new(tool)),
+                    cwd,
+                })
+                .map_err(D::Error::custom),
+        }
+    }
+}
+
+impl serde::Serialize for Audited {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::Error;
+        let mut value = match &self.call {
+            Call::Tool(tool) => serde_json::to_value(tool).map_err(S::Error::custom)?,
+            Call::Custom { name, arguments } => {
+                serde_json::json!({ "name": name, "arguments": arguments })
+            }
+        };
+        if let (Some(cwd), Some(arguments)) = (
+            &self.cwd,
+            value
+                .get_mut("arguments")
+                .and_then(|arguments| arguments.as_object_mut()),
+        ) {
+            arguments.insert("cwd".into(), cwd.clone().into());
+        }
+        value.serialize(serializer)
+    }
+}
+
+impl Audited {
+    fn name(&self) -> &str {
+        match &self.call {
+            Call::Tool(tool) => tool.name(),
+            Call::Custom { name, .. } => name,
+        }
+    }
+}
+
+/// Arguments longer than this are shortened in the activity log
+const MAX_ACTIVITY_ARGUMENTS: usize = 300;
+
+impl mcplease::traits::Tool<FsTools> for Audited {
+    fn execute(self, state: &mut FsTools) -> anyhow::Result<String> {
+        let tool = self.name().to_string();
+        let mut arguments = serde_json::to_value(&self)
+            .ok()
+            .and_then(|value| {
+                value
+                    .get("arguments")
+                    .map(|arguments| arguments.to_string())
+            })
+            .unwrap_or_default();
+        if let Some((index, _)) = arguments.char_indices().nth(MAX_ACTIVITY_ARGUMENTS) {
+            arguments.truncate(index);
+            arguments.push('…');
+        }
+
+        let changes_files = !Annotations::for_tool(&tool).read_only;
+        state.take_resolution_notes();
+        state.set_call_changes_files(changes_files);
+        let mut result = match self.call {
+            _ if let Err(error) = state.set_call_cwd(self.cwd.as_deref()) => Err(error),
+            _ if state.read_only() && changes_files => Err(Hinted::new(
+                format!("{tool} is not available: fs-mcp is serving with --read-only"),
+                Some("only tools that leave files unchanged can be used in this session".into()),
+            )
+            .with_code(ErrorCode::ReadOnly)
+            .into()),
+            Call::Tool(_) if Config::global().is_disabled(&tool) => Err(Hinted::new(
+                format!("{tool} is not available: it is disabled in the config file"),
+                None,
+            )
+            .into()),
+            // A confirmed operation was already charged when it was held
+            _ if changes_files
+                && tool != "confirm_operation"
+                && let Err(error) = state.charge_change_rate() =>
+            {
+                Err(error)
+            }
+            Call::Tool(tool) => ConfirmOperation::execute_or_hold(*tool, state),
+            Call::Custom { name, arguments } => match Config::global().custom_tool(&name) {
+                Some(tool) => tool.execute(state, &arguments),
+                None => Err(anyhow::anyhow!("Unknown tool {name}")),
+            },
+        };
+
+        // A failed `cwd` left nothing to clear
+        let _ = state.set_call_cwd(None);
+        state.set_call_changes_files(false);
+        let notes = state.take_resolution_notes();
+        if let (Ok(output), false) = (&mut result, notes.is_empty()) {
+            output.truncate(output.trim_end().len());
+            output.push_str(&format!("\n\n{}", notes.join("\n")));
+        }
+
+        // The rendered message replaces what is shown, but the causes stay in the chain
+        result = result.map_err(|error| {
+            let rendered = hints::render(&error);
+            error.context(rendered)
+        });
+
+        // Tools only ever see overlay paths while simulating, but responses should name the
+        // real ones
+        if let Some(overlay) = state.simulation() {
+            result = result
+                .map(|output| overlay.scrub(&output))
+                .map_err(|error| {
+                    let scrubbed = overlay.scrub(&error.to_string());
+                    error.context(scrubbed)
+                });
+        }
+
+        if matches!(tool.as_str(), "list" | "read" | "search") {
+            if let (Ok(output), Ok(budget)) = (&mut result, state.response_budget()) {
+                let settings = state.settings(None).unwrap_or_default();
+                if let Some(footer) = budget.footer(|bytes| settings.format_size(bytes)) {
+                    output.truncate(output.trim_end().len());
+                    output.push_str(&format!("\n\n{footer}"));
+                }
+            }
+        }
+
+        let response_bytes = match &result {
+            Ok(output) => output.len(),
+            Err(error) => error.to_string().len(),
+        };
+
+        // A failure to record activity shouldn't fail the call itself
+        let _ = state.record_activity(
+            ActivityEntry {
+                at: SystemTime::now(),
+                tool,
+                arguments,
+                error: result.as_ref().err().map(|error| {
+                    error
+                        .to_string()
+                        .lines()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string()
+                }),
+            },
+            response_bytes as u64,
+        );
+
+        result
+    }
+}
+
+impl mcplease::traits::AsToolsList for Audited {
+    fn tools_list() -> Vec<mcplease::types::ToolSchema> {
+        let mut tools = Tools::tools_list();
+        tools.retain(|tool| !Config::global().is_disabled(&tool.name));
+        tools.extend(Config::global().tools.iter().filter_map(|tool| {
+            tool.schema()
+                .inspect_err(|error| log::warn!("leaving out custom tool {}: {error}", tool.name))
+                .ok()
+        }));
+        for tool in &mut tools {
+            if let InputSchema::Tagged(Tagged::Object { properties, .. }) = &mut tool.input_schema {
+                properties.insert("cwd".into(), Box::new(cwd_schema()));
+            }
+        }
+        tools
+    }
+}
+
+/// The schema of the `cwd` argument added to every tool
+fn cwd_schema() -> InputSchema {
+    InputSchema::Tagged(Tagged::String {
+        title: None,
+        description: Some(
+            "Working directory for this call only, instead of the session's. Relative paths \
+             are resolved against it. Can itself be relative to the session's working directory"
+                .into(),
+        ),
+        r#enum: None,
+        examples: None,
+    })
+}
+
+impl clap::FromArgMatches for Audited {
+    fn from_arg_matches(matches: &clap::ArgMatches) -> Result<Self, clap::Error> {
+        Tools::from_arg_matches(matches).map(|tool| Self {
+            call: Call::Tool(Box::new(tool)),
+            cwd: None,
+        })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &clap::ArgMatches) -> Result<(), clap::Error> {
+        match &mut self.call {
+            Call::Tool(tool) => tool.update_from_arg_matches(matches),
+            // Custom tools are only available over MCP
+            Call::Custom { .. } => Ok(()),
+        }
+    }
+}
+
+impl clap::Subcommand for Audited {
+    fn augment_subcommands(command: clap::Command) -> clap::Command {
+        Tools::augment_subcommands(command)
+    }
+
+    fn augment_subcommands_for_update(command: clap::Command) -> clap::Command {
+        Tools::augment_subcommands_for_update(command)
+    }
+
+    fn has_subcommand(name: &str) -> bool {
+        Tools::has_subcommand(name)
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{
+    hints::{self, ErrorCode, Hint, Hinted},
+    state::Bookmark,
+    tools::FsTools,
+};
+use anyhow::Result;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, time::SystemTime};
+
+/// Save a file, or a line in one, under a name for this session, to come back to it later with
+/// goto_bookmark (e.g. call sites still to update during a refactor). Saving an existing name
+/// replaces it
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "add_bookmark")]
+pub struct AddBookmark {
+    /// Name to save the bookmark under
+    pub name: String,
+
+    /// File to bookmark. Omit to remove the bookmark
+    /// Can be absolute, or relative to session context path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// 1-based line to bookmark within the file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub line: Option<usize>,
+
+    /// Why the place is bookmarked, shown by list_bookmarks and goto_bookmark
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub note: Option<String>,
+}
+
+impl WithExamples for AddBookmark {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Marking a call site to update later in a refactor",
+                item: Self {
+                    name: "old-api-caller".into(),
+                    path: Some("src/server/handlers.rs".into()),
+                    line: Some(212),
+                    note: Some("still calls connect_legacy".into()),
+                },
+            },
+            Example {
+                description: "Bookmarking a whole file",
+                item: Self {
+                    name: "schema".into(),
+                    path: Some("migrations/0042_users.sql".into()),
+                    line: None,
+                    note: None,
+                },
+            },
+            Example {
+                description: "Removing a bookmark that is done with",
+                item: Self {
+                    name: "old-api-caller".into(),
+                    path: None,
+                    line: None,
+                    note: None,
+                },
+            },
+        ]
+    }
+}
+
+impl Tool<FsTools> for AddBookmark {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err(
+                Hinted::new("Bookmark names can't be empty".to_string(), None)
+                    .with_code(ErrorCode::InvalidArgument)
+                    .into(),
+            );
+        }
+        let existing = state.bookmarks(None)?.remove(name);
+
+        let Some(path) = &self.path else {
+            return match existing {
+                Some(_) => {
+                    state.set_bookmark(None, name, None)?;
+                    Ok(format!("Removed bookmark {name}"))
+                }
+                None => Err(Hinted::new(
+                    format!("There is no bookmark named {name}"),
+                    Some("list_bookmarks shows the bookmarks of this session".into()),
+                )
+                .into()),
+            };
+        };
+
+        let path = state.resolve_path(path, None)?;
+        if !path.is_file() {
+            return Err(hints::not_found(&path));
+        }
+        let text = match self.line {
+            Some(line) => {
+                let contents = fs::read_to_string(&path).hint_path(&path)?;
+                let line_count = contents.lines().count();
+                match contents.lines().nth(line.wrapping_sub(1)) {
+                    Some(text) => Some(text.to_string()),
+                    None => {
+                        return Err(Hinted::new(
+                            format!(
+                                "{} has {line_count} lines, so there is no line {line}",
+                                path.display()
+                            ),
+                            Some("lines are numbered from 1".into()),
+                        )
+                        .into());
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let bookmark = Bookmark {
+            path,
+            line: self.line,
+            text,
+            note: self.note,
+            created_at: SystemTime::now(),
+        };
+        let mut response = match existing {
+            Some(previous) => format!(
+                "Moved bookmark {name} from {} to {}",
+                previous.location(),
+                bookmark.location()
+            ),
+            None => format!("Bookmarked {} as {name}", bookmark.location()),
+        };
+        if let Some(text) = bookmark.text.as_deref().map(str::trim) {
+            if !text.is_empty() {
+                response.push_str(&format!(":\n{text}"));
+            }
+        }
+        state.set_bookmark(None, name, Some(bookmark))?;
+        Ok(response)
+    }
+}
@@ -0,0 +1,594 @@
+use crate::{
+    hints::{self, ErrorCode, Hint, Hinted},
+    quota::QuotaUsage,
+    tools::FsTools,
+    trash,
+};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, sync::LazyLock};
+
+/// Apply a unified diff, as made by `diff -u` or `git diff`, to files under the working
+/// directory. Hunks are found near the line their `@@` header gives even if the file has shifted
+/// since, and with `fuzz` their outermost context lines may differ. Each hunk is reported as
+/// applied or failed, and files are only changed when every hunk applies. Cheaper than writing
+/// whole files for small edits
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "apply_patch")]
+pub struct ApplyPatch {
+    /// The unified diff, with `---` and `+++` lines naming each file and `@@` hunks. Line counts
+    /// in `@@` headers are not relied on, and `--- /dev/null` creates a file while
+    /// `+++ /dev/null` deletes one
+    pub patch: String,
+
+    /// How many leading path components to strip from the file names in the patch, like
+    /// `patch -p`
+    /// Default: 1 for git diffs, whose names start with `a/` and `b/`, and 0 otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub strip: Option<usize>,
+
+    /// How many context lines at each end of a hunk may be ignored to make it apply, like
+    /// `patch -F`
+    /// Default: 2
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub fuzz: Option<usize>,
+
+    /// Report whether each hunk would apply, without changing any file
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub dry_run: Option<bool>,
+}
+
+impl WithExamples for ApplyPatch {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Changing a few lines of a file",
+                item: Self {
+                    patch: "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -10,3 +10,3 @@\n fn answer() -> u32 {\n-    41\n+    42\n }\n"
+                        .into(),
+                    strip: None,
+                    fuzz: None,
+                    dry_run: None,
+                },
+            },
+            Example {
+                description: "Checking that a patch still applies, without applying it",
+                item: Self {
+                    patch: "--- README.md\n+++ README.md\n@@ -1,2 +1,2 @@\n # Project\n-Old tagline\n+New tagline\n"
+                        .into(),
+                    strip: Some(0),
+                    fuzz: Some(0),
+                    dry_run: Some(true),
+                },
+            },
+        ]
+    }
+}
+
+static HUNK_HEADER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^@@ -(\d+)(?:,\d+)? \+\d+(?:,\d+)? @@").expect("valid regex"));
+
+/// One `@@` section of a patch
+#[derive(Debug, Default)]
+struct Hunk {
+    header: String,
+    /// The 1-based line the hunk starts at in the original file, if the header gives one
+    old_start: Option<usize>,
+    /// Each line with its marker: ' ' for context, '-' for removed, and '+' for added
+    lines: Vec<(char, String)>,
+    /// Whether the original file ends without a newline after this hunk
+    old_no_newline: bool,
+    /// Whether the changed file ends without a newline after this hunk
+    new_no_newline: bool,
+}
+
+/// The changes a patch makes to one file
+#[derive(Debug)]
+struct FilePatch {
+    /// The names in the `---` and `+++` lines, None for `/dev/null`
+    old: Option<String>,
+    new: Option<String>,
+    hunks: Vec<Hunk>,
+}
+
+/// The name of a file in a `---` or `+++` line, without any timestamp after it
+fn file_name(line: &str) -> Option<String> {
+    let name = line[4..].split('\t').next().unwrap_or_default().trim_end();
+    (name != "/dev/null").then(|| name.to_string())
+}
+
+fn parse(patch: &str) -> Vec<FilePatch> {
+    let lines = patch.lines().collect::<Vec<_>>();
+    let mut files: Vec<FilePatch> = vec![];
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        let next = lines.get(index + 1).copied().unwrap_or_default();
+        if line.starts_with("--- ") && next.starts_with("+++ ") {
+            files.push(FilePatch {
+                old: file_name(line),
+                new: file_name(next),
+                hunks: vec![],
+            });
+            index += 2;
+            continue;
+        }
+        if let (true, Some(file)) = (line.starts_with("@@"), files.last_mut()) {
+            let mut hunk = Hunk {
+                header: line.to_string(),
+                old_start: HUNK_HEADER
+                    .captures(line)
+                    .and_then(|captures| captures[1].parse().ok()),
+                ..Hunk::default()
+            };
+            index += 1;
+            while let Some(line) = lines.get(index) {
+                let next = lines.get(index + 1).copied().unwrap_or_default();
+                if line.starts_with("@@") || line.starts_with("--- ") && next.starts_with("+++ ") {
+                    break;
+                }
+                match line.chars().next() {
+                    Some(marker @ (' ' | '-' | '+')) => {
+                        hunk.lines.push((marker, line[1..].to_string()))
+                    }
+                    // Blank context lines often lose their leading space
+                    None => hunk.lines.push((' ', String::new())),
+                    Some('\\') => match hunk.lines.last() {
+                        Some(('-', _)) => hunk.old_no_newline = true,
+                        Some(('+', _)) => hunk.new_no_newline = true,
+                        Some(_) => (hunk.old_no_newline, hunk.new_no_newline) = (true, true),
+                        None => {}
+                    },
+                    Some(_) => break,
+                }
+                index += 1;
+            }
+            // Blank lines between files or hunks aren't context
+            while hunk.lines.last().is_some_and(|(_, line)| line.is_empty())
+                && lines[index - 1].is_empty()
+            {
+                hunk.lines.pop();
+                index -= 1;
+            }
+            file.hunks.push(hunk);
+            continue;
+        }
+        index += 1;
+    }
+    files
+}
+
+/// A file's contents as lines, and how to write them back
+#[derive(Debug, Clone)]
+struct Text {
+    lines: Vec<String>,
+    crlf: bool,
+    final_newline: bool,
+}
+
+impl Text {
+    fn new(contents: &str) -> Self {
+        Self {
+            lines: contents.lines().map(str::to_string).collect(),
+            crlf: contents.contains("\r\n"),
+            final_newline: contents.is_empty() || contents.ends_with('\n'),
+        }
+    }
+
+    fn render(&self) -> String {
+        let newline = if self.crlf { "\r\n" } else { "\n" };
+        let mut contents = self.lines.join(newline);
+        if self.final_newline && !self.lines.is_empty() {
+            contents.push_str(newline);
+        }
+        contents
+    }
+
+    /// The start of the occurrence of `pattern` nearest to `expected`, at or after `from`
+    fn find(&self, pattern: &[&str], expected: usize, from: usize) -> Option<usize> {
+        if pattern.is_empty() {
+            return Some(expected.clamp(from, self.lines.len()));
+        }
+        let last = self.lines.len().checked_sub(pattern.len())?;
+        if from > last {
+            return None;
+        }
+        let expected = expected.clamp(from, last);
+        let matches = |start: &usize| {
+            self.lines[*start..*start + pattern.len()]
+                .iter()
+                .zip(pattern)
+                .all(|(line, expected)| line == expected)
+        };
+        (0..=last - from)
+            .flat_map(|distance| {
+                [
+                    expected.checked_add(distance),
+                    expected.checked_sub(distance),
+                ]
+            })
+            .flatten()
+            .filter(|start| (from..=last).contains(start))
+            .find(matches)
+    }
+}
+
+/// Where and how a hunk applied
+struct Applied {
+    /// 1-based line in the changed file
+    line: usize,
+    offset: isize,
+    fuzz: usize,
+}
+
+/// Apply `hunk`, which is expected at the 0-based `expected` line, to `text`, leaving `text`
+/// unchanged if it doesn't apply
+fn apply_hunk(
+    text: &mut Text,
+    hunk: &Hunk,
+    expected: usize,
+    from: usize,
+    max_fuzz: usize,
+) -> Result<(Applied, usize), String> {
+    let old = hunk
+        .lines
+        .iter()
+        .filter(|(marker, _)| *marker != '+')
+        .map(|(_, line)| line.as_str())
+        .collect::<Vec<_>>();
+    let new = hunk
+        .lines
+        .iter()
+        .filter(|(marker, _)| *marker != '-')
+        .map(|(_, line)| line.as_str())
+        .collect::<Vec<_>>();
+    let leading = hunk
+        .lines
+        .iter()
+        .take_while(|(marker, _)| *marker == ' ')
+        .count();
+    let trailing = hunk
+        .lines
+        .iter()
+        .rev()
+        .take_while(|(marker, _)| *marker == ' ')
+        .count();
+    if hunk.lines.iter().all(|(marker, _)| *marker == ' ') {
+        return Err("it has no added or removed lines".into());
+    }
+    // Like `patch -F`, fuzz never ignores every context line, or a hunk whose context is
+    // nowhere in the file would apply at the line its header gives
+    let context = hunk
+        .lines
+        .iter()
+        .filter(|(marker, _)| *marker == ' ')
+        .count();
+    let fuzz_levels = (0..=max_fuzz.min(leading.max(trailing)))
+        .map(|fuzz| (fuzz, fuzz.min(leading), fuzz.min(trailing)))
+        .filter(|(_, front, back)| context == 0 || front + back < context)
+        .collect::<Vec<_>>();
+
+    for &(fuzz, front, back) in &fuzz_levels {
+        let pattern = &old[front..old.len() - back];
+        let Some(start) = text.find(pattern, expected + front, from) else {
+            continue;
+        };
+        let replacement = &new[front..new.len() - back];
+        text.lines.splice(
+            start..start + pattern.len(),
+            replacement.iter().map(|line| line.to_string()),
+        );
+        let end = start + replacement.len();
+        // Only a hunk that reaches the end of the file can change its final newline
+        if end + back >= text.lines.len() {
+            if hunk.new_no_newline {
+                text.final_newline = false;
+            } else if hunk.old_no_newline {
+                text.final_newline = true;
+            }
+        }
+        let applied = Applied {
+            line: start - front + 1,
+            offset: (start - front) as isize - expected as isize,
+            fuzz,
+        };
+        return Ok((applied, end));
+    }
+
+    let already_applied = fuzz_levels.iter().any(|(_, front, back)| {
+        let added = &new[*front..new.len() - back];
+        !added.is_empty() && new != old && text.find(added, expected, 0).is_some()
+    });
+    if already_applied {
+        return Err("already applied: the file already has the lines it adds".into());
+    }
+    let Some(first) = old.iter().find(|line| !line.trim().is_empty()) else {
+        return Err("its context is not in the file".into());
+    };
+    let Some(found) = text.lines.iter().position(|line| line == first) else {
+        return Err(format!("`{}` is not in the file", first.trim()));
+    };
+    // Show where the nearest candidate stops matching, to help fix the hunk
+    let skipped = old
+        .iter()
+        .position(|line| line == first)
+        .unwrap_or_default();
+    let start = found.saturating_sub(skipped);
+    let mismatch = old
+        .iter()
+        .enumerate()
+        .find(|(index, line)| text.lines.get(start + index).map(String::as_str) != Some(**line));
+    Err(match mismatch {
+        Some((index, line)) => format!(
+            "line {} is `{}`, where the hunk expects `{}`",
+            start + index + 1,
+            text.lines.get(start + index).map_or("", |line| line.trim()),
+            line.trim()
+        ),
+        None => "its context is not in the file".into(),
+    })
+}
+
+/// A file to change, with the outcome of each of its hunks
+struct Change {
+    path: PathBuf,
+    /// None when the file doesn't exist yet
+    before: Option<String>,
+    /// None when the file is deleted
+    after: Option<Text>,
+    outcomes: Vec<(String, Result<Applied, String>)>,
+}
+
+impl ApplyPatch {
+    /// The path a name in the patch refers to, relative to the working directory
+    fn strip_name<'a>(&self, name: &'a str, git: bool) -> &'a str {
+        let strip = self.strip.unwrap_or(usize::from(git));
+        let mut name = name;
+        for _ in 0..strip {
+            name = name.split_once('/').map_or(name, |(_, rest)| rest);
+        }
+        name
+    }
+}
+
+impl Tool<FsTools> for ApplyPatch {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let files = parse(&self.patch);
+        if files.iter().all(|file| file.hunks.is_empty()) {
+            return Err(Hinted::new(
+                "The patch has no hunks",
+                Some(
+                    "give each file a `--- old` and `+++ new` line, followed by `@@ -line +line @@` \
+                     hunks"
+                        .into(),
+                ),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into());
+        }
+
+        let mut changes: Vec<Change> = vec![];
+        for file in &files {
+            let git = file
+                .old
+                .as_deref()
+                .is_none_or(|name| name.starts_with("a/"))
+                && file
+                    .new
+                    .as_deref()
+                    .is_none_or(|name| name.starts_with("b/"));
+            let old = file.old.as_deref().map(|name| self.strip_name(name, git));
+            let new = file.new.as_deref().map(|name| self.strip_name(name, git));
+            // A renamed file is patched where it is, unless it has been renamed already
+            let path = match (old, new) {
+                (None, None) => continue,
+                (Some(old), Some(new)) if !state.resolve_new_path(new, None)?.exists() => {
+                    state.resolve_path(old, None)?
+                }
+                (None, Some(new)) => state.resolve_new_path(new, None)?,
+                (_, Some(name)) | (Some(name), None) => state.resolve_path(name, None)?,
+            };
+
+            let index = match changes.iter().position(|change| change.path == path) {
+                Some(index) => index,
+                None => {
+                    let before = match old {
+                        None if path.exists() => {
+                            return Err(Hinted::new(
+                                format!(
+                                    "The patch creates {}, which already exists",
+                                    path.display()
+                                ),
+                                Some("read it, and diff against its current contents".into()),
+                            )
+                            .with_code(ErrorCode::AlreadyExists)
+                            .with_path(&path)
+                            .into());
+                        }
+                        None => None,
+                        Some(_) if !path.exists() => return Err(hints::not_found(&path)),
+                        Some(_) => Some(std::fs::read_to_string(&path).hint_path(&path)?),
+                    };
+                    changes.push(Change {
+                        after: Some(Text::new(before.as_deref().unwrap_or_default())),
+                        path,
+                        before,
+                        outcomes: vec![],
+                    });
+                    changes.len() - 1
+                }
+            };
+
+            let change = &mut changes[index];
+            let Some(text) = &mut change.after else {
+                continue;
+            };
+            // How far lines have moved from where the patch expects them, by earlier hunks
+            let mut shift = 0isize;
+            let mut from = 0;
+            for hunk in &file.hunks {
+                let stated = hunk.old_start.map_or(from as isize, |start| {
+                    start.saturating_sub(1) as isize + shift
+                });
+                let expected = stated.max(0) as usize;
+                let outcome = match apply_hunk(text, hunk, expected, from, self.fuzz.unwrap_or(2)) {
+                    Ok((applied, end)) => {
+                        let added = hunk
+                            .lines
+                            .iter()
+                            .filter(|(marker, _)| *marker == '+')
+                            .count();
+                        let removed = hunk
+                            .lines
+                            .iter()
+                            .filter(|(marker, _)| *marker == '-')
+                            .count();
+                        shift += applied.offset + added as isize - removed as isize;
+                        from = end;
+                        Ok(applied)
+                    }
+                    Err(problem) => Err(problem),
+                };
+                change.outcomes.push((hunk.header.clone(), outcome));
+            }
+            if new.is_none() && text.lines.is_empty() {
+                change.after = None;
+            }
+        }
+
+        let hunks = changes
+            .iter()
+            .map(|change| change.outcomes.len())
+            .sum::<usize>();
+        let failed = changes
+            .iter()
+            .flat_map(|change| &change.outcomes)
+            .filter(|(_, outcome)| outcome.is_err())
+            .count();
+        let dry_run = self.dry_run.unwrap_or_default();
+
+        let counted = |count: usize, noun: &str| match count {
+            1 => format!("1 {noun}"),
+            count => format!("{count} {noun}s"),
+        };
+        let summary = format!(
+            "{} to {}",
+            counted(hunks, "hunk"),
+            counted(changes.len(), "file")
+        );
+        let mut output = match (failed, dry_run) {
+            (0, true) => format!(
+                "The patch would apply, {summary}. No filesystem operation has been performed\n"
+            ),
+            (0, false) => format!("Applied {summary}\n"),
+            (failed, _) => format!(
+                "{failed} of {} failed, so no file was changed. Fix or drop the failed \
+                 hunks and apply the patch again\n",
+                counted(hunks, "hunk")
+            ),
+        };
+        for change in &changes {
+            output.push_str(&format!("\n{}", change.path.display()));
+            match (&change.before, &change.after) {
+                (None, _) => output.push_str(" (created)"),
+                (_, None) => output.push_str(" (deleted)"),
+                _ => {}
+            }
+            output.push('\n');
+            for (number, (header, outcome)) in change.outcomes.iter().enumerate() {
+                let number = number + 1;
+                match outcome {
+                    Ok(Applied { line, offset, fuzz }) => {
+                        output.push_str(&format!("  hunk {number} applied at line {line}"));
+                        let mut notes = vec![];
+                        if *offset != 0 {
+                            notes.push(format!("offset {offset:+} lines"));
+                        }
+                        if *fuzz > 0 {
+                            notes.push(format!("fuzz {fuzz}"));
+                        }
+                        if !notes.is_empty() {
+                            output.push_str(&format!(" ({})", notes.join(", ")));
+                        }
+                        output.push('\n');
+                    }
+                    Err(problem) => {
+                        output.push_str(&format!("  hunk {number} FAILED {header}: {problem}\n"))
+                    }
+                }
+            }
+        }
+        if failed > 0 || dry_run {
+            return Ok(output);
+        }
+
+        let paths = changes
+            .iter()
+            .map(|change| change.path.as_path())
+            .collect::<Vec<_>>();
+        let _lock = state.lock_paths(&paths)?;
+        let settings = state.settings(None)?;
+        for change in &changes {
+            state.check_symlinks(&change.path, true)?;
+            if let Some(pattern) = settings.generated_pattern(&change.path) {
+                return Err(Hinted::new(
+                    format!(
+                        "{} matches the generated file pattern \"{pattern}\", so the patch was not \
+                         applied",
+                        change.path.display()
+                    ),
+                    Some(
+                        "regenerate it with the tool that owns it, and leave it out of the patch"
+                            .into(),
+                    ),
+                )
+                .with_path(&change.path)
+                .into());
+            }
+        }
+
+        let rendered = changes
+            .iter()
+            .map(|change| change.after.as_ref().map(Text::render))
+            .collect::<Vec<_>>();
+        state.charge_quota(QuotaUsage {
+            bytes_written: rendered
+                .iter()
+                .flatten()
+                .map(|after| after.len() as u64)
+                .sum(),
+            files_created: changes
+                .iter()
+                .filter(|change| change.before.is_none())
+                .count() as u64,
+            files_deleted: changes
+                .iter()
+                .filter(|change| change.after.is_none())
+                .count() as u64,
+        })?;
+        for (change, after) in changes.iter().zip(&rendered) {
+            match after {
+                Some(after) => {
+                    if let Some(parent) = change.path.parent() {
+                        std::fs::create_dir_all(parent).hint_path(parent)?;
+                    }
+                    std::fs::write(&change.path, after).hint_path(&change.path)?;
+                }
+                None => {
+                    trash::trash(&change.path)?;
+                }
+            }
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,295 @@
+use crate::{
+    hints::{self, ErrorCode, Hinted},
+    quota::QuotaUsage,
+    settings::Verbosity,
+    tools::FsTools,
+};
+use anyhow::{Context, Result, anyhow, bail};
+use clap::ArgAction;
+use flate2::{Compression, write::GzEncoder};
+use ignore::WalkBuilder;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind},
+    path::{Component, Path, PathBuf},
+};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+/// Bundle a directory, file, or glob of files into a zip or tar.gz archive
+#[derive(Debug, Serialize, Deserialize, JsonSchema, clap::Args)]
+#[serde(rename = "archive")]
+pub struct Archive {
+    /// Directory, file, or glob pattern (e.g. "dist/**/*.js") to include.
+    /// Can be absolute, or relative to session context path.
+    /// Directories are included recursively, skipping hidden and gitignored files
+    pub source: String,
+
+    /// Path of the archive to create.
+    /// Can be absolute, or relative to session context path.
+    pub destination: String,
+
+    /// Archive format. Inferred from the destination extension (.zip, .tar.gz, .tgz) if not
+    /// provided
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub format: Option<ArchiveFormat>,
+
+    /// Overwrite the destination archive if it exists
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub overwrite: Option<bool>,
+
+    /// Response detail: "quiet" names the archive, "normal" adds the file count and size, and
+    /// "detailed" also lists every file included
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub verbosity: Option<Verbosity>,
+}
+
+/// Supported archive formats
+#[derive(
+    Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// Use the explicit format if provided, otherwise infer it from the file name
+    pub(crate) fn detect(path: &Path, explicit: Option<Self>) -> Result<Self> {
+        if let Some(format) = explicit {
+            return Ok(format);
+        }
+
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        if file_name.ends_with(".zip") {
+            Ok(Self::Zip)
+        } else if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            Ok(Self::TarGz)
+        } else {
+            Err(anyhow!(
+                "Unable to infer archive format from {}, use `format` to specify \"zip\" or \"tar_gz\"",
+                path.display()
+            ))
+        }
+    }
+}
+
+/// Whether an archive entry path stays within the directory it is extracted into
+pub(crate) fn is_enclosed(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+impl WithExamples for Archive {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Bundling a build output directory relative to a session",
+                item: Self {
+                    source: "dist".into(),
+                    destination: "release/dist.tar.gz".into(),
+                    format: None,
+                    overwrite: None,
+                    verbosity: None,
+                },
+            },
+            Example {
+                description: "Zipping all markdown files, replacing an existing archive",
+                item: Self {
+                    source: "/some/absolute/path/docs/**/*.md".into(),
+                    destination: "/some/absolute/path/docs.zip".into(),
+                    format: None,
+                    overwrite: Some(true),
+                    verbosity: None,
+                },
+            },
+        ]
+    }
+}
+
+impl Archive {
+    fn overwrite(&self) -> bool {
+        self.overwrite.unwrap_or_default()
+    }
+
+    /// Collect the files to include, each paired with its name inside the archive
+    fn collect_files(&self, state: &mut FsTools) -> Result<Vec<(PathBuf, String)>> {
+        let mut files = Vec::new();
+
+        if self.source.contains(['*', '?', '[']) {
+            let pattern = state.resolve_path(&self.source, None)?;
+            let base: PathBuf = pattern
+                .components()
+                .take_while(|component| {
+                    !component
+                        .as_os_str()
+                        .to_string_lossy()
+                        .contains(['*', '?', '['])
+                })
+                .collect();
+
+            let pattern = pattern.to_string_lossy();
+            for path in glob::glob(&pattern)
+                .with_context(|| format!("Invalid glob pattern: {pattern}"))?
+                .flatten()
+            {
+                Self::collect_path(&path, &base, &mut files)?;
+            }
+        } else {
+            let path = state.resolve_path(&self.source, None)?;
+            if !path.exists() {
+                return Err(hints::not_found(&path));
+            }
+            let base = path.parent().map(Path::to_owned).unwrap_or_default();
+            Self::collect_path(&path, &base, &mut files)?;
+        }
+
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    fn collect_path(path: &Path, base: &Path, files: &mut Vec<(PathBuf, String)>) -> Result<()> {
+        let mut push = |path: &Path| {
+            let relative = path.strip_prefix(base).unwrap_or(path);
+            let name = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((path.to_owned(), name));
+        };
+
+        if path.is_dir() {
+            let walker = WalkBuilder::new(path).standard_filters(true).build();
+            for entry in walker {
+                let entry = entry?;
+                if entry.file_type().is_some_and(|ft| ft.is_file()) {
+                    push(entry.path());
+                }
+            }
+        } else if path.is_file() {
+            push(path);
+        }
+
+        Ok(())
+    }
+
+    fn write_zip(file: File, files: &[(PathBuf, String)]) -> Result<()> {
+        let mut zip = ZipWriter::new(file);
+        for (path, name) in files {
+            let mut options =
+                SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                options = options.unix_permissions(fs::metadata(path)?.permissions().mode());
+            }
+            zip.start_file(name, options)?;
+            let mut source =
+                File::open(path).with_context(|| format!("Unable to open {}", path.display()))?;
+            io::copy(&mut source, &mut zip)
+                .with_context(|| format!("Unable to read {}", path.display()))?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+
+    fn write_tar_gz(file: File, files: &[(PathBuf, String)]) -> Result<()> {
+        let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        for (path, name) in files {
+            builder
+                .append_path_with_name(path, name)
+                .with_context(|| format!("Unable to add {}", path.display()))?;
+        }
+        builder.into_inner()?.finish()?;
+        Ok(())
+    }
+}
+
+impl Tool<FsTools> for Archive {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let destination = state.resolve_new_path(&self.destination, None)?;
+        let format = ArchiveFormat::detect(&destination, self.format)?;
+
+        let mut files = self.collect_files(state)?;
+        files.retain(|(path, _)| path != &destination);
+        if files.is_empty() {
+            bail!("No files found matching {}", self.source);
+        }
+
+        // Charged for the files' full size, which compression only makes smaller, unless the
+        // archive is about to be refused for already existing
+        let exists = destination.exists();
+        if self.overwrite() || !exists {
+            let bytes = files
+                .iter()
+                .map(|(path, _)| fs::metadata(path).map_or(0, |metadata| metadata.len()))
+                .sum();
+            state.charge_quota(QuotaUsage::written(bytes, !exists))?;
+        }
+
+        let mut open_options = OpenOptions::new();
+        if self.overwrite() {
+            open_options.write(true).create(true).truncate(true);
+        } else {
+            open_options.write(true).create_new(true);
+        }
+
+        let file = match open_options.open(&destination) {
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                return Err(Hinted::new(
+                    format!("{} already exists", destination.display()),
+                    Some("use `overwrite` to intentionally replace it".into()),
+                )
+                .with_code(ErrorCode::AlreadyExists)
+                .with_path(&destination)
+                .into());
+            }
+            Err(e) => return Err(hints::io_error(e, &destination)),
+            Ok(file) => file,
+        };
+
+        match format {
+            ArchiveFormat::Zip => Self::write_zip(file, &files)?,
+            ArchiveFormat::TarGz => Self::write_tar_gz(file, &files)?,
+        }
+
+        let settings = state.settings(None)?;
+        let verbosity = settings.verbosity(self.verbosity);
+        if verbosity == Verbosity::Quiet {
+            return Ok(format!("Created {}", destination.display()));
+        }
+
+        let size = settings.format_size(fs::metadata(&destination)?.len());
+        let mut result = format!(
+            "Created {} ({} files, {size})",
+            destination.display(),
+            files.len()
+        );
+
+        if verbosity == Verbosity::Detailed {
+            result.push_str("\n\n");
+            for (_, name) in &files {
+                result.push_str(name);
+                result.push('\n');
+            }
+        }
+
+        Ok(result)
+    }
+}
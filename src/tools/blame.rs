@@ -0,0 +1,235 @@
+use crate::{
+    git,
+    hints::{self, ErrorCode, Hinted},
+    tools::FsTools,
+};
+use anyhow::Result;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+/// Show which commit last changed each line of a file, with its author and age, to tell whether
+/// code is long-settled or recently churned before editing it. Read-only
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "blame")]
+pub struct Blame {
+    /// File to blame
+    /// Can be absolute, or relative to session context path.
+    pub path: String,
+
+    /// Only blame lines `start` to `end`, inclusive and 1-based
+    /// Defaults to the whole file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, num_args = 2, value_names = ["START", "END"])]
+    pub lines: Option<Vec<usize>>,
+}
+
+impl WithExamples for Blame {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Checking how recently a function was changed before editing it",
+                item: Self {
+                    path: "src/parser.rs".into(),
+                    lines: Some(vec![120, 160]),
+                },
+            },
+            Example {
+                description: "Blaming a whole config file",
+                item: Self {
+                    path: "Cargo.toml".into(),
+                    lines: None,
+                },
+            },
+        ]
+    }
+}
+
+/// A commit as described by `git blame --porcelain`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct BlameCommit {
+    pub author: String,
+    /// Seconds since the epoch
+    pub time: u64,
+    pub summary: String,
+}
+
+/// One line of `git blame --porcelain`
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct BlameLine {
+    pub commit: String,
+    pub line: usize,
+    pub text: String,
+}
+
+impl Blame {
+    /// The lines and commits of `git blame --porcelain`. Commit details are only given the first
+    /// time a commit appears, so they are collected separately
+    pub(crate) fn parse(porcelain: &str) -> (Vec<BlameLine>, HashMap<String, BlameCommit>) {
+        let mut lines = vec![];
+        let mut commits: HashMap<String, BlameCommit> = HashMap::new();
+        let mut current: Option<(String, usize)> = None;
+        for row in porcelain.lines() {
+            if let Some(text) = row.strip_prefix('\t') {
+                if let Some((commit, line)) = current.take() {
+                    lines.push(BlameLine {
+                        commit,
+                        line,
+                        text: text.to_string(),
+                    });
+                }
+                continue;
+            }
+
+            let Some((key, value)) = row.split_once(' ') else {
+                continue;
+            };
+            match &current {
+                None => {
+                    let line = value
+                        .split(' ')
+                        .nth(1)
+                        .and_then(|line| line.parse().ok())
+                        .unwrap_or_default();
+                    commits.entry(key.to_string()).or_default();
+                    current = Some((key.to_string(), line));
+                }
+                Some((commit, _)) => {
+                    let commit = commits.entry(commit.clone()).or_default();
+                    match key {
+                        "author" => commit.author = value.to_string(),
+                        "author-time" => commit.time = value.parse().unwrap_or_default(),
+                        "summary" => commit.summary = value.to_string(),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        (lines, commits)
+    }
+
+    fn line_range(&self) -> Result<Option<(usize, usize)>> {
+        match self.lines.as_deref() {
+            None => Ok(None),
+            Some(&[start, end]) if start >= 1 && start <= end => Ok(Some((start, end))),
+            Some(lines) => Err(Hinted::new(
+                format!("`lines` must be [start, end] with 1 <= start <= end, not {lines:?}"),
+                Some("line numbers are 1-based and both ends are included".into()),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into()),
+        }
+    }
+
+    fn short(commit: &str) -> &str {
+        &commit[..8.min(commit.len())]
+    }
+
+    fn is_uncommitted(commit: &str) -> bool {
+        commit.bytes().all(|byte| byte == b'0')
+    }
+}
+
+impl Tool<FsTools> for Blame {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let path = state.resolve_path(&self.path, None)?;
+        if !path.is_file() {
+            return Err(hints::not_found(&path));
+        }
+        let line_range = self.line_range()?;
+
+        let mut args = vec!["blame".to_string(), "--porcelain".to_string()];
+        if let Some((start, end)) = line_range {
+            args.push(format!("-L{start},{end}"));
+        }
+        args.push("--".into());
+        args.push(path.to_string_lossy().into_owned());
+        let porcelain = git::run(git::directory_for(&path), &args).map_err(|error| {
+            if error.to_string().contains("no such path") {
+                Hinted::new(
+                    format!("{} is not tracked by git", path.display()),
+                    Some("commit it first; vcs_status lists the untracked files".into()),
+                )
+                .into()
+            } else {
+                error
+            }
+        })?;
+        let (lines, commits) = Self::parse(&porcelain);
+
+        let formatter = state.settings(None)?.timestamp_formatter();
+        let age = |commit: &str| match commits.get(commit) {
+            Some(_) if Self::is_uncommitted(commit) => "uncommitted".to_string(),
+            Some(details) => {
+                formatter.format(SystemTime::UNIX_EPOCH + Duration::from_secs(details.time))
+            }
+            None => String::new(),
+        };
+        let author = |commit: &str| match commits.get(commit) {
+            Some(_) if Self::is_uncommitted(commit) => String::new(),
+            Some(details) => details.author.clone(),
+            None => String::new(),
+        };
+
+        let rows = lines
+            .iter()
+            .map(|line| {
+                let commit = &line.commit;
+                (Self::short(commit), author(commit), age(commit))
+            })
+            .collect::<Vec<_>>();
+        let author_width = rows
+            .iter()
+            .map(|(_, author, _)| author.chars().count())
+            .max();
+        let age_width = rows.iter().map(|(_, _, age)| age.chars().count()).max();
+
+        let range = match (line_range, lines.first(), lines.last()) {
+            (Some((start, end)), _, _) => format!("lines {start}-{end}"),
+            (None, Some(first), Some(last)) => format!("lines {}-{}", first.line, last.line),
+            _ => "no lines".into(),
+        };
+        let mut newest: Vec<(&String, &BlameCommit)> = commits
+            .iter()
+            .filter(|(commit, _)| !Self::is_uncommitted(commit))
+            .collect();
+        newest.sort_by(|a, b| b.1.time.cmp(&a.1.time).then_with(|| a.0.cmp(b.0)));
+        let commit_count = match newest.len() {
+            1 => "1 commit".to_string(),
+            count => format!("{count} commits"),
+        };
+        let mut output = format!("Blame of {} {range} ({commit_count}", path.display());
+        if let Some((commit, _)) = newest.first() {
+            output.push_str(&format!(", last changed {}", age(commit)));
+        }
+        output.push_str("):\n\n");
+
+        let (author_width, age_width) = (author_width.unwrap_or(0), age_width.unwrap_or(0));
+        for ((commit, author, age), line) in rows.iter().zip(&lines) {
+            output.push_str(&format!(
+                "{commit:<8}  {author:<author_width$}  {age:<age_width$}  {:>5}\t{}\n",
+                line.line, line.text
+            ));
+        }
+
+        if !newest.is_empty() {
+            output.push_str("\nCommits, newest first:\n");
+            for (commit, details) in &newest {
+                output.push_str(&format!(
+                    "  {}  {}  {}: {}\n",
+                    Self::short(commit),
+                    age(commit),
+                    details.author,
+                    details.summary
+                ));
+            }
+        }
+        Ok(output)
+    }
+}
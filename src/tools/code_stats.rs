@@ -0,0 +1,283 @@
+use crate::{
+    deadline,
+    file_types::{self, CommentSyntax},
+    hints::{self, ErrorCode, Hinted, Unreadable},
+    project_ignore::ProjectIgnore,
+    tools::FsTools,
+    visibility::Visibility,
+};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Summarize a directory tree by language, with the number of files and their lines of code,
+/// comments, and blank lines (like `tokei`), for a cheap overview of an unfamiliar repository.
+/// Read-only
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "code_stats")]
+pub struct CodeStats {
+    /// Directory to summarize
+    /// Can be absolute, or relative to session context path.
+    /// Defaults to the current session context if not provided
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// Include paths excluded by the working directory's `.fs-mcp-ignore` file
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub bypass_ignore_file: Option<bool>,
+
+    /// Include hidden files and directories, whose names start with `.`
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_hidden: Option<bool>,
+
+    /// Include paths ignored by `.gitignore`, `.ignore`, and git's excludes, such as vendored
+    /// dependencies and build output. Paths in `.fs-mcp-ignore` are included with
+    /// `bypass_ignore_file` instead
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_ignored: Option<bool>,
+
+    /// Stop walking after this many milliseconds and return what was counted so far
+    /// Default: 30000, or the server's `--timeout-ms`, which also caps this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub time_limit_ms: Option<u64>,
+}
+
+impl WithExamples for CodeStats {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Getting an overview of the languages in the current project",
+                item: Self {
+                    path: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    time_limit_ms: None,
+                },
+            },
+            Example {
+                description: "Sizing up one crate of a workspace",
+                item: Self {
+                    path: Some("crates/parser".into()),
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    time_limit_ms: None,
+                },
+            },
+        ]
+    }
+}
+
+/// The lines of one language, or of one file
+#[derive(Debug, Default, Clone, Copy)]
+struct LineCounts {
+    files: usize,
+    code: usize,
+    comments: usize,
+    blanks: usize,
+}
+
+impl LineCounts {
+    /// Classify each line of `contents` as code, comment, or blank. A line with both code and
+    /// a comment counts as code
+    fn of(contents: &str, syntax: CommentSyntax) -> Self {
+        let mut counts = Self {
+            files: 1,
+            ..Self::default()
+        };
+        let mut in_block = false;
+        for line in contents.lines() {
+            let mut rest = line.trim();
+            if rest.is_empty() && !in_block {
+                counts.blanks += 1;
+                continue;
+            }
+
+            let mut has_code = false;
+            while !rest.is_empty() {
+                if in_block {
+                    let Some((_, end)) = syntax.block else {
+                        break;
+                    };
+                    match rest.find(end) {
+                        Some(index) => {
+                            in_block = false;
+                            rest = rest[index + end.len()..].trim_start();
+                        }
+                        None => break,
+                    }
+                } else if syntax.line.iter().any(|marker| rest.starts_with(marker)) {
+                    break;
+                } else if let Some((start, _)) =
+                    syntax.block.filter(|(start, _)| rest.starts_with(start))
+                {
+                    in_block = true;
+                    rest = &rest[start.len()..];
+                } else {
+                    has_code = true;
+                    // Comments may still start after the code on this line
+                    match syntax.block.and_then(|(start, _)| rest.find(start)) {
+                        Some(index) => rest = &rest[index..],
+                        None => break,
+                    }
+                }
+            }
+
+            if has_code {
+                counts.code += 1;
+            } else {
+                counts.comments += 1;
+            }
+        }
+        counts
+    }
+
+    fn add(&mut self, other: Self) {
+        self.files += other.files;
+        self.code += other.code;
+        self.comments += other.comments;
+        self.blanks += other.blanks;
+    }
+
+    fn lines(&self) -> usize {
+        self.code + self.comments + self.blanks
+    }
+}
+
+impl Tool<FsTools> for CodeStats {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let base_path = state.resolve_path(self.path.as_deref().unwrap_or("."), None)?;
+        if !base_path.exists() {
+            return Err(hints::not_found(&base_path));
+        }
+        if !base_path.is_dir() {
+            return Err(Hinted::new(
+                format!("Path is not a directory: {}", base_path.display()),
+                Some("use file_stats to count the lines of a single file".into()),
+            )
+            .with_code(ErrorCode::NotADirectory)
+            .with_path(&base_path)
+            .into());
+        }
+
+        let ignore = ProjectIgnore::load(state, self.bypass_ignore_file.unwrap_or_default())?;
+        let walk = Visibility::new(self.include_hidden, self.include_ignored)
+            .walk_builder(&base_path)
+            .filter_entry(move |entry| {
+                let is_dir = entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_dir());
+                !ignore.is_ignored(entry.path(), is_dir)
+            })
+            .build();
+
+        let mut unreadable = Unreadable::default();
+        let mut languages: BTreeMap<&'static str, LineCounts> = BTreeMap::new();
+        let mut other_files = 0;
+        let deadline = state.deadline(self.time_limit_ms, deadline::DEFAULT_TIME_LIMIT);
+        let mut timed_out = false;
+        for entry in walk {
+            if deadline.is_reached() {
+                timed_out = true;
+                break;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    unreadable.record_walk_error(&error);
+                    continue;
+                }
+            };
+            if !entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+            {
+                continue;
+            }
+            let Some(language) = file_types::language(entry.path()) else {
+                other_files += 1;
+                continue;
+            };
+            match std::fs::read(entry.path()) {
+                Ok(bytes) => {
+                    let counts = LineCounts::of(
+                        &String::from_utf8_lossy(&bytes),
+                        file_types::comment_syntax(language),
+                    );
+                    languages.entry(language).or_default().add(counts);
+                }
+                Err(error) => unreadable.record(entry.path(), error),
+            }
+        }
+
+        let mut total = LineCounts::default();
+        for counts in languages.values() {
+            total.add(*counts);
+        }
+        let mut output = match total.files {
+            0 => format!(
+                "No files in a recognized language under {}\n",
+                base_path.display()
+            ),
+            _ => {
+                let mut languages = languages.into_iter().collect::<Vec<_>>();
+                languages.sort_by(|(a_name, a), (b_name, b)| {
+                    b.code.cmp(&a.code).then_with(|| a_name.cmp(b_name))
+                });
+                let rows = languages
+                    .iter()
+                    .map(|(language, counts)| (language.to_string(), *counts))
+                    .chain([("total".to_string(), total)])
+                    .collect::<Vec<_>>();
+                let width = rows
+                    .iter()
+                    .map(|(name, _)| name.len())
+                    .max()
+                    .unwrap_or(0)
+                    .max("language".len());
+
+                let mut output = format!(
+                    "Code statistics for {}:\n\n{:width$} | files | code | comments | blanks | lines\n",
+                    base_path.display(),
+                    "language"
+                );
+                for (name, counts) in rows {
+                    output.push_str(&format!(
+                        "{name:width$} | {} | {} | {} | {} | {}\n",
+                        counts.files,
+                        counts.code,
+                        counts.comments,
+                        counts.blanks,
+                        counts.lines()
+                    ));
+                }
+                output
+            }
+        };
+
+        if other_files > 0 {
+            output.push_str(&format!(
+                "\n{other_files} other files, in formats without a recognized language, were not \
+                 counted\n"
+            ));
+        }
+        if timed_out {
+            output.push_str(&deadline.note());
+        }
+        unreadable.append_to(&mut output);
+        Ok(output)
+    }
+}
@@ -0,0 +1,320 @@
+use crate::{
+    hints::{self, ErrorCode, Hinted, Unreadable},
+    tools::FsTools,
+};
+use anyhow::Result;
+use clap::ArgAction;
+use ignore::WalkBuilder;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Compare two directory trees, listing the paths only in one of them and the files whose
+/// contents differ, optionally with a unified diff of each changed text file. Useful for
+/// checking generated output against an expected fixture tree. Read-only
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "compare_directories")]
+pub struct CompareDirectories {
+    /// The first directory, e.g. the expected tree
+    pub left: String,
+
+    /// The second directory, e.g. the generated tree
+    pub right: String,
+
+    /// Include a unified diff of every changed text file
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub diff: Option<bool>,
+
+    /// Skip paths ignored by `.gitignore` and similar files, which are compared by default since
+    /// generated trees are often ignored
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub respect_gitignore: Option<bool>,
+
+    /// Maximum number of paths listed in each section
+    /// Default: 50
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub max_entries: Option<usize>,
+}
+
+impl WithExamples for CompareDirectories {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Checking generated output against the expected fixtures",
+                item: Self {
+                    left: "tests/fixtures/expected".into(),
+                    right: "target/generated".into(),
+                    diff: Some(true),
+                    respect_gitignore: None,
+                    max_entries: None,
+                },
+            },
+            Example {
+                description: "Seeing which files differ between two checkouts",
+                item: Self {
+                    left: "/src/project-v1".into(),
+                    right: "/src/project-v2".into(),
+                    diff: None,
+                    respect_gitignore: Some(true),
+                    max_entries: Some(20),
+                },
+            },
+        ]
+    }
+}
+
+/// How a path present in both trees compares
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Difference {
+    /// Different contents, with the sizes on each side
+    Contents(u64, u64),
+    /// A file on one side and a directory on the other
+    Kind,
+}
+
+/// The result of comparing two trees, with paths relative to their roots
+#[derive(Debug, Default)]
+pub(crate) struct Comparison {
+    pub only_left: Vec<PathBuf>,
+    pub only_right: Vec<PathBuf>,
+    pub differing: Vec<(PathBuf, Difference)>,
+    pub identical: usize,
+}
+
+impl CompareDirectories {
+    /// Every path under `root` except `.git`, relative to it, and whether it is a directory
+    fn entries(
+        root: &Path,
+        respect_gitignore: bool,
+        unreadable: &mut Unreadable,
+    ) -> BTreeMap<PathBuf, bool> {
+        let mut entries = BTreeMap::new();
+        let walk = WalkBuilder::new(root)
+            .standard_filters(respect_gitignore)
+            .hidden(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build();
+        for entry in walk {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    unreadable.record_walk_error(&error);
+                    continue;
+                }
+            };
+            let Ok(relative) = entry.path().strip_prefix(root) else {
+                continue;
+            };
+            if relative.as_os_str().is_empty() {
+                continue;
+            }
+            let is_dir = entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_dir());
+            entries.insert(relative.to_path_buf(), is_dir);
+        }
+        entries
+    }
+
+    /// Compare the trees at `left` and `right`. Paths inside a directory that is only on one
+    /// side are covered by that directory and not listed separately
+    pub(crate) fn compare(
+        left: &Path,
+        right: &Path,
+        respect_gitignore: bool,
+        unreadable: &mut Unreadable,
+    ) -> Comparison {
+        let left_entries = Self::entries(left, respect_gitignore, unreadable);
+        let right_entries = Self::entries(right, respect_gitignore, unreadable);
+        let mut comparison = Comparison::default();
+
+        let only_in = |entries: &BTreeMap<PathBuf, bool>, other: &BTreeMap<PathBuf, bool>| {
+            entries
+                .keys()
+                .filter(|path| {
+                    !other.contains_key(*path)
+                        && path.parent().is_none_or(|parent| {
+                            parent.as_os_str().is_empty() || other.contains_key(parent)
+                        })
+                })
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        comparison.only_left = only_in(&left_entries, &right_entries);
+        comparison.only_right = only_in(&right_entries, &left_entries);
+
+        for (path, &left_is_dir) in &left_entries {
+            let Some(&right_is_dir) = right_entries.get(path) else {
+                continue;
+            };
+            if left_is_dir != right_is_dir {
+                comparison.differing.push((path.clone(), Difference::Kind));
+                continue;
+            }
+            if left_is_dir {
+                continue;
+            }
+
+            let (left_path, right_path) = (left.join(path), right.join(path));
+            let (left_contents, right_contents) =
+                match (fs::read(&left_path), fs::read(&right_path)) {
+                    (Ok(left_contents), Ok(right_contents)) => (left_contents, right_contents),
+                    (Err(error), _) => {
+                        unreadable.record(&left_path, error.kind());
+                        continue;
+                    }
+                    (_, Err(error)) => {
+                        unreadable.record(&right_path, error.kind());
+                        continue;
+                    }
+                };
+            if left_contents == right_contents {
+                comparison.identical += 1;
+            } else {
+                comparison.differing.push((
+                    path.clone(),
+                    Difference::Contents(left_contents.len() as u64, right_contents.len() as u64),
+                ));
+            }
+        }
+
+        comparison
+    }
+
+    fn push_section(output: &mut String, title: &str, paths: &[String], max_entries: usize) {
+        if paths.is_empty() {
+            return;
+        }
+        output.push_str(&format!("\n{title} ({}):\n", paths.len()));
+        for path in paths.iter().take(max_entries) {
+            output.push_str(&format!("  {path}\n"));
+        }
+        if paths.len() > max_entries {
+            output.push_str(&format!("  ... and {} more\n", paths.len() - max_entries));
+        }
+    }
+}
+
+impl Tool<FsTools> for CompareDirectories {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let left = state.resolve_path(&self.left, None)?;
+        let right = state.resolve_path(&self.right, None)?;
+        for path in [&left, &right] {
+            if !path.exists() {
+                return Err(hints::not_found(path));
+            }
+            if !path.is_dir() {
+                return Err(Hinted::new(
+                    format!("Path is not a directory: {}", path.display()),
+                    Some("compare two files with read, or pass their parent directories".into()),
+                )
+                .with_code(ErrorCode::NotADirectory)
+                .with_path(path)
+                .into());
+            }
+        }
+
+        let mut unreadable = Unreadable::default();
+        let comparison = Self::compare(
+            &left,
+            &right,
+            self.respect_gitignore.unwrap_or_default(),
+            &mut unreadable,
+        );
+        let settings = state.settings(None)?;
+        let max_entries = self.max_entries.unwrap_or(50);
+
+        let mut output = format!(
+            "Compared {} (left) with {} (right): {} only in left, {} only in right, {} differ, {} \
+             identical\n",
+            left.display(),
+            right.display(),
+            comparison.only_left.len(),
+            comparison.only_right.len(),
+            comparison.differing.len(),
+            comparison.identical
+        );
+
+        let display = |root: &Path, path: &PathBuf| {
+            if root.join(path).is_dir() {
+                format!("{}/", path.display())
+            } else {
+                path.display().to_string()
+            }
+        };
+        let only_left = comparison
+            .only_left
+            .iter()
+            .map(|path| display(&left, path))
+            .collect::<Vec<_>>();
+        let only_right = comparison
+            .only_right
+            .iter()
+            .map(|path| display(&right, path))
+            .collect::<Vec<_>>();
+        let differing = comparison
+            .differing
+            .iter()
+            .map(|(path, difference)| match difference {
+                Difference::Contents(left_size, right_size) if left_size == right_size => {
+                    format!("{} (same size, different contents)", path.display())
+                }
+                Difference::Contents(left_size, right_size) => format!(
+                    "{} ({} vs {})",
+                    path.display(),
+                    settings.format_size(*left_size),
+                    settings.format_size(*right_size)
+                ),
+                Difference::Kind if left.join(path).is_dir() => {
+                    format!("{} (directory in left, file in right)", path.display())
+                }
+                Difference::Kind => {
+                    format!("{} (file in left, directory in right)", path.display())
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Self::push_section(&mut output, "Only in left", &only_left, max_entries);
+        Self::push_section(&mut output, "Only in right", &only_right, max_entries);
+        Self::push_section(&mut output, "Differ", &differing, max_entries);
+
+        if self.diff.unwrap_or_default() {
+            for (path, difference) in comparison.differing.iter().take(max_entries) {
+                if *difference == Difference::Kind {
+                    continue;
+                }
+                let (Ok(before), Ok(after)) = (
+                    fs::read_to_string(left.join(path)),
+                    fs::read_to_string(right.join(path)),
+                ) else {
+                    output.push_str(&format!("\nBinary file {} differs\n", path.display()));
+                    continue;
+                };
+                let path = path.display().to_string();
+                output.push('\n');
+                output.push_str(
+                    &TextDiff::from_lines(&before, &after)
+                        .unified_diff()
+                        .header(&format!("left/{path}"), &format!("right/{path}"))
+                        .to_string(),
+                );
+            }
+        }
+
+        unreadable.append_to(&mut output);
+        Ok(output)
+    }
+}
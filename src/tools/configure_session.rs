@@ -0,0 +1,300 @@
+use crate::{
+    hints::Hinted,
+    settings::{SizeUnits, Timestamps, Verbosity},
+    tools::FsTools,
+};
+use anyhow::{Result, anyhow};
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+/// View or change session-level preferences. Fields that are not provided are left unchanged
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "configure_session")]
+pub struct ConfigureSession {
+    /// Language for relative times like "3 hours ago", as an ISO 639-1 code (e.g. "en", "de", "ja")
+    ///
+    /// Default: "en"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub locale: Option<String>,
+
+    /// Units for file sizes: "binary" (KiB, MiB), "decimal" (KB, MB), or "bytes" (exact byte
+    /// counts, useful when output is parsed by a program)
+    ///
+    /// Default: "binary"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub size_units: Option<SizeUnits>,
+
+    /// How points in time are displayed: "relative" ("3 hours ago"), "iso8601"
+    /// ("2024-01-15T09:30:00Z"), or "both". Absolute times stay unambiguous when a
+    /// conversation spans hours
+    ///
+    /// Default: "relative"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub timestamps: Option<Timestamps>,
+
+    /// Default response detail for tools that accept a `verbosity` parameter: "quiet" (minimal
+    /// confirmation), "normal", or "detailed" (diffs, metadata, and hints). Individual calls can
+    /// still override this
+    ///
+    /// Default: "normal"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub verbosity: Option<Verbosity>,
+
+    /// Whether write, move, and touch create missing parent directories when their
+    /// `create_directories` parameter is not provided. Individual calls can still override this
+    ///
+    /// Default: true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub auto_create_directories: Option<bool>,
+
+    /// Glob patterns for generated files (lockfiles, generated code) that write refuses to
+    /// change unless called with `allow_generated`. Patterns containing a `/` match the whole
+    /// path, others match the file name. Replaces the current list; pass an empty list to
+    /// disable the guard
+    ///
+    /// Default: ["Cargo.lock", "package-lock.json", "yarn.lock", "pnpm-lock.yaml", "*.pb.go",
+    /// "*/generated/*"]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, num_args = 0..)]
+    pub generated_patterns: Option<Vec<String>>,
+
+    /// Whether `$VAR` and `${VAR}` in path arguments expand to environment variables, e.g.
+    /// `$CARGO_HOME/registry`, when no path alias has that name. Off by default, since the
+    /// expanded path shows up in responses and errors, and a variable may hold a secret. A
+    /// variable that isn't set is always left as written
+    ///
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub expand_env_vars: Option<bool>,
+
+    /// When a path doesn't exist, use the one existing path that matches it ignoring case,
+    /// e.g. `src/main.rs` for `SRC/Main.rs`, and note it in the response. Helps with paths
+    /// written for case-insensitive filesystems. Nothing is used if several paths match, and
+    /// paths a tool creates, such as write and move destinations, are always used as given
+    ///
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub case_insensitive_paths: Option<bool>,
+
+    /// Hold high-risk calls (recursive deletes, overwrites of large files, and renames across
+    /// files) as pending operations, which are only applied when confirm_operation is called
+    /// with the token they return. Gives the user a chance to approve them first. Once it is on,
+    /// only a person can turn it off or raise `confirm_overwrite_above_kb`, by running
+    /// `fs-mcp configure-session` in a terminal
+    ///
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub confirmation_mode: Option<bool>,
+
+    /// In confirmation mode, overwrites of files larger than this many KiB are held
+    ///
+    /// Default: 64
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub confirm_overwrite_above_kb: Option<u64>,
+}
+
+impl WithExamples for ConfigureSession {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Viewing the current preferences",
+                item: Self {
+                    locale: None,
+                    size_units: None,
+                    timestamps: None,
+                    verbosity: None,
+                    auto_create_directories: None,
+                    generated_patterns: None,
+                    expand_env_vars: None,
+                    case_insensitive_paths: None,
+                    confirmation_mode: None,
+                    confirm_overwrite_above_kb: None,
+                },
+            },
+            Example {
+                description: "Machine-friendly output with exact byte counts and absolute timestamps",
+                item: Self {
+                    locale: None,
+                    size_units: Some(SizeUnits::Bytes),
+                    timestamps: Some(Timestamps::Iso8601),
+                    verbosity: None,
+                    auto_create_directories: None,
+                    generated_patterns: None,
+                    expand_env_vars: None,
+                    case_insensitive_paths: None,
+                    confirmation_mode: None,
+                    confirm_overwrite_above_kb: None,
+                },
+            },
+            Example {
+                description: "Keeping responses short to save tokens",
+                item: Self {
+                    locale: None,
+                    size_units: None,
+                    timestamps: None,
+                    verbosity: Some(Verbosity::Quiet),
+                    auto_create_directories: None,
+                    generated_patterns: None,
+                    expand_env_vars: None,
+                    case_insensitive_paths: None,
+                    confirmation_mode: None,
+                    confirm_overwrite_above_kb: None,
+                },
+            },
+            Example {
+                description: "Never creating parent directories implicitly",
+                item: Self {
+                    locale: None,
+                    size_units: None,
+                    timestamps: None,
+                    verbosity: None,
+                    auto_create_directories: Some(false),
+                    generated_patterns: None,
+                    expand_env_vars: None,
+                    case_insensitive_paths: None,
+                    confirmation_mode: None,
+                    confirm_overwrite_above_kb: None,
+                },
+            },
+            Example {
+                description: "Holding destructive calls until the user approves them",
+                item: Self {
+                    locale: None,
+                    size_units: None,
+                    timestamps: None,
+                    verbosity: None,
+                    auto_create_directories: None,
+                    generated_patterns: None,
+                    expand_env_vars: None,
+                    case_insensitive_paths: None,
+                    confirmation_mode: Some(true),
+                    confirm_overwrite_above_kb: Some(16),
+                },
+            },
+            Example {
+                description: "German relative times",
+                item: Self {
+                    locale: Some("de".into()),
+                    size_units: None,
+                    timestamps: None,
+                    verbosity: None,
+                    auto_create_directories: None,
+                    generated_patterns: None,
+                    expand_env_vars: None,
+                    case_insensitive_paths: None,
+                    confirmation_mode: None,
+                    confirm_overwrite_above_kb: None,
+                },
+            },
+        ]
+    }
+}
+
+impl Tool<FsTools> for ConfigureSession {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        if let Some(locale) = &self.locale {
+            if crate::settings::language(locale).is_none() {
+                return Err(anyhow!("Unsupported locale: {locale}"));
+            }
+        }
+
+        for pattern in self.generated_patterns.iter().flatten() {
+            glob::Pattern::new(pattern)
+                .map_err(|e| anyhow!("Invalid generated file pattern {pattern}: {e}"))?;
+        }
+
+        let current = state.settings(None)?;
+        let loosens_confirmation = current.confirmation_mode()
+            && (self.confirmation_mode == Some(false)
+                || self
+                    .confirm_overwrite_above_kb
+                    .is_some_and(|kb| kb > current.confirm_overwrite_above_kb()));
+        if state.serving() && loosens_confirmation {
+            return Err(Hinted::new(
+                "Confirmation mode can only be turned off or loosened by a person, so nothing was \
+                 changed",
+                Some(
+                    "ask a person to run `fs-mcp configure-session --confirmation-mode false` in \
+                     a terminal"
+                        .into(),
+                ),
+            )
+            .into());
+        }
+
+        state.update_settings(None, |settings| {
+            if let Some(locale) = self.locale {
+                settings.locale = Some(locale.to_lowercase());
+            }
+            if let Some(size_units) = self.size_units {
+                settings.size_units = Some(size_units);
+            }
+            if let Some(timestamps) = self.timestamps {
+                settings.timestamps = Some(timestamps);
+            }
+            if let Some(verbosity) = self.verbosity {
+                settings.verbosity = Some(verbosity);
+            }
+            if let Some(auto_create_directories) = self.auto_create_directories {
+                settings.auto_create_directories = Some(auto_create_directories);
+            }
+            if let Some(generated_patterns) = self.generated_patterns {
+                settings.generated_patterns = Some(generated_patterns);
+            }
+            if let Some(expand_env_vars) = self.expand_env_vars {
+                settings.expand_env_vars = Some(expand_env_vars);
+            }
+            if let Some(case_insensitive_paths) = self.case_insensitive_paths {
+                settings.case_insensitive_paths = Some(case_insensitive_paths);
+            }
+            if let Some(confirmation_mode) = self.confirmation_mode {
+                settings.confirmation_mode = Some(confirmation_mode);
+            }
+            if let Some(confirm_overwrite_above_kb) = self.confirm_overwrite_above_kb {
+                settings.confirm_overwrite_above_kb = Some(confirm_overwrite_above_kb);
+            }
+        })?;
+
+        let settings = state.settings(None)?;
+        Ok(format!(
+            "Session preferences:\n\
+             locale: {}\n\
+             size_units: {} (e.g. {})\n\
+             timestamps: {} (e.g. {})\n\
+             verbosity: {}\n\
+             auto_create_directories: {}\n\
+             generated_patterns: {:?}\n\
+             expand_env_vars: {}\n\
+             case_insensitive_paths: {}\n\
+             confirmation_mode: {}\n\
+             confirm_overwrite_above_kb: {}",
+            settings.locale(),
+            settings.size_units(),
+            settings.format_size(123_456_789),
+            settings.timestamps(),
+            settings
+                .timestamp_formatter()
+                .format(SystemTime::now() - Duration::from_secs(3 * 60 * 60)),
+            settings.verbosity(None),
+            settings.create_directories(None),
+            settings.generated_patterns(),
+            settings.expand_env_vars(),
+            settings.case_insensitive_paths(),
+            settings.confirmation_mode(),
+            settings.confirm_overwrite_above_kb(),
+        ))
+    }
+}
@@ -0,0 +1,193 @@
+use super::{Read, Tools, r#move::Impact, read::is_glob};
+use crate::{
+    hints::{ErrorCode, Hint, Hinted},
+    settings::SessionSettings,
+    state::PendingOperation,
+    tools::FsTools,
+};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// Apply an operation that confirmation mode held back, using the token it returned, or
+/// discard it with `cancel`. Confirmation mode is turned on with configure_session
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "confirm_operation")]
+pub struct ConfirmOperation {
+    /// The token of the pending operation, as returned by the call that was held
+    pub token: String,
+
+    /// Discard the pending operation instead of applying it
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub cancel: Option<bool>,
+}
+
+impl WithExamples for ConfirmOperation {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Applying a recursive delete after the user approved it",
+                item: Self {
+                    token: "q7x2mv".into(),
+                    cancel: None,
+                },
+            },
+            Example {
+                description: "Discarding an overwrite that is no longer wanted",
+                item: Self {
+                    token: "q7x2mv".into(),
+                    cancel: Some(true),
+                },
+            },
+        ]
+    }
+}
+
+impl ConfirmOperation {
+    /// Run `tool`, unless the session is in confirmation mode and it is a high-risk call, in
+    /// which case it is held as a pending operation and its token is returned instead
+    pub(crate) fn execute_or_hold(mut tool: Tools, state: &mut FsTools) -> Result<String> {
+        let settings = state.settings(None)?;
+        if !settings.confirmation_mode() {
+            return tool.execute(state);
+        }
+        let Some(description) = Self::risk(&mut tool, state, &settings)? else {
+            return tool.execute(state);
+        };
+
+        let token = std::iter::repeat_with(fastrand::alphanumeric)
+            .take(6)
+            .collect::<String>()
+            .to_lowercase();
+        let context_path = state.get_context(None)?;
+        state.add_pending_operation(PendingOperation {
+            token: token.clone(),
+            call: serde_json::to_value(&tool)?,
+            description: description.clone(),
+            context_path,
+            created_at: SystemTime::now(),
+        })?;
+
+        Ok(format!(
+            "Confirmation required: this call would {description}. It is pending as {token}, and \
+             no filesystem operation has been performed.\nAfter the user approves, call \
+             confirm_operation with `token: \"{token}\"` to apply it, or with `cancel: true` to \
+             discard it"
+        ))
+    }
+
+    /// What `tool` would do, if it is a call that confirmation mode holds: a recursive delete,
+    /// an overwrite of a large file, or a rename across files. The call is adjusted to skip
+    /// its own `confirm` step, since confirming the token replaces it
+    fn risk(
+        tool: &mut Tools,
+        state: &mut FsTools,
+        settings: &SessionSettings,
+    ) -> Result<Option<String>> {
+        match tool {
+            Tools::Delete(delete) if !delete.stage.unwrap_or_default() => {
+                if is_glob(state, &delete.path) {
+                    // Without `confirm`, a glob delete only lists its matches
+                    if !delete.confirm.unwrap_or_default() {
+                        return Ok(None);
+                    }
+                    let matches = Read::expand_glob(state, &delete.path)?;
+                    return Ok(Some(format!(
+                        "delete the {} files matched by {}",
+                        matches.len(),
+                        delete.path
+                    )));
+                }
+
+                let path = state.resolve_path(&delete.path, None)?;
+                if !std::fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.is_dir()) {
+                    return Ok(None);
+                }
+                let impact = Impact::measure(&path).hint_path(&path)?;
+                delete.confirm = Some(true);
+                Ok(Some(format!(
+                    "delete {} and everything in it ({})",
+                    path.display(),
+                    impact.describe(settings)
+                )))
+            }
+
+            Tools::Write(write) if write.overwrite.unwrap_or_default() => {
+                let path = state.resolve_path(&write.path, None)?;
+                let Ok(metadata) = std::fs::metadata(&path) else {
+                    return Ok(None);
+                };
+                let threshold = settings.confirm_overwrite_above_kb() * 1024;
+                Ok((metadata.len() > threshold).then(|| {
+                    format!(
+                        "overwrite {} ({})",
+                        path.display(),
+                        settings.format_size(metadata.len())
+                    )
+                }))
+            }
+
+            Tools::RenameSymbol(rename) if rename.confirm.unwrap_or_default() => Ok(Some(format!(
+                "rename {} to {} in every file matched by {}",
+                rename.symbol, rename.replacement, rename.files
+            ))),
+
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Tool<FsTools> for ConfirmOperation {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let Some(operation) = state.take_pending_operation(&self.token)? else {
+            let pending = state.pending_operations()?;
+            let suggestion = if pending.is_empty() {
+                "no operations are pending; tokens are only handed out in confirmation mode".into()
+            } else {
+                format!(
+                    "pending operations are: {}",
+                    pending
+                        .iter()
+                        .map(|operation| format!("{} ({})", operation.token, operation.description))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            };
+            return Err(Hinted::new(
+                format!("No operation is pending as {}", self.token),
+                Some(suggestion),
+            )
+            .into());
+        };
+
+        if self.cancel.unwrap_or_default() {
+            return Ok(format!(
+                "Discarded {}, which would {}. No filesystem operation has been performed",
+                operation.token, operation.description
+            ));
+        }
+
+        if state.get_context(None)? != operation.context_path {
+            return Err(Hinted::new(
+                format!(
+                    "The working directory has changed since {} was held, so its relative paths \
+                     may mean something else now. It has been discarded",
+                    operation.token
+                ),
+                Some("make the original call again to get a new token".into()),
+            )
+            .with_code(ErrorCode::Conflict)
+            .into());
+        }
+
+        let tool: Tools = serde_json::from_value(operation.call)?;
+        tool.execute(state)
+    }
+}
@@ -0,0 +1,130 @@
+use super::set_path_alias::alias_name;
+use crate::{
+    hints::{ErrorCode, Hinted},
+    tools::FsTools,
+};
+use anyhow::{Result, anyhow};
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Create a session for a sub-agent that starts out with this session's working directory,
+/// preferences, and path aliases, so the sub-agent doesn't have to repeat the setup. The
+/// sub-agent uses it by running fs-mcp with the environment variable `FS_MCP_SESSION` set to
+/// the new session's name
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "create_child_session")]
+pub struct CreateChildSession {
+    /// Name of the new session, made of letters, digits, `-`, and `_`
+    pub name: String,
+
+    /// Working directory for the new session, instead of this session's
+    /// Can be absolute, or relative to session context path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub working_directory: Option<String>,
+
+    /// Additional or replaced path aliases for the new session, as `NAME=path`
+    /// Paths can be absolute, or relative to session context path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub aliases: Option<Vec<String>>,
+
+    /// Replace an existing session with the same name
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub overwrite: Option<bool>,
+}
+
+impl WithExamples for CreateChildSession {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Handing a subtask to a sub-agent with the same setup",
+                item: Self {
+                    name: "docs-review".into(),
+                    working_directory: None,
+                    aliases: None,
+                    overwrite: None,
+                },
+            },
+            Example {
+                description: "Scoping a sub-agent to one crate of a workspace",
+                item: Self {
+                    name: "core-tests".into(),
+                    working_directory: Some("crates/core".into()),
+                    aliases: Some(vec!["FIXTURES=crates/core/tests/fixtures".into()]),
+                    overwrite: None,
+                },
+            },
+        ]
+    }
+}
+
+impl Tool<FsTools> for CreateChildSession {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let name = &self.name;
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(anyhow!(
+                "Invalid session name {name}: use letters, digits, `-`, and `_`"
+            ));
+        }
+
+        if name == state.default_session_id() {
+            return Err(anyhow!("{name} is the current session"));
+        }
+
+        if state.session_exists(name)? && !self.overwrite.unwrap_or_default() {
+            return Err(Hinted::new(
+                format!("A session named {name} already exists"),
+                Some("choose another name, or use `overwrite` to replace it".into()),
+            )
+            .with_code(ErrorCode::AlreadyExists)
+            .into());
+        }
+
+        let context_path = self
+            .working_directory
+            .as_deref()
+            .map(|path| state.resolve_path(path, None))
+            .transpose()?;
+
+        let mut aliases = BTreeMap::new();
+        for alias in self.aliases.iter().flatten() {
+            let Some((alias, path)) = alias.split_once('=') else {
+                return Err(anyhow!("Invalid alias {alias}: use NAME=path"));
+            };
+            let alias = alias_name(alias, '$')?;
+            aliases.insert(alias.to_string(), state.resolve_path(path, None)?);
+        }
+
+        let child = state.create_child_session(name, context_path, aliases)?;
+        let context_path = state.get_context(Some(name))?;
+
+        let mut output = format!(
+            "Created session {name} from {}\nWorking directory: {}\n",
+            state.default_session_id(),
+            context_path.map_or_else(|| "(none)".into(), |path| path.display().to_string())
+        );
+        if !child.path_aliases.is_empty() {
+            output.push_str("Aliases:\n");
+            for (alias, path) in &child.path_aliases {
+                output.push_str(&format!("  ${alias} = {}\n", path.display()));
+            }
+        }
+        output.push_str(&format!(
+            "\nRun the sub-agent's fs-mcp with FS_MCP_SESSION={name} to use this session"
+        ));
+
+        Ok(output)
+    }
+}
@@ -0,0 +1,51 @@
+use super::set_path_alias::update_alias;
+use crate::tools::FsTools;
+use anyhow::Result;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+
+/// Register a shorthand name for a path in this session, so that e.g. `@tests/fixtures/a.json`
+/// can be used in any path argument instead of `crates/core/tests/fixtures/a.json`. Shares its
+/// names with set_path_alias
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "define_alias")]
+pub struct DefineAlias {
+    /// Shorthand name, made of letters, digits, and underscores (e.g. "tests"). A leading `@` is
+    /// ignored
+    pub name: String,
+
+    /// Path the shorthand stands for. Omit to remove the alias
+    /// Can be absolute, or relative to session context path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl WithExamples for DefineAlias {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Registering a shorthand for a deeply nested test directory",
+                item: Self {
+                    name: "tests".into(),
+                    path: Some("crates/core/tests".into()),
+                },
+            },
+            Example {
+                description: "Removing a shorthand",
+                item: Self {
+                    name: "@tests".into(),
+                    path: None,
+                },
+            },
+        ]
+    }
+}
+
+impl Tool<FsTools> for DefineAlias {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        update_alias(state, &self.name, self.path.as_deref(), '@')
+    }
+}
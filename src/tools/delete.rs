@@ -1,36 +1,471 @@
-use crate::tools::FsTools;
+use super::{
+    Read,
+    r#move::{Impact, relocate},
+    read::is_glob,
+};
+use crate::{
+    hints::{self, ErrorCode, Hint},
+    quota::QuotaUsage,
+    settings::{SessionSettings, Verbosity},
+    state::{JournalEntry, StagedEntry},
+    tools::FsTools,
+    trash,
+};
 use anyhow::Result;
+use clap::ArgAction;
 use mcplease::{
     traits::{Tool, WithExamples},
     types::Example,
 };
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Files at most this large are copied into the undo journal when deleted permanently
+const SNAPSHOT_MAX_BYTES: u64 = 1024 * 1024;
 
-/// Remove a file from disk
+/// How many snapshots the undo journal keeps before discarding the oldest
+const MAX_JOURNAL_ENTRIES: usize = 50;
+
+/// Delete a file or directory by moving it to the operating system's trash, where the user can
+/// restore it. With `permanent` it is removed from disk instead, and files up to 1MiB are kept
+/// in an undo journal, so they can be brought back with restore_deleted
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
 #[serde(rename = "delete")]
 pub struct Delete {
-    /// Path to delete
+    /// Path to delete. Directories are deleted with everything they contain. Glob patterns
+    /// such as "src/**/*.generated.rs" delete every file they match, after listing the matches
+    /// unless `confirm` is given
     /// Can be absolute, or relative to working directory.
     /// Be absolutely certain of the working directory when using a relative path.
     pub path: String,
+
+    /// Move the file into a session staging area instead of deleting it, so that it can be
+    /// reviewed and then restored with unstage or permanently removed with purge_staged
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub stage: Option<bool>,
+
+    /// Remove from disk instead of moving to the trash, e.g. for files that shouldn't fill up
+    /// the trash or where there is no trash
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub permanent: Option<bool>,
+
+    /// Response detail: "quiet" names the deleted path, "normal" confirms the deletion, and
+    /// "detailed" also reports the size and last modification time of what was removed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub verbosity: Option<Verbosity>,
+
+    /// Proceed with deleting the files matched by a glob, or a directory of more than 100 files
+    /// or 50MiB. Without it, such a delete only reports what it would remove
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub confirm: Option<bool>,
 }
 
 impl WithExamples for Delete {
     fn examples() -> Vec<Example<Self>> {
-        vec![Example {
-            description: "Deleting a file relative to a session",
-            item: Self {
-                path: "src/mod/file.rs".into(),
+        vec![
+            Example {
+                description: "Deleting a file relative to a session",
+                item: Self {
+                    path: "src/mod/file.rs".into(),
+                    stage: None,
+                    permanent: None,
+                    verbosity: None,
+                    confirm: None,
+                },
+            },
+            Example {
+                description: "Staging a file for deletion so the cleanup can be reviewed first",
+                item: Self {
+                    path: "src/legacy/old_parser.rs".into(),
+                    stage: Some(true),
+                    permanent: None,
+                    verbosity: None,
+                    confirm: None,
+                },
+            },
+            Example {
+                description: "Permanently deleting a build artifact too large for the trash",
+                item: Self {
+                    path: "target/release/app.core".into(),
+                    stage: None,
+                    permanent: Some(true),
+                    verbosity: None,
+                    confirm: None,
+                },
             },
-        }]
+            Example {
+                description: "Deleting generated files after reviewing the list of matches",
+                item: Self {
+                    path: "src/**/*.generated.ts".into(),
+                    stage: None,
+                    permanent: None,
+                    verbosity: None,
+                    confirm: Some(true),
+                },
+            },
+        ]
+    }
+}
+
+impl Delete {
+    fn stage(&self) -> bool {
+        self.stage.unwrap_or_default()
+    }
+
+    fn permanent(&self) -> bool {
+        self.permanent.unwrap_or_default()
+    }
+
+    /// Delete, trash, or stage every file matched by the glob in `self.path`, listing the
+    /// matches instead if `confirm` was not given
+    fn delete_glob(&self, state: &mut FsTools) -> Result<String> {
+        let paths = Read::expand_glob(state, &self.path)?;
+        let (action, done) = if self.stage() {
+            ("staged", "Staged")
+        } else if self.permanent() {
+            ("deleted", "Deleted")
+        } else {
+            ("moved to the trash", "Moved to the trash")
+        };
+
+        if !self.confirm.unwrap_or_default() {
+            let mut output = format!(
+                "{} matches {} files, which would be {action}:\n",
+                self.path,
+                paths.len()
+            );
+            for path in &paths {
+                output.push_str(&format!("{}\n", path.display()));
+            }
+            output.push_str(
+                "\nCall delete again with `confirm: true` to proceed. No filesystem operation has \
+                 been performed",
+            );
+            return Ok(output);
+        }
+
+        let _locks = state.lock_paths(&paths.iter().map(PathBuf::as_path).collect::<Vec<_>>())?;
+        state.charge_quota(QuotaUsage::deleted(paths.len() as u64))?;
+
+        let mut output = String::new();
+        for (finished, path) in paths.iter().enumerate() {
+            let id = if self.stage() {
+                Self::stage_file(state, path).map(|entry| Some(entry.id))
+            } else if self.permanent() {
+                Self::remove_file(state, path).map(|snapshot| snapshot.map(|snapshot| snapshot.id))
+            } else {
+                trash::trash(path).map(|()| None)
+            };
+            // Stop at the first failure, giving back the quota for the files that were left
+            // alone and reporting the ones already handled, which can't be taken back here
+            let id = match id {
+                Ok(id) => id,
+                Err(error) => {
+                    let remaining = (paths.len() - finished) as u64;
+                    state.update_quota(|quota| {
+                        quota.usage.files_deleted =
+                            quota.usage.files_deleted.saturating_sub(remaining);
+                    })?;
+                    let message = format!(
+                        "{done} {finished} of {} files before stopping at {}:\n{output}{error}",
+                        paths.len(),
+                        path.display()
+                    );
+                    return Err(error.context(message));
+                }
+            };
+            output.push_str(&format!("{}", path.display()));
+            if let Some(id) = id {
+                output.push_str(&format!(" ({id})"));
+            }
+            output.push('\n');
+        }
+
+        let mut output = format!("{done} {} files:\n{output}", paths.len());
+        output.push_str(if self.stage() {
+            "\nUse unstage with these ids to restore, or purge_staged to delete permanently"
+        } else if self.permanent() {
+            "\nFiles up to 1MiB were kept in the undo journal; use restore_deleted with these ids \
+             to bring one back"
+        } else {
+            "\nThe user can restore them from the trash"
+        });
+        Ok(output)
+    }
+
+    /// Delete a file permanently, first snapshotting it into the undo journal if it's small
+    fn remove_file(state: &mut FsTools, path: &Path) -> Result<Option<JournalEntry>> {
+        let metadata = std::fs::metadata(path).hint_path(path)?;
+        let snapshot = if metadata.is_file() && metadata.len() <= SNAPSHOT_MAX_BYTES {
+            Some(Self::snapshot(state, path)?)
+        } else {
+            None
+        };
+        std::fs::remove_file(path).hint_path(path)?;
+        Ok(snapshot)
+    }
+
+    /// Trash or remove a directory and everything in it, reporting the scale of the deletion
+    /// first if it is large and `confirm` was not given
+    fn delete_directory(
+        &self,
+        state: &mut FsTools,
+        path: &Path,
+        settings: &SessionSettings,
+    ) -> Result<String> {
+        let impact = Impact::measure(path).hint_path(path)?;
+        if impact.needs_confirmation() && !self.confirm.unwrap_or_default() {
+            return Ok(format!(
+                "Deleting {} would remove {}. Call delete again with `confirm: true` to \
+                 proceed. No filesystem operation has been performed",
+                path.display(),
+                impact.describe(settings)
+            ));
+        }
+
+        state.charge_quota(QuotaUsage::deleted(impact.files as u64))?;
+        if !self.permanent() {
+            trash::trash(path)?;
+            return Ok(match settings.verbosity(self.verbosity) {
+                Verbosity::Quiet => format!("Trashed {}", path.display()),
+                _ => format!(
+                    "Moved {} ({}) to the trash",
+                    path.display(),
+                    impact.describe(settings)
+                ),
+            });
+        }
+
+        std::fs::remove_dir_all(path).hint_path(path)?;
+
+        Ok(match settings.verbosity(self.verbosity) {
+            Verbosity::Quiet => format!("Deleted {}", path.display()),
+            _ => format!(
+                "Successfully deleted {} ({})",
+                path.display(),
+                impact.describe(settings)
+            ),
+        })
+    }
+
+    /// Move `path` into the staging area and record it
+    fn stage_file(state: &mut FsTools, path: &Path) -> Result<StagedEntry> {
+        if path.is_dir() {
+            return Err(hints::Hinted::new(
+                format!(
+                    "{} is a directory, and only files can be staged",
+                    path.display()
+                ),
+                Some("leave out `stage` to move it to the trash instead".into()),
+            )
+            .with_code(ErrorCode::IsADirectory)
+            .with_path(path)
+            .into());
+        }
+        if !path.is_file() {
+            return Err(hints::not_found(path));
+        }
+
+        let id = std::iter::repeat_with(fastrand::alphanumeric)
+            .take(6)
+            .collect::<String>()
+            .to_lowercase();
+        let staging_dir = state.staging_dir(None).join(&id);
+        std::fs::create_dir_all(&staging_dir).hint_path(&staging_dir)?;
+        let staged = staging_dir.join(path.file_name().unwrap_or_default());
+        relocate(path, &staged).hint_path(path)?;
+
+        let entry = StagedEntry {
+            id,
+            original: path.to_path_buf(),
+            staged,
+            staged_at: SystemTime::now(),
+        };
+        state.update_staged(None, |staged| staged.push(entry.clone()))?;
+        Ok(entry)
+    }
+
+    /// The response to a permanent deletion of a file, and where its snapshot is if it has one
+    fn describe_removal(path: &Path, snapshot: Option<JournalEntry>) -> String {
+        let mut result = format!("Successfully deleted {}", path.display());
+        if let Some(snapshot) = &snapshot {
+            result.push_str(&format!(
+                "\nA copy was kept in the undo journal as {} (sha256 {}); use restore_deleted \
+                 to bring it back",
+                snapshot.id,
+                &snapshot.sha256[..12]
+            ));
+        }
+        result
+    }
+
+    /// Hex-encoded SHA-256 of `bytes`
+    pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+        Sha256::digest(bytes)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Copy `path` into the undo journal, discarding the oldest snapshots beyond the limit
+    fn snapshot(state: &mut FsTools, path: &Path) -> Result<JournalEntry> {
+        let contents = std::fs::read(path).hint_path(path)?;
+        let id = std::iter::repeat_with(fastrand::alphanumeric)
+            .take(6)
+            .collect::<String>()
+            .to_lowercase();
+        let snapshot_dir = state.journal_dir(None).join(&id);
+        std::fs::create_dir_all(&snapshot_dir).hint_path(&snapshot_dir)?;
+        let snapshot = snapshot_dir.join(path.file_name().unwrap_or_default());
+        std::fs::write(&snapshot, &contents).hint_path(&snapshot)?;
+
+        let entry = JournalEntry {
+            id,
+            original: path.to_path_buf(),
+            snapshot,
+            sha256: Self::sha256_hex(&contents),
+            size: contents.len() as u64,
+            deleted_at: SystemTime::now(),
+        };
+
+        let mut evicted = vec![];
+        state.update_journal(None, |journal| {
+            journal.push(entry.clone());
+            let excess = journal.len().saturating_sub(MAX_JOURNAL_ENTRIES);
+            evicted.extend(journal.drain(..excess));
+        })?;
+        for old in evicted {
+            if let Some(dir) = old.snapshot.parent() {
+                let _ = std::fs::remove_dir_all(dir);
+            }
+        }
+
+        Ok(entry)
     }
 }
 
 impl Tool<FsTools> for Delete {
-    fn execute(self, state: &mut FsTools) -> Result<String> {
+    fn execute(mut self, state: &mut FsTools) -> Result<String> {
+        if self.stage() && self.permanent() {
+            return Err(hints::Hinted::new(
+                "A file can't be both staged and permanently deleted",
+                Some("leave out `permanent` to stage it, or `stage` to delete it".into()),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into());
+        }
+        // A simulated delete only removes the overlay's copy, which shouldn't reach the trash
+        if state.simulation().is_some() {
+            self.permanent = Some(true);
+        }
+        if is_glob(state, &self.path) {
+            return self.delete_glob(state);
+        }
+
         let path = state.resolve_path(&self.path, None)?;
-        std::fs::remove_file(&path)?;
-        Ok(format!("Successfully deleted {}", path.display()))
+        let _lock = state.lock_paths(&[&path])?;
+        let settings = state.settings(None)?;
+        let metadata = std::fs::metadata(&path).ok();
+        let Ok(link_metadata) = std::fs::symlink_metadata(&path) else {
+            return Err(hints::not_found(&path));
+        };
+
+        // Deletions count toward the quota wherever the files go
+        if self.stage() {
+            if !link_metadata.is_dir() {
+                state.charge_quota(QuotaUsage::deleted(1))?;
+            }
+            let entry = Self::stage_file(state, &path)?;
+            let staged_count = state.staged(None)?.len();
+            return Ok(format!(
+                "Staged {} for deletion as {}. {staged_count} files are staged; use unstage to \
+                 restore or purge_staged to delete permanently",
+                path.display(),
+                entry.id
+            ));
+        }
+
+        if link_metadata.is_dir() {
+            return self.delete_directory(state, &path, &settings);
+        }
+
+        state.charge_quota(QuotaUsage::deleted(1))?;
+        let verbosity = settings.verbosity(self.verbosity);
+        let mut result = if self.permanent() {
+            let snapshot = Self::remove_file(state, &path)?;
+            if verbosity == Verbosity::Quiet {
+                return Ok(match snapshot {
+                    Some(snapshot) => {
+                        format!("Deleted {} (snapshot {})", path.display(), snapshot.id)
+                    }
+                    None => format!("Deleted {}", path.display()),
+                });
+            }
+            Self::describe_removal(&path, snapshot)
+        } else {
+            trash::trash(&path)?;
+            if verbosity == Verbosity::Quiet {
+                return Ok(format!("Trashed {}", path.display()));
+            }
+            format!("Moved {} to the trash", path.display())
+        };
+        if verbosity == Verbosity::Detailed {
+            if let Some(metadata) = metadata {
+                result.push_str(&format!(
+                    " ({}, last modified {})",
+                    settings.format_size(metadata.len()),
+                    settings.timestamp_formatter().format(metadata.modified()?)
+                ));
+            }
+        }
+
+        Ok(result)
     }
 }
+
+/// The staged entries with the given ids, or every staged entry if `ids` is None
+pub(crate) fn select_staged(
+    staged: &[StagedEntry],
+    ids: Option<&[String]>,
+) -> Result<Vec<StagedEntry>> {
+    let Some(ids) = ids else {
+        return Ok(staged.to_vec());
+    };
+
+    ids.iter()
+        .map(|id| {
+            staged
+                .iter()
+                .find(|entry| entry.id == *id)
+                .cloned()
+                .ok_or_else(|| {
+                    hints::Hinted::new(
+                        format!("No staged file has id {id}"),
+                        Some("omit `ids` to see every staged file".into()),
+                    )
+                    .into()
+                })
+        })
+        .collect()
+}
+
+/// One line describing a staged entry
+pub(crate) fn describe_staged(entry: &StagedEntry, settings: &SessionSettings) -> String {
+    format!(
+        "{}  {} (staged {})",
+        entry.id,
+        entry.original.display(),
+        settings.timestamp_formatter().format(entry.staged_at)
+    )
+}
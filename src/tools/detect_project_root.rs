@@ -0,0 +1,241 @@
+use crate::{
+    hints::{self, Hinted},
+    tools::FsTools,
+};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Files and directories that mark the root of a project, in the order they are reported
+const MARKERS: [&str; 4] = ["Cargo.toml", "package.json", "pyproject.toml", ".git"];
+
+/// Find the root of the project containing a path, by walking up from it looking for `.git`,
+/// `Cargo.toml`, `package.json`, and `pyproject.toml`, and report what kind of project it is.
+/// A crate or package inside a Cargo or npm workspace reports the workspace as the root. Use
+/// this to pick the working directory before set_working_directory
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "detect_project_root")]
+pub struct DetectProjectRoot {
+    /// Where to start looking, a file or directory inside the project
+    /// Can be absolute, or relative to session context path.
+    /// Default: the working directory, or the server's current directory if none is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// Also set the working directory to the detected root
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub set_working_directory: Option<bool>,
+}
+
+impl WithExamples for DetectProjectRoot {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Finding the project a file belongs to",
+                item: Self {
+                    path: Some("/home/user/code/shop/crates/billing/src/invoice.rs".into()),
+                    set_working_directory: None,
+                },
+            },
+            Example {
+                description: "Starting a session in the project the server was launched in",
+                item: Self {
+                    path: None,
+                    set_working_directory: Some(true),
+                },
+            },
+        ]
+    }
+}
+
+/// A directory with project markers in it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Project {
+    pub directory: PathBuf,
+    pub markers: Vec<&'static str>,
+    pub workspace: bool,
+}
+
+impl Project {
+    fn at(directory: &Path) -> Option<Self> {
+        let markers = MARKERS
+            .into_iter()
+            .filter(|marker| directory.join(marker).exists())
+            .collect::<Vec<_>>();
+        if markers.is_empty() {
+            return None;
+        }
+        let workspace = is_cargo_workspace(&directory.join("Cargo.toml"))
+            || is_npm_workspace(&directory.join("package.json"))
+            || directory.join("pnpm-workspace.yaml").is_file();
+        Some(Self {
+            directory: directory.to_path_buf(),
+            markers,
+            workspace,
+        })
+    }
+
+    /// What kind of project this is, e.g. "Rust workspace" or "Python project"
+    pub(crate) fn kind(&self) -> String {
+        let kinds = self
+            .markers
+            .iter()
+            .filter_map(|marker| {
+                let manifest = self.directory.join(marker);
+                match *marker {
+                    "Cargo.toml" if is_cargo_workspace(&manifest) => Some("Rust workspace"),
+                    "Cargo.toml" => Some("Rust crate"),
+                    "package.json" if is_npm_workspace(&manifest) => Some("JavaScript workspace"),
+                    "package.json" => Some("JavaScript package"),
+                    "pyproject.toml" => Some("Python project"),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        match kinds.is_empty() {
+            true => "git repository".into(),
+            false => kinds.join(" and "),
+        }
+    }
+}
+
+/// The projects found walking up from a path
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Detection {
+    /// The closest directory with any marker
+    pub nearest: Option<Project>,
+    /// The closest workspace containing the nearest project
+    pub workspace: Option<Project>,
+    /// The top of the git repository, where the walk stops
+    pub repository: Option<PathBuf>,
+}
+
+impl Detection {
+    /// The directory to use as the working directory
+    pub(crate) fn root(&self) -> Option<&Project> {
+        self.workspace.as_ref().or(self.nearest.as_ref())
+    }
+}
+
+fn is_cargo_workspace(manifest: &Path) -> bool {
+    fs::read_to_string(manifest).is_ok_and(|contents| {
+        contents.lines().any(|line| {
+            let line = line.trim();
+            line == "[workspace]" || line.starts_with("[workspace.")
+        })
+    })
+}
+
+fn is_npm_workspace(manifest: &Path) -> bool {
+    fs::read_to_string(manifest)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<serde_json::Value>(&contents).ok())
+        .is_some_and(|package| package.get("workspaces").is_some())
+}
+
+impl DetectProjectRoot {
+    /// Walk up from `start`, which is a directory or a file, to the top of its git repository or
+    /// else the filesystem root
+    pub(crate) fn detect(start: &Path) -> Detection {
+        let start = match start.is_file() {
+            true => start.parent().unwrap_or(start),
+            false => start,
+        };
+        let mut detection = Detection::default();
+        for directory in start.ancestors() {
+            let Some(project) = Project::at(directory) else {
+                continue;
+            };
+            let is_repository = project.markers.contains(&".git");
+            if project.workspace && detection.workspace.is_none() {
+                detection.workspace = Some(project.clone());
+            }
+            if detection.nearest.is_none() {
+                detection.nearest = Some(project);
+            }
+            if is_repository {
+                detection.repository = Some(directory.to_path_buf());
+                break;
+            }
+        }
+        detection
+    }
+}
+
+impl Tool<FsTools> for DetectProjectRoot {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let working_directory = state.get_context(None)?;
+        let start = match (&self.path, &working_directory) {
+            (Some(path), _) => state.resolve_path(path, None)?,
+            (None, Some(working_directory)) => working_directory.clone(),
+            (None, None) => std::env::current_dir()?,
+        };
+        if !start.exists() {
+            return Err(hints::not_found(&start));
+        }
+
+        let detection = Self::detect(&start);
+        let Some(root) = detection.root() else {
+            return Err(Hinted::new(
+                format!(
+                    "No project markers ({}) in {} or any directory above it",
+                    MARKERS.join(", "),
+                    start.display()
+                ),
+                Some("pass a path inside the project, or set the working directory to it".into()),
+            )
+            .into());
+        };
+
+        let mut output = format!(
+            "Project root: {} ({})\nMarkers: {}\n",
+            root.directory.display(),
+            root.kind(),
+            root.markers.join(", ")
+        );
+        if let Some(nearest) = detection
+            .nearest
+            .as_ref()
+            .filter(|nearest| *nearest != root)
+        {
+            output.push_str(&format!(
+                "Nearest project: {} ({}, a member of the workspace)\n",
+                nearest.directory.display(),
+                nearest.kind()
+            ));
+        }
+        if let Some(repository) = detection
+            .repository
+            .as_ref()
+            .filter(|repository| **repository != root.directory)
+        {
+            output.push_str(&format!("Git repository: {}\n", repository.display()));
+        }
+
+        let root = root.directory.clone();
+        if working_directory.as_ref() == Some(&root) {
+            output.push_str("\nThis is already the working directory");
+        } else if self.set_working_directory.unwrap_or_default() {
+            state.set_working_directory(root.clone(), None)?;
+            output.push_str(&format!(
+                "\nSet the working directory to {}",
+                root.display()
+            ));
+        } else {
+            output.push_str(
+                "\nPass `set_working_directory: true`, or call set_working_directory, to use it",
+            );
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,207 @@
+use crate::{
+    git,
+    hints::{self, Hinted},
+    tools::FsTools,
+};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::fs;
+
+/// Show the unified diff of a file, a directory, or the whole repository against the last
+/// commit, including staged changes and new untracked files, to review changes made so far.
+/// Read-only
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "diff_vs_head")]
+pub struct DiffVsHead {
+    /// File or directory to diff
+    /// Can be absolute, or relative to session context path.
+    /// Defaults to the whole repository if not provided
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// Lines of unchanged context around each change
+    /// Default: 3
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub context_lines: Option<usize>,
+
+    /// Only list the changed files with their added and removed line counts
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub stat_only: Option<bool>,
+
+    /// Leave out untracked files, which are otherwise shown as added in full
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub exclude_untracked: Option<bool>,
+}
+
+impl WithExamples for DiffVsHead {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Reviewing every change made to the repository so far",
+                item: Self {
+                    path: None,
+                    context_lines: None,
+                    stat_only: None,
+                    exclude_untracked: None,
+                },
+            },
+            Example {
+                description: "Reviewing the changes to one file with more surrounding context",
+                item: Self {
+                    path: Some("src/lib.rs".into()),
+                    context_lines: Some(10),
+                    stat_only: None,
+                    exclude_untracked: None,
+                },
+            },
+            Example {
+                description: "Summarizing which files under a directory have changed",
+                item: Self {
+                    path: Some("src".into()),
+                    context_lines: None,
+                    stat_only: Some(true),
+                    exclude_untracked: None,
+                },
+            },
+        ]
+    }
+}
+
+impl Tool<FsTools> for DiffVsHead {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let scope = match &self.path {
+            Some(path) => state.resolve_path(path, None)?,
+            None => state.resolve_path(".", None)?,
+        };
+        if !scope.exists() {
+            return Err(hints::not_found(&scope));
+        }
+        let directory = git::directory_for(&scope);
+        let root = git::toplevel(directory)?;
+        let scope_arg = scope.to_string_lossy().into_owned();
+        let pathspec = match self.path {
+            Some(_) => scope_arg.as_str(),
+            None => root.to_str().unwrap_or("."),
+        };
+
+        let context = format!("--unified={}", self.context_lines.unwrap_or(3));
+        let stat_only = self.stat_only.unwrap_or_default();
+        let mode = if stat_only { "--numstat" } else { &context };
+        let diff = git::run(
+            directory,
+            &["diff", "--no-color", mode, "HEAD", "--", pathspec],
+        )
+        .map_err(|error| {
+            if error.to_string().contains("revision") {
+                Hinted::new(
+                    format!("{} has no commits to diff against", root.display()),
+                    Some("use vcs_status to see the files added so far".into()),
+                )
+                .into()
+            } else {
+                error
+            }
+        })?;
+
+        let untracked = if self.exclude_untracked.unwrap_or_default() {
+            vec![]
+        } else {
+            let listed = git::run(
+                directory,
+                &[
+                    "ls-files",
+                    "--others",
+                    "--exclude-standard",
+                    "--full-name",
+                    "-z",
+                    "--",
+                    pathspec,
+                ],
+            )?;
+            listed
+                .split('\0')
+                .filter(|path| !path.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+
+        let mut output = String::new();
+        let mut changed = 0;
+        if stat_only {
+            for line in diff.lines() {
+                let mut columns = line.splitn(3, '\t');
+                let (Some(added), Some(removed), Some(path)) =
+                    (columns.next(), columns.next(), columns.next())
+                else {
+                    continue;
+                };
+                changed += 1;
+                match (added, removed) {
+                    ("-", "-") => output.push_str(&format!("  {path} (binary)\n")),
+                    _ => output.push_str(&format!("  {path} +{added} -{removed}\n")),
+                }
+            }
+            for path in &untracked {
+                changed += 1;
+                match fs::read_to_string(root.join(path)) {
+                    Ok(contents) => output.push_str(&format!(
+                        "  {path} +{} (untracked)\n",
+                        contents.lines().count()
+                    )),
+                    Err(_) => output.push_str(&format!("  {path} (untracked, binary)\n")),
+                }
+            }
+        } else {
+            changed += diff
+                .lines()
+                .filter(|line| line.starts_with("diff --git "))
+                .count();
+            output.push_str(&diff);
+            for path in &untracked {
+                changed += 1;
+                if !output.is_empty() && !output.ends_with('\n') {
+                    output.push('\n');
+                }
+                output.push_str(&format!(
+                    "diff --git a/{path} b/{path}\nnew untracked file\n"
+                ));
+                match fs::read_to_string(root.join(path)) {
+                    Ok(contents) => output.push_str(
+                        &TextDiff::from_lines("", &contents)
+                            .unified_diff()
+                            .header("/dev/null", &format!("b/{path}"))
+                            .to_string(),
+                    ),
+                    Err(_) => output.push_str(&format!("Binary file b/{path} added\n")),
+                }
+            }
+        }
+
+        let under = match self.path {
+            Some(_) => scope.display().to_string(),
+            None => root.display().to_string(),
+        };
+        if changed == 0 {
+            return Ok(format!("No changes against HEAD under {under}"));
+        }
+        let files = match changed {
+            1 => "1 file".to_string(),
+            changed => format!("{changed} files"),
+        };
+        Ok(format!(
+            "Changes against HEAD under {under} ({files}), paths relative to {}:\n\n{output}",
+            root.display()
+        ))
+    }
+}
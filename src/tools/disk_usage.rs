@@ -0,0 +1,206 @@
+use crate::{
+    deadline,
+    hints::{self, ErrorCode, Hinted},
+    tools::FsTools,
+};
+use anyhow::Result;
+use ignore::WalkBuilder;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf};
+
+/// Summarize recursive disk usage per directory, largest first (like `du -d N`)
+#[derive(Debug, Serialize, Deserialize, JsonSchema, clap::Args)]
+#[serde(rename = "disk_usage")]
+pub struct DiskUsage {
+    /// Directory to summarize
+    /// Can be absolute, or relative to session context path.
+    /// Defaults to the current session context if not provided
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// How many levels of directories below `path` to report. Sizes always include everything
+    /// beneath each directory, regardless of depth.
+    /// Includes hidden and gitignored files, since those are frequently what takes up space
+    ///
+    /// Default: 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub max_depth: Option<usize>,
+
+    /// Maximum number of directories to report
+    ///
+    /// Default: 30
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub max_entries: Option<usize>,
+
+    /// Stop walking after this many milliseconds and return what was found so far
+    /// Default: 30000, or the server's `--timeout-ms`, which also caps this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub time_limit_ms: Option<u64>,
+}
+
+impl WithExamples for DiskUsage {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Finding what takes up space in the current project",
+                item: Self {
+                    path: None,
+                    max_depth: None,
+                    max_entries: None,
+                    time_limit_ms: None,
+                },
+            },
+            Example {
+                description: "Looking two levels deep into a specific directory",
+                item: Self {
+                    path: Some("/some/absolute/path/node_modules".into()),
+                    max_depth: Some(2),
+                    max_entries: Some(10),
+                    time_limit_ms: None,
+                },
+            },
+            Example {
+                description: "Summarizing a mounted network share, giving up after five seconds",
+                item: Self {
+                    path: Some("/mnt/shared".into()),
+                    max_depth: None,
+                    max_entries: None,
+                    time_limit_ms: Some(5000),
+                },
+            },
+        ]
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Usage {
+    bytes: u64,
+    files: usize,
+}
+
+impl DiskUsage {
+    fn max_depth(&self) -> usize {
+        self.max_depth.unwrap_or(1).max(1)
+    }
+
+    fn max_entries(&self) -> usize {
+        self.max_entries.unwrap_or(30)
+    }
+}
+
+impl Tool<FsTools> for DiskUsage {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let base_path = state.resolve_path(self.path.as_deref().unwrap_or("."), None)?;
+        if !base_path.exists() {
+            return Err(hints::not_found(&base_path));
+        }
+
+        if !base_path.is_dir() {
+            return Err(Hinted::new(
+                format!("Path is not a directory: {}", base_path.display()),
+                Some("use list with include_metadata to see the size of a file".into()),
+            )
+            .with_code(ErrorCode::NotADirectory)
+            .with_path(&base_path)
+            .into());
+        }
+
+        let max_depth = self.max_depth();
+        let mut total = Usage::default();
+        let mut directories: HashMap<PathBuf, Usage> = HashMap::new();
+        let mut unreadable = 0;
+        let deadline = state.deadline(self.time_limit_ms, deadline::DEFAULT_TIME_LIMIT);
+        let mut timed_out = false;
+
+        for entry in WalkBuilder::new(&base_path).standard_filters(false).build() {
+            if deadline.is_reached() {
+                timed_out = true;
+                break;
+            }
+            let Ok(entry) = entry else {
+                unreadable += 1;
+                continue;
+            };
+
+            if !entry.file_type().is_some_and(|ft| ft.is_file()) {
+                continue;
+            }
+
+            let Ok(metadata) = entry.metadata() else {
+                unreadable += 1;
+                continue;
+            };
+
+            let bytes = metadata.len();
+            total.bytes += bytes;
+            total.files += 1;
+
+            let Ok(relative) = entry.path().strip_prefix(&base_path) else {
+                continue;
+            };
+
+            // Attribute the file to each ancestor directory within the reported depth
+            let mut ancestor = PathBuf::new();
+            let directory_components = relative.components().count().saturating_sub(1);
+            for component in relative
+                .components()
+                .take(directory_components.min(max_depth))
+            {
+                ancestor.push(component);
+                let usage = directories.entry(ancestor.clone()).or_default();
+                usage.bytes += bytes;
+                usage.files += 1;
+            }
+        }
+
+        let mut directories = directories.into_iter().collect::<Vec<_>>();
+        directories.sort_by(|(a_path, a), (b_path, b)| {
+            b.bytes.cmp(&a.bytes).then_with(|| a_path.cmp(b_path))
+        });
+
+        let settings = state.settings(None)?;
+        let mut output = format!(
+            "Disk usage of {} (total {}, {} files):\n\n",
+            base_path.display(),
+            settings.format_size(total.bytes),
+            total.files
+        );
+
+        let shown = directories.len().min(self.max_entries());
+        for (path, usage) in &directories[..shown] {
+            let mut path = path.clone();
+            path.push("");
+            output.push_str(&format!(
+                "{} | {} | {} files\n",
+                path.display(),
+                settings.format_size(usage.bytes),
+                usage.files
+            ));
+        }
+
+        if directories.len() > shown {
+            output.push_str(&format!(
+                "... and {} smaller directories\n",
+                directories.len() - shown
+            ));
+        }
+
+        if unreadable > 0 {
+            output.push_str(&format!("\n{unreadable} paths could not be read\n"));
+        }
+
+        if timed_out {
+            output.push_str(&deadline.note());
+        }
+
+        Ok(output)
+    }
+}
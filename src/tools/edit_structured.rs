@@ -0,0 +1,741 @@
+use crate::{
+    hints::{ErrorCode, Hint, Hinted},
+    quota::QuotaUsage,
+    tools::{
+        FsTools,
+        read_structured::{self, StructuredFormat},
+    },
+};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use similar::TextDiff;
+use std::path::Path;
+use toml_edit::{DocumentMut, Item, TableLike};
+
+/// Set or delete the value at a key path in a JSON, YAML, or TOML file, without rewriting the
+/// rest of it. TOML keeps its comments and formatting, and JSON only has the edited value
+/// replaced. YAML has to be rewritten from its data, so edits that would lose its comments,
+/// anchors, or formatting are refused unless `rewrite_yaml` is set
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "edit_structured")]
+pub struct EditStructured {
+    /// File to edit
+    /// Can be absolute, or relative to session context path.
+    pub path: String,
+
+    /// Key path of the value to set or delete, dotted with array indices as numbers, like
+    /// `package.version` or `workspace.members.0`, or a JSON pointer, like
+    /// `/scripts/build`, for keys that contain dots. Missing objects and tables on the way are
+    /// created when setting
+    pub key_path: String,
+
+    /// The new value as JSON, e.g. `"1.2.0"`, `true`, or `{"version": "1", "features":
+    /// ["derive"]}`. Text that isn't valid JSON is used as a string
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub value: Option<String>,
+
+    /// Delete the value at `key_path` instead of setting it
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub delete: Option<bool>,
+
+    /// The file's format, for files without a .json, .yaml, .yml, or .toml extension
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub format: Option<StructuredFormat>,
+
+    /// Edit a YAML file even though rewriting it from its data loses its comments, anchors, or
+    /// formatting
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub rewrite_yaml: Option<bool>,
+
+    /// Edit a file that matches one of the session's generated file patterns (lockfiles and
+    /// generated code, see configure_session)
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub allow_generated: Option<bool>,
+}
+
+impl WithExamples for EditStructured {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Bumping a crate's version",
+                item: Self {
+                    path: "Cargo.toml".into(),
+                    key_path: "package.version".into(),
+                    value: Some("\"0.2.0\"".into()),
+                    delete: None,
+                    format: None,
+                    rewrite_yaml: None,
+                    allow_generated: None,
+                },
+            },
+            Example {
+                description: "Adding a dependency with features",
+                item: Self {
+                    path: "Cargo.toml".into(),
+                    key_path: "dependencies.serde".into(),
+                    value: Some(r#"{"version": "1", "features": ["derive"]}"#.into()),
+                    delete: None,
+                    format: None,
+                    rewrite_yaml: None,
+                    allow_generated: None,
+                },
+            },
+            Example {
+                description: "Removing an npm script",
+                item: Self {
+                    path: "package.json".into(),
+                    key_path: "scripts.prepublish".into(),
+                    value: None,
+                    delete: Some(true),
+                    format: None,
+                    rewrite_yaml: None,
+                    allow_generated: None,
+                },
+            },
+        ]
+    }
+}
+
+/// What to do at the key path
+enum Edit {
+    Set(Value),
+    Delete,
+}
+
+/// Why an edit couldn't be made, with the key path up to where it failed
+struct EditError {
+    at: String,
+    problem: String,
+}
+
+impl EditError {
+    fn new(segments: &[String], problem: impl Into<String>) -> Self {
+        Self {
+            at: segments.join("."),
+            problem: problem.into(),
+        }
+    }
+}
+
+/// Apply `edit` to a JSON value
+fn edit_json(document: &mut Value, segments: &[String], edit: Edit) -> Result<(), EditError> {
+    let (last, parents) = segments.split_last().expect("key paths are not empty");
+    let mut current = document;
+    for (index, segment) in parents.iter().enumerate() {
+        let create = matches!(edit, Edit::Set(_));
+        current = match current {
+            Value::Object(map) => {
+                if create && !map.contains_key(segment) {
+                    map.insert(segment.clone(), Value::Object(Default::default()));
+                }
+                map.get_mut(segment)
+                    .ok_or_else(|| EditError::new(&segments[..=index], "does not exist"))?
+            }
+            Value::Array(array) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| array.get_mut(i))
+                .ok_or_else(|| {
+                    EditError::new(&segments[..=index], "is not an item of the array")
+                })?,
+            _ => return Err(EditError::new(&segments[..index], "has no keys")),
+        };
+    }
+
+    match (current, edit) {
+        (Value::Object(map), Edit::Set(value)) => {
+            map.insert(last.clone(), value);
+        }
+        (Value::Object(map), Edit::Delete) => {
+            map.shift_remove(last)
+                .ok_or_else(|| EditError::new(segments, "does not exist"))?;
+        }
+        (Value::Array(array), edit) => {
+            let index = last
+                .parse::<usize>()
+                .ok()
+                .filter(|index| {
+                    *index < array.len() || matches!(edit, Edit::Set(_)) && *index == array.len()
+                })
+                .ok_or_else(|| {
+                    EditError::new(
+                        segments,
+                        format!("is not an index of the array of {} items", array.len()),
+                    )
+                })?;
+            match edit {
+                Edit::Set(value) if index == array.len() => array.push(value),
+                Edit::Set(value) => array[index] = value,
+                Edit::Delete => {
+                    array.remove(index);
+                }
+            }
+        }
+        _ => return Err(EditError::new(parents, "has no keys")),
+    }
+    Ok(())
+}
+
+/// A JSON value as an inline TOML value
+fn json_to_toml(value: &Value) -> Result<toml_edit::Value, String> {
+    Ok(match value {
+        Value::Null => return Err("TOML has no null; use `delete` to remove a key".into()),
+        Value::Bool(boolean) => (*boolean).into(),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => integer.into(),
+            None => number.as_f64().unwrap_or_default().into(),
+        },
+        Value::String(string) => string.as_str().into(),
+        Value::Array(array) => array
+            .iter()
+            .map(json_to_toml)
+            .collect::<Result<toml_edit::Array, _>>()?
+            .into(),
+        Value::Object(map) => {
+            let mut table = toml_edit::InlineTable::new();
+            for (key, value) in map {
+                table.insert(key, json_to_toml(value)?);
+            }
+            table.into()
+        }
+    })
+}
+
+/// Set `item` to `value`, keeping the comments and spacing around the value it replaces
+fn replace_toml_value(item: &mut Item, mut value: toml_edit::Value) {
+    if let Some(old) = item.as_value() {
+        *value.decor_mut() = old.decor().clone();
+    }
+    *item = Item::Value(value);
+}
+
+/// Apply `edit` to a TOML document, keeping its formatting
+fn edit_toml(document: &mut DocumentMut, segments: &[String], edit: Edit) -> Result<(), EditError> {
+    let value = match &edit {
+        Edit::Set(value) => {
+            Some(json_to_toml(value).map_err(|problem| EditError::new(segments, problem))?)
+        }
+        Edit::Delete => None,
+    };
+    let (last, parents) = segments.split_last().expect("key paths are not empty");
+    let mut table: &mut dyn TableLike = document.as_table_mut();
+    let mut index = 0;
+    while index < parents.len() {
+        let segment = &parents[index];
+        if !table.contains_key(segment) {
+            if value.is_none() {
+                return Err(EditError::new(&segments[..=index], "does not exist"));
+            }
+            let mut new_table = toml_edit::Table::new();
+            new_table.set_implicit(true);
+            table.insert(segment, Item::Table(new_table));
+        }
+        let item = table.get_mut(segment).expect("inserted above");
+        table = match item {
+            Item::ArrayOfTables(array) => {
+                index += 1;
+                let Some(position) = segments.get(index).and_then(|i| i.parse::<usize>().ok())
+                else {
+                    return Err(EditError::new(
+                        &segments[..index],
+                        "is an array of tables; index it by number",
+                    ));
+                };
+                if index == parents.len() {
+                    // The last segment indexes the array of tables itself
+                    return match value {
+                        None if position < array.len() => {
+                            array.remove(position);
+                            Ok(())
+                        }
+                        _ => Err(EditError::new(
+                            segments,
+                            "can only be deleted, as a table of an array of tables",
+                        )),
+                    };
+                }
+                array.get_mut(position).ok_or_else(|| {
+                    EditError::new(&segments[..=index], "is not an item of the array")
+                })?
+            }
+            Item::Value(toml_edit::Value::Array(array)) if index + 1 == parents.len() => {
+                let length = array.len();
+                let position = last
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|position| *position < length || value.is_some() && *position == length)
+                    .ok_or_else(|| {
+                        EditError::new(
+                            segments,
+                            format!("is not an index of the array of {length} items"),
+                        )
+                    })?;
+                match value {
+                    Some(value) if position == length => array.push_formatted(value),
+                    Some(mut value) => {
+                        if let Some(old) = array.get(position) {
+                            *value.decor_mut() = old.decor().clone();
+                        }
+                        array.replace_formatted(position, value);
+                    }
+                    None => {
+                        array.remove(position);
+                    }
+                }
+                return Ok(());
+            }
+            item => item
+                .as_table_like_mut()
+                .ok_or_else(|| EditError::new(&segments[..=index], "has no keys"))?,
+        };
+        index += 1;
+    }
+
+    match value {
+        Some(value) => match table.get_mut(last) {
+            Some(item) => replace_toml_value(item, value),
+            None => {
+                table.insert(last, Item::Value(value));
+            }
+        },
+        None => {
+            table
+                .remove(last)
+                .ok_or_else(|| EditError::new(segments, "does not exist"))?;
+        }
+    }
+    Ok(())
+}
+
+/// The indentation of the first indented line, which pretty-printed JSON uses for each level
+fn json_indentation(contents: &str) -> Option<&str> {
+    contents.lines().find_map(|line| {
+        let indentation = &line[..line.len() - line.trim_start().len()];
+        (!indentation.is_empty()).then_some(indentation)
+    })
+}
+
+/// A member of a JSON object or an item of an array, as byte offsets into the text
+struct JsonMember {
+    key: Option<String>,
+    start: usize,
+    value_start: usize,
+    value_end: usize,
+}
+
+/// Byte offsets into valid JSON text
+struct JsonText<'a> {
+    text: &'a str,
+}
+
+impl JsonText<'_> {
+    fn byte(&self, index: usize) -> u8 {
+        self.text.as_bytes().get(index).copied().unwrap_or_default()
+    }
+
+    fn skip_whitespace(&self, mut index: usize) -> usize {
+        while matches!(self.byte(index), b' ' | b'\t' | b'\n' | b'\r') {
+            index += 1;
+        }
+        index
+    }
+
+    /// The end of the string starting at `start`
+    fn string_end(&self, start: usize) -> usize {
+        let mut index = start + 1;
+        while index < self.text.len() && self.byte(index) != b'"' {
+            if self.byte(index) == b'\\' {
+                index += 1;
+            }
+            index += 1;
+        }
+        index + 1
+    }
+
+    /// The end of the value starting at `start`
+    fn value_end(&self, start: usize) -> usize {
+        match self.byte(start) {
+            b'"' => self.string_end(start),
+            b'{' | b'[' => {
+                let mut depth = 0;
+                let mut index = start;
+                while index < self.text.len() {
+                    match self.byte(index) {
+                        b'"' => {
+                            index = self.string_end(index);
+                            continue;
+                        }
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                return index + 1;
+                            }
+                        }
+                        _ => {}
+                    }
+                    index += 1;
+                }
+                index
+            }
+            _ => {
+                let mut index = start;
+                while index < self.text.len()
+                    && !matches!(
+                        self.byte(index),
+                        b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r'
+                    )
+                {
+                    index += 1;
+                }
+                index
+            }
+        }
+    }
+
+    /// The members of the object or array opening at `open`, and where it closes
+    fn members(&self, open: usize) -> (Vec<JsonMember>, usize) {
+        let is_object = self.byte(open) == b'{';
+        let mut members = vec![];
+        let mut index = self.skip_whitespace(open + 1);
+        if matches!(self.byte(index), b'}' | b']') {
+            return (members, index);
+        }
+        loop {
+            let start = index;
+            let mut key = None;
+            if is_object {
+                let key_end = self.string_end(index);
+                key = serde_json::from_str(&self.text[index..key_end]).ok();
+                // Past the colon
+                index = self.skip_whitespace(self.skip_whitespace(key_end) + 1);
+            }
+            let value_end = self.value_end(index);
+            members.push(JsonMember {
+                key,
+                start,
+                value_start: index,
+                value_end,
+            });
+            index = self.skip_whitespace(value_end);
+            if self.byte(index) != b',' {
+                return (members, index);
+            }
+            index = self.skip_whitespace(index + 1);
+        }
+    }
+
+    /// The spaces and tabs that start the line `index` is on
+    fn line_indentation(&self, index: usize) -> &str {
+        let line_start = self.text[..index]
+            .rfind('\n')
+            .map_or(0, |newline| newline + 1);
+        let line = &self.text[line_start..];
+        &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+    }
+}
+
+/// The value at `segments` in a JSON value that is known to have it
+fn json_at<'a>(value: &'a Value, segments: &[String]) -> Option<&'a Value> {
+    segments
+        .iter()
+        .try_fold(value, |value, segment| match value {
+            Value::Object(map) => map.get(segment),
+            Value::Array(array) => array.get(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+/// `value` as JSON text starting on a line indented by `indentation`, pretty-printed with
+/// `unit` for each level, or on one line without it
+fn render_json(value: &Value, unit: Option<&str>, indentation: &str, newline: &str) -> String {
+    let Some(unit) = unit else {
+        return value.to_string();
+    };
+    let mut buffer = vec![];
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(unit.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut buffer, formatter);
+    value
+        .serialize(&mut serializer)
+        .expect("JSON values serialize");
+    String::from_utf8(buffer)
+        .expect("JSON is utf8")
+        .replace('\n', &format!("{newline}{indentation}"))
+}
+
+/// Splice an edit that `edited` is the result of into the JSON text it was parsed from,
+/// leaving the rest of the text as it is, down to its line endings and number formatting
+fn splice_json(contents: &str, segments: &[String], edited: &Value, delete: bool) -> String {
+    let text = JsonText { text: contents };
+    let unit = json_indentation(contents);
+    let newline = match contents.contains("\r\n") {
+        true => "\r\n",
+        false => "\n",
+    };
+    let mut open = text.skip_whitespace(0);
+    for (depth, segment) in segments.iter().enumerate() {
+        let (members, close) = text.members(open);
+        let found = match text.byte(open) {
+            b'{' => members
+                .iter()
+                .rposition(|member| member.key.as_ref() == Some(segment)),
+            _ => segment
+                .parse::<usize>()
+                .ok()
+                .filter(|index| *index < members.len()),
+        };
+        let (range, replacement) = match found {
+            Some(index) if depth + 1 < segments.len() => {
+                open = members[index].value_start;
+                continue;
+            }
+            Some(index) if delete => {
+                let member = &members[index];
+                let range = match (index.checked_sub(1), members.get(index + 1)) {
+                    (Some(previous), _) => members[previous].value_end..member.value_end,
+                    (None, Some(next)) => member.start..next.start,
+                    (None, None) => open + 1..close,
+                };
+                (range, String::new())
+            }
+            Some(index) => {
+                let member = &members[index];
+                let range = member.value_start..member.value_end;
+                let multiline = contents[range.clone()].contains('\n');
+                let value = json_at(edited, segments).unwrap_or(&Value::Null);
+                let indentation = text.line_indentation(member.start);
+                let unit = unit.filter(|_| multiline);
+                (range, render_json(value, unit, indentation, newline))
+            }
+            None => {
+                let value = json_at(edited, &segments[..=depth]).unwrap_or(&Value::Null);
+                let container_indentation = text.line_indentation(open);
+                let (indentation, multiline) = match members.last() {
+                    Some(last) => (
+                        text.line_indentation(last.start).to_string(),
+                        contents[open..last.start].contains('\n'),
+                    ),
+                    None => (
+                        format!("{container_indentation}{}", unit.unwrap_or_default()),
+                        unit.is_some(),
+                    ),
+                };
+                let unit = unit.filter(|_| multiline);
+                let mut member = String::new();
+                if text.byte(open) == b'{' {
+                    member.push_str(&Value::String(segment.clone()).to_string());
+                    member.push_str(match unit.is_some() || contents.contains("\": ") {
+                        true => ": ",
+                        false => ":",
+                    });
+                }
+                member.push_str(&render_json(value, unit, &indentation, newline));
+                match (members.last(), multiline) {
+                    (Some(last), true) => (
+                        last.value_end..last.value_end,
+                        format!(",{newline}{indentation}{member}"),
+                    ),
+                    (Some(last), false) => {
+                        // Separated like the members already on the line
+                        let separator = match (members.first(), members.get(1)) {
+                            (Some(first), Some(second)) => &contents[first.value_end..second.start],
+                            _ if unit.is_some() || contents.contains(", ") => ", ",
+                            _ => ",",
+                        };
+                        (
+                            last.value_end..last.value_end,
+                            format!("{separator}{member}"),
+                        )
+                    }
+                    (None, true) => (
+                        open + 1..close,
+                        format!("{newline}{indentation}{member}{newline}{container_indentation}"),
+                    ),
+                    (None, false) => (open + 1..close, member),
+                }
+            }
+        };
+        let mut spliced = contents.to_string();
+        spliced.replace_range(range, &replacement);
+        return spliced;
+    }
+    contents.to_string()
+}
+
+impl EditStructured {
+    fn edit(&self) -> Result<Edit> {
+        match (&self.value, self.delete.unwrap_or_default()) {
+            (Some(value), false) => Ok(Edit::Set(
+                serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.clone())),
+            )),
+            (None, true) => Ok(Edit::Delete),
+            (Some(_), true) => Err(Hinted::new(
+                "`value` and `delete` are mutually exclusive",
+                Some("pass `value` to set the key, or `delete: true` to remove it".into()),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into()),
+            (None, false) => Err(Hinted::new(
+                "Nothing to do without `value` or `delete`",
+                Some("pass `value` to set the key, or `delete: true` to remove it".into()),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into()),
+        }
+    }
+
+    /// The contents after the edit
+    fn apply(&self, format: StructuredFormat, contents: &str, path: &Path) -> Result<String> {
+        let segments = read_structured::key_path_segments(&self.key_path);
+        if segments.is_empty() {
+            return Err(Hinted::new(
+                "`key_path` names no key",
+                Some("use write to replace the whole file".into()),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into());
+        }
+        let edit = self.edit()?;
+        let edited = match format {
+            StructuredFormat::Toml => {
+                let mut document = contents.parse::<DocumentMut>().map_err(|error| {
+                    Hinted::new(
+                        format!(
+                            "{} is not valid TOML: {}",
+                            path.display(),
+                            error.to_string().trim_end()
+                        ),
+                        Some("use read to see the file as text".into()),
+                    )
+                    .with_code(ErrorCode::InvalidArgument)
+                    .with_path(path)
+                })?;
+                edit_toml(&mut document, &segments, edit).map(|()| document.to_string())
+            }
+            StructuredFormat::Json => {
+                let mut document = format.parse(contents, path)?;
+                let delete = matches!(edit, Edit::Delete);
+                edit_json(&mut document, &segments, edit)
+                    .map(|()| splice_json(contents, &segments, &document, delete))
+            }
+            StructuredFormat::Yaml => {
+                let documents = serde_yaml_ng::Deserializer::from_str(contents).count();
+                if documents > 1 {
+                    return Err(Hinted::new(
+                        format!(
+                            "{} has {documents} YAML documents, and edit_structured only edits \
+                             files with one",
+                            path.display()
+                        ),
+                        Some("use apply_patch to change it".into()),
+                    )
+                    .with_code(ErrorCode::InvalidArgument)
+                    .with_path(path)
+                    .into());
+                }
+                let mut document = format.parse(contents, path)?;
+                if !self.rewrite_yaml.unwrap_or_default()
+                    && serde_yaml_ng::to_string(&document).ok().as_deref() != Some(contents)
+                {
+                    return Err(Hinted::new(
+                        format!(
+                            "{} can only be edited by rewriting it from its data, which would \
+                             lose its comments, anchors, or formatting, so it was not changed",
+                            path.display()
+                        ),
+                        Some(
+                            "use apply_patch to change it, or pass `rewrite_yaml: true` if \
+                             losing them is fine"
+                                .into(),
+                        ),
+                    )
+                    .with_path(path)
+                    .into());
+                }
+                match edit_json(&mut document, &segments, edit) {
+                    Ok(()) => serde_yaml_ng::to_string(&document)
+                        .map_err(|error| EditError::new(&segments, error.to_string())),
+                    Err(error) => Err(error),
+                }
+            }
+        };
+        edited.map_err(|error| {
+            let at = match error.at.is_empty() {
+                true => "The top level".to_string(),
+                false => error.at,
+            };
+            Hinted::new(
+                format!("{at} in {} {}", path.display(), error.problem),
+                Some("use read_structured to see the keys of the file".into()),
+            )
+            .with_code(ErrorCode::NotFound)
+            .with_path(path)
+            .into()
+        })
+    }
+}
+
+impl Tool<FsTools> for EditStructured {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let path = state.resolve_path(&self.path, None)?;
+        let _lock = state.lock_paths(&[&path])?;
+        state.check_symlinks(&path, true)?;
+        if !self.allow_generated.unwrap_or_default()
+            && let Some(pattern) = state.settings(None)?.generated_pattern(&path)
+        {
+            return Err(Hinted::new(
+                format!(
+                    "{} matches the generated file pattern \"{pattern}\" and was not edited",
+                    path.display()
+                ),
+                Some(
+                    "regenerate it with the tool that owns it (e.g. cargo update or npm \
+                     install), or pass `allow_generated: true` if editing it by hand is really \
+                     intended"
+                        .into(),
+                ),
+            )
+            .with_path(&path)
+            .into());
+        }
+        let format = StructuredFormat::of(&path, self.format)?;
+        let contents = std::fs::read_to_string(&path).hint_path(&path)?;
+        let edited = self.apply(format, &contents, &path)?;
+        if edited == contents {
+            return Ok(format!(
+                "{} already has that value at {}. No filesystem operation has been performed",
+                path.display(),
+                self.key_path
+            ));
+        }
+
+        state.charge_quota(QuotaUsage::written(edited.len() as u64, false))?;
+        std::fs::write(&path, &edited).hint_path(&path)?;
+
+        let action = match self.delete.unwrap_or_default() {
+            true => "Deleted",
+            false => "Set",
+        };
+        let file = path.display().to_string();
+        let diff = TextDiff::from_lines(&contents, &edited)
+            .unified_diff()
+            .context_radius(1)
+            .header(&file, &file)
+            .to_string();
+        Ok(format!("{action} {} in {file}:\n\n{diff}", self.key_path))
+    }
+}
@@ -0,0 +1,85 @@
+use crate::tools::FsTools;
+use anyhow::Result;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+/// Check whether paths exist, and whether each is a file, directory, or symlink, without reading
+/// or listing anything. Read-only
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "exists")]
+pub struct Exists {
+    /// Paths to check
+    /// Can be absolute, or relative to session context path.
+    pub paths: Vec<String>,
+}
+
+impl WithExamples for Exists {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Checking for a config file before reading it",
+                item: Self {
+                    paths: vec!["Cargo.toml".into()],
+                },
+            },
+            Example {
+                description: "Checking which of several build outputs have been generated",
+                item: Self {
+                    paths: vec![
+                        "target/debug/app".into(),
+                        "target/doc".into(),
+                        "/some/absolute/path/dist/bundle.js".into(),
+                    ],
+                },
+            },
+        ]
+    }
+}
+
+impl Exists {
+    /// What is at `path`: "file", "directory", or a symlink and what it points to
+    pub(crate) fn describe(path: &Path) -> String {
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            return "does not exist".into();
+        };
+        if !metadata.file_type().is_symlink() {
+            return Self::kind(&metadata).into();
+        }
+
+        let target = fs::read_link(path)
+            .map(|target| target.display().to_string())
+            .unwrap_or_else(|_| "?".into());
+        match fs::metadata(path) {
+            Ok(metadata) => format!("symlink to {} -> {target}", Self::kind(&metadata)),
+            Err(_) => format!("broken symlink -> {target}"),
+        }
+    }
+
+    fn kind(metadata: &fs::Metadata) -> &'static str {
+        if metadata.is_dir() {
+            "directory"
+        } else if metadata.is_file() {
+            "file"
+        } else {
+            "special file"
+        }
+    }
+}
+
+impl Tool<FsTools> for Exists {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let mut output = String::new();
+        for path in &self.paths {
+            let description = match state.resolve_path(path, None) {
+                Ok(resolved) => Self::describe(&resolved),
+                Err(error) => format!("error: {error}"),
+            };
+            output.push_str(&format!("{path}: {description}\n"));
+        }
+        Ok(output)
+    }
+}
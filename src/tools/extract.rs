@@ -0,0 +1,317 @@
+use super::archive::{ArchiveFormat, is_enclosed};
+use crate::{
+    hints::{self, ErrorCode, Hinted},
+    quota::QuotaUsage,
+    settings::Verbosity,
+    tools::FsTools,
+};
+use anyhow::{Context, Result, bail};
+use clap::ArgAction;
+use flate2::read::GzDecoder;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+use zip::ZipArchive;
+
+/// Extract a zip or tar.gz archive into a directory. Entries that would be written outside of
+/// the destination directory are skipped and reported, and nothing is extracted if an entry would
+/// be written through a symlink already in the destination
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "extract")]
+pub struct Extract {
+    /// Path of the archive to extract
+    /// Can be absolute, or relative to session context path.
+    pub archive: String,
+
+    /// Directory to extract into. Will be created if it does not exist
+    /// Can be absolute, or relative to session context path.
+    pub destination: String,
+
+    /// Archive format. Inferred from the archive extension (.zip, .tar.gz, .tgz) if not provided
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub format: Option<ArchiveFormat>,
+
+    /// Overwrite files in the destination that already exist
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub overwrite: Option<bool>,
+
+    /// Response detail: "quiet" reports only the number of files extracted, "normal" adds the
+    /// archive, destination, and any skipped entries, and "detailed" also lists every extracted
+    /// file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub verbosity: Option<Verbosity>,
+}
+
+impl WithExamples for Extract {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Extracting a downloaded release relative to a session",
+                item: Self {
+                    archive: "downloads/tool-v1.2.0.tar.gz".into(),
+                    destination: "vendor/tool".into(),
+                    format: None,
+                    overwrite: None,
+                    verbosity: None,
+                },
+            },
+            Example {
+                description: "Extracting over an existing directory by absolute path",
+                item: Self {
+                    archive: "/some/absolute/path/fixtures.zip".into(),
+                    destination: "/some/absolute/path/tests/fixtures".into(),
+                    format: None,
+                    overwrite: Some(true),
+                    verbosity: None,
+                },
+            },
+        ]
+    }
+}
+
+#[derive(Debug, Default)]
+struct Extraction {
+    extracted: Vec<PathBuf>,
+    skipped: Vec<String>,
+}
+
+impl Extract {
+    fn overwrite(&self) -> bool {
+        self.overwrite.unwrap_or_default()
+    }
+
+    fn check_conflicts(&self, destination: &Path, entries: &[PathBuf]) -> Result<()> {
+        if self.overwrite() {
+            return Ok(());
+        }
+
+        let conflicts = entries
+            .iter()
+            .map(|entry| destination.join(entry))
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>();
+
+        if let Some(first) = conflicts.first() {
+            bail!(
+                "{} and {} other file(s) already exist, use `overwrite` to intentionally replace them. \
+                 No files have been extracted",
+                first.display(),
+                conflicts.len() - 1
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Refuse entries whose path in `destination` goes through a symlink that is already there,
+    /// since writing them would follow the link, possibly to outside of the destination
+    fn check_symlinks(destination: &Path, entries: &[PathBuf]) -> Result<()> {
+        for entry in entries {
+            let mut current = destination.to_path_buf();
+            for component in entry.components() {
+                current.push(component);
+                if current.is_symlink() {
+                    return Err(Hinted::new(
+                        format!(
+                            "{} is a symlink, which extracting {} would write through. No files \
+                             have been extracted",
+                            current.display(),
+                            entry.display()
+                        ),
+                        Some("remove the symlink, or extract into another directory".into()),
+                    )
+                    .with_code(ErrorCode::Conflict)
+                    .with_path(&current)
+                    .into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Charge the session's quota for extracting `files`, each with its size, before any of
+    /// them is written
+    fn charge(state: &mut FsTools, destination: &Path, files: &[(PathBuf, u64)]) -> Result<()> {
+        state.charge_quota(QuotaUsage {
+            bytes_written: files.iter().map(|(_, size)| size).sum(),
+            files_created: files
+                .iter()
+                .filter(|(path, _)| !destination.join(path).exists())
+                .count() as u64,
+            files_deleted: 0,
+        })
+    }
+
+    fn extract_zip(
+        &self,
+        state: &mut FsTools,
+        archive_path: &Path,
+        destination: &Path,
+    ) -> Result<Extraction> {
+        let mut archive = ZipArchive::new(File::open(archive_path)?)
+            .with_context(|| format!("Unable to read {} as zip", archive_path.display()))?;
+
+        let mut entries = vec![];
+        let mut files = vec![];
+        for i in 0..archive.len() {
+            let Ok(entry) = archive.by_index(i) else {
+                continue;
+            };
+            let Some(path) = entry.enclosed_name() else {
+                continue;
+            };
+            if entry.is_file() {
+                files.push((path.clone(), entry.size()));
+            }
+            entries.push(path);
+        }
+        self.check_conflicts(destination, &entries)?;
+        Self::check_symlinks(destination, &entries)?;
+        Self::charge(state, destination, &files)?;
+
+        let mut extraction = Extraction::default();
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            let name = entry.name().unwrap_or_default().to_string();
+
+            let Some(relative) = entry.enclosed_name() else {
+                extraction
+                    .skipped
+                    .push(format!("{name} (outside destination)"));
+                continue;
+            };
+
+            if entry.is_symlink() {
+                extraction.skipped.push(format!("{name} (symlink)"));
+                continue;
+            }
+
+            let path = destination.join(&relative);
+            if entry.is_dir() {
+                fs::create_dir_all(&path)?;
+                continue;
+            }
+
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut file = File::create(&path)
+                .with_context(|| format!("Unable to create {}", path.display()))?;
+            io::copy(&mut entry, &mut file)?;
+            extraction.extracted.push(relative);
+
+            #[cfg(unix)]
+            if let Some(mode) = entry.unix_mode() {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&path, fs::Permissions::from_mode(mode & 0o777))?;
+            }
+        }
+
+        Ok(extraction)
+    }
+
+    fn extract_tar_gz(
+        &self,
+        state: &mut FsTools,
+        archive_path: &Path,
+        destination: &Path,
+    ) -> Result<Extraction> {
+        let open = || -> Result<tar::Archive<GzDecoder<File>>> {
+            Ok(tar::Archive::new(GzDecoder::new(File::open(archive_path)?)))
+        };
+
+        let mut entries = Vec::new();
+        let mut files = vec![];
+        for entry in open()?.entries()? {
+            let entry = entry?;
+            let path = entry.path()?.into_owned();
+            if is_enclosed(&path) {
+                if entry.header().entry_type().is_file() {
+                    files.push((path.clone(), entry.header().size()?));
+                }
+                entries.push(path);
+            }
+        }
+        self.check_conflicts(destination, &entries)?;
+        Self::check_symlinks(destination, &entries)?;
+        Self::charge(state, destination, &files)?;
+
+        let mut extraction = Extraction::default();
+        let mut archive = open()?;
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.into_owned();
+            let is_file = entry.header().entry_type().is_file();
+
+            if !is_enclosed(&path) || !entry.unpack_in(destination)? {
+                extraction
+                    .skipped
+                    .push(format!("{} (outside destination)", path.display()));
+            } else if is_file {
+                extraction.extracted.push(path);
+            }
+        }
+
+        Ok(extraction)
+    }
+}
+
+impl Tool<FsTools> for Extract {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let archive = state.resolve_path(&self.archive, None)?;
+        let destination = state.resolve_new_path(&self.destination, None)?;
+        let format = ArchiveFormat::detect(&archive, self.format)?;
+
+        if !archive.is_file() {
+            return Err(hints::not_found(&archive));
+        }
+
+        fs::create_dir_all(&destination)
+            .with_context(|| format!("Failed to create {}", destination.display()))?;
+
+        let extraction = match format {
+            ArchiveFormat::Zip => self.extract_zip(state, &archive, &destination)?,
+            ArchiveFormat::TarGz => self.extract_tar_gz(state, &archive, &destination)?,
+        };
+
+        let verbosity = state.settings(None)?.verbosity(self.verbosity);
+        if verbosity == Verbosity::Quiet {
+            return Ok(format!("Extracted {} files", extraction.extracted.len()));
+        }
+
+        let mut result = format!(
+            "Extracted {} files from {} into {}",
+            extraction.extracted.len(),
+            archive.display(),
+            destination.display()
+        );
+
+        if verbosity == Verbosity::Detailed {
+            result.push_str("\n\n");
+            for path in &extraction.extracted {
+                result.push_str(&format!("{}\n", path.display()));
+            }
+        }
+
+        if !extraction.skipped.is_empty() {
+            result.push_str(&format!(
+                "\n\nSkipped {} entries:\n{}",
+                extraction.skipped.len(),
+                extraction.skipped.join("\n")
+            ));
+        }
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,365 @@
+use crate::{
+    deadline, hints, project_ignore::ProjectIgnore, tools::FsTools, visibility::Visibility,
+};
+use anyhow::{Context, Result};
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use regex::{Captures, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::{collections::HashSet, path::Path};
+
+/// Run a regex with capture groups over files and return only the captured values, e.g. to
+/// collect every version string in a tree. Use search instead to see matches in context
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "extract_matches")]
+pub struct ExtractMatches {
+    /// Regex with capture groups. If it has no capture groups, the whole match is returned
+    pub pattern: String,
+
+    /// File or directory to extract from. Gitignored and hidden files are skipped
+    /// Can be absolute, or relative to session context path.
+    /// Defaults to current session context if not provided.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// Only return this capture group, by name or number. By default every capture group is
+    /// returned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub group: Option<String>,
+
+    /// File extensions to include (e.g., ["rs", "toml"])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub include_extensions: Option<Vec<String>>,
+
+    /// Return each distinct value once, in order of first appearance, instead of every match
+    /// with its location
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub unique: Option<bool>,
+
+    /// Return the values as JSON instead of lines of text: an object whose `values` (with
+    /// `unique`) or `matches` lists what was found, with `more` counting what `max_results` left
+    /// out and `time_limit_reached` saying whether the results are partial
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub json: Option<bool>,
+
+    /// Case sensitive matching
+    /// Default: true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub case_sensitive: Option<bool>,
+
+    /// Maximum number of values to return
+    /// Default: 1000
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub max_results: Option<usize>,
+
+    /// Include paths excluded by the working directory's `.fs-mcp-ignore` file
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub bypass_ignore_file: Option<bool>,
+
+    /// Include hidden files and directories, whose names start with `.`
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_hidden: Option<bool>,
+
+    /// Include paths ignored by `.gitignore`, `.ignore`, and git's excludes. Paths in
+    /// `.fs-mcp-ignore` are included with `bypass_ignore_file` instead
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_ignored: Option<bool>,
+
+    /// Stop walking after this many milliseconds and return what was found so far
+    /// Default: 30000, or the server's `--timeout-ms`, which also caps this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub time_limit_ms: Option<u64>,
+}
+
+impl WithExamples for ExtractMatches {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Collecting every distinct dependency version in Cargo manifests",
+                item: Self {
+                    pattern: r#"^(\w[\w-]*) = "([\d.]+)""#.into(),
+                    path: None,
+                    group: None,
+                    include_extensions: Some(vec!["toml".into()]),
+                    unique: Some(true),
+                    json: None,
+                    case_sensitive: None,
+                    max_results: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    time_limit_ms: None,
+                },
+            },
+            Example {
+                description: "Listing environment variables read by the code as JSON",
+                item: Self {
+                    pattern: r#"env::var\("(?<name>[A-Z_]+)"\)"#.into(),
+                    path: Some("src".into()),
+                    group: Some("name".into()),
+                    include_extensions: Some(vec!["rs".into()]),
+                    unique: Some(true),
+                    json: Some(true),
+                    case_sensitive: None,
+                    max_results: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    time_limit_ms: None,
+                },
+            },
+        ]
+    }
+}
+
+/// One match: where it was found and the values it captured
+struct Extracted {
+    location: String,
+    values: Vec<String>,
+}
+
+impl ExtractMatches {
+    fn unique(&self) -> bool {
+        self.unique.unwrap_or_default()
+    }
+
+    fn json(&self) -> bool {
+        self.json.unwrap_or_default()
+    }
+
+    fn max_results(&self) -> usize {
+        self.max_results.unwrap_or(1000)
+    }
+
+    /// The values to report for one regex match. `group` must already have been validated
+    fn captured_values(regex: &Regex, captures: &Captures<'_>, group: Option<&str>) -> Vec<String> {
+        let value = |m: Option<regex::Match<'_>>| m.map_or("", |m| m.as_str()).to_string();
+
+        match group {
+            Some(group) => match group.parse::<usize>() {
+                Ok(index) => vec![value(captures.get(index))],
+                Err(_) => vec![value(captures.name(group))],
+            },
+            None if regex.captures_len() == 1 => vec![value(captures.get(0))],
+            None => captures.iter().skip(1).map(value).collect(),
+        }
+    }
+
+    /// The distinct values of the matches, in order of first appearance
+    fn distinct(extracted: Vec<Extracted>) -> Vec<Vec<String>> {
+        let mut seen = HashSet::new();
+        extracted
+            .into_iter()
+            .map(|extracted| extracted.values)
+            .filter(|values| seen.insert(values.clone()))
+            .collect()
+    }
+
+    fn has_group(regex: &Regex, group: &str) -> bool {
+        match group.parse::<usize>() {
+            Ok(index) => index < regex.captures_len(),
+            Err(_) => regex.capture_names().flatten().any(|name| name == group),
+        }
+    }
+
+    fn should_extract(&self, path: &Path) -> bool {
+        match &self.include_extensions {
+            Some(extensions) => path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| extensions.iter().any(|allowed| allowed == extension)),
+            None => true,
+        }
+    }
+
+    fn extract_file(
+        &self,
+        path: &Path,
+        regex: &Regex,
+        extracted: &mut Vec<Extracted>,
+        total: &mut usize,
+    ) -> Result<()> {
+        let Ok(bytes) = std::fs::read(path) else {
+            return Ok(());
+        };
+        if bytes.contains(&0) {
+            return Ok(());
+        }
+        let content = String::from_utf8_lossy(&bytes);
+
+        for (index, line) in content.lines().enumerate() {
+            for captures in regex.captures_iter(line) {
+                *total += 1;
+                if extracted.len() < self.max_results() || self.unique() {
+                    extracted.push(Extracted {
+                        location: format!("{}:{}", path.display(), index + 1),
+                        values: Self::captured_values(regex, &captures, self.group.as_deref()),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Tool<FsTools> for ExtractMatches {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let path = state.resolve_path(self.path.as_deref().unwrap_or("."), None)?;
+        if !path.exists() {
+            return Err(hints::not_found(&path));
+        }
+
+        let regex = RegexBuilder::new(&self.pattern)
+            .case_insensitive(!self.case_sensitive.unwrap_or(true))
+            .build()
+            .context("Invalid regex pattern")?;
+
+        if let Some(group) = &self.group {
+            // Validate up front so the error doesn't depend on whether anything matched
+            if !Self::has_group(&regex, group) {
+                return Err(hints::Hinted::new(
+                    format!("The pattern has no capture group {group}"),
+                    Some(format!(
+                        "use a number from 0 to {} or one of the named groups",
+                        regex.captures_len() - 1
+                    )),
+                )
+                .into());
+            }
+        }
+
+        let mut extracted = vec![];
+        let mut total = 0;
+        let ignore = ProjectIgnore::load(state, self.bypass_ignore_file.unwrap_or_default())?;
+        let walk = Visibility::new(self.include_hidden, self.include_ignored)
+            .walk_builder(&path)
+            .filter_entry(move |entry| {
+                let is_dir = entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_dir());
+                !ignore.is_ignored(entry.path(), is_dir)
+            })
+            .build();
+        let deadline = state.deadline(self.time_limit_ms, deadline::DEFAULT_TIME_LIMIT);
+        let mut timed_out = false;
+        for entry in walk.flatten() {
+            if deadline.is_reached() {
+                timed_out = true;
+                break;
+            }
+            if entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+                && self.should_extract(entry.path())
+            {
+                self.extract_file(entry.path(), &regex, &mut extracted, &mut total)?;
+            }
+        }
+
+        let max_results = self.max_results();
+        let render_values = |values: &[String]| -> Value {
+            match values {
+                [value] => json!(value),
+                values => json!(values),
+            }
+        };
+
+        if self.json() {
+            let output = if self.unique() {
+                let values = Self::distinct(extracted);
+                json!({
+                    "values": values.iter().take(max_results).map(|v| render_values(v)).collect::<Vec<_>>(),
+                    "match_count": total,
+                    "more": values.len().saturating_sub(max_results),
+                    "time_limit_reached": timed_out,
+                })
+            } else {
+                json!({
+                    "matches": extracted
+                        .iter()
+                        .map(|extracted| {
+                            json!({
+                                "location": extracted.location,
+                                "value": render_values(&extracted.values),
+                            })
+                        })
+                        .collect::<Vec<_>>(),
+                    "more": total - extracted.len(),
+                    "time_limit_reached": timed_out,
+                })
+            };
+            return Ok(serde_json::to_string_pretty(&output)?);
+        }
+
+        let time_limit_note = if timed_out {
+            deadline.note()
+        } else {
+            String::new()
+        };
+        if extracted.is_empty() {
+            return Ok(format!(
+                "No matches found for pattern \"{}\" in {}{time_limit_note}",
+                self.pattern,
+                path.display()
+            ));
+        }
+
+        if self.unique() {
+            let values = Self::distinct(extracted);
+            let distinct = values.len();
+            let shown = &values[..distinct.min(max_results)];
+
+            let mut output = format!("{distinct} distinct values from {total} matches:\n\n");
+            for values in shown {
+                output.push_str(&values.join("\t"));
+                output.push('\n');
+            }
+            if distinct > shown.len() {
+                output.push_str(&format!(
+                    "\n... and {} more distinct values (limit {max_results})",
+                    distinct - shown.len()
+                ));
+            }
+            output.push_str(&time_limit_note);
+            return Ok(output);
+        }
+
+        let mut output = format!("{total} matches:\n\n");
+        for extracted in &extracted {
+            output.push_str(&format!(
+                "{}: {}\n",
+                extracted.location,
+                extracted.values.join("\t")
+            ));
+        }
+        if total > extracted.len() {
+            output.push_str(&format!(
+                "\n... and {} more matches (limit {max_results})",
+                total - extracted.len()
+            ));
+        }
+        output.push_str(&time_limit_note);
+
+        Ok(output)
+    }
+}
@@ -0,0 +1,319 @@
+use crate::{
+    deadline,
+    hints::{self, Hint, Unreadable},
+    project_ignore::ProjectIgnore,
+    settings::SessionSettings,
+    tools::FsTools,
+    visibility::Visibility,
+};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+/// Count the lines, non-blank lines, and bytes of a file, and find its longest line, to judge
+/// how big it is before reading it. For a directory, the counts are totalled per extension,
+/// like a small `tokei`. Read-only
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "file_stats")]
+pub struct FileStats {
+    /// File or directory to count
+    /// Can be absolute, or relative to session context path.
+    /// Defaults to the current session context if not provided
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// For a directory, how many of the files with the most lines to list after the totals
+    /// Default: 5
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub largest_files: Option<usize>,
+
+    /// Include paths excluded by the working directory's `.fs-mcp-ignore` file
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub bypass_ignore_file: Option<bool>,
+
+    /// Include hidden files and directories, whose names start with `.`
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_hidden: Option<bool>,
+
+    /// Include paths ignored by `.gitignore`, `.ignore`, and git's excludes. Paths in
+    /// `.fs-mcp-ignore` are included with `bypass_ignore_file` instead
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_ignored: Option<bool>,
+
+    /// Stop walking after this many milliseconds and return what was counted so far
+    /// Default: 30000, or the server's `--timeout-ms`, which also caps this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub time_limit_ms: Option<u64>,
+}
+
+impl WithExamples for FileStats {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Checking how long a file is before deciding how to read it",
+                item: Self {
+                    path: Some("src/parser.rs".into()),
+                    largest_files: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    time_limit_ms: None,
+                },
+            },
+            Example {
+                description: "Sizing up the code of the current project by language",
+                item: Self {
+                    path: None,
+                    largest_files: Some(10),
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    time_limit_ms: None,
+                },
+            },
+        ]
+    }
+}
+
+/// How many bytes at the start of a file are checked for NUL bytes to tell binary files apart
+const BINARY_SAMPLE_BYTES: usize = 8 * 1024;
+
+/// The counts of one file
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Counts {
+    pub bytes: u64,
+    pub binary: bool,
+    pub lines: usize,
+    pub non_blank: usize,
+    /// Length in characters of the longest line, and its 1-based line number
+    pub longest: Option<(usize, usize)>,
+}
+
+impl Counts {
+    /// Count the lines of the file at `path`, reading it a line at a time. Binary files only
+    /// have their size counted
+    pub(crate) fn of(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let mut counts = Self {
+            bytes: file.metadata()?.len(),
+            ..Self::default()
+        };
+        let mut reader = BufReader::new(file);
+        if reader
+            .fill_buf()?
+            .iter()
+            .take(BINARY_SAMPLE_BYTES)
+            .any(|&byte| byte == 0)
+        {
+            counts.binary = true;
+            return Ok(counts);
+        }
+
+        let mut line = vec![];
+        while reader.read_until(b'\n', &mut line)? > 0 {
+            counts.lines += 1;
+            let text = String::from_utf8_lossy(&line);
+            let text = text.trim_end_matches(['\n', '\r']);
+            if !text.trim().is_empty() {
+                counts.non_blank += 1;
+            }
+            let length = text.chars().count();
+            if counts.longest.is_none_or(|(longest, _)| length > longest) {
+                counts.longest = Some((length, counts.lines));
+            }
+            line.clear();
+        }
+        Ok(counts)
+    }
+
+    fn describe(&self, settings: &SessionSettings) -> String {
+        if self.binary {
+            return format!("binary, {}", settings.format_size(self.bytes));
+        }
+        let longest = match self.longest {
+            Some((length, line)) => format!(", longest line {length} characters (line {line})"),
+            None => String::new(),
+        };
+        format!(
+            "{} lines ({} non-blank){longest}, {}",
+            self.lines,
+            self.non_blank,
+            settings.format_size(self.bytes)
+        )
+    }
+}
+
+/// The counts of the files with one extension
+#[derive(Debug, Default, Clone, Copy)]
+struct Totals {
+    files: usize,
+    lines: usize,
+    non_blank: usize,
+    bytes: u64,
+}
+
+impl Totals {
+    fn add(&mut self, counts: &Counts) {
+        self.files += 1;
+        self.lines += counts.lines;
+        self.non_blank += counts.non_blank;
+        self.bytes += counts.bytes;
+    }
+}
+
+impl FileStats {
+    fn directory(
+        &self,
+        state: &mut FsTools,
+        base_path: &Path,
+        settings: &SessionSettings,
+    ) -> Result<String> {
+        let ignore = ProjectIgnore::load(state, self.bypass_ignore_file.unwrap_or_default())?;
+        let walk = Visibility::new(self.include_hidden, self.include_ignored)
+            .walk_builder(base_path)
+            .filter_entry(move |entry| {
+                let is_dir = entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_dir());
+                !ignore.is_ignored(entry.path(), is_dir)
+            })
+            .build();
+
+        let mut unreadable = Unreadable::default();
+        let mut total = Totals::default();
+        let mut by_extension: BTreeMap<String, Totals> = BTreeMap::new();
+        let mut files: Vec<(usize, PathBuf)> = vec![];
+        let deadline = state.deadline(self.time_limit_ms, deadline::DEFAULT_TIME_LIMIT);
+        let mut timed_out = false;
+        for entry in walk {
+            if deadline.is_reached() {
+                timed_out = true;
+                break;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    unreadable.record_walk_error(&error);
+                    continue;
+                }
+            };
+            if !entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+            {
+                continue;
+            }
+            let counts = match Counts::of(entry.path()) {
+                Ok(counts) => counts,
+                Err(error) => {
+                    unreadable.record(entry.path(), error);
+                    continue;
+                }
+            };
+
+            let extension = match (counts.binary, entry.path().extension()) {
+                (true, _) => "(binary)".to_string(),
+                (false, Some(extension)) => extension.to_string_lossy().to_lowercase(),
+                (false, None) => "(none)".to_string(),
+            };
+            total.add(&counts);
+            by_extension.entry(extension).or_default().add(&counts);
+            if !counts.binary {
+                files.push((counts.lines, entry.into_path()));
+            }
+        }
+
+        let mut output = format!(
+            "File statistics for {} ({} files, {} lines, {} non-blank, {}):\n\n",
+            base_path.display(),
+            total.files,
+            total.lines,
+            total.non_blank,
+            settings.format_size(total.bytes)
+        );
+
+        let mut by_extension = by_extension.into_iter().collect::<Vec<_>>();
+        by_extension.sort_by(|(a_name, a), (b_name, b)| {
+            b.lines
+                .cmp(&a.lines)
+                .then_with(|| b.bytes.cmp(&a.bytes))
+                .then_with(|| a_name.cmp(b_name))
+        });
+        let width = by_extension
+            .iter()
+            .map(|(extension, _)| extension.len())
+            .max()
+            .unwrap_or(0)
+            .max("extension".len());
+        output.push_str(&format!(
+            "{:width$} | files | lines | non-blank | size\n",
+            "extension"
+        ));
+        for (extension, totals) in &by_extension {
+            output.push_str(&format!(
+                "{extension:width$} | {} | {} | {} | {}\n",
+                totals.files,
+                totals.lines,
+                totals.non_blank,
+                settings.format_size(totals.bytes)
+            ));
+        }
+
+        files.sort_by(|(a_lines, a_path), (b_lines, b_path)| {
+            b_lines.cmp(a_lines).then_with(|| a_path.cmp(b_path))
+        });
+        files.truncate(self.largest_files.unwrap_or(5));
+        if !files.is_empty() {
+            output.push_str("\nMost lines:\n");
+            for (lines, path) in &files {
+                let relative = path.strip_prefix(base_path).unwrap_or(path);
+                output.push_str(&format!("{} | {lines} lines\n", relative.display()));
+            }
+        }
+
+        if timed_out {
+            output.push_str(&deadline.note());
+        }
+        unreadable.append_to(&mut output);
+        Ok(output)
+    }
+}
+
+impl Tool<FsTools> for FileStats {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let base_path = state.resolve_path(self.path.as_deref().unwrap_or("."), None)?;
+        if !base_path.exists() {
+            return Err(hints::not_found(&base_path));
+        }
+
+        let settings = state.settings(None)?;
+        if base_path.is_dir() {
+            return self.directory(state, &base_path, &settings);
+        }
+
+        let counts = Counts::of(&base_path).hint_path(&base_path)?;
+        Ok(format!(
+            "{}: {}",
+            base_path.display(),
+            counts.describe(&settings)
+        ))
+    }
+}
@@ -0,0 +1,38 @@
+use crate::tools::FsTools;
+use anyhow::Result;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+
+/// Show the current working directory and the previous ones of this session, most recent first
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "get_working_directory")]
+pub struct GetWorkingDirectory {}
+
+impl WithExamples for GetWorkingDirectory {
+    fn examples() -> Vec<Example<Self>> {
+        vec![Example {
+            description: "Checking which project relative paths refer to",
+            item: Self {},
+        }]
+    }
+}
+
+impl Tool<FsTools> for GetWorkingDirectory {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let mut output = match state.get_context(None)? {
+            Some(context) => format!("Working directory: {}\n", context.display()),
+            None => "No working directory is set\n".to_string(),
+        };
+        let history = state.directory_history(None)?;
+        if !history.is_empty() {
+            output.push_str("\nPrevious working directories, most recent first:\n");
+            for directory in history.iter().rev() {
+                output.push_str(&format!("  {}\n", directory.display()));
+            }
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{
+    hints::{self, Hint, Hinted},
+    state::Bookmark,
+    tools::FsTools,
+};
+use anyhow::Result;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Show the lines around a bookmark saved with add_bookmark. If the bookmarked line has moved
+/// since, because of edits above it, the nearest line with the same text is shown instead
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "goto_bookmark")]
+pub struct GotoBookmark {
+    /// Name of the bookmark
+    pub name: String,
+
+    /// Lines to show before and after the bookmarked line
+    /// Default: 5
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub context_lines: Option<usize>,
+}
+
+impl WithExamples for GotoBookmark {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Returning to a call site marked earlier",
+                item: Self {
+                    name: "old-api-caller".into(),
+                    context_lines: None,
+                },
+            },
+            Example {
+                description: "Seeing more of the function around a bookmark",
+                item: Self {
+                    name: "old-api-caller".into(),
+                    context_lines: Some(20),
+                },
+            },
+        ]
+    }
+}
+
+impl GotoBookmark {
+    /// The current 1-based line of a bookmark in `lines`: its saved line if the text there is
+    /// unchanged, or else the nearest line with the saved text
+    pub(crate) fn find_line(bookmark: &Bookmark, lines: &[&str]) -> Option<usize> {
+        let line = bookmark.line?;
+        let Some(text) = &bookmark.text else {
+            return Some(line);
+        };
+        if lines.get(line.wrapping_sub(1)) == Some(&text.as_str()) {
+            return Some(line);
+        }
+        lines
+            .iter()
+            .enumerate()
+            .filter(|(_, candidate)| **candidate == text.as_str())
+            .map(|(index, _)| index + 1)
+            .min_by_key(|candidate| candidate.abs_diff(line))
+    }
+}
+
+impl Tool<FsTools> for GotoBookmark {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let bookmarks = state.bookmarks(None)?;
+        let Some(bookmark) = bookmarks.get(self.name.trim()) else {
+            let names = bookmarks.keys().cloned().collect::<Vec<_>>().join(", ");
+            let hint = match names.is_empty() {
+                true => "this session has no bookmarks; add_bookmark saves one".to_string(),
+                false => format!("this session's bookmarks are {names}"),
+            };
+            return Err(Hinted::new(
+                format!("There is no bookmark named {}", self.name),
+                Some(hint),
+            )
+            .into());
+        };
+        if !bookmark.path.is_file() {
+            return Err(hints::not_found(&bookmark.path));
+        }
+
+        let contents = fs::read_to_string(&bookmark.path).hint_path(&bookmark.path)?;
+        let lines = contents.lines().collect::<Vec<_>>();
+        let mut output = format!("{}: {}", self.name, bookmark.location());
+        if let Some(note) = &bookmark.note {
+            output.push_str(&format!(" - {note}"));
+        }
+        output.push('\n');
+
+        let Some(saved_line) = bookmark.line else {
+            output.push_str(&format!("{} lines; read the file to see it\n", lines.len()));
+            return Ok(output);
+        };
+        let Some(line) = Self::find_line(bookmark, &lines) else {
+            output.push_str(&format!(
+                "Line {saved_line} has changed since it was bookmarked, and its text is no longer \
+                 in the file. It was:\n{}\n",
+                bookmark.text.as_deref().unwrap_or_default()
+            ));
+            return Ok(output);
+        };
+        if line != saved_line {
+            output.push_str(&format!("The line has moved to {line}\n"));
+        }
+
+        let context = self.context_lines.unwrap_or(5);
+        let first = line.saturating_sub(context).max(1);
+        let last = (line + context).min(lines.len());
+        output.push('\n');
+        for number in first..=last {
+            let marker = if number == line { '>' } else { ' ' };
+            output.push_str(&format!("{marker}{number:>5}\t{}\n", lines[number - 1]));
+        }
+        Ok(output)
+    }
+}
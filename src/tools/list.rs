@@ -1,16 +1,29 @@
-use crate::tools::FsTools;
-use anyhow::{Result, anyhow};
+use crate::{
+    budget::Limits,
+    deadline::{self, Deadline},
+    exclude::Exclude,
+    hints::{self, ErrorCode, Hinted, Unreadable},
+    project_ignore::ProjectIgnore,
+    settings::SessionSettings,
+    tools::FsTools,
+    visibility::Visibility,
+};
+use anyhow::{Context, Result};
 use clap::ArgAction;
 use glob::Pattern;
-use ignore::{Walk, WalkBuilder};
+use ignore::Walk;
 use mcplease::{
     traits::{Tool, WithExamples},
     types::Example,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use size::Size;
-use std::path::Path;
+use std::{
+    cmp::Ordering,
+    fs::Metadata,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
 /// List file system contents with session context support and globbing
 #[derive(Debug, Serialize, Deserialize, JsonSchema, clap::Args)]
@@ -26,11 +39,147 @@ pub struct List {
     #[arg(long, action = ArgAction::SetTrue)]
     pub recursive: Option<bool>,
 
+    /// How many levels of directories to descend into, where 1 lists only the directory's own
+    /// entries. Implies `recursive`, and also limits glob patterns. Directories at the limit
+    /// are listed without their contents
+    /// Default: no limit, lowered for the session after several large responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub max_depth: Option<usize>,
+
+    /// Show each directory once, with the number and total size of the files under it,
+    /// instead of listing every file. Implies `recursive`; `max_depth` limits which directories
+    /// are shown, but files below it are still counted. List one of the directories afterwards
+    /// to expand it
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub summarize_directories: Option<bool>,
+
     /// Include metadata like file size and last modified
     #[arg(long, action = ArgAction::SetTrue)]
     pub include_metadata: Option<bool>,
+
+    /// Order entries by "name", "size", or "mtime" (last modification)
+    /// Default: name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub sort_by: Option<SortBy>,
+
+    /// "asc" or "desc"
+    /// Default: asc when sorting by name, and desc (largest or most recent first) otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub order: Option<SortOrder>,
+
+    /// Only list "files" or only list "dirs"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub only: Option<EntryKind>,
+
+    /// Only list files of at least this many bytes. Directories are left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub min_size: Option<u64>,
+
+    /// Only list files of at most this many bytes. Directories are left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub max_size: Option<u64>,
+
+    /// Only list entries modified within this long before now, e.g. "30m", "2h", or "7days"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub modified_within: Option<String>,
+
+    /// Maximum number of entries to return. When sorted by name, larger listings keep the
+    /// shallowest entries and directories, leaving out the deepest files first; otherwise the
+    /// first entries in sort order are kept. The response gives a `cursor` for the rest
+    /// Default: 500, lowered for the session after several large responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub max_entries: Option<usize>,
+
+    /// List the entries a truncated listing left out, starting at this position, as given by
+    /// that listing. Use the same other arguments as the truncated call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub cursor: Option<usize>,
+
+    /// Glob patterns of paths to leave out, such as `**/generated/**` or `*.min.js`. Patterns
+    /// without a `/` match names at any depth; the others match paths relative to the working
+    /// directory. Excluded directories aren't walked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub exclude: Option<Vec<String>>,
+
+    /// Include paths excluded by the working directory's `.fs-mcp-ignore` file
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub bypass_ignore_file: Option<bool>,
+
+    /// Include hidden files and directories, whose names start with `.`
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_hidden: Option<bool>,
+
+    /// Include paths ignored by `.gitignore`, `.ignore`, and git's excludes. Paths in
+    /// `.fs-mcp-ignore` are included with `bypass_ignore_file` instead
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_ignored: Option<bool>,
+
+    /// Stop walking after this many milliseconds and return what was found so far
+    /// Default: 30000, or the server's `--timeout-ms`, which also caps this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub time_limit_ms: Option<u64>,
+}
+
+/// What to order list entries by
+#[derive(
+    Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SortBy {
+    Name,
+    Size,
+    Mtime,
+}
+
+/// Direction of a sort
+#[derive(
+    Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    Desc,
 }
 
+/// Kind of entry to restrict a listing to
+#[derive(
+    Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    Files,
+    Dirs,
+}
+
+/// An entry that passed the filters, with what is needed to sort and display it
+struct Listed {
+    name: PathBuf,
+    is_dir: bool,
+    metadata: Option<Metadata>,
+}
+
+/// Directories named in the summary of a truncated listing
+const MAX_ELIDED_DIRECTORIES: usize = 10;
+
 impl WithExamples for List {
     fn examples() -> Vec<Example<Self>> {
         vec![
@@ -39,7 +188,22 @@ impl WithExamples for List {
                 item: Self {
                     path: Some("src/**/*.rs".into()),
                     recursive: None,
+                    max_depth: None,
+                    summarize_directories: None,
                     include_metadata: Some(true),
+                    sort_by: None,
+                    order: None,
+                    only: None,
+                    min_size: None,
+                    max_size: None,
+                    modified_within: None,
+                    max_entries: None,
+                    cursor: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    time_limit_ms: None,
                 },
             },
             Example {
@@ -47,7 +211,160 @@ impl WithExamples for List {
                 item: Self {
                     path: Some("/some/absolute/path".into()),
                     recursive: Some(true),
+                    max_depth: None,
+                    summarize_directories: None,
                     include_metadata: None,
+                    sort_by: None,
+                    order: None,
+                    only: None,
+                    min_size: None,
+                    max_size: None,
+                    modified_within: None,
+                    max_entries: None,
+                    cursor: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    time_limit_ms: None,
+                },
+            },
+            Example {
+                description: "Showing the files changed most recently, within the last hour",
+                item: Self {
+                    path: None,
+                    recursive: Some(true),
+                    max_depth: None,
+                    summarize_directories: None,
+                    include_metadata: Some(true),
+                    sort_by: Some(SortBy::Mtime),
+                    order: None,
+                    only: Some(EntryKind::Files),
+                    min_size: None,
+                    max_size: None,
+                    modified_within: Some("1h".into()),
+                    max_entries: Some(20),
+                    cursor: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    time_limit_ms: None,
+                },
+            },
+            Example {
+                description: "Listing a project's dotfiles, such as .env and .github",
+                item: Self {
+                    path: None,
+                    recursive: None,
+                    max_depth: None,
+                    summarize_directories: None,
+                    include_metadata: None,
+                    sort_by: None,
+                    order: None,
+                    only: None,
+                    min_size: None,
+                    max_size: None,
+                    modified_within: None,
+                    max_entries: None,
+                    cursor: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: Some(true),
+                    include_ignored: None,
+                    time_limit_ms: None,
+                },
+            },
+            Example {
+                description: "Finding the largest files in a directory tree",
+                item: Self {
+                    path: Some("assets".into()),
+                    recursive: Some(true),
+                    max_depth: None,
+                    summarize_directories: None,
+                    include_metadata: Some(true),
+                    sort_by: Some(SortBy::Size),
+                    order: None,
+                    only: None,
+                    min_size: Some(1024 * 1024),
+                    max_size: None,
+                    modified_within: None,
+                    max_entries: None,
+                    cursor: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    time_limit_ms: None,
+                },
+            },
+            Example {
+                description: "Listing a web app's scripts, without generated or minified ones",
+                item: Self {
+                    path: Some("src/**/*.js".into()),
+                    recursive: None,
+                    max_depth: None,
+                    summarize_directories: None,
+                    include_metadata: None,
+                    sort_by: None,
+                    order: None,
+                    only: None,
+                    min_size: None,
+                    max_size: None,
+                    modified_within: None,
+                    max_entries: None,
+                    cursor: None,
+                    exclude: Some(vec!["**/generated/**".into(), "*.min.js".into()]),
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    time_limit_ms: None,
+                },
+            },
+            Example {
+                description: "Getting the shape of a monorepo without descending into every package",
+                item: Self {
+                    path: None,
+                    recursive: None,
+                    max_depth: Some(2),
+                    summarize_directories: None,
+                    include_metadata: None,
+                    sort_by: None,
+                    order: None,
+                    only: Some(EntryKind::Dirs),
+                    min_size: None,
+                    max_size: None,
+                    modified_within: None,
+                    max_entries: None,
+                    cursor: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    time_limit_ms: None,
+                },
+            },
+            Example {
+                description: "Seeing where the files of a large monorepo are, one line per directory",
+                item: Self {
+                    path: Some("packages".into()),
+                    recursive: None,
+                    max_depth: Some(2),
+                    summarize_directories: Some(true),
+                    include_metadata: None,
+                    sort_by: Some(SortBy::Size),
+                    order: None,
+                    only: None,
+                    min_size: None,
+                    max_size: None,
+                    modified_within: None,
+                    max_entries: None,
+                    cursor: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    time_limit_ms: None,
                 },
             },
         ]
@@ -55,26 +372,145 @@ impl WithExamples for List {
 }
 
 impl Tool<FsTools> for List {
-    fn execute(self, state: &mut FsTools) -> Result<String> {
+    fn execute(mut self, state: &mut FsTools) -> Result<String> {
+        let limits = state.limits()?;
+        if self.max_entries.is_none() {
+            self.max_entries = Some(limits.max_entries);
+        }
+        if self.max_depth.is_none() && self.recursive() {
+            self.max_depth = limits.max_depth;
+        }
+
         // Parse path to separate directory from glob pattern
         let (base_path, pattern) = self.parse_path_and_pattern()?;
 
         let base_path = state.resolve_path(base_path, None)?;
 
+        if !base_path.exists() {
+            return Err(hints::not_found(&base_path));
+        }
+
         if !base_path.is_dir() {
-            return Err(anyhow!("Path is not a directory: {}", base_path.display()));
+            return Err(Hinted::new(
+                format!("Path is not a directory: {}", base_path.display()),
+                Some("use read to view the contents of a file".into()),
+            )
+            .with_code(ErrorCode::NotADirectory)
+            .with_path(&base_path)
+            .into());
         }
 
         // Compile glob pattern if provided
         let glob_pattern = pattern.map(Pattern::new).transpose()?;
 
-        let entries = self.build_entries(&base_path, glob_pattern)?;
+        let settings = state.settings(None)?;
+        let ignore = ProjectIgnore::load(state, self.bypass_ignore_file.unwrap_or_default())?;
+        let exclude = Exclude::new(state, self.exclude.as_deref())?;
+        let mut unreadable = Unreadable::default();
+        let deadline = state.deadline(self.time_limit_ms, deadline::DEFAULT_TIME_LIMIT);
+        let (listed, timed_out) = self.build_entries(
+            &base_path,
+            glob_pattern,
+            ignore,
+            exclude,
+            &deadline,
+            &mut unreadable,
+        )?;
+        let time_limit_note = if timed_out {
+            deadline.note()
+        } else {
+            String::new()
+        };
+        let max_entries = self.max_entries();
 
-        let content = format!(
-            "All paths relative to {}:\n\n{}",
-            base_path.display(),
-            entries.join("\n"),
-        );
+        if self.summarize_directories() {
+            let summaries = self.summarize(&listed);
+            let mut content = format!(
+                "Directories under {}, with the files each contains:\n\n",
+                base_path.display()
+            );
+            for (directory, files, bytes) in summaries.iter().take(max_entries) {
+                let noun = if *files == 1 { "file" } else { "files" };
+                content.push_str(&format!(
+                    "{directory}  {files} {noun}, {}\n",
+                    settings.format_size(*bytes)
+                ));
+            }
+            if summaries.len() > max_entries {
+                content.push_str(&format!(
+                    "\n... {} more directories not shown (limit {max_entries}). Summarize one of \
+                     the directories above to see inside it",
+                    summaries.len() - max_entries
+                ));
+            } else {
+                content.push_str("\nList one of these directories to see its files");
+            }
+            content.push_str(&time_limit_note);
+            unreadable.append_to(&mut content);
+            return Ok(content);
+        }
+        let (kept, elided) = self.truncate(listed);
+
+        if let Some(cursor) = self.cursor {
+            let page = elided.iter().skip(cursor).take(max_entries);
+            let mut content = format!(
+                "All paths relative to {}, entries {} to {} of the {} left out of the listing:\n\n",
+                base_path.display(),
+                cursor + 1,
+                (cursor + max_entries).min(elided.len()),
+                elided.len()
+            );
+            for listed in page {
+                content.push_str(&self.format_entry(listed, &settings)?);
+                content.push('\n');
+            }
+            if cursor + max_entries < elided.len() {
+                content.push_str(&format!(
+                    "\nPass cursor: {} for the next entries",
+                    cursor + max_entries
+                ));
+            }
+            content.push_str(&time_limit_note);
+            unreadable.append_to(&mut content);
+            return Ok(content);
+        }
+
+        let mut content = format!("All paths relative to {}:\n\n", base_path.display());
+        let entries = kept
+            .iter()
+            .map(|listed| self.format_entry(listed, &settings))
+            .collect::<Result<Vec<_>>>()?;
+        content.push_str(&entries.join("\n"));
+
+        if !elided.is_empty() {
+            content.push_str(&format!(
+                "\n\n... {} more entries not shown (limit {max_entries})",
+                elided.len()
+            ));
+            if self.sort_by() == SortBy::Name {
+                content.push_str(&format!(", in {}", Self::elided_directories(&elided)));
+            }
+            content.push_str(". Pass cursor: 0 to list them, or narrow the path");
+        }
+        if let Some(max_depth) = self.max_depth {
+            let at_limit = kept
+                .iter()
+                .filter(|listed| listed.is_dir && listed.name.components().count() >= max_depth)
+                .count();
+            let directories = match at_limit {
+                0 => None,
+                1 => Some(format!("1 directory at depth {max_depth} was")),
+                count => Some(format!("{count} directories at depth {max_depth} were")),
+            };
+            if let Some(directories) = directories {
+                content.push_str(&format!(
+                    "\n\n{directories} not expanded; list them directly or raise max_depth to \
+                     see inside"
+                ));
+            }
+        }
+        content.push_str(&time_limit_note);
+        unreadable.append_to(&mut content);
 
         Ok(content)
     }
@@ -92,12 +528,9 @@ impl List {
             let mut split_pos = None;
             for (i, char) in path_str.char_indices() {
                 if char == '*' || char == '?' || char == '[' {
-                    // Find the last path separator before this position
-                    if let Some(sep_pos) = path_str[..i].rfind('/') {
-                        split_pos = Some(sep_pos);
-                    } else if let Some(sep_pos) = path_str[..i].rfind('\\') {
-                        split_pos = Some(sep_pos);
-                    }
+                    // Find the last path separator before this position, which on Windows
+                    // may be either `/` or `\`, mixed in the same path
+                    split_pos = path_str[..i].rfind(std::path::is_separator);
                     break;
                 }
             }
@@ -118,24 +551,90 @@ impl List {
 
     fn recursive(&self) -> bool {
         self.recursive.unwrap_or_default()
+            || self.max_depth.is_some()
+            || self.summarize_directories()
     }
 
-    fn build_walk(&self, base_path: &Path, glob_pattern: Option<&Pattern>) -> Walk {
-        let mut walker = WalkBuilder::new(base_path);
-        if glob_pattern.is_none() && !self.recursive() {
-            walker.max_depth(Some(1));
+    fn summarize_directories(&self) -> bool {
+        self.summarize_directories.unwrap_or_default()
+    }
+
+    /// Each directory of a listing, with the number and total size of the files anywhere under
+    /// it, in sort order. The listed directory itself is named `./`. Only directories within
+    /// `max_depth` are included, though deeper files are counted
+    fn summarize(&self, listed: &[Listed]) -> Vec<(String, usize, u64)> {
+        let mut totals = std::collections::BTreeMap::<&Path, (usize, u64)>::new();
+        totals.insert(Path::new(""), (0, 0));
+        for entry in listed {
+            if entry.is_dir {
+                totals.entry(&entry.name).or_default();
+                continue;
+            }
+            let bytes = entry.metadata.as_ref().map_or(0, Metadata::len);
+            for directory in entry.name.ancestors().skip(1) {
+                let total = totals.entry(directory).or_default();
+                total.0 += 1;
+                total.1 += bytes;
+            }
         }
 
-        walker.standard_filters(true);
+        let mut summaries = totals
+            .into_iter()
+            .filter(|(directory, _)| {
+                self.max_depth
+                    .is_none_or(|max_depth| directory.components().count() < max_depth.max(1))
+            })
+            .map(|(directory, (files, bytes))| {
+                let name = match directory.to_string_lossy() {
+                    name if name.is_empty() => "./".to_string(),
+                    name => format!("{}/", name.trim_end_matches(['/', '\\'])),
+                };
+                (name, files, bytes)
+            })
+            .collect::<Vec<_>>();
+        if self.sort_by() == SortBy::Size {
+            summaries.sort_by(|a, b| a.2.cmp(&b.2).then_with(|| b.0.cmp(&a.0)));
+            if self.order() == SortOrder::Desc {
+                summaries.reverse();
+            }
+        } else if self.order() == SortOrder::Desc {
+            summaries.reverse();
+        }
+        summaries
+    }
 
-        // Add glob pattern filtering if provided
-        if let Some(pattern) = glob_pattern.cloned() {
-            let base_path = base_path.to_owned();
-            walker.filter_entry(move |entry| {
-                entry.path() != base_path && pattern.matches_path(entry.path())
-            });
+    fn build_walk(
+        &self,
+        base_path: &Path,
+        glob_pattern: Option<&Pattern>,
+        ignore: ProjectIgnore,
+        exclude: Exclude,
+    ) -> Walk {
+        let mut walker =
+            Visibility::new(self.include_hidden, self.include_ignored).walk_builder(base_path);
+        // Summaries count the files below `max_depth` too
+        if let Some(max_depth) = self.max_depth.filter(|_| !self.summarize_directories()) {
+            walker.max_depth(Some(max_depth.max(1)));
+        } else if glob_pattern.is_none() && !self.recursive() {
+            walker.max_depth(Some(1));
         }
 
+        // Add glob pattern filtering if provided
+        let glob_pattern = glob_pattern.cloned();
+        let base_path = base_path.to_owned();
+        walker.filter_entry(move |entry| {
+            let is_dir = entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_dir());
+            if ignore.is_ignored(entry.path(), is_dir) || exclude.is_excluded(entry.path()) {
+                return false;
+            }
+            match &glob_pattern {
+                Some(pattern) => entry.path() != base_path && pattern.matches_path(entry.path()),
+                None => true,
+            }
+        });
+
         walker.build()
     }
 
@@ -143,35 +642,241 @@ impl List {
         self.include_metadata.unwrap_or_default()
     }
 
+    fn sort_by(&self) -> SortBy {
+        self.sort_by.unwrap_or(SortBy::Name)
+    }
+
+    fn order(&self) -> SortOrder {
+        self.order.unwrap_or(match self.sort_by() {
+            SortBy::Name => SortOrder::Asc,
+            SortBy::Size | SortBy::Mtime => SortOrder::Desc,
+        })
+    }
+
+    /// Whether entries need their metadata read, for display, filtering, or sorting
+    fn needs_metadata(&self) -> bool {
+        self.include_metadata()
+            || self.summarize_directories()
+            || self.sort_by() != SortBy::Name
+            || self.min_size.is_some()
+            || self.max_size.is_some()
+            || self.modified_within.is_some()
+    }
+
+    /// The earliest modification time that `modified_within` allows
+    fn modified_since(&self) -> Result<Option<SystemTime>> {
+        let Some(modified_within) = &self.modified_within else {
+            return Ok(None);
+        };
+        let duration = humantime::parse_duration(modified_within).with_context(|| {
+            format!("Invalid duration {modified_within}: use e.g. \"30m\", \"2h\", or \"7days\"")
+        })?;
+        Ok(SystemTime::now().checked_sub(duration))
+    }
+
+    fn passes_filters(
+        &self,
+        is_dir: bool,
+        metadata: Option<&Metadata>,
+        modified_since: Option<SystemTime>,
+    ) -> Result<bool> {
+        match self.only {
+            Some(EntryKind::Files) if is_dir => return Ok(false),
+            Some(EntryKind::Dirs) if !is_dir => return Ok(false),
+            _ => {}
+        }
+
+        let Some(metadata) = metadata else {
+            return Ok(true);
+        };
+
+        if self.min_size.is_some() || self.max_size.is_some() {
+            if is_dir
+                || self.min_size.is_some_and(|min| metadata.len() < min)
+                || self.max_size.is_some_and(|max| metadata.len() > max)
+            {
+                return Ok(false);
+            }
+        }
+
+        if let Some(since) = modified_since {
+            if metadata.modified()? < since {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn compare(&self, a: &Listed, b: &Listed) -> Ordering {
+        let ordering = match (self.sort_by(), &a.metadata, &b.metadata) {
+            (SortBy::Size, Some(a_metadata), Some(b_metadata)) => {
+                a_metadata.len().cmp(&b_metadata.len())
+            }
+            (SortBy::Mtime, Some(a_metadata), Some(b_metadata)) => {
+                a_metadata.modified().ok().cmp(&b_metadata.modified().ok())
+            }
+            _ => Ordering::Equal,
+        }
+        .then_with(|| a.name.cmp(&b.name));
+
+        match self.order() {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    }
+
+    fn max_entries(&self) -> usize {
+        self.max_entries
+            .unwrap_or(Limits::DEFAULT.max_entries)
+            .max(1)
+    }
+
+    /// Split sorted entries into those to show and those left out, both in sort order
+    fn truncate(&self, listed: Vec<Listed>) -> (Vec<Listed>, Vec<Listed>) {
+        let max_entries = self.max_entries();
+        if listed.len() <= max_entries {
+            return (listed, vec![]);
+        }
+
+        if self.sort_by() != SortBy::Name {
+            let mut kept = listed;
+            let elided = kept.split_off(max_entries);
+            return (kept, elided);
+        }
+
+        // Keep the shallowest entries, preferring directories at the same depth, so that the
+        // structure of the tree stays visible
+        let mut ranked = (0..listed.len()).collect::<Vec<_>>();
+        ranked.sort_by_key(|&index| {
+            let listed = &listed[index];
+            (listed.name.components().count(), !listed.is_dir)
+        });
+        let mut keep = vec![false; listed.len()];
+        for &index in &ranked[..max_entries] {
+            keep[index] = true;
+        }
+
+        listed.into_iter().zip(keep).fold(
+            (vec![], vec![]),
+            |(mut kept, mut elided), (listed, keep)| {
+                if keep {
+                    kept.push(listed);
+                } else {
+                    elided.push(listed);
+                }
+                (kept, elided)
+            },
+        )
+    }
+
+    /// e.g. "src/tools/ (120), tests/ (3)": where the left out entries are, most first
+    fn elided_directories(elided: &[Listed]) -> String {
+        let mut counts = std::collections::BTreeMap::<PathBuf, usize>::new();
+        for listed in elided {
+            let parent = listed.name.parent().unwrap_or(Path::new("")).to_path_buf();
+            *counts.entry(parent).or_default() += 1;
+        }
+
+        let mut counts = counts.into_iter().collect::<Vec<_>>();
+        counts.sort_by(|(a_dir, a_count), (b_dir, b_count)| {
+            b_count.cmp(a_count).then_with(|| a_dir.cmp(b_dir))
+        });
+
+        let mut directories = counts
+            .iter()
+            .take(MAX_ELIDED_DIRECTORIES)
+            .map(|(dir, count)| {
+                let mut dir = dir.clone();
+                dir.push("");
+                format!("{} ({count})", dir.display())
+            })
+            .collect::<Vec<_>>();
+        if counts.len() > MAX_ELIDED_DIRECTORIES {
+            directories.push(format!(
+                "and {} other directories",
+                counts.len() - MAX_ELIDED_DIRECTORIES
+            ));
+        }
+        directories.join(", ")
+    }
+
+    fn format_entry(&self, listed: &Listed, settings: &SessionSettings) -> Result<String> {
+        let metadata_string = match &listed.metadata {
+            Some(metadata) if self.include_metadata() => {
+                let formatter = settings.timestamp_formatter();
+                let len = settings.format_size(metadata.len());
+                let created = formatter.format(metadata.created()?);
+                let modified = formatter.format(metadata.modified()?);
+                format!(" | {len} | created {created} | modified {modified}")
+            }
+            _ => String::new(),
+        };
+
+        Ok(format!("{}{}", listed.name.display(), metadata_string))
+    }
+
+    /// Walk the listing, keeping the entries that pass the filters, in sort order, and whether
+    /// the walk stopped at the deadline
     fn build_entries(
         &self,
         base_path: &Path,
         glob_pattern: Option<Pattern>,
-    ) -> Result<Vec<String>> {
-        let walker = self.build_walk(base_path, glob_pattern.as_ref());
-        let mut entries = Vec::new();
-        let formatter = timeago::Formatter::new();
-        for entry in walker.flatten() {
-            let mut file_name =
+        ignore: ProjectIgnore,
+        exclude: Exclude,
+        deadline: &Deadline,
+        unreadable: &mut Unreadable,
+    ) -> Result<(Vec<Listed>, bool)> {
+        let walker = self.build_walk(base_path, glob_pattern.as_ref(), ignore, exclude);
+        let modified_since = self.modified_since()?;
+        let mut listed = Vec::new();
+        let mut timed_out = false;
+        for entry in walker {
+            if deadline.is_reached() {
+                timed_out = true;
+                break;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    unreadable.record_walk_error(&error);
+                    continue;
+                }
+            };
+            if entry.path() == base_path {
+                continue;
+            }
+
+            let mut name =
                 pathdiff::diff_paths(entry.path(), base_path).unwrap_or(entry.path().to_owned());
 
-            if entry.file_type().is_some_and(|ft| ft.is_dir()) {
-                file_name.push("");
+            let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+            if is_dir {
+                name.push("");
             }
 
-            let metadata_string = if self.include_metadata() {
-                let metadata = entry.metadata()?;
-                let len = Size::from_bytes(metadata.len());
-                let created = formatter.convert(metadata.created()?.elapsed()?);
-                let modified = formatter.convert(metadata.modified()?.elapsed()?);
-                format!(" | {len} | created {created} | modified {modified}")
+            let metadata = if self.needs_metadata() {
+                match entry.metadata() {
+                    Ok(metadata) => Some(metadata),
+                    Err(error) => {
+                        unreadable.record_walk_error(&error);
+                        continue;
+                    }
+                }
             } else {
-                String::new()
+                None
             };
 
-            entries.push(format!("{}{}", file_name.display(), metadata_string));
+            if self.passes_filters(is_dir, metadata.as_ref(), modified_since)? {
+                listed.push(Listed {
+                    name,
+                    is_dir,
+                    metadata,
+                });
+            }
         }
-        entries.sort();
-        Ok(entries)
+
+        listed.sort_by(|a, b| self.compare(a, b));
+        Ok((listed, timed_out))
     }
 }
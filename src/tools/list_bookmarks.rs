@@ -0,0 +1,53 @@
+use crate::tools::FsTools;
+use anyhow::Result;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+
+/// List this session's bookmarks, saved with add_bookmark, with their notes
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "list_bookmarks")]
+pub struct ListBookmarks {}
+
+impl WithExamples for ListBookmarks {
+    fn examples() -> Vec<Example<Self>> {
+        vec![Example {
+            description: "Checking which places are left to come back to",
+            item: Self {},
+        }]
+    }
+}
+
+impl Tool<FsTools> for ListBookmarks {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let bookmarks = state.bookmarks(None)?;
+        if bookmarks.is_empty() {
+            return Ok("No bookmarks in this session. add_bookmark saves one".into());
+        }
+
+        let formatter = state.settings(None)?.timestamp_formatter();
+        let width = bookmarks.keys().map(|name| name.chars().count()).max();
+        let width = width.unwrap_or(0);
+        let mut output = match bookmarks.len() {
+            1 => "1 bookmark:\n\n".to_string(),
+            count => format!("{count} bookmarks:\n\n"),
+        };
+        for (name, bookmark) in &bookmarks {
+            output.push_str(&format!(
+                "{name:<width$}  {} (added {})",
+                bookmark.location(),
+                formatter.format(bookmark.created_at)
+            ));
+            if let Some(note) = &bookmark.note {
+                output.push_str(&format!(" - {note}"));
+            }
+            if !bookmark.path.exists() {
+                output.push_str(" [file no longer exists]");
+            }
+            output.push('\n');
+        }
+        Ok(output)
+    }
+}
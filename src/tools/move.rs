@@ -1,17 +1,33 @@
-use crate::tools::FsTools;
-use anyhow::{Result, anyhow};
+use crate::{
+    hints::{self, ErrorCode, Hint, Hinted},
+    quota::QuotaUsage,
+    settings::{SessionSettings, Verbosity},
+    tools::FsTools,
+};
+use anyhow::Result;
 use clap::ArgAction;
+use filetime::FileTime;
 use mcplease::{
     traits::{Tool, WithExamples},
     types::Example,
 };
 use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+/// Directories with more files than this are only moved or deleted with `confirm`
+const CONFIRM_FILES: usize = 100;
+
+/// Directories larger than this are only moved or deleted with `confirm`
+const CONFIRM_BYTES: u64 = 50 * 1024 * 1024;
 
-/// Move a file from one location to another
+/// Entries visited when measuring a directory before giving up on an exact count
+const IMPACT_TRAVERSAL_CAP: usize = 10_000;
+
+/// Move a file or directory from one location to another, including across filesystems
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
 #[serde(rename = "move")]
 pub struct Move {
-    /// Path to move from
+    /// File or directory to move from. Directories are moved with everything they contain
     /// Can be absolute, or relative to session context path.
     pub source: String,
     /// Path to move to
@@ -26,10 +42,33 @@ pub struct Move {
     pub overwrite: Option<bool>,
 
     /// Create any directories leading up to the destination path if they don't already exist
-    /// Default: true
+    /// Default: the session's `auto_create_directories` preference (true unless changed with
+    /// configure_session)
     #[serde(skip_serializing_if = "Option::is_none")]
     #[arg(long, action = ArgAction::SetTrue)]
     pub create_directories: Option<bool>,
+
+    /// Response detail: "quiet" names only the destination, "normal" names source and
+    /// destination, and "detailed" also reports the size of what was moved and whether an
+    /// existing file was replaced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub verbosity: Option<Verbosity>,
+
+    /// Proceed with moving a directory of more than 100 files or 50MiB. Without it, such a
+    /// move only reports how many files and bytes it would involve
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub confirm: Option<bool>,
+
+    /// Move even if the source or destination goes through a symlinked directory that points
+    /// outside of the working directory. Without it, such moves are refused. A symlink that is
+    /// itself the source or destination is moved or replaced, not followed
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub follow_symlinks: Option<bool>,
 }
 
 impl WithExamples for Move {
@@ -42,6 +81,9 @@ impl WithExamples for Move {
                     destination: "src/tool/mod.rs".into(),
                     overwrite: None,
                     create_directories: Some(true),
+                    verbosity: None,
+                    confirm: None,
+                    follow_symlinks: None,
                 },
             },
             Example {
@@ -51,6 +93,21 @@ impl WithExamples for Move {
                     destination: "/some/absolute/path/src/lib.rs".into(),
                     overwrite: Some(true),
                     create_directories: None,
+                    verbosity: None,
+                    confirm: None,
+                    follow_symlinks: None,
+                },
+            },
+            Example {
+                description: "Moving a large directory after reviewing its size",
+                item: Self {
+                    source: "fixtures".into(),
+                    destination: "tests/fixtures".into(),
+                    overwrite: None,
+                    create_directories: None,
+                    verbosity: None,
+                    confirm: Some(true),
+                    follow_symlinks: None,
                 },
             },
         ]
@@ -62,39 +119,241 @@ impl Move {
         self.overwrite.unwrap_or_default()
     }
 
-    fn create_directories(&self) -> bool {
-        self.create_directories.unwrap_or(true)
+    /// Copy `source` to `destination` recursively, keeping permissions and modification times
+    pub(crate) fn copy_recursive(source: &Path, destination: &Path) -> io::Result<()> {
+        let metadata = fs::symlink_metadata(source)?;
+        let file_type = metadata.file_type();
+
+        if file_type.is_symlink() {
+            #[cfg(unix)]
+            return std::os::unix::fs::symlink(fs::read_link(source)?, destination);
+            #[cfg(not(unix))]
+            {
+                fs::copy(source, destination)?;
+                return Ok(());
+            }
+        }
+
+        if file_type.is_dir() {
+            fs::create_dir(destination)?;
+            for entry in fs::read_dir(source)? {
+                let entry = entry?;
+                Self::copy_recursive(&entry.path(), &destination.join(entry.file_name()))?;
+            }
+            fs::set_permissions(destination, metadata.permissions())?;
+        } else {
+            // fs::copy also copies permission bits
+            fs::copy(source, destination)?;
+        }
+
+        // Set times last, since populating a directory updates its modification time
+        filetime::set_file_times(
+            destination,
+            FileTime::from_last_access_time(&metadata),
+            FileTime::from_last_modification_time(&metadata),
+        )
     }
 }
 
 impl Tool<FsTools> for Move {
     fn execute(self, state: &mut FsTools) -> Result<String> {
         let source = state.resolve_path(&self.source, None)?;
-        let destination = state.resolve_path(&self.destination, None)?;
+        let destination = state.resolve_new_path(&self.destination, None)?;
+        let _lock = state.lock_paths(&[&source, &destination])?;
 
-        if destination.exists() && !self.overwrite() {
-            return Err(anyhow!(
-                "{} already exists, use `overwrite` to intentionally replace it",
-                destination.display()
-            ));
+        if !self.follow_symlinks.unwrap_or_default() {
+            state.check_symlinks(&source, false)?;
+            state.check_symlinks(&destination, false)?;
+        }
+
+        let replaced = destination.exists();
+        if replaced && !self.overwrite() {
+            return Err(Hinted::new(
+                format!("{} already exists", destination.display()),
+                Some("use `overwrite` to intentionally replace it".into()),
+            )
+            .with_code(ErrorCode::AlreadyExists)
+            .with_path(&destination)
+            .into());
         }
 
         if !source.exists() {
-            return Err(anyhow!("{} not found", source.display()));
+            return Err(hints::not_found(&source));
+        }
+
+        let settings = state.settings(None)?;
+        let impact = source
+            .is_dir()
+            .then(|| Impact::measure(&source))
+            .transpose()
+            .hint_path(&source)?;
+        if let Some(impact) = &impact {
+            if impact.needs_confirmation() && !self.confirm.unwrap_or_default() {
+                return Ok(format!(
+                    "Moving {} would move {}. Call move again with `confirm: true` to proceed. \
+                     No filesystem operation has been performed",
+                    source.display(),
+                    impact.describe(&settings)
+                ));
+            }
         }
 
-        if self.create_directories() {
+        if settings.create_directories(self.create_directories) {
             if let Some(parent_dir) = destination.parent() {
                 std::fs::create_dir_all(parent_dir)?;
             }
         }
 
-        std::fs::rename(&source, &destination)?;
+        let bytes = relocation_bytes(&source, &destination).hint_path(&source)?;
+        state.charge_quota(QuotaUsage::written(bytes, false))?;
+        let relocation = relocate(&source, &destination).hint_path(&destination)?;
+
+        let verbosity = settings.verbosity(self.verbosity);
+        if verbosity == Verbosity::Quiet {
+            return Ok(format!("Moved to {}", destination.display()));
+        }
 
-        Ok(format!(
+        let mut result = format!(
             "Successfully moved {} to {}",
             source.display(),
             destination.display()
-        ))
+        );
+        if let Some(impact) = &impact {
+            result.push_str(&format!(" ({})", impact.describe(&settings)));
+        }
+        if relocation.copied {
+            result.push_str("\nThe destination is on a different filesystem, so the contents were copied and the original removed");
+        }
+
+        if verbosity == Verbosity::Detailed {
+            let metadata = std::fs::metadata(&destination)?;
+            if metadata.is_file() {
+                result.push_str(&format!("\nSize: {}", settings.format_size(metadata.len())));
+            }
+            if replaced {
+                result.push_str("\nThe previous contents of the destination were replaced");
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// The scale of an operation on a directory: how many files it contains and their total size
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Impact {
+    pub files: usize,
+    pub bytes: u64,
+    /// Whether the traversal stopped at [`IMPACT_TRAVERSAL_CAP`], making the counts lower bounds
+    pub truncated: bool,
+}
+
+impl Impact {
+    /// Count the files under `path` without following symlinks, visiting at most
+    /// [`IMPACT_TRAVERSAL_CAP`] entries
+    pub(crate) fn measure(path: &Path) -> io::Result<Self> {
+        let mut impact = Self {
+            files: 0,
+            bytes: 0,
+            truncated: false,
+        };
+        let mut pending = vec![path.to_path_buf()];
+        let mut visited = 0;
+
+        while let Some(dir) = pending.pop() {
+            for entry in fs::read_dir(&dir)? {
+                visited += 1;
+                if visited > IMPACT_TRAVERSAL_CAP {
+                    impact.truncated = true;
+                    return Ok(impact);
+                }
+
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                if metadata.is_dir() {
+                    pending.push(entry.path());
+                } else {
+                    impact.files += 1;
+                    impact.bytes += metadata.len();
+                }
+            }
+        }
+
+        Ok(impact)
+    }
+
+    /// Whether the operation is large enough that it should only go ahead with `confirm`
+    pub(crate) fn needs_confirmation(&self) -> bool {
+        self.truncated || self.files > CONFIRM_FILES || self.bytes > CONFIRM_BYTES
+    }
+
+    /// e.g. "1204 files, 18.2 MB", with "at least" when the traversal was cut short
+    pub(crate) fn describe(&self, settings: &SessionSettings) -> String {
+        let at_least = if self.truncated { "at least " } else { "" };
+        let files = if self.files == 1 { "file" } else { "files" };
+        format!(
+            "{at_least}{} {files}, {at_least}{}",
+            self.files,
+            settings.format_size(self.bytes)
+        )
+    }
+}
+
+/// The outcome of [`relocate`]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Relocation {
+    /// Whether the move had to copy and then delete because a rename was not possible
+    pub copied: bool,
+}
+
+/// Rename `source` to `destination`, falling back to copying and then removing the original
+/// when they are on different filesystems. Directories are moved recursively, and copies
+/// keep permissions and modification times
+pub(crate) fn relocate(source: &Path, destination: &Path) -> io::Result<Relocation> {
+    match fs::rename(source, destination) {
+        Ok(()) => Ok(Relocation { copied: false }),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            Move::copy_recursive(source, destination)?;
+            if fs::symlink_metadata(source)?.is_dir() {
+                fs::remove_dir_all(source)?;
+            } else {
+                fs::remove_file(source)?;
+            }
+            Ok(Relocation { copied: true })
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// The bytes that [`relocate`] writes: none for a rename, and everything under `source` when
+/// `destination` is on a different filesystem, so that it has to be copied
+pub(crate) fn relocation_bytes(source: &Path, destination: &Path) -> io::Result<u64> {
+    let Some(existing) = destination.ancestors().find(|ancestor| ancestor.exists()) else {
+        return Ok(0);
+    };
+    #[cfg(unix)]
+    let same_filesystem = {
+        use std::os::unix::fs::MetadataExt;
+        fs::symlink_metadata(source)?.dev() == fs::metadata(existing)?.dev()
+    };
+    // Windows can rename within a volume, which is the first component of absolute paths
+    #[cfg(not(unix))]
+    let same_filesystem = source.components().next() == existing.components().next();
+    match same_filesystem {
+        true => Ok(0),
+        false => tree_size(source),
+    }
+}
+
+/// The size of a file, or of every file under a directory, without following symlinks
+fn tree_size(path: &Path) -> io::Result<u64> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut bytes = 0;
+    for entry in fs::read_dir(path)? {
+        bytes += tree_size(&entry?.path())?;
     }
+    Ok(bytes)
 }
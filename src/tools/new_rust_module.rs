@@ -0,0 +1,343 @@
+use crate::{
+    hints::{self, ErrorCode, Hint, Hinted},
+    quota::QuotaUsage,
+    tools::FsTools,
+};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+/// A top-level `mod name;` declaration, capturing the name
+static MOD_DECLARATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:pub(?:\([^)]*\))?\s+)?mod\s+(?:r#)?(\w+)\s*;").expect("valid regex")
+});
+
+/// Create a new Rust module file and declare it with `mod name;` in its parent module, in one
+/// step. The file goes where the parent expects it: next to `lib.rs`, `main.rs`, or `mod.rs`,
+/// and in a directory named after any other parent. Either half that already exists is left as
+/// is, so this also completes a half-finished module
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "new_rust_module")]
+pub struct NewRustModule {
+    /// Name of the module, a Rust identifier such as `rate_limiter`
+    pub name: String,
+
+    /// The parent module's file
+    /// Can be absolute, or relative to session context path.
+    /// Default: src/lib.rs, or src/main.rs if there is no src/lib.rs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub parent: Option<String>,
+
+    /// Visibility of the declaration, such as "pub" or "pub(crate)"
+    /// Default: private
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub visibility: Option<String>,
+
+    /// Contents of the new file
+    /// Default: empty
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub contents: Option<String>,
+
+    /// Create `name/mod.rs` instead of `name.rs`
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub mod_rs: Option<bool>,
+}
+
+impl WithExamples for NewRustModule {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Adding a private module to the crate root",
+                item: Self {
+                    name: "rate_limiter".into(),
+                    parent: None,
+                    visibility: None,
+                    contents: Some("pub struct RateLimiter {}\n".into()),
+                    mod_rs: None,
+                },
+            },
+            Example {
+                description: "Adding a public submodule of src/tools.rs, in src/tools/",
+                item: Self {
+                    name: "cache".into(),
+                    parent: Some("src/tools.rs".into()),
+                    visibility: Some("pub".into()),
+                    contents: None,
+                    mod_rs: None,
+                },
+            },
+            Example {
+                description: "Adding a module that will have submodules of its own",
+                item: Self {
+                    name: "storage".into(),
+                    parent: Some("src/lib.rs".into()),
+                    visibility: Some("pub(crate)".into()),
+                    contents: None,
+                    mod_rs: Some(true),
+                },
+            },
+        ]
+    }
+}
+
+impl NewRustModule {
+    fn validate(&self) -> Result<()> {
+        let mut chars = self.name.chars();
+        let is_identifier = chars
+            .next()
+            .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier {
+            return Err(Hinted::new(
+                format!("{:?} is not a valid module name", self.name),
+                Some("use a snake_case identifier such as `rate_limiter`".into()),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into());
+        }
+        if let Some(visibility) = &self.visibility {
+            if !visibility.starts_with("pub") {
+                return Err(Hinted::new(
+                    format!("{visibility:?} is not a visibility"),
+                    Some("use e.g. \"pub\", \"pub(crate)\", or leave it out for private".into()),
+                )
+                .with_code(ErrorCode::InvalidArgument)
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    fn parent(&self, state: &mut FsTools) -> Result<PathBuf> {
+        if let Some(parent) = &self.parent {
+            let parent = state.resolve_path(parent, None)?;
+            return match parent.is_file() {
+                true => Ok(parent),
+                false => Err(hints::not_found(&parent)),
+            };
+        }
+        for root in ["src/lib.rs", "src/main.rs"] {
+            let root = state.resolve_path(root, None)?;
+            if root.is_file() {
+                return Ok(root);
+            }
+        }
+        Err(Hinted::new(
+            "There is no src/lib.rs or src/main.rs in the working directory".to_string(),
+            Some("pass the parent module's file as `parent`".into()),
+        )
+        .into())
+    }
+
+    /// Where the files of the submodules of `parent` go
+    pub(crate) fn child_directory(parent: &Path) -> PathBuf {
+        let directory = parent.parent().unwrap_or(Path::new(""));
+        match parent.file_name().and_then(|name| name.to_str()) {
+            Some("lib.rs" | "main.rs" | "mod.rs") => directory.to_path_buf(),
+            _ => directory.join(parent.file_stem().unwrap_or_default()),
+        }
+    }
+
+    /// `source` with `declaration` added for the module `name`, and its 1-based line, or None
+    /// if `source` already declares the module. The declaration goes in alphabetical order among
+    /// the existing top-level declarations, or else after the leading inner docs and attributes
+    pub(crate) fn declare(source: &str, name: &str, declaration: &str) -> Option<(String, usize)> {
+        let already_declared = Regex::new(&format!(
+            r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+(?:r#)?{name}\s*[;{{]"
+        ))
+        .expect("names are identifiers");
+        if already_declared.is_match(source) {
+            return None;
+        }
+
+        let newline = if source.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let mut lines = source
+            .split_inclusive('\n')
+            .map(String::from)
+            .collect::<Vec<_>>();
+        if let Some(last) = lines.last_mut() {
+            // Give the last line an ending, so that a declaration can follow it
+            if !last.ends_with('\n') {
+                last.push_str(newline);
+            }
+        }
+
+        let declared = lines
+            .iter()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                let captures = MOD_DECLARATION.captures(line)?;
+                Some((index, captures[1].to_string()))
+            })
+            .collect::<Vec<_>>();
+
+        let mut inserted = vec![format!("{declaration}{newline}")];
+        let index = if let Some((index, _)) = declared
+            .iter()
+            .find(|(_, existing)| existing.as_str() > name)
+        {
+            // Keep attributes and docs with the declaration they belong to
+            let mut index = *index;
+            while index > 0 && {
+                let previous = lines[index - 1].trim_start();
+                previous.starts_with("#[") || previous.starts_with("///")
+            } {
+                index -= 1;
+            }
+            index
+        } else if let Some((index, _)) = declared.last() {
+            index + 1
+        } else {
+            let header = lines
+                .iter()
+                .position(|line| {
+                    let line = line.trim();
+                    !(line.is_empty() || line.starts_with("//!") || line.starts_with("#!["))
+                })
+                .unwrap_or(lines.len());
+            // Back up over blank lines, keeping the header's separation from the code
+            let mut index = header;
+            while index > 0 && lines[index - 1].trim().is_empty() {
+                index -= 1;
+            }
+            if index > 0 {
+                inserted.insert(0, newline.to_string());
+            }
+            if index < lines.len() {
+                inserted.push(newline.to_string());
+            }
+            index
+        };
+
+        let line = index
+            + inserted
+                .iter()
+                .take_while(|line| line.trim().is_empty())
+                .count()
+            + 1;
+        let mut updated = lines[..index].concat();
+        updated.push_str(&inserted.concat());
+        updated.push_str(&lines[index..].concat());
+        Some((updated, line))
+    }
+}
+
+impl Tool<FsTools> for NewRustModule {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        self.validate()?;
+        let parent = self.parent(state)?;
+        let directory = Self::child_directory(&parent);
+        let (path, other) = match self.mod_rs.unwrap_or_default() {
+            true => (
+                directory.join(&self.name).join("mod.rs"),
+                directory.join(format!("{}.rs", self.name)),
+            ),
+            false => (
+                directory.join(format!("{}.rs", self.name)),
+                directory.join(&self.name).join("mod.rs"),
+            ),
+        };
+        if other.exists() {
+            return Err(Hinted::new(
+                format!(
+                    "The module {} already exists as {}",
+                    self.name,
+                    other.display()
+                ),
+                Some(format!(
+                    "pass `mod_rs: {}` to use it",
+                    !self.mod_rs.unwrap_or_default()
+                )),
+            )
+            .with_code(ErrorCode::AlreadyExists)
+            .with_path(&other)
+            .into());
+        }
+
+        let source = fs::read_to_string(&parent).hint_path(&parent)?;
+        let declaration = match &self.visibility {
+            Some(visibility) => format!("{visibility} mod {};", self.name),
+            None => format!("mod {};", self.name),
+        };
+        let declared = Self::declare(&source, &self.name, &declaration);
+        let contents = self.contents.as_deref().unwrap_or_default();
+        let creates = !path.exists();
+
+        if !creates && declared.is_none() {
+            return Ok(format!(
+                "{} already exists and {} already declares it, so nothing was changed",
+                path.display(),
+                parent.display()
+            ));
+        }
+
+        let mut bytes = 0;
+        if creates {
+            bytes += contents.len() as u64;
+        }
+        if let Some((updated, _)) = &declared {
+            bytes += updated.len() as u64;
+        }
+        state.charge_quota(QuotaUsage::written(bytes, creates))?;
+
+        let mut output = String::new();
+        if creates {
+            if let Some(directory) = path.parent() {
+                fs::create_dir_all(directory).hint_path(directory)?;
+            }
+            fs::write(&path, contents).map_err(|error| hints::io_error(error, &path))?;
+            let size = match contents.len() {
+                0 => "empty".to_string(),
+                1 => "1 byte".to_string(),
+                bytes => format!("{bytes} bytes"),
+            };
+            output.push_str(&format!("Created {} ({size})\n", path.display()));
+        } else {
+            output.push_str(&format!(
+                "{} already exists and was left as is\n",
+                path.display()
+            ));
+        }
+
+        match declared {
+            Some((updated, line)) => {
+                fs::write(&parent, &updated).map_err(|error| hints::io_error(error, &parent))?;
+                output.push_str(&format!(
+                    "Declared `{declaration}` in {} at line {line}:\n\n",
+                    parent.display()
+                ));
+                let lines = updated.lines().collect::<Vec<_>>();
+                let first = line.saturating_sub(1).max(1);
+                let last = (line + 1).min(lines.len());
+                for number in first..=last {
+                    let marker = if number == line { '+' } else { ' ' };
+                    output.push_str(&format!("{marker}{number:>5}\t{}\n", lines[number - 1]));
+                }
+            }
+            None => output.push_str(&format!(
+                "{} already declares the module\n",
+                parent.display()
+            )),
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,249 @@
+use crate::{
+    file_types,
+    hints::{ErrorCode, Hint, Hinted},
+    tools::FsTools,
+};
+use anyhow::Result;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Preview a file without reading all of it: its first and last lines, and an outline of what
+/// is in between, adapted to the format (headings of Markdown, keys of JSON and YAML, tables of
+/// TOML, and declarations of source code). The most signal per token for "what is this file".
+/// Read-only
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "preview")]
+pub struct Preview {
+    /// File to preview
+    /// Can be absolute, or relative to session context path.
+    pub path: String,
+
+    /// How many lines to show from the start of the file, and from the end
+    /// Default: 10
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub lines: Option<usize>,
+
+    /// The most outline entries to show
+    /// Default: 50
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub max_outline_entries: Option<usize>,
+}
+
+impl WithExamples for Preview {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Getting the gist of a long source file before reading parts of it",
+                item: Self {
+                    path: "src/server.rs".into(),
+                    lines: None,
+                    max_outline_entries: None,
+                },
+            },
+            Example {
+                description: "Seeing the sections of a long README, with a short head and tail",
+                item: Self {
+                    path: "README.md".into(),
+                    lines: Some(3),
+                    max_outline_entries: Some(100),
+                },
+            },
+        ]
+    }
+}
+
+/// How the lines between the head and the tail are outlined
+enum Outline {
+    /// Headings, outside of code fences
+    Markdown,
+    /// Keys of the outermost two levels of objects, by indentation
+    Json,
+    /// Table headers
+    Toml,
+    /// Keys at the top level
+    Yaml,
+    /// Lines matching a pattern of declarations
+    Code(Regex),
+    /// Nothing to outline
+    Plain,
+}
+
+/// Patterns of lines declaring functions, types, and the like, for each language
+fn declaration_pattern(language: &str) -> Option<&'static str> {
+    Some(match language {
+        "rust" => {
+            r#"^\s*(pub(\([^)]*\))?\s+)?((async|const|unsafe|default|extern\s+"[^"]*")\s+)*(fn|struct|enum|union|trait|impl|mod|type|macro_rules!)[\s<!]"#
+        }
+        "python" => r"^\s*(async\s+)?(def|class)\s",
+        "js" | "ts" => {
+            r"^\s*(export\s+(default\s+)?)?((async\s+)?function\b|(abstract\s+)?class\s|interface\s|type\s+\w+.*=|enum\s|(const|let)\s+\w+\s*=\s*(async\s+)?(function\b|\([^)]*\)\s*=>|\w+\s*=>))"
+        }
+        "go" => r"^(func|type)\s",
+        "ruby" => r"^\s*(def|class|module)\s",
+        "shell" => r"^\s*(function\s+\w+|\w+\s*\(\)\s*\{)",
+        "sql" => r"(?i)^\s*create\s",
+        "css" => r"^[^\s@/*}].*\{\s*$",
+        "c" | "cpp" | "csharp" | "java" | "kotlin" | "php" | "swift" => {
+            r"^\s*((public|private|protected|internal|static|final|abstract|override|open|sealed|suspend|inline|virtual|async|export|data)\s+)*(class|interface|struct|enum|record|object|fun|func|function|namespace|trait|protocol|extension)\s|^[A-Za-z_][\w\s\*&:<>,]*\s\**[A-Za-z_][\w:]*\s*\([^;]*$"
+        }
+        _ => return None,
+    })
+}
+
+impl Outline {
+    fn for_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("md" | "markdown" | "mdx") => return Self::Markdown,
+            Some("json" | "jsonc" | "json5") => return Self::Json,
+            Some("toml") => return Self::Toml,
+            Some("yaml" | "yml") => return Self::Yaml,
+            _ => {}
+        }
+        file_types::language(path)
+            .and_then(declaration_pattern)
+            .and_then(|pattern| Regex::new(pattern).ok())
+            .map_or(Self::Plain, Self::Code)
+    }
+
+    /// The 0-based indices of the outlined lines among `lines`, which start at `first`
+    fn entries(&self, lines: &[&str], first: usize, last: usize) -> Vec<usize> {
+        let indentation = |line: &str| line.len() - line.trim_start().len();
+        match self {
+            Self::Markdown => {
+                let mut in_fence = false;
+                (0..last)
+                    .filter(|index| {
+                        let line = lines[*index];
+                        if line.trim_start().starts_with("```") {
+                            in_fence = !in_fence;
+                            return false;
+                        }
+                        let level = line.len() - line.trim_start_matches('#').len();
+                        !in_fence && (1..=6).contains(&level) && line[level..].starts_with(' ')
+                    })
+                    .filter(|index| *index >= first)
+                    .collect()
+            }
+            Self::Json => {
+                // The indentation of one level, from the first indented line
+                let unit = lines
+                    .iter()
+                    .map(|line| indentation(line))
+                    .find(|indentation| *indentation > 0)
+                    .unwrap_or(1);
+                (first..last)
+                    .filter(|index| {
+                        let line = lines[*index];
+                        line.trim_start().starts_with('"')
+                            && line.contains("\":")
+                            && indentation(line) <= 2 * unit
+                    })
+                    .collect()
+            }
+            Self::Toml => (first..last)
+                .filter(|index| lines[*index].trim_start().starts_with('['))
+                .collect(),
+            Self::Yaml => (first..last)
+                .filter(|index| {
+                    let line = lines[*index];
+                    !line.starts_with([' ', '\t', '#', '-'])
+                        && (line.trim_end().ends_with(':') || line.contains(": "))
+                })
+                .collect(),
+            Self::Code(pattern) => (first..last)
+                .filter(|index| pattern.is_match(lines[*index]))
+                .collect(),
+            Self::Plain => vec![],
+        }
+    }
+}
+
+/// Lines are cut to this many characters, since a preview is for the gist of a file
+const MAX_ENTRY_CHARS: usize = 120;
+
+fn numbered(number: usize, line: &str) -> String {
+    match line.char_indices().nth(MAX_ENTRY_CHARS) {
+        Some((index, _)) => format!("{number}\t{}…\n", &line[..index]),
+        None => format!("{number}\t{line}\n"),
+    }
+}
+
+impl Tool<FsTools> for Preview {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let path = state.resolve_path(&self.path, None)?;
+        if path.is_dir() {
+            return Err(Hinted::new(
+                format!("{} is a directory", path.display()),
+                Some("use list to see what it contains".into()),
+            )
+            .with_code(ErrorCode::IsADirectory)
+            .with_path(&path)
+            .into());
+        }
+        let bytes = std::fs::read(&path).hint_path(&path)?;
+        if bytes.iter().take(8 * 1024).any(|&byte| byte == 0) {
+            return Err(Hinted::new(
+                format!("{} is a binary file", path.display()),
+                Some("use file_stats to see its size".into()),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .with_path(&path)
+            .into());
+        }
+        let contents = String::from_utf8_lossy(&bytes);
+        let lines = contents.lines().collect::<Vec<_>>();
+        let settings = state.settings(None)?;
+        let mut output = format!(
+            "{}: {} lines, {}\n\n",
+            path.display(),
+            lines.len(),
+            settings.format_size(bytes.len() as u64)
+        );
+
+        let shown = self.lines.unwrap_or(10);
+        if lines.len() <= 2 * shown {
+            for (index, line) in lines.iter().enumerate() {
+                output.push_str(&numbered(index + 1, line));
+            }
+            return Ok(output);
+        }
+
+        for (index, line) in lines[..shown].iter().enumerate() {
+            output.push_str(&numbered(index + 1, line));
+        }
+
+        let tail = lines.len() - shown;
+        let entries = Outline::for_path(&path).entries(&lines, shown, tail);
+        let max_entries = self.max_outline_entries.unwrap_or(50);
+        if entries.is_empty() {
+            output.push_str(&format!("\n... lines {} to {tail} left out\n\n", shown + 1));
+        } else {
+            output.push_str(&format!("\nOutline of lines {} to {tail}:\n", shown + 1));
+            for index in entries.iter().take(max_entries) {
+                output.push_str(&numbered(index + 1, lines[*index]));
+            }
+            if entries.len() > max_entries {
+                output.push_str(&format!(
+                    "... and {} more (limit {max_entries})\n",
+                    entries.len() - max_entries
+                ));
+            }
+            output.push('\n');
+        }
+
+        for (index, line) in lines.iter().enumerate().skip(tail) {
+            output.push_str(&numbered(index + 1, line));
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,37 @@
+use crate::{hints::Hinted, tools::FsTools};
+use anyhow::Result;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+
+/// Switch back to the previous working directory, like `cd -`. Calling it again switches back
+/// again, which makes it quick to alternate between two projects
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "previous_working_directory")]
+pub struct PreviousWorkingDirectory {}
+
+impl WithExamples for PreviousWorkingDirectory {
+    fn examples() -> Vec<Example<Self>> {
+        vec![Example {
+            description: "Returning to the project worked on before the current one",
+            item: Self {},
+        }]
+    }
+}
+
+impl Tool<FsTools> for PreviousWorkingDirectory {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let Some(previous) = state.directory_history(None)?.pop() else {
+            return Err(Hinted::new(
+                "There is no previous working directory in this session",
+                Some("use set_working_directory to change directories".into()),
+            )
+            .into());
+        };
+        let response = format!("Set context to {}", previous.display());
+        state.set_working_directory(previous, None)?;
+        Ok(response)
+    }
+}
@@ -0,0 +1,85 @@
+use super::delete::{describe_staged, select_staged};
+use crate::{hints::Hint, quota::QuotaUsage, tools::FsTools};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+
+/// Permanently delete files staged by `delete` with `stage`. This cannot be undone
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "purge_staged")]
+pub struct PurgeStaged {
+    /// Ids of the staged files to purge, as reported by delete. Omit to purge every staged file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub ids: Option<Vec<String>>,
+
+    /// List what would be purged without deleting anything
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub preview: Option<bool>,
+}
+
+impl WithExamples for PurgeStaged {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Reviewing everything that would be purged",
+                item: Self {
+                    ids: None,
+                    preview: Some(true),
+                },
+            },
+            Example {
+                description: "Completing a reviewed cleanup",
+                item: Self {
+                    ids: None,
+                    preview: None,
+                },
+            },
+        ]
+    }
+}
+
+impl Tool<FsTools> for PurgeStaged {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let settings = state.settings(None)?;
+        let staged = state.staged(None)?;
+        let selected = select_staged(&staged, self.ids.as_deref())?;
+
+        if selected.is_empty() {
+            return Ok("No files are staged".into());
+        }
+
+        if self.preview.unwrap_or_default() {
+            let mut output = format!("Would permanently delete {} files:\n", selected.len());
+            for entry in &selected {
+                output.push_str(&describe_staged(entry, &settings));
+                output.push('\n');
+            }
+            return Ok(output);
+        }
+
+        state.charge_quota(QuotaUsage::deleted(selected.len() as u64))?;
+
+        let mut output = String::new();
+        for entry in &selected {
+            if entry.staged.exists() {
+                std::fs::remove_file(&entry.staged).hint_path(&entry.staged)?;
+            }
+            if let Some(staging_dir) = entry.staged.parent() {
+                let _ = std::fs::remove_dir(staging_dir);
+            }
+            state.update_staged(None, |staged| staged.retain(|e| e.id != entry.id))?;
+            output.push_str(&format!("Purged {}\n", entry.original.display()));
+        }
+
+        let remaining = state.staged(None)?.len();
+        output.push_str(&format!("{remaining} files remain staged"));
+        Ok(output)
+    }
+}
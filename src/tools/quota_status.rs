@@ -0,0 +1,139 @@
+use crate::{hints::Hinted, quota::QuotaLimits, tools::FsTools};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Show how much of this session's quota of bytes written, files created, and files deleted
+/// has been used, and how many calls changed files in the last minute, or set limits. Limits
+/// can be tightened at any time, but only a person running fs-mcp in a terminal can loosen them
+/// or reset the usage
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "quota_status")]
+pub struct QuotaStatus {
+    /// Limit the total bytes written by tools that write, copy, or extract files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub max_bytes_written: Option<u64>,
+
+    /// Limit the number of files created by tools that create or restore files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub max_files_created: Option<u64>,
+
+    /// Limit the number of files deleted permanently by delete and purge_staged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub max_files_deleted: Option<u64>,
+
+    /// Limit how many calls to tools that change files can be made in any minute, to stop a
+    /// runaway loop early
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub max_changes_per_minute: Option<u64>,
+
+    /// Remove all limits. Only available from a terminal
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub remove_limits: Option<bool>,
+
+    /// Set the usage back to zero. Only available from a terminal
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub reset: Option<bool>,
+}
+
+impl WithExamples for QuotaStatus {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Checking how much of the quota is left",
+                item: Self {
+                    max_bytes_written: None,
+                    max_files_created: None,
+                    max_files_deleted: None,
+                    max_changes_per_minute: None,
+                    remove_limits: None,
+                    reset: None,
+                },
+            },
+            Example {
+                description: "Capping a generation task at 200 new files and 5MB",
+                item: Self {
+                    max_bytes_written: Some(5_000_000),
+                    max_files_created: Some(200),
+                    max_files_deleted: None,
+                    max_changes_per_minute: None,
+                    remove_limits: None,
+                    reset: None,
+                },
+            },
+            Example {
+                description: "Stopping a loop that rewrites files over and over",
+                item: Self {
+                    max_bytes_written: None,
+                    max_files_created: None,
+                    max_files_deleted: None,
+                    max_changes_per_minute: Some(30),
+                    remove_limits: None,
+                    reset: None,
+                },
+            },
+        ]
+    }
+}
+
+impl Tool<FsTools> for QuotaStatus {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let mut quota = state.quota()?;
+        let mut limits = if self.remove_limits.unwrap_or_default() {
+            QuotaLimits::default()
+        } else {
+            quota.limits
+        };
+        limits.max_bytes_written = self.max_bytes_written.or(limits.max_bytes_written);
+        limits.max_files_created = self.max_files_created.or(limits.max_files_created);
+        limits.max_files_deleted = self.max_files_deleted.or(limits.max_files_deleted);
+        limits.max_changes_per_minute = self
+            .max_changes_per_minute
+            .or(limits.max_changes_per_minute);
+
+        let reset = self.reset.unwrap_or_default();
+        if state.serving() && (reset || limits.loosens(&quota.limits)) {
+            return Err(Hinted::new(
+                "Quotas can only be reset or loosened by a person, so nothing was changed",
+                Some(
+                    "ask a person to run `fs-mcp quota-status --reset` (or with higher limits) \
+                     in a terminal"
+                        .into(),
+                ),
+            )
+            .into());
+        }
+
+        let mut output = String::new();
+        if limits != quota.limits {
+            quota.limits = limits;
+            output.push_str("Updated the limits\n");
+        }
+        if reset {
+            quota.usage = Default::default();
+            output.push_str("Reset the usage\n");
+        }
+        state.update_quota(|stored| *stored = quota)?;
+
+        let recent_changes = state.change_rate_mut().count(Instant::now());
+        output.push_str(&format!(
+            "Quota for session {}:\n{}",
+            state.default_session_id(),
+            quota.describe(recent_changes)
+        ));
+        Ok(output)
+    }
+}
@@ -1,17 +1,35 @@
-use crate::tools::FsTools;
-use anyhow::{Context, Result, anyhow};
+use crate::{
+    budget::Limits,
+    file_types,
+    hints::{self, ErrorCode, Hint, Hinted},
+    read_cache::{CachedRead, ReadCache},
+    settings::SessionSettings,
+    state::normalize_windows_path,
+    tools::{
+        FsTools,
+        set_permissions::{current_mode, describe},
+    },
+};
+use anyhow::{Context, Result};
+use clap::ArgAction;
 use mcplease::{
     traits::{Tool, WithExamples},
     types::Example,
 };
 use serde::{Deserialize, Serialize};
-use std::{io::Read as _, path::Path};
+use std::{
+    borrow::Cow,
+    io::{Read as _, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
 
-/// Read utf8 contents from a file. Non-utf8 characters will be replaced lossily
+/// Read utf8 contents from a file. Non-utf8 characters will be replaced lossily. The BEGIN
+/// line notes a byte order mark (BOM) or a missing final newline, which are otherwise invisible
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
 #[serde(rename = "read")]
 pub struct Read {
-    /// Path or paths to read
+    /// Path or paths to read. Glob patterns such as "src/tools/*.rs" are expanded to the files
+    /// they match, subject to `max_total_bytes`
     /// Can be absolute, or relative to session context path.
     pub paths: Vec<String>,
 
@@ -20,6 +38,77 @@ pub struct Read {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[arg(long)]
     pub max_length: Option<usize>,
+
+    /// Which part of a file longer than `max_length` to return: "head", "tail" for the end of
+    /// a log, or "head_tail" for half from each end. Skipped bytes are counted in an elision
+    /// marker, and so are skipped lines with `line_numbers`, which needs them
+    /// Default: head
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub truncate_strategy: Option<TruncateStrategy>,
+
+    /// Byte offset to start reading each file at, e.g. the NEXT OFFSET of an earlier read, or a
+    /// position computed by another tool. An offset inside a multi-byte character starts at the
+    /// next character instead
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub offset: Option<u64>,
+
+    /// Number of bytes to read from `offset`. A range ending inside a multi-byte character stops
+    /// before it, so that it can be read from the NEXT OFFSET given on the BEGIN line
+    /// Default: to the end of the file, or `max_length` bytes if that is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub length: Option<usize>,
+
+    /// Max combined size in bytes of files matched by glob patterns. Matched files beyond this
+    /// budget are listed but not read
+    /// Default: 262144 (256KiB), lowered for the session after several large responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub max_total_bytes: Option<u64>,
+
+    /// Prefix each line with its 1-based line number, matching the `path:line` references
+    /// returned by search
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub line_numbers: Option<bool>,
+
+    /// Add each file's last modification time, size, permissions, and language to its BEGIN
+    /// line, to check freshness without a separate list call
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_metadata: Option<bool>,
+
+    /// Leave out the contents of files that are unchanged since this server last read them,
+    /// marking them UNCHANGED instead, to save re-reading files that are still in context
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub skip_unchanged: Option<bool>,
+}
+
+/// Which part of a truncated file `read` returns
+#[derive(
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    schemars::JsonSchema,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TruncateStrategy {
+    #[default]
+    Head,
+    Tail,
+    HeadTail,
 }
 
 impl WithExamples for Read {
@@ -30,6 +119,13 @@ impl WithExamples for Read {
                 item: Self {
                     paths: vec!["src/main.rs".into()],
                     max_length: None,
+                    truncate_strategy: None,
+                    offset: None,
+                    length: None,
+                    max_total_bytes: None,
+                    line_numbers: None,
+                    include_metadata: None,
+                    skip_unchanged: None,
                 },
             },
             Example {
@@ -37,6 +133,41 @@ impl WithExamples for Read {
                 item: Self {
                     paths: vec!["/some/absolute/path/src/main.rs".into()],
                     max_length: Some(100),
+                    truncate_strategy: None,
+                    offset: None,
+                    length: None,
+                    max_total_bytes: None,
+                    line_numbers: None,
+                    include_metadata: None,
+                    skip_unchanged: None,
+                },
+            },
+            Example {
+                description: "Reading the most recent entries of a large log",
+                item: Self {
+                    paths: vec!["logs/server.log".into()],
+                    max_length: Some(4096),
+                    truncate_strategy: Some(TruncateStrategy::Tail),
+                    offset: None,
+                    length: None,
+                    max_total_bytes: None,
+                    line_numbers: Some(true),
+                    include_metadata: None,
+                    skip_unchanged: None,
+                },
+            },
+            Example {
+                description: "Continuing through a large file from where the last read stopped",
+                item: Self {
+                    paths: vec!["data/export.csv".into()],
+                    max_length: None,
+                    truncate_strategy: None,
+                    offset: Some(65536),
+                    length: Some(65536),
+                    max_total_bytes: None,
+                    line_numbers: None,
+                    include_metadata: None,
+                    skip_unchanged: None,
                 },
             },
             Example {
@@ -48,6 +179,69 @@ impl WithExamples for Read {
                         "src/tools/read.rs".into(),
                     ],
                     max_length: None,
+                    truncate_strategy: None,
+                    offset: None,
+                    length: None,
+                    max_total_bytes: None,
+                    line_numbers: None,
+                    include_metadata: None,
+                    skip_unchanged: None,
+                },
+            },
+            Example {
+                description: "Reading a file with line numbers to refer to specific lines",
+                item: Self {
+                    paths: vec!["src/tools/search.rs".into()],
+                    max_length: None,
+                    truncate_strategy: None,
+                    offset: None,
+                    length: None,
+                    max_total_bytes: None,
+                    line_numbers: Some(true),
+                    include_metadata: None,
+                    skip_unchanged: None,
+                },
+            },
+            Example {
+                description: "Reading a config file and checking when it last changed",
+                item: Self {
+                    paths: vec!["config/settings.toml".into()],
+                    max_length: None,
+                    truncate_strategy: None,
+                    offset: None,
+                    length: None,
+                    max_total_bytes: None,
+                    line_numbers: None,
+                    include_metadata: Some(true),
+                    skip_unchanged: None,
+                },
+            },
+            Example {
+                description: "Re-reading files after a build, leaving out any that didn't change",
+                item: Self {
+                    paths: vec!["src/lib.rs".into(), "src/main.rs".into()],
+                    max_length: None,
+                    truncate_strategy: None,
+                    offset: None,
+                    length: None,
+                    max_total_bytes: None,
+                    line_numbers: None,
+                    include_metadata: None,
+                    skip_unchanged: Some(true),
+                },
+            },
+            Example {
+                description: "Reading every tool implementation with a glob",
+                item: Self {
+                    paths: vec!["src/tools/*.rs".into()],
+                    max_length: None,
+                    truncate_strategy: None,
+                    offset: None,
+                    length: None,
+                    max_total_bytes: None,
+                    line_numbers: None,
+                    include_metadata: None,
+                    skip_unchanged: None,
                 },
             },
         ]
@@ -55,73 +249,394 @@ impl WithExamples for Read {
 }
 
 impl Tool<FsTools> for Read {
-    fn execute(self, state: &mut FsTools) -> Result<String> {
+    fn execute(mut self, state: &mut FsTools) -> Result<String> {
+        if self.max_total_bytes.is_none() {
+            self.max_total_bytes = Some(state.limits()?.max_total_bytes);
+        }
+
         let separator = std::iter::repeat_with(fastrand::alphanumeric)
             .take(10)
             .collect::<String>();
-        Ok(self
-            .paths
-            .iter()
-            .map(|path| {
-                self.read_file(state, path, &separator).unwrap_or_else(|e| {
-                    format!(
-                        "=={separator} BEGIN ERROR {path} {separator}==\n\
-                        {e}\n=={separator} END ERROR {path} {separator}=="
-                    )
-                })
-            })
-            .collect())
+        let error_block = |path: &str, e: anyhow::Error| {
+            format!(
+                "=={separator} BEGIN ERROR {path} {separator}==\n\
+                {}\n=={separator} END ERROR {path} {separator}==",
+                hints::render(&e)
+            )
+        };
+
+        let settings = match self.include_metadata.unwrap_or_default() {
+            true => Some(state.settings(None)?),
+            false => None,
+        };
+        let settings = settings.as_ref();
+
+        let mut output = String::new();
+        let mut remaining_bytes = self.max_total_bytes();
+        let mut omitted = vec![];
+
+        for path in &self.paths {
+            if !is_glob(state, path) {
+                output.push_str(
+                    &state
+                        .resolve_path(path, None)
+                        .and_then(|resolved| {
+                            self.read_file(&resolved, &separator, settings, state.read_cache_mut())
+                        })
+                        .unwrap_or_else(|e| error_block(path, e)),
+                );
+                continue;
+            }
+
+            let matches = match Self::expand_glob(state, path) {
+                Ok(matches) => matches,
+                Err(e) => {
+                    output.push_str(&error_block(path, e));
+                    continue;
+                }
+            };
+
+            for matched in matches {
+                let len = std::fs::metadata(&matched).map_or(0, |metadata| metadata.len());
+                if !omitted.is_empty() || len > remaining_bytes {
+                    omitted.push(matched);
+                    continue;
+                }
+                remaining_bytes -= len;
+                output.push_str(
+                    &self
+                        .read_file(&matched, &separator, settings, state.read_cache_mut())
+                        .unwrap_or_else(|e| error_block(&matched.to_string_lossy(), e)),
+                );
+            }
+        }
+
+        if !omitted.is_empty() {
+            output.push_str(&format!(
+                "\n{} matched files were not read because they would exceed max_total_bytes ({}):\n",
+                omitted.len(),
+                self.max_total_bytes()
+            ));
+            for path in omitted {
+                output.push_str(&format!("{}\n", path.display()));
+            }
+        }
+
+        Ok(output)
     }
 }
 
+/// Whether `path` is a glob pattern to expand rather than a path to use as it is. A path that
+/// exists is used as it is even if it has glob characters, like `app/[id]/page.tsx`
+pub(crate) fn is_glob(state: &mut FsTools, path: &str) -> bool {
+    // The `?` of a Windows `\\?\` prefix is not a wildcard
+    let normalized = match cfg!(windows) {
+        true => normalize_windows_path(path),
+        false => Cow::Borrowed(path),
+    };
+    normalized.contains(['*', '?', '['])
+        && !state
+            .resolve_path(path, None)
+            .is_ok_and(|resolved| std::fs::symlink_metadata(resolved).is_ok())
+}
+
 impl Read {
-    fn read_head(
+    fn line_numbers(&self) -> bool {
+        self.line_numbers.unwrap_or_default()
+    }
+
+    /// Prefix each line of `content` with its line number, counting from `first_line` and
+    /// right-aligned to a common width
+    pub(crate) fn number_lines(content: &str, first_line: usize) -> String {
+        let width = (first_line + content.lines().count().max(1) - 1)
+            .to_string()
+            .len();
+        content
+            .lines()
+            .enumerate()
+            .map(|(index, line)| format!("{:>width$}\t{line}", first_line + index))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn max_total_bytes(&self) -> u64 {
+        self.max_total_bytes
+            .unwrap_or(Limits::DEFAULT.max_total_bytes)
+    }
+
+    /// Expand a glob pattern into the files it matches, in sorted order
+    pub(crate) fn expand_glob(state: &mut FsTools, pattern: &str) -> Result<Vec<PathBuf>> {
+        let resolved = state.resolve_path(pattern, None)?;
+        // A file that exists is not a pattern, even with glob characters in its path
+        if resolved.is_file() {
+            return Ok(vec![resolved]);
+        }
+        let resolved = resolved.to_string_lossy();
+        let mut matches = glob::glob(&resolved)
+            .with_context(|| format!("Invalid glob pattern: {pattern}"))?
+            .flatten()
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>();
+
+        if matches.is_empty() {
+            return Err(hints::Hinted::new(
+                format!("No files match {resolved}"),
+                Some("use list with the same pattern to check what exists".into()),
+            )
+            .into());
+        }
+
+        matches.sort();
+        Ok(matches)
+    }
+
+    /// `, MODIFIED: ..., SIZE: ..., PERMISSIONS: ..., LANGUAGE: ...` for a BEGIN line
+    fn metadata_header(path: &Path, settings: &SessionSettings) -> String {
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return String::new();
+        };
+        let mut header = String::new();
+        if let Ok(modified) = metadata.modified() {
+            let modified = settings.timestamp_formatter().format(modified);
+            header.push_str(&format!(", MODIFIED: {modified}"));
+        }
+        header.push_str(&format!(
+            ", SIZE: {}, PERMISSIONS: {}",
+            settings.format_size(metadata.len()),
+            describe(current_mode(&metadata.permissions()))
+        ));
+        if let Some(language) = file_types::language(path) {
+            header.push_str(&format!(", LANGUAGE: {language}"));
+        }
+        header
+    }
+
+    /// Read `max_length` bytes of a file longer than that, from the ends chosen by
+    /// `truncate_strategy`, seeking past the skipped bytes unless their lines are needed to
+    /// number the tail
+    fn read_truncated(
         &self,
         path: &Path,
         max_length: usize,
         actual_length: usize,
         separator: &str,
+        metadata: &str,
     ) -> Result<String> {
-        let mut bytes = vec![0u8; max_length];
-        std::fs::File::open(path)
-            .with_context(|| format!("Unable to open {}", path.display()))?
-            .read_exact(&mut bytes)
+        let strategy = self.truncate_strategy.unwrap_or_default();
+        let head_length = match strategy {
+            TruncateStrategy::Head => max_length,
+            TruncateStrategy::Tail => 0,
+            TruncateStrategy::HeadTail => max_length / 2,
+        };
+        let tail_length = max_length - head_length;
+        let skipped_length = actual_length - max_length;
+
+        let mut file = std::io::BufReader::new(
+            std::fs::File::open(path)
+                .with_context(|| format!("Unable to open {}", path.display()))?,
+        );
+        let mut head = vec![0u8; head_length];
+        file.read_exact(&mut head)
+            .with_context(|| format!("Unable to read from {}", path.display()))?;
+        let mut tail = vec![0u8; tail_length];
+        let skipped_lines = match (tail_length, self.line_numbers()) {
+            (0, _) => None,
+            (_, true) => Some(Self::count_lines(&mut file, skipped_length as u64, path)?),
+            (_, false) => {
+                file.seek_relative(skipped_length as i64)
+                    .with_context(|| format!("Unable to read from {}", path.display()))?;
+                None
+            }
+        };
+        file.read_exact(&mut tail)
             .with_context(|| format!("Unable to read from {}", path.display()))?;
+
+        let marker = match skipped_lines {
+            Some(1) => format!("[... {skipped_length} bytes, 1 line, skipped ...]"),
+            Some(lines) => format!("[... {skipped_length} bytes, {lines} lines, skipped ...]"),
+            None => format!("[... {skipped_length} bytes skipped ...]"),
+        };
+        let head = String::from_utf8_lossy(&head);
+        let tail = String::from_utf8_lossy(&tail);
+        let tail_first_line = head.matches('\n').count() + skipped_lines.unwrap_or_default() + 1;
+        let content = match strategy {
+            TruncateStrategy::Head => self.render(&head, 1),
+            TruncateStrategy::Tail => format!("{marker}\n{}", self.render(&tail, tail_first_line)),
+            TruncateStrategy::HeadTail => format!(
+                "{}\n{marker}\n{}",
+                self.render(&head, 1),
+                self.render(&tail, tail_first_line)
+            ),
+        };
+        let strategy = match strategy {
+            TruncateStrategy::Head => "",
+            TruncateStrategy::Tail => ", STRATEGY: tail",
+            TruncateStrategy::HeadTail => ", STRATEGY: head_tail",
+        };
+
         Ok(format!(
-            "=={separator} BEGIN TRUNCATED {path}, FULL LENGTH: {actual_length}, TRUNCATED LENGTH: {max_length} {separator}==\n\
+            "=={separator} BEGIN TRUNCATED {path}, FULL LENGTH: {actual_length}, TRUNCATED LENGTH: {max_length}{strategy}{metadata} {separator}==\n\
             {content}\n\
             =={separator} END TRUNCATED {path}, FULL LENGTH: {actual_length}, TRUNCATED LENGTH: {max_length} {separator}==\n",
             path = path.display(),
-            content = String::from_utf8_lossy(&bytes)
         ))
     }
 
-    fn read_file(&self, state: &mut FsTools, path: &str, separator: &str) -> Result<String> {
-        let path = state.resolve_path(path, None)?;
+    /// Read the bytes from `offset` to `offset + length`, moving either end that falls inside a
+    /// utf8 character to a character boundary rather than making up replacement characters
+    fn read_range(&self, path: &Path, separator: &str, metadata: &str) -> Result<String> {
+        let actual_length = std::fs::metadata(path).hint_path(path)?.len();
+        let offset = self.offset.unwrap_or_default();
+        if offset > actual_length {
+            return Err(Hinted::new(
+                format!(
+                    "Offset {offset} is past the end of {}, which is {actual_length} bytes long",
+                    path.display()
+                ),
+                Some(format!("use an offset from 0 to {actual_length}")),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .with_path(path)
+            .into());
+        }
+        let length = self
+            .length
+            .or(self.max_length)
+            .map_or(actual_length - offset, |length| {
+                (length as u64).min(actual_length - offset)
+            });
 
+        let mut file = std::io::BufReader::new(std::fs::File::open(path).hint_path(path)?);
+        // The lines before the range are only counted when they're needed to number its lines
+        let preceding_lines = match self.line_numbers() {
+            true => Self::count_lines(&mut file, offset, path)?,
+            false => {
+                file.seek(SeekFrom::Start(offset)).hint_path(path)?;
+                0
+            }
+        };
+        let mut bytes = vec![0u8; usize::try_from(length)?];
+        file.read_exact(&mut bytes)
+            .with_context(|| format!("Unable to read from {}", path.display()))?;
+
+        let continuation = |byte: &u8| byte & 0b1100_0000 == 0b1000_0000;
+        let skipped = bytes
+            .iter()
+            .take(3)
+            .take_while(|byte| continuation(byte))
+            .count();
+        let mut bytes = &bytes[skipped..];
+        if let Err(error) = std::str::from_utf8(bytes) {
+            if error.error_len().is_none() {
+                bytes = &bytes[..error.valid_up_to()];
+            }
+        }
+
+        let start = offset + skipped as u64;
+        let end = start + bytes.len() as u64;
+        let next = match end < actual_length {
+            true => format!(", NEXT OFFSET: {end}"),
+            false => String::new(),
+        };
+        Ok(format!(
+            "=={separator} BEGIN RANGE {path}, FULL LENGTH: {actual_length}, OFFSET: {start}, LENGTH: {len}{next}{metadata} {separator}==\n\
+            {content}\n\
+            =={separator} END RANGE {path}, OFFSET: {start}, LENGTH: {len} {separator}==\n",
+            path = path.display(),
+            len = bytes.len(),
+            content = self.render(&String::from_utf8_lossy(bytes), preceding_lines + 1),
+        ))
+    }
+
+    /// Count the newlines in the next `length` bytes of `file`, leaving it after them
+    fn count_lines(file: &mut impl std::io::Read, length: u64, path: &Path) -> Result<usize> {
+        let mut lines = 0;
+        let mut remaining = length;
+        let mut chunk = vec![0u8; 64 * 1024];
+        while remaining > 0 {
+            let chunk = &mut chunk[..remaining.min(64 * 1024) as usize];
+            file.read_exact(chunk)
+                .with_context(|| format!("Unable to read from {}", path.display()))?;
+            lines += chunk.iter().filter(|byte| **byte == b'\n').count();
+            remaining -= chunk.len() as u64;
+        }
+        Ok(lines)
+    }
+
+    fn read_file(
+        &self,
+        path: &Path,
+        separator: &str,
+        settings: Option<&SessionSettings>,
+        cache: &mut ReadCache,
+    ) -> Result<String> {
         if !path.exists() {
-            return Err(anyhow!("{} does not exist", path.display()));
+            return Err(hints::not_found(path));
+        }
+        let metadata = settings
+            .map(|settings| Self::metadata_header(path, settings))
+            .unwrap_or_default();
+
+        if self.offset.is_some() || self.length.is_some() {
+            return self.read_range(path, separator, &metadata);
         }
 
         if let Some(max_length) = self.max_length {
             let actual_length = usize::try_from(
-                std::fs::metadata(&path)
+                std::fs::metadata(path)
                     .with_context(|| format!("Unable to open metadata for {}", path.display()))?
                     .len(),
             )?;
             if max_length < actual_length {
-                return self.read_head(&path, max_length, actual_length, separator);
+                return self.read_truncated(path, max_length, actual_length, separator, &metadata);
             }
         }
 
-        let full_contents = std::fs::read_to_string(&path)
-            .with_context(|| format!("Unable to read {}", path.display()))?;
+        let CachedRead {
+            contents,
+            unchanged,
+        } = cache.read(path)?;
+        if unchanged && self.skip_unchanged.unwrap_or_default() {
+            return Ok(format!(
+                "=={separator} UNCHANGED {path}, LENGTH: {len}{metadata} {separator}==\n",
+                path = path.display(),
+                len = contents.len(),
+            ));
+        }
+        let unchanged = if unchanged {
+            ", UNCHANGED SINCE LAST READ"
+        } else {
+            ""
+        };
+        let invisible = Self::invisible_bytes(&contents);
 
         Ok(format!(
-            "=={separator} BEGIN {path}, LENGTH: {len} {separator}==\n\
-            {full_contents}\n=={separator} END {path}, LENGTH: {len} {separator}==\n",
+            "=={separator} BEGIN {path}, LENGTH: {len}{unchanged}{invisible}{metadata} {separator}==\n\
+            {content}\n=={separator} END {path}, LENGTH: {len} {separator}==\n",
             path = path.display(),
-            len = full_contents.len(),
+            len = contents.len(),
+            content = self.render(&contents, 1),
         ))
     }
+
+    /// `, BOM` and `, NO FINAL NEWLINE` for a BEGIN line, for whichever apply to `contents`
+    pub(crate) fn invisible_bytes(contents: &str) -> String {
+        let mut notes = String::new();
+        if contents.starts_with('\u{feff}') {
+            notes.push_str(", BOM");
+        }
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            notes.push_str(", NO FINAL NEWLINE");
+        }
+        notes
+    }
+
+    /// `content` as returned, numbering lines from `first_line` if `line_numbers` is set
+    fn render(&self, content: &str, first_line: usize) -> String {
+        if self.line_numbers() {
+            Self::number_lines(content, first_line)
+        } else {
+            content.to_string()
+        }
+    }
 }
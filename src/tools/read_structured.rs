@@ -0,0 +1,310 @@
+use crate::{
+    hints::{ErrorCode, Hint, Hinted},
+    tools::FsTools,
+};
+use anyhow::Result;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+/// Read a JSON, YAML, or TOML file by its structure: the value at a key path, or a skeleton of
+/// its keys down to a depth. Checking one version in a 400 KB package-lock.json doesn't need
+/// the rest of it. Read-only
+#[derive(Debug, Serialize, Deserialize, JsonSchema, clap::Args)]
+#[serde(rename = "read_structured")]
+pub struct ReadStructured {
+    /// File to read
+    /// Can be absolute, or relative to session context path.
+    pub path: String,
+
+    /// Key path of the value to return, either dotted with array indices as numbers, like
+    /// `dependencies.serde` or `workspace.members.0`, or a JSON pointer, like
+    /// `/packages/node_modules~1a`, for keys that contain dots. Without it, a skeleton of the
+    /// keys is returned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub key_path: Option<String>,
+
+    /// How many levels of keys the skeleton shows, below `key_path` if it is given. Containers
+    /// deeper than this are summarized with their size
+    /// Default: 2, or the whole value when `key_path` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub depth: Option<usize>,
+
+    /// The file's format, for files without a .json, .yaml, .yml, or .toml extension
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub format: Option<StructuredFormat>,
+}
+
+/// A format that read_structured and edit_structured understand
+#[derive(
+    Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum StructuredFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl WithExamples for ReadStructured {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Checking which version of a package is locked",
+                item: Self {
+                    path: "package-lock.json".into(),
+                    key_path: Some("/packages/node_modules~1react/version".into()),
+                    depth: None,
+                    format: None,
+                },
+            },
+            Example {
+                description: "Seeing what a large config file is made of",
+                item: Self {
+                    path: "docker-compose.yml".into(),
+                    key_path: None,
+                    depth: Some(2),
+                    format: None,
+                },
+            },
+            Example {
+                description: "Listing a crate's dependencies without their details",
+                item: Self {
+                    path: "Cargo.toml".into(),
+                    key_path: Some("dependencies".into()),
+                    depth: Some(1),
+                    format: None,
+                },
+            },
+        ]
+    }
+}
+
+impl StructuredFormat {
+    /// The format of `path`, from `format` if given or else its extension
+    pub(crate) fn of(path: &Path, format: Option<Self>) -> Result<Self> {
+        if let Some(format) = format {
+            return Ok(format);
+        }
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("json") => Ok(Self::Json),
+            Some("yaml" | "yml") => Ok(Self::Yaml),
+            Some("toml") => Ok(Self::Toml),
+            _ => Err(Hinted::new(
+                format!("The format of {} is unknown", path.display()),
+                Some("pass `format` as json, yaml, or toml".into()),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .with_path(path)
+            .into()),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Json => "JSON",
+            Self::Yaml => "YAML",
+            Self::Toml => "TOML",
+        }
+    }
+
+    /// Parse `contents` into a JSON value
+    pub(crate) fn parse(self, contents: &str, path: &Path) -> Result<Value> {
+        let parsed = match self {
+            Self::Json => serde_json::from_str(contents).map_err(|error| error.to_string()),
+            Self::Yaml => serde_yaml_ng::from_str(contents).map_err(|error| error.to_string()),
+            Self::Toml => contents
+                .parse::<toml::Table>()
+                .map(|table| toml_to_json(toml::Value::Table(table)))
+                .map_err(|error| error.to_string()),
+        };
+        parsed.map_err(|error| {
+            Hinted::new(
+                format!(
+                    "{} is not valid {}: {}",
+                    path.display(),
+                    self.name(),
+                    error.trim_end()
+                ),
+                Some("use read to see the file as text".into()),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .with_path(path)
+            .into()
+        })
+    }
+}
+
+/// A TOML value as JSON, with dates and times as strings
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(string) => Value::String(string),
+        toml::Value::Integer(integer) => integer.into(),
+        toml::Value::Float(float) => float.into(),
+        toml::Value::Boolean(boolean) => boolean.into(),
+        toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        toml::Value::Array(array) => array.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(table) => table
+            .into_iter()
+            .map(|(key, value)| (key, toml_to_json(value)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+    }
+}
+
+/// The keys of a key path, from a JSON pointer or a dotted path
+pub(crate) fn key_path_segments(key_path: &str) -> Vec<String> {
+    match key_path.strip_prefix('/') {
+        Some(pointer) => pointer
+            .split('/')
+            .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+        None => key_path
+            .split('.')
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string)
+            .collect(),
+    }
+}
+
+/// The value at `segments` inside `value`, or an error naming the keys that do exist where the
+/// path stops matching
+pub(crate) fn lookup<'a>(value: &'a Value, segments: &[String], path: &Path) -> Result<&'a Value> {
+    let mut current = value;
+    for (index, segment) in segments.iter().enumerate() {
+        let next = match current {
+            Value::Object(map) => map.get(segment),
+            Value::Array(array) => segment.parse::<usize>().ok().and_then(|i| array.get(i)),
+            _ => None,
+        };
+        current = match next {
+            Some(next) => next,
+            None => {
+                let at = match index {
+                    0 => "the top level".to_string(),
+                    _ => segments[..index].join("."),
+                };
+                let suggestion = match current {
+                    Value::Object(map) => format!(
+                        "keys at {at}: {}",
+                        map.keys().take(20).cloned().collect::<Vec<_>>().join(", ")
+                    ),
+                    Value::Array(array) => {
+                        format!("{at} is an array of {} items, indexed from 0", array.len())
+                    }
+                    _ => format!("{at} is {}, which has no keys", describe(current)),
+                };
+                return Err(Hinted::new(
+                    format!("{} has no {segment} at {at}", path.display()),
+                    Some(suggestion),
+                )
+                .with_code(ErrorCode::NotFound)
+                .with_path(path)
+                .into());
+            }
+        };
+    }
+    Ok(current)
+}
+
+/// Skeleton entries of one object or array are cut off after this many
+const MAX_SKELETON_ENTRIES: usize = 50;
+
+/// Scalars in a skeleton are cut to this many characters
+const MAX_SCALAR_CHARS: usize = 60;
+
+/// A value's kind and size, or a short rendering of a scalar
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Object(map) => match map.len() {
+            1 => "object (1 key)".into(),
+            keys => format!("object ({keys} keys)"),
+        },
+        Value::Array(array) => match array.len() {
+            1 => "array (1 item)".into(),
+            items => format!("array ({items} items)"),
+        },
+        scalar => {
+            let rendered = scalar.to_string();
+            match rendered.char_indices().nth(MAX_SCALAR_CHARS) {
+                Some((index, _)) => format!("{}…", &rendered[..index]),
+                None => rendered,
+            }
+        }
+    }
+}
+
+/// Append the keys of `value` down to `depth` levels, indented by `indent`
+fn skeleton(value: &Value, depth: usize, indent: usize, output: &mut String) {
+    if depth == 0 {
+        return;
+    }
+    let entries: Vec<(String, &Value)> = match value {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| (key.clone(), value))
+            .collect(),
+        Value::Array(array) => array
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (index.to_string(), value))
+            .collect(),
+        _ => return,
+    };
+    let pad = "  ".repeat(indent);
+    for (key, child) in entries.iter().take(MAX_SKELETON_ENTRIES) {
+        output.push_str(&format!("{pad}{key}: {}\n", describe(child)));
+        skeleton(child, depth - 1, indent + 1, output);
+    }
+    if entries.len() > MAX_SKELETON_ENTRIES {
+        output.push_str(&format!(
+            "{pad}... and {} more\n",
+            entries.len() - MAX_SKELETON_ENTRIES
+        ));
+    }
+}
+
+impl Tool<FsTools> for ReadStructured {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let path = state.resolve_path(&self.path, None)?;
+        let format = StructuredFormat::of(&path, self.format)?;
+        let contents = std::fs::read_to_string(&path).hint_path(&path)?;
+        let document = format.parse(&contents, &path)?;
+
+        let Some(key_path) = &self.key_path else {
+            let mut output = format!(
+                "{} is {}; keys to depth {}:\n\n",
+                path.display(),
+                describe(&document),
+                self.depth.unwrap_or(2)
+            );
+            skeleton(&document, self.depth.unwrap_or(2), 0, &mut output);
+            return Ok(output);
+        };
+
+        let value = lookup(&document, &key_path_segments(key_path), &path)?;
+        match (self.depth, value) {
+            (Some(depth), Value::Object(_) | Value::Array(_)) => {
+                let mut output = format!("{key_path} is {}:\n\n", describe(value));
+                skeleton(value, depth, 0, &mut output);
+                Ok(output)
+            }
+            _ => Ok(format!(
+                "{key_path} in {}:\n\n{}",
+                path.display(),
+                serde_json::to_string_pretty(value)?
+            )),
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{
+    deadline,
+    hints::{self, ErrorCode, Hinted, Unreadable},
+    project_ignore::ProjectIgnore,
+    tools::FsTools,
+    visibility::Visibility,
+};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Show the most recently modified files under a directory, newest first, to pick up where work
+/// left off. Read-only
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "recent_files")]
+pub struct RecentFiles {
+    /// Directory to look in
+    /// Can be absolute, or relative to session context path.
+    /// Defaults to the current session context if not provided
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// Only include files with these extensions (e.g., ["rs", "toml"])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub extensions: Option<Vec<String>>,
+
+    /// Number of files to return
+    /// Default: 20
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub limit: Option<usize>,
+
+    /// Include paths excluded by the working directory's `.fs-mcp-ignore` file
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub bypass_ignore_file: Option<bool>,
+
+    /// Include hidden files and directories, whose names start with `.`
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_hidden: Option<bool>,
+
+    /// Include paths ignored by `.gitignore`, `.ignore`, and git's excludes. Paths in
+    /// `.fs-mcp-ignore` are included with `bypass_ignore_file` instead
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_ignored: Option<bool>,
+
+    /// Stop walking after this many milliseconds and return what was found so far
+    /// Default: 30000, or the server's `--timeout-ms`, which also caps this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub time_limit_ms: Option<u64>,
+}
+
+impl WithExamples for RecentFiles {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Seeing what was being worked on in the current project",
+                item: Self {
+                    path: None,
+                    extensions: None,
+                    limit: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    time_limit_ms: None,
+                },
+            },
+            Example {
+                description: "Finding the Rust sources changed most recently in a crate",
+                item: Self {
+                    path: Some("crates/core".into()),
+                    extensions: Some(vec!["rs".into()]),
+                    limit: Some(5),
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    time_limit_ms: None,
+                },
+            },
+        ]
+    }
+}
+
+impl RecentFiles {
+    fn matches_extension(&self, path: &Path) -> bool {
+        let Some(extensions) = &self.extensions else {
+            return true;
+        };
+        path.extension().is_some_and(|extension| {
+            extensions
+                .iter()
+                .any(|wanted| extension.eq_ignore_ascii_case(wanted.trim_start_matches('.')))
+        })
+    }
+}
+
+impl Tool<FsTools> for RecentFiles {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let base_path = state.resolve_path(self.path.as_deref().unwrap_or("."), None)?;
+        if !base_path.exists() {
+            return Err(hints::not_found(&base_path));
+        }
+        if !base_path.is_dir() {
+            return Err(Hinted::new(
+                format!("Path is not a directory: {}", base_path.display()),
+                Some("use exists or read with include_metadata to check a single file".into()),
+            )
+            .with_code(ErrorCode::NotADirectory)
+            .with_path(&base_path)
+            .into());
+        }
+
+        let settings = state.settings(None)?;
+        let ignore = ProjectIgnore::load(state, self.bypass_ignore_file.unwrap_or_default())?;
+        let walk = Visibility::new(self.include_hidden, self.include_ignored)
+            .walk_builder(&base_path)
+            .filter_entry(move |entry| {
+                let is_dir = entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_dir());
+                !ignore.is_ignored(entry.path(), is_dir)
+            })
+            .build();
+
+        let mut unreadable = Unreadable::default();
+        let mut files: Vec<(SystemTime, u64, PathBuf)> = vec![];
+        let deadline = state.deadline(self.time_limit_ms, deadline::DEFAULT_TIME_LIMIT);
+        let mut timed_out = false;
+        for entry in walk {
+            if deadline.is_reached() {
+                timed_out = true;
+                break;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    unreadable.record_walk_error(&error);
+                    continue;
+                }
+            };
+            if !entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+                || !self.matches_extension(entry.path())
+            {
+                continue;
+            }
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(error) => {
+                    unreadable.record_walk_error(&error);
+                    continue;
+                }
+            };
+            match metadata.modified() {
+                Ok(modified) => files.push((modified, metadata.len(), entry.into_path())),
+                Err(error) => unreadable.record(entry.path(), error),
+            }
+        }
+
+        let total = files.len();
+        files.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.2.cmp(&b.2)));
+        files.truncate(self.limit.unwrap_or(20));
+
+        let time_limit_note = if timed_out {
+            deadline.note()
+        } else {
+            String::new()
+        };
+        if files.is_empty() {
+            let mut output = format!("No files found under {}", base_path.display());
+            output.push_str(&time_limit_note);
+            unreadable.append_to(&mut output);
+            return Ok(output);
+        }
+
+        let formatter = settings.timestamp_formatter();
+        let total = match total {
+            1 => "1 file".to_string(),
+            total => format!("{total} files"),
+        };
+        let mut output = format!(
+            "Most recently modified of {total} under {}:\n\n",
+            base_path.display()
+        );
+        let times = files
+            .iter()
+            .map(|(modified, _, _)| formatter.format(*modified))
+            .collect::<Vec<_>>();
+        let width = times
+            .iter()
+            .map(|time| time.chars().count())
+            .max()
+            .unwrap_or(0);
+        for (time, (_, len, path)) in times.iter().zip(&files) {
+            let relative = path.strip_prefix(&base_path).unwrap_or(path);
+            output.push_str(&format!(
+                "{time:<width$}  {} ({})\n",
+                relative.display(),
+                settings.format_size(*len)
+            ));
+        }
+        output.push_str(&time_limit_note);
+        unreadable.append_to(&mut output);
+        Ok(output)
+    }
+}
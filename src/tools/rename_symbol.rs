@@ -0,0 +1,250 @@
+use super::Read;
+use crate::{
+    hints::{ErrorCode, Hint, Hinted},
+    quota::QuotaUsage,
+    tools::FsTools,
+};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use regex::{NoExpand, Regex};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::path::PathBuf;
+
+/// Files whose diffs are shown in a preview. Further changed files are only listed
+const MAX_PREVIEW_DIFFS: usize = 20;
+
+/// Rename an identifier across a glob of files, matching whole words only so that e.g.
+/// renaming `id` leaves `user_id` alone. Previews the per-file diff first, and only changes
+/// files when called again with `confirm: true`. Faster and safer than search and repeated write
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "rename_symbol")]
+pub struct RenameSymbol {
+    /// Identifier to rename, made of letters, digits, and underscores
+    pub symbol: String,
+
+    /// New name for the identifier
+    pub replacement: String,
+
+    /// Glob of files to rename in, e.g. "src/**/*.rs"
+    /// Can be absolute, or relative to session context path.
+    pub files: String,
+
+    /// Apply the rename. Without it, only the diff of every affected file is returned
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub confirm: Option<bool>,
+
+    /// Rename in files that match one of the session's generated file patterns (lockfiles and
+    /// generated code, see configure_session). Without it, a glob that reaches one is refused
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub allow_generated: Option<bool>,
+}
+
+impl WithExamples for RenameSymbol {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Previewing the rename of a function across a crate",
+                item: Self {
+                    symbol: "parse_config".into(),
+                    replacement: "load_config".into(),
+                    files: "src/**/*.rs".into(),
+                    confirm: None,
+                    allow_generated: None,
+                },
+            },
+            Example {
+                description: "Applying the rename after reviewing the preview",
+                item: Self {
+                    symbol: "parse_config".into(),
+                    replacement: "load_config".into(),
+                    files: "src/**/*.rs".into(),
+                    confirm: Some(true),
+                    allow_generated: None,
+                },
+            },
+        ]
+    }
+}
+
+/// A file that the rename would change
+struct Change {
+    path: PathBuf,
+    occurrences: usize,
+    before: String,
+    after: String,
+}
+
+impl RenameSymbol {
+    fn confirm(&self) -> bool {
+        self.confirm.unwrap_or_default()
+    }
+
+    fn is_identifier(name: &str) -> bool {
+        !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+    }
+}
+
+impl Tool<FsTools> for RenameSymbol {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        for name in [&self.symbol, &self.replacement] {
+            if !Self::is_identifier(name) {
+                return Err(Hinted::new(
+                    format!("{name} is not an identifier"),
+                    Some(
+                        "rename_symbol only renames whole identifiers; use search and write for \
+                         other replacements"
+                            .into(),
+                    ),
+                )
+                .with_code(ErrorCode::InvalidArgument)
+                .into());
+            }
+        }
+
+        let regex = Regex::new(&format!(r"\b{}\b", regex::escape(&self.symbol)))?;
+
+        let mut changes = vec![];
+        for path in Read::expand_glob(state, &self.files)? {
+            let bytes = std::fs::read(&path).hint_path(&path)?;
+            // Binary and non-utf8 files can't be rewritten without corrupting them
+            let Ok(before) = String::from_utf8(bytes) else {
+                continue;
+            };
+            if before.contains('\0') {
+                continue;
+            }
+
+            let occurrences = regex.find_iter(&before).count();
+            if occurrences > 0 {
+                let after = regex
+                    .replace_all(&before, NoExpand(&self.replacement))
+                    .into_owned();
+                changes.push(Change {
+                    path,
+                    occurrences,
+                    before,
+                    after,
+                });
+            }
+        }
+
+        if changes.is_empty() {
+            return Ok(format!(
+                "{} does not occur as a whole word in {}",
+                self.symbol, self.files
+            ));
+        }
+
+        if !self.allow_generated.unwrap_or_default() {
+            let settings = state.settings(None)?;
+            for change in &changes {
+                if let Some(pattern) = settings.generated_pattern(&change.path) {
+                    return Err(Hinted::new(
+                        format!(
+                            "{} matches the generated file pattern \"{pattern}\", so nothing was \
+                             renamed",
+                            change.path.display()
+                        ),
+                        Some(
+                            "narrow `files` to leave it out and regenerate it with the tool that \
+                             owns it, or pass `allow_generated: true` if renaming in it is really \
+                             intended"
+                                .into(),
+                        ),
+                    )
+                    .with_path(&change.path)
+                    .into());
+                }
+            }
+        }
+
+        let occurrences = changes
+            .iter()
+            .map(|change| change.occurrences)
+            .sum::<usize>();
+        let summary = format!(
+            "{occurrences} occurrences of {} in {} files",
+            self.symbol,
+            changes.len()
+        );
+
+        if self.confirm() {
+            let paths = changes
+                .iter()
+                .map(|change| change.path.as_path())
+                .collect::<Vec<_>>();
+            let _lock = state.lock_paths(&paths)?;
+            for change in &changes {
+                state.check_symlinks(&change.path, true)?;
+                // Another process may have changed the file between reading and locking it
+                let current = std::fs::read(&change.path).hint_path(&change.path)?;
+                if current != change.before.as_bytes() {
+                    return Err(Hinted::new(
+                        format!(
+                            "{} changed while renaming, so nothing was renamed",
+                            change.path.display()
+                        ),
+                        Some("call rename_symbol again".into()),
+                    )
+                    .with_path(&change.path)
+                    .into());
+                }
+            }
+
+            let bytes = changes.iter().map(|change| change.after.len() as u64).sum();
+            state.charge_quota(QuotaUsage::written(bytes, false))?;
+
+            let mut output = format!("Renamed {summary} to {}:\n", self.replacement);
+            for change in &changes {
+                std::fs::write(&change.path, &change.after).hint_path(&change.path)?;
+                output.push_str(&format!(
+                    "{} ({})\n",
+                    change.path.display(),
+                    change.occurrences
+                ));
+            }
+            return Ok(output);
+        }
+
+        let mut output = format!(
+            "Renaming to {} would change {summary}. Call rename_symbol again with `confirm: true` \
+             to apply. No filesystem operation has been performed\n\n",
+            self.replacement
+        );
+        for change in changes.iter().take(MAX_PREVIEW_DIFFS) {
+            let path = change.path.display().to_string();
+            let diff = TextDiff::from_lines(&change.before, &change.after);
+            output.push_str(
+                &diff
+                    .unified_diff()
+                    .context_radius(1)
+                    .header(&path, &path)
+                    .to_string(),
+            );
+        }
+        if changes.len() > MAX_PREVIEW_DIFFS {
+            output.push_str(&format!(
+                "\n... and {} more files:\n",
+                changes.len() - MAX_PREVIEW_DIFFS
+            ));
+            for change in &changes[MAX_PREVIEW_DIFFS..] {
+                output.push_str(&format!(
+                    "{} ({})\n",
+                    change.path.display(),
+                    change.occurrences
+                ));
+            }
+        }
+
+        Ok(output)
+    }
+}
@@ -0,0 +1,133 @@
+use super::Delete;
+use crate::{
+    hints::{ErrorCode, Hint, Hinted},
+    quota::QuotaUsage,
+    tools::FsTools,
+};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+
+/// Bring back a file that delete removed permanently, from the snapshot kept in the undo
+/// journal. Only files up to 1MiB are snapshotted
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "restore_deleted")]
+pub struct RestoreDeleted {
+    /// Id of the snapshot to restore, as reported by delete. Omit to list the snapshots in the
+    /// undo journal without restoring anything
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub id: Option<String>,
+
+    /// Replace a file that has since been created at the original location
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub overwrite: Option<bool>,
+}
+
+impl WithExamples for RestoreDeleted {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Reviewing which deleted files can be restored",
+                item: Self {
+                    id: None,
+                    overwrite: None,
+                },
+            },
+            Example {
+                description: "Restoring a file that was deleted by mistake",
+                item: Self {
+                    id: Some("p2m7ds".into()),
+                    overwrite: None,
+                },
+            },
+        ]
+    }
+}
+
+impl Tool<FsTools> for RestoreDeleted {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let settings = state.settings(None)?;
+        let journal = state.journal(None)?;
+
+        let Some(id) = &self.id else {
+            if journal.is_empty() {
+                return Ok("The undo journal is empty".into());
+            }
+            let mut output = format!("{} deleted files can be restored:\n", journal.len());
+            for entry in journal.iter().rev() {
+                output.push_str(&format!(
+                    "{}  {} ({}, deleted {})\n",
+                    entry.id,
+                    entry.original.display(),
+                    settings.format_size(entry.size),
+                    settings.timestamp_formatter().format(entry.deleted_at)
+                ));
+            }
+            return Ok(output);
+        };
+
+        let Some(entry) = journal.iter().find(|entry| &entry.id == id) else {
+            return Err(Hinted::new(
+                format!("No snapshot with id {id} in the undo journal"),
+                Some("omit `id` to list the snapshots that can be restored".into()),
+            )
+            .into());
+        };
+
+        if entry.original.exists() && !self.overwrite.unwrap_or_default() {
+            return Err(Hinted::new(
+                format!(
+                    "{} exists again, so it was not restored",
+                    entry.original.display()
+                ),
+                Some("use `overwrite` to replace it with the snapshot".into()),
+            )
+            .into());
+        }
+
+        let contents = std::fs::read(&entry.snapshot).hint_path(&entry.snapshot)?;
+        if Delete::sha256_hex(&contents) != entry.sha256 {
+            return Err(Hinted::new(
+                format!(
+                    "The snapshot of {} has been modified since it was taken, so it was not \
+                     restored",
+                    entry.original.display()
+                ),
+                Some(format!(
+                    "inspect {} with read before restoring it by hand",
+                    entry.snapshot.display()
+                )),
+            )
+            .with_code(ErrorCode::Conflict)
+            .into());
+        }
+
+        if let Some(parent) = entry.original.parent() {
+            std::fs::create_dir_all(parent).hint_path(parent)?;
+        }
+        state.charge_quota(QuotaUsage::written(
+            contents.len() as u64,
+            !entry.original.exists(),
+        ))?;
+        std::fs::write(&entry.original, &contents).hint_path(&entry.original)?;
+
+        if let Some(snapshot_dir) = entry.snapshot.parent() {
+            let _ = std::fs::remove_dir_all(snapshot_dir);
+        }
+        state.update_journal(None, |journal| journal.retain(|e| &e.id != id))?;
+
+        Ok(format!(
+            "Restored {} ({}, sha256 {})",
+            entry.original.display(),
+            settings.format_size(entry.size),
+            &entry.sha256[..12]
+        ))
+    }
+}
@@ -0,0 +1,385 @@
+use crate::{
+    hints::{self, ErrorCode, Hint, Hinted},
+    quota::QuotaUsage,
+    tools::FsTools,
+};
+use anyhow::Result;
+use clap::ArgAction;
+use ignore::WalkBuilder;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Built-in templates, each a list of paths and contents
+const TEMPLATES: &[(&str, &[(&str, &str)])] = &[
+    (
+        "rust_module",
+        &[(
+            "src/{{name:snake}}.rs",
+            "#[derive(Debug, Clone, Default)]\npub struct {{name:pascal}} {}\n\n\
+             impl {{name:pascal}} {\n    pub fn new() -> Self {\n        Self::default()\n    }\n}\n\n\
+             #[cfg(test)]\nmod tests {\n    use super::*;\n\n    #[test]\n    fn new() {\n        \
+             {{name:pascal}}::new();\n    }\n}\n",
+        )],
+    ),
+    (
+        "rust_integration_test",
+        &[(
+            "tests/{{name:snake}}.rs",
+            "#[test]\nfn {{name:snake}}() {\n    todo!()\n}\n",
+        )],
+    ),
+    (
+        "python_module",
+        &[
+            (
+                "{{name:snake}}.py",
+                "\"\"\"{{name:snake}}\"\"\"\n\n\nclass {{name:pascal}}:\n    pass\n",
+            ),
+            (
+                "tests/test_{{name:snake}}.py",
+                "from {{name:snake}} import {{name:pascal}}\n\n\n\
+                 def test_{{name:snake}}():\n    assert {{name:pascal}}()\n",
+            ),
+        ],
+    ),
+];
+
+/// Create a tree of files from a template, replacing `{{variable}}` in paths and contents.
+/// `{{variable:snake}}`, `:pascal`, `:camel`, `:kebab`, `:upper`, and `:lower` change the
+/// case of the value. The template is a directory, or one of the built-in templates:
+/// rust_module, rust_integration_test, and python_module, which take a `name` variable
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "scaffold")]
+pub struct Scaffold {
+    /// A built-in template name, or the path of a template directory
+    pub template: String,
+
+    /// Directory to create the files in
+    /// Default: the working directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub destination: Option<String>,
+
+    /// Values of the template's variables, each as `name=value`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "variable")]
+    pub variables: Option<Vec<String>>,
+
+    /// Replace files that already exist. Without this, nothing is created if any file exists
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub overwrite: Option<bool>,
+}
+
+impl WithExamples for Scaffold {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Starting a new Rust module with a test",
+                item: Self {
+                    template: "rust_module".into(),
+                    destination: None,
+                    variables: Some(vec!["name=RateLimiter".into()]),
+                    overwrite: None,
+                },
+            },
+            Example {
+                description: "Instantiating a project template directory",
+                item: Self {
+                    template: "templates/handler".into(),
+                    destination: Some("src/handlers".into()),
+                    variables: Some(vec!["name=billing".into(), "route=/billing".into()]),
+                    overwrite: None,
+                },
+            },
+        ]
+    }
+}
+
+/// A file to create, with its path relative to the destination
+struct Planned {
+    path: String,
+    contents: Vec<u8>,
+}
+
+impl Scaffold {
+    /// Split a word into its lowercase parts, at underscores, dashes, spaces, and case changes
+    fn words(value: &str) -> Vec<String> {
+        let mut words = vec![];
+        let mut current = String::new();
+        let mut previous: Option<char> = None;
+        let chars = value.chars().collect::<Vec<_>>();
+        for (index, &c) in chars.iter().enumerate() {
+            if matches!(c, '_' | '-' | ' ' | '.') {
+                if !current.is_empty() {
+                    words.push(std::mem::take(&mut current));
+                }
+                previous = None;
+                continue;
+            }
+            // A word starts at a capital after a lowercase letter, or at the last capital of an
+            // acronym, as in `HTTPClient`
+            let next_is_lowercase = chars.get(index + 1).is_some_and(|next| next.is_lowercase());
+            let starts_word = previous.is_some_and(|previous| {
+                previous.is_lowercase()
+                    || previous.is_ascii_digit()
+                    || (previous.is_uppercase() && next_is_lowercase)
+            });
+            if c.is_uppercase() && starts_word && !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            current.extend(c.to_lowercase());
+            previous = Some(c);
+        }
+        if !current.is_empty() {
+            words.push(current);
+        }
+        words
+    }
+
+    /// Apply a case conversion such as `snake` to a variable's value
+    pub(crate) fn convert_case(value: &str, case: &str) -> Option<String> {
+        let capitalized = |word: &String| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        };
+        let words = Self::words(value);
+        Some(match case {
+            "snake" => words.join("_"),
+            "kebab" => words.join("-"),
+            "pascal" => words.iter().map(capitalized).collect(),
+            "camel" => words
+                .iter()
+                .enumerate()
+                .map(|(index, word)| {
+                    if index == 0 {
+                        word.clone()
+                    } else {
+                        capitalized(word)
+                    }
+                })
+                .collect(),
+            "upper" => value.to_uppercase(),
+            "lower" => value.to_lowercase(),
+            _ => return None,
+        })
+    }
+
+    /// Replace every `{{variable}}` and `{{variable:case}}` in `text`, collecting the names of
+    /// undefined variables and unknown cases in `missing`
+    pub(crate) fn substitute(
+        text: &str,
+        variables: &BTreeMap<String, String>,
+        missing: &mut BTreeSet<String>,
+    ) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            let Some(length) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            output.push_str(&rest[..start]);
+            let placeholder = rest[start + 2..start + 2 + length].trim();
+            let (name, case) = match placeholder.split_once(':') {
+                Some((name, case)) => (name.trim(), Some(case.trim())),
+                None => (placeholder, None),
+            };
+            let value = variables.get(name).and_then(|value| match case {
+                Some(case) => Self::convert_case(value, case),
+                None => Some(value.clone()),
+            });
+            match value {
+                Some(value) => output.push_str(&value),
+                None => {
+                    missing.insert(placeholder.to_string());
+                }
+            }
+            rest = &rest[start + 2 + length + 2..];
+        }
+        output.push_str(rest);
+        output
+    }
+
+    fn variables(&self) -> Result<BTreeMap<String, String>> {
+        let mut variables = BTreeMap::new();
+        for variable in self.variables.iter().flatten() {
+            let Some((name, value)) = variable.split_once('=') else {
+                return Err(Hinted::new(
+                    format!("Variable {variable:?} has no value"),
+                    Some("give each variable as `name=value`".into()),
+                )
+                .into());
+            };
+            variables.insert(name.trim().to_string(), value.to_string());
+        }
+        Ok(variables)
+    }
+
+    /// The template's files, with paths relative to its root using `/`
+    fn template_files(&self, state: &mut FsTools) -> Result<Vec<(String, Vec<u8>)>> {
+        if let Some((_, files)) = TEMPLATES.iter().find(|(name, _)| *name == self.template) {
+            return Ok(files
+                .iter()
+                .map(|(path, contents)| (path.to_string(), contents.as_bytes().to_vec()))
+                .collect());
+        }
+
+        let root = state.resolve_path(&self.template, None)?;
+        if !root.is_dir() {
+            let names = TEMPLATES
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(Hinted::new(
+                format!(
+                    "{} is not a built-in template or a directory",
+                    self.template
+                ),
+                Some(format!("the built-in templates are {names}")),
+            )
+            .into());
+        }
+
+        let mut files = vec![];
+        let walk = WalkBuilder::new(&root)
+            .standard_filters(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build();
+        for entry in walk {
+            let entry = entry?;
+            if !entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+            {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(&root)?;
+            let relative = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((relative, fs::read(entry.path()).hint_path(entry.path())?));
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// Substitute variables into the template, checking that every variable is defined and
+    /// every path stays inside the destination
+    fn plan(&self, files: Vec<(String, Vec<u8>)>) -> Result<Vec<Planned>> {
+        let variables = self.variables()?;
+        let mut missing = BTreeSet::new();
+        let mut planned = vec![];
+        for (path, contents) in files {
+            let path = Self::substitute(&path, &variables, &mut missing);
+            // Binary files are copied as they are
+            let contents = match String::from_utf8(contents) {
+                Ok(text) => Self::substitute(&text, &variables, &mut missing).into_bytes(),
+                Err(error) => error.into_bytes(),
+            };
+            let escapes = Path::new(&path)
+                .components()
+                .any(|component| !matches!(component, Component::Normal(_)));
+            if escapes || path.is_empty() {
+                return Err(Hinted::new(
+                    format!("The template would create {path:?}, outside of the destination"),
+                    Some("template paths and variables can't contain `..` or be absolute".into()),
+                )
+                .with_code(ErrorCode::OutsideSandbox)
+                .into());
+            }
+            planned.push(Planned { path, contents });
+        }
+
+        if !missing.is_empty() {
+            let missing = missing.into_iter().collect::<Vec<_>>().join(", ");
+            return Err(Hinted::new(
+                format!("The template uses variables that were not given: {missing}"),
+                Some(
+                    "pass each as `name=value` in `variables`; cases are snake, pascal, camel, \
+                     kebab, upper, and lower"
+                        .into(),
+                ),
+            )
+            .into());
+        }
+        Ok(planned)
+    }
+}
+
+impl Tool<FsTools> for Scaffold {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let files = self.template_files(state)?;
+        if files.is_empty() {
+            return Err(
+                Hinted::new(format!("The template {} has no files", self.template), None).into(),
+            );
+        }
+        let planned = self.plan(files)?;
+        let destination =
+            state.resolve_new_path(self.destination.as_deref().unwrap_or("."), None)?;
+
+        let existing = planned
+            .iter()
+            .map(|file| destination.join(&file.path))
+            .filter(|path| path.exists())
+            .collect::<Vec<PathBuf>>();
+        if !existing.is_empty() && !self.overwrite.unwrap_or_default() {
+            let existing = existing
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+            return Ok(format!(
+                "These files already exist, so nothing was created. Pass `overwrite: true` to \
+                 replace them:\n{existing}"
+            ));
+        }
+
+        let bytes = planned.iter().map(|file| file.contents.len() as u64).sum();
+        let created = (planned.len() - existing.len()) as u64;
+        state.charge_quota(QuotaUsage {
+            bytes_written: bytes,
+            files_created: created,
+            files_deleted: 0,
+        })?;
+
+        let mut output = match planned.len() {
+            1 => "Created 1 file".to_string(),
+            count => format!("Created {count} files"),
+        };
+        output.push_str(&format!(
+            " in {} from {}:\n",
+            destination.display(),
+            self.template
+        ));
+        for file in &planned {
+            let path = destination.join(&file.path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).hint_path(parent)?;
+            }
+            fs::write(&path, &file.contents).map_err(|error| hints::io_error(error, &path))?;
+            let note = if existing.contains(&path) {
+                " (replaced)"
+            } else {
+                ""
+            };
+            output.push_str(&format!("{}{note}\n", file.path));
+        }
+        Ok(output)
+    }
+}
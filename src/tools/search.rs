@@ -1,4 +1,14 @@
-use crate::tools::FsTools;
+use crate::{
+    budget::Limits,
+    config::Config,
+    exclude::Exclude,
+    file_types,
+    hints::{self, ErrorCode, Unreadable},
+    project_ignore::ProjectIgnore,
+    state::SearchRecord,
+    tools::FsTools,
+    visibility::Visibility,
+};
 use anyhow::{Context, Result};
 use clap::ArgAction;
 use grep::matcher::Matcher;
@@ -10,7 +20,32 @@ use mcplease::{
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// The default time limit. It is shorter than that of the other tools that walk directories,
+/// since a search that runs out of time can be resumed
+const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(5);
+
+/// The options that decide which lines match, rather than how matches are shown
+const MATCHING_OPTIONS: &[&str] = &[
+    "case_sensitive",
+    "whole_word",
+    "literal",
+    "multiline",
+    "invert_match",
+    "files_without_match",
+    "include_extensions",
+    "file_types",
+    "exclude",
+    "bypass_ignore_file",
+    "include_hidden",
+    "include_ignored",
+    "within_lines",
+];
 
 /// Search for text patterns in files using ripgrep-like functionality
 #[derive(Debug, Serialize, Deserialize, JsonSchema, clap::Args)]
@@ -31,31 +66,154 @@ pub struct Search {
     #[arg(long, action = ArgAction::SetTrue)]
     pub case_sensitive: Option<bool>,
 
+    /// Only match whole words, so that `id` doesn't match `user_id` or `identity`
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub whole_word: Option<bool>,
+
+    /// Treat the pattern as plain text rather than a regex, so that characters such as `(`,
+    /// `.`, and `*` match themselves
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub literal: Option<bool>,
+
+    /// Match the pattern against whole files rather than line by line, so that it can span
+    /// lines, e.g. `fn new\([^)]*\)\s*->\s*Result`. Every line of a match is shown. `^` and
+    /// `$` still match at the start and end of each line
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub multiline: Option<bool>,
+
+    /// Report the lines that do not match the pattern instead, like `grep -v`. Can't be
+    /// combined with `multiline`
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub invert_match: Option<bool>,
+
+    /// Only list the files with no matching lines, like `grep -L`, e.g. to find source files
+    /// without a license header. The `search_id` refines to these files
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub files_without_match: Option<bool>,
+
     /// File extensions to include (e.g., ["rs", "js", "py"])
     /// If not specified, searches all text files
     #[serde(skip_serializing_if = "Option::is_none")]
     #[arg(long)]
     pub include_extensions: Option<Vec<String>>,
 
+    /// Kinds of files to include by name, such as "rust", "python", "web", or "config", instead
+    /// of listing their extensions. Combined with `include_extensions` if both are given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub file_types: Option<Vec<String>>,
+
+    /// Glob patterns of paths to leave out, such as `**/generated/**` or `*.min.js`. Patterns
+    /// without a `/` match names at any depth; the others match paths relative to the working
+    /// directory. Excluded directories aren't searched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub exclude: Option<Vec<String>>,
+
     /// Maximum number of results to return
-    /// Default: 50
+    /// Default: 50, lowered for the session after several large responses
     #[serde(skip_serializing_if = "Option::is_none")]
     #[arg(long)]
     pub max_results: Option<usize>,
 
+    /// Maximum number of results to return from any one file, so that a noisy file such as
+    /// generated code can't use up `max_results`. Matches past it don't count toward
+    /// `max_results`, and the response says how many were left out of each file
+    /// Default: no limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub max_results_per_file: Option<usize>,
+
+    /// Keep searching after `max_results` to count every match. Otherwise the search stops
+    /// once it has found more than `max_results`, and can be continued with `resume`
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub count_all_matches: Option<bool>,
+
     /// Highlight style for matches in output
     /// Options: "none", "box", "emphasis", "ansi", "markdown"
-    /// Default: "box"
-    #[arg(value_enum)]
-    #[serde(default)]
-    #[arg(long)]
-    pub highlight_style: HighlightStyle,
+    /// Default: "box", unless the config file sets another
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub highlight_style: Option<HighlightStyle>,
 
     /// Number of context lines to show before and after each match
     /// Default: 1
     #[serde(skip_serializing_if = "Option::is_none")]
     #[arg(long)]
     pub context_lines: Option<usize>,
+
+    /// Show each file's path once, with its match count, followed by its matching lines.
+    /// Context lines shared by nearby matches are shown once, marked with `-` after the line
+    /// number instead of `:`
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub group_by_file: Option<bool>,
+
+    /// Show where each match starts within its line, after the line number: its 1-based
+    /// character column, then its 0-based byte offset in the line in brackets, e.g.
+    /// `src/lib.rs:12:5[4],19[22]: ...` for two matches. Not available with `invert_match`
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub columns: Option<bool>,
+
+    /// Only search the files matched by a previous search, identified by the `search_id` it
+    /// returned. If `path` is also provided, only those files within it are searched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub refine: Option<String>,
+
+    /// Stop walking the tree after this many milliseconds and return the matches found so far.
+    /// The response then says how much is left, and the search can be continued with `resume`
+    /// Default: 5000, or the server's `--timeout-ms`, which also caps this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub time_limit_ms: Option<u64>,
+
+    /// Continue a search that stopped at its time limit or at `max_results`, identified by the
+    /// `search_id` it returned. Use the same pattern and options as the original search,
+    /// without `path` or `refine`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub resume: Option<String>,
+
+    /// Include paths excluded by the working directory's `.fs-mcp-ignore` file
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub bypass_ignore_file: Option<bool>,
+
+    /// Include hidden files and directories, whose names start with `.`
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_hidden: Option<bool>,
+
+    /// Include paths ignored by `.gitignore`, `.ignore`, and git's excludes. Paths in
+    /// `.fs-mcp-ignore` are included with `bypass_ignore_file` instead
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub include_ignored: Option<bool>,
+
+    /// Only report matches on lines `start` to `end`, inclusive and 1-based, e.g. to check
+    /// whether a pattern appears inside one function. Only valid when `path` is a single file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, num_args = 2, value_names = ["START", "END"])]
+    pub within_lines: Option<Vec<usize>>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, JsonSchema, Clone, Copy, clap::ValueEnum)]
@@ -144,10 +302,149 @@ impl WithExamples for Search {
                     pattern: "fn main".to_string(),
                     path: Some("src/".to_string()),
                     case_sensitive: Some(false),
+                    whole_word: None,
+                    literal: None,
+                    multiline: None,
+                    invert_match: None,
+                    files_without_match: None,
                     include_extensions: Some(vec!["rs".to_string()]),
+                    file_types: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    within_lines: None,
                     max_results: Some(10),
-                    highlight_style: HighlightStyle::Box,
+                    max_results_per_file: None,
+                    count_all_matches: None,
+                    highlight_style: Some(HighlightStyle::Box),
+                    context_lines: None,
+                    group_by_file: None,
+                    columns: None,
+                    refine: None,
+                    time_limit_ms: None,
+                    resume: None,
+                },
+            },
+            Example {
+                description: "Finding every use of a function, one section per file",
+                item: Self {
+                    pattern: "parse_config".to_string(),
+                    path: None,
+                    case_sensitive: Some(true),
+                    whole_word: Some(true),
+                    literal: None,
+                    multiline: None,
+                    invert_match: None,
+                    files_without_match: None,
+                    include_extensions: None,
+                    file_types: Some(vec!["rust".to_string()]),
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    within_lines: None,
+                    max_results: None,
+                    max_results_per_file: None,
+                    count_all_matches: None,
+                    highlight_style: Some(HighlightStyle::None),
+                    context_lines: Some(0),
+                    group_by_file: Some(true),
+                    columns: None,
+                    refine: None,
+                    time_limit_ms: None,
+                    resume: None,
+                },
+            },
+            Example {
+                description: "Finding calls of a method by its exact text, parentheses included",
+                item: Self {
+                    pattern: "session.get(".to_string(),
+                    path: Some("src/".to_string()),
+                    case_sensitive: Some(true),
+                    whole_word: None,
+                    literal: Some(true),
+                    multiline: None,
+                    invert_match: None,
+                    files_without_match: None,
+                    include_extensions: None,
+                    file_types: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    within_lines: None,
+                    max_results: None,
+                    max_results_per_file: None,
+                    count_all_matches: None,
+                    highlight_style: Some(HighlightStyle::Box),
+                    context_lines: Some(0),
+                    group_by_file: None,
+                    columns: None,
+                    refine: None,
+                    time_limit_ms: None,
+                    resume: None,
+                },
+            },
+            Example {
+                description: "Finding constructors that return Result, with signatures split \
+                              across lines",
+                item: Self {
+                    pattern: r"fn new\([^)]*\)\s*->\s*Result".to_string(),
+                    path: Some("src/".to_string()),
+                    case_sensitive: Some(true),
+                    whole_word: None,
+                    literal: None,
+                    multiline: Some(true),
+                    invert_match: None,
+                    files_without_match: None,
+                    include_extensions: None,
+                    file_types: Some(vec!["rust".to_string()]),
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    within_lines: None,
+                    max_results: None,
+                    max_results_per_file: None,
+                    count_all_matches: None,
+                    highlight_style: Some(HighlightStyle::None),
+                    context_lines: Some(0),
+                    group_by_file: None,
+                    columns: None,
+                    refine: None,
+                    time_limit_ms: None,
+                    resume: None,
+                },
+            },
+            Example {
+                description: "Finding the source files that are missing a license header",
+                item: Self {
+                    pattern: "SPDX-License-Identifier".to_string(),
+                    path: Some("src/".to_string()),
+                    case_sensitive: Some(true),
+                    whole_word: None,
+                    literal: Some(true),
+                    multiline: None,
+                    invert_match: None,
+                    files_without_match: Some(true),
+                    include_extensions: None,
+                    file_types: Some(vec!["rust".to_string()]),
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    within_lines: None,
+                    max_results: None,
+                    max_results_per_file: None,
+                    count_all_matches: None,
+                    highlight_style: Some(HighlightStyle::None),
                     context_lines: None,
+                    group_by_file: None,
+                    columns: None,
+                    refine: None,
+                    time_limit_ms: None,
+                    resume: None,
                 },
             },
             Example {
@@ -156,22 +453,270 @@ impl WithExamples for Search {
                     pattern: "TODO|FIXME".to_string(),
                     path: None,
                     case_sensitive: Some(false),
+                    whole_word: None,
+                    literal: None,
+                    multiline: None,
+                    invert_match: None,
+                    files_without_match: None,
                     include_extensions: None,
+                    file_types: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    within_lines: None,
                     max_results: Some(20),
-                    highlight_style: HighlightStyle::Emphasis,
+                    max_results_per_file: None,
+                    count_all_matches: None,
+                    highlight_style: Some(HighlightStyle::Emphasis),
                     context_lines: None,
+                    group_by_file: None,
+                    columns: None,
+                    refine: None,
+                    time_limit_ms: None,
+                    resume: None,
                 },
             },
             Example {
-                description: "Search with ANSI color highlighting and 2 lines of context",
+                description: "Search Rust sources and manifests with ANSI color highlighting and 2 lines of context",
                 item: Self {
                     pattern: "error".to_string(),
                     path: Some("src/".to_string()),
                     case_sensitive: Some(false),
+                    whole_word: None,
+                    literal: None,
+                    multiline: None,
+                    invert_match: None,
+                    files_without_match: None,
                     include_extensions: None,
+                    file_types: Some(vec!["rust".to_string(), "toml".to_string()]),
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    within_lines: None,
                     max_results: Some(15),
-                    highlight_style: HighlightStyle::Ansi,
+                    max_results_per_file: None,
+                    count_all_matches: None,
+                    highlight_style: Some(HighlightStyle::Ansi),
                     context_lines: Some(2),
+                    group_by_file: None,
+                    columns: None,
+                    refine: None,
+                    time_limit_ms: None,
+                    resume: None,
+                },
+            },
+            Example {
+                description: "Narrowing a previous search to the files that matched it",
+                item: Self {
+                    pattern: "impl Tool".to_string(),
+                    path: None,
+                    case_sensitive: Some(true),
+                    whole_word: None,
+                    literal: None,
+                    multiline: None,
+                    invert_match: None,
+                    files_without_match: None,
+                    include_extensions: None,
+                    file_types: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    within_lines: None,
+                    max_results: None,
+                    max_results_per_file: None,
+                    count_all_matches: None,
+                    highlight_style: Some(HighlightStyle::Box),
+                    context_lines: Some(0),
+                    group_by_file: None,
+                    columns: None,
+                    refine: Some("k3f9xq".to_string()),
+                    time_limit_ms: None,
+                    resume: None,
+                },
+            },
+            Example {
+                description: "Searching generated code and CI config, which are usually ignored \
+                              or hidden",
+                item: Self {
+                    pattern: "RUST_VERSION".to_string(),
+                    path: None,
+                    case_sensitive: Some(true),
+                    whole_word: None,
+                    literal: None,
+                    multiline: None,
+                    invert_match: None,
+                    files_without_match: None,
+                    include_extensions: None,
+                    file_types: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: Some(true),
+                    include_ignored: Some(true),
+                    within_lines: None,
+                    max_results: None,
+                    max_results_per_file: None,
+                    count_all_matches: None,
+                    highlight_style: Some(HighlightStyle::Box),
+                    context_lines: None,
+                    group_by_file: None,
+                    columns: None,
+                    refine: None,
+                    time_limit_ms: None,
+                    resume: None,
+                },
+            },
+            Example {
+                description: "Getting the first matches in a huge tree quickly, to continue later \
+                              with `resume` if needed",
+                item: Self {
+                    pattern: "deprecated".to_string(),
+                    path: Some("/".to_string()),
+                    case_sensitive: None,
+                    whole_word: None,
+                    literal: None,
+                    multiline: None,
+                    invert_match: None,
+                    files_without_match: None,
+                    include_extensions: None,
+                    file_types: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    within_lines: None,
+                    max_results: None,
+                    max_results_per_file: None,
+                    count_all_matches: None,
+                    highlight_style: Some(HighlightStyle::Box),
+                    context_lines: None,
+                    group_by_file: None,
+                    columns: None,
+                    refine: None,
+                    time_limit_ms: Some(500),
+                    resume: None,
+                },
+            },
+            Example {
+                description: "Checking whether one function, on lines 120 to 164, uses unsafe",
+                item: Self {
+                    pattern: "unsafe".to_string(),
+                    path: Some("src/buffer.rs".to_string()),
+                    case_sensitive: Some(true),
+                    whole_word: None,
+                    literal: None,
+                    multiline: None,
+                    invert_match: None,
+                    files_without_match: None,
+                    include_extensions: None,
+                    file_types: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    within_lines: Some(vec![120, 164]),
+                    max_results: None,
+                    max_results_per_file: None,
+                    count_all_matches: None,
+                    highlight_style: Some(HighlightStyle::Box),
+                    context_lines: Some(0),
+                    group_by_file: None,
+                    columns: None,
+                    refine: None,
+                    time_limit_ms: None,
+                    resume: None,
+                },
+            },
+            Example {
+                description: "Finding the exact positions of calls to rename by hand",
+                item: Self {
+                    pattern: "get_user(".to_string(),
+                    path: Some("src/".to_string()),
+                    case_sensitive: Some(true),
+                    whole_word: None,
+                    literal: Some(true),
+                    multiline: None,
+                    invert_match: None,
+                    files_without_match: None,
+                    include_extensions: None,
+                    file_types: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    within_lines: None,
+                    max_results: None,
+                    max_results_per_file: None,
+                    count_all_matches: None,
+                    highlight_style: Some(HighlightStyle::None),
+                    context_lines: Some(0),
+                    group_by_file: None,
+                    columns: Some(true),
+                    refine: None,
+                    time_limit_ms: None,
+                    resume: None,
+                },
+            },
+            Example {
+                description: "Finding uses of a type without a generated file crowding them out",
+                item: Self {
+                    pattern: "UserId".to_string(),
+                    path: None,
+                    case_sensitive: Some(true),
+                    whole_word: Some(true),
+                    literal: None,
+                    multiline: None,
+                    invert_match: None,
+                    files_without_match: None,
+                    include_extensions: None,
+                    file_types: None,
+                    exclude: None,
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    within_lines: None,
+                    max_results: None,
+                    max_results_per_file: Some(3),
+                    count_all_matches: None,
+                    highlight_style: Some(HighlightStyle::Box),
+                    context_lines: Some(0),
+                    group_by_file: Some(true),
+                    columns: None,
+                    refine: None,
+                    time_limit_ms: None,
+                    resume: None,
+                },
+            },
+            Example {
+                description: "Searching a web app's scripts, leaving out generated and minified files",
+                item: Self {
+                    pattern: "fetchUser".to_string(),
+                    path: Some("src".into()),
+                    case_sensitive: Some(true),
+                    whole_word: None,
+                    literal: Some(true),
+                    multiline: None,
+                    invert_match: None,
+                    files_without_match: None,
+                    include_extensions: None,
+                    file_types: Some(vec!["web".to_string()]),
+                    exclude: Some(vec!["**/generated/**".to_string(), "*.min.js".to_string()]),
+                    bypass_ignore_file: None,
+                    include_hidden: None,
+                    include_ignored: None,
+                    within_lines: None,
+                    max_results: None,
+                    max_results_per_file: None,
+                    count_all_matches: None,
+                    highlight_style: Some(HighlightStyle::Box),
+                    context_lines: None,
+                    group_by_file: Some(true),
+                    columns: None,
+                    refine: None,
+                    time_limit_ms: None,
+                    resume: None,
                 },
             },
         ]
@@ -179,220 +724,816 @@ impl WithExamples for Search {
 }
 
 impl Tool<FsTools> for Search {
-    fn execute(self, state: &mut FsTools) -> Result<String> {
-        let search_path = state.resolve_path(self.path.as_deref().unwrap_or("."), None)?;
+    fn execute(mut self, state: &mut FsTools) -> Result<String> {
+        if self.max_results.is_none() {
+            self.max_results = Some(state.limits()?.max_results);
+        }
+        let time_limit = state
+            .deadline(self.time_limit_ms, self.time_limit())
+            .limit();
+        self.time_limit_ms = Some(time_limit.as_millis().try_into().unwrap_or(u64::MAX));
 
-        let matcher = RegexMatcherBuilder::new()
-            .case_insensitive(!self.case_sensitive())
-            .build(&self.pattern)
-            .context("Invalid regex pattern")?;
+        if let Some(file_types) = &self.file_types {
+            let extensions = file_types::extensions(file_types)?;
+            self.include_extensions
+                .get_or_insert_with(Vec::new)
+                .extend(extensions);
+        }
 
-        self.search_with_matcher(&search_path, matcher)
+        if self.max_results_per_file == Some(0) {
+            return Err(hints::Hinted::new(
+                "`max_results_per_file` must be at least 1",
+                Some("leave it out to show every match in each file".into()),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into());
+        }
+
+        if self.columns.unwrap_or_default() && self.invert_match.unwrap_or_default() {
+            return Err(hints::Hinted::new(
+                "Lines found with `invert_match` have no match to give the column of",
+                Some("leave out `columns`".into()),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into());
+        }
+
+        let line_range = self.line_range()?;
+        if self.multiline.unwrap_or_default() && self.invert_match.unwrap_or_default() {
+            return Err(hints::Hinted::new(
+                "`invert_match` works line by line, so it can't be combined with `multiline`",
+                Some("use `files_without_match` to find files without a multiline match".into()),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into());
+        }
+
+        if let Some(id) = &self.resume {
+            return self.resume(state, id);
+        }
+
+        // A refinement without a path doesn't need a working directory
+        let search_path = match (&self.path, &self.refine) {
+            (None, Some(_)) => None,
+            (path, _) => {
+                let search_path = state.resolve_path(path.as_deref().unwrap_or("."), None)?;
+                if !search_path.exists() {
+                    return Err(hints::not_found(&search_path));
+                }
+                if line_range.is_some() && !search_path.is_file() {
+                    return Err(hints::Hinted::new(
+                        format!(
+                            "`within_lines` needs a single file, but {} is a directory",
+                            search_path.display()
+                        ),
+                        Some("pass the path of the file the lines are in".into()),
+                    )
+                    .with_code(ErrorCode::InvalidArgument)
+                    .into());
+                }
+                Some(search_path)
+            }
+        };
+
+        let (roots, scope) = match &self.refine {
+            Some(id) => {
+                let roots = state
+                    .search_record(id)?
+                    .files
+                    .into_iter()
+                    .filter(|file| {
+                        search_path
+                            .as_ref()
+                            .is_none_or(|path| file.starts_with(path))
+                    })
+                    .collect::<Vec<_>>();
+                (roots, format!("the files matched by search {id}"))
+            }
+            None => {
+                let search_path = search_path.unwrap_or_default();
+                let scope = search_path.display().to_string();
+                (vec![search_path], scope)
+            }
+        };
+
+        self.run(state, roots, &scope, vec![])
     }
 }
 
 impl Search {
+    /// The validated `within_lines`, as an inclusive range
+    fn line_range(&self) -> Result<Option<std::ops::RangeInclusive<u64>>> {
+        let Some(within_lines) = &self.within_lines else {
+            return Ok(None);
+        };
+        match within_lines[..] {
+            [start, end] if start >= 1 && start <= end => {
+                if self.refine.is_some() || self.resume.is_some() {
+                    return Err(hints::Hinted::new(
+                        "`within_lines` needs a single file, so it can't be combined with `refine` \
+                         or `resume`",
+                        Some("pass the file as `path` instead".into()),
+                    )
+                    .into());
+                }
+                Ok(Some(start as u64..=end as u64))
+            }
+            _ => Err(hints::Hinted::new(
+                format!("`within_lines` must be [start, end] with 1 <= start <= end, not {within_lines:?}"),
+                Some("line numbers are 1-based and both ends are included".into()),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into()),
+        }
+    }
+
+    /// The pattern as a regex, escaped if `literal` and bounded by word boundaries if
+    /// `whole_word`. Used for both matching and highlighting
+    fn regex(&self) -> String {
+        let pattern = match self.literal.unwrap_or_default() {
+            true => regex::escape(&self.pattern),
+            false => self.pattern.clone(),
+        };
+        match self.whole_word.unwrap_or_default() {
+            true => format!(r"\b(?:{pattern})\b"),
+            false => pattern,
+        }
+    }
+
     fn case_sensitive(&self) -> bool {
         self.case_sensitive.unwrap_or(false)
     }
 
     fn max_results(&self) -> usize {
-        self.max_results.unwrap_or(50)
+        self.max_results.unwrap_or(Limits::DEFAULT.max_results)
     }
 
     fn highlight_style(&self) -> HighlightStyle {
         self.highlight_style
+            .or(Config::global().defaults.highlight_style)
+            .unwrap_or_default()
     }
 
     fn context_lines(&self) -> usize {
         self.context_lines.unwrap_or(1)
     }
 
-    fn search_with_matcher(&self, search_path: &Path, matcher: impl Matcher) -> Result<String> {
+    fn time_limit(&self) -> Duration {
+        self.time_limit_ms
+            .map_or(DEFAULT_TIME_LIMIT, Duration::from_millis)
+    }
+
+    /// The options that decide what matches, as passed, for checking that a resumed search
+    /// repeats them
+    fn matching_options(&self) -> serde_json::Map<String, serde_json::Value> {
+        let Ok(serde_json::Value::Object(mut options)) = serde_json::to_value(self) else {
+            return serde_json::Map::new();
+        };
+        options.retain(|name, _| MATCHING_OPTIONS.contains(&name.as_str()));
+        options
+    }
+
+    /// Continue the walk of a search that ran out of time
+    fn resume(&self, state: &mut FsTools, id: &str) -> Result<String> {
+        if self.path.is_some() || self.refine.is_some() {
+            return Err(hints::Hinted::new(
+                "`resume` continues the paths of the original search, so it can't be combined \
+                 with `path` or `refine`",
+                Some("omit `path` and `refine`, or start a new search without `resume`".into()),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into());
+        }
+
+        let record = state.search_record(id)?;
+        if record.pattern != self.pattern {
+            return Err(hints::Hinted::new(
+                format!(
+                    "Search {id} was for pattern \"{}\", not \"{}\"",
+                    record.pattern, self.pattern
+                ),
+                Some("resume with the original pattern, or start a new search".into()),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into());
+        }
+        let options = self.matching_options();
+        if let Some(name) = MATCHING_OPTIONS
+            .iter()
+            .find(|name| record.options.get(**name) != options.get(**name))
+        {
+            let describe = |value: Option<&serde_json::Value>| match value {
+                Some(value) => value.to_string(),
+                None => "not set".to_string(),
+            };
+            return Err(hints::Hinted::new(
+                format!(
+                    "Search {id} was run with `{name}` {}, not {}",
+                    describe(record.options.get(*name)),
+                    describe(options.get(*name))
+                ),
+                Some(
+                    "resume with the options of the original search, or start a new search".into(),
+                ),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into());
+        }
+        if record.pending.is_empty() {
+            return Ok(format!(
+                "Search {id} already searched everything; there is nothing left to resume"
+            ));
+        }
+
+        let scope = format!("the rest of search {id}");
+        self.run(state, record.pending, &scope, record.files)
+    }
+
+    /// Search `roots`, appending a `search_id` for refining or resuming to the output.
+    /// `matched_files` are files already matched by the search being resumed
+    fn run(
+        &self,
+        state: &mut FsTools,
+        roots: Vec<PathBuf>,
+        scope: &str,
+        mut matched_files: Vec<PathBuf>,
+    ) -> Result<String> {
+        let matcher = RegexMatcherBuilder::new()
+            .case_insensitive(!self.case_sensitive())
+            .multi_line(self.multiline.unwrap_or_default())
+            .build(&self.regex())
+            .context("Invalid regex pattern")?;
+
+        let ignore = ProjectIgnore::load(state, self.bypass_ignore_file.unwrap_or_default())?;
+        let exclude = Exclude::new(state, self.exclude.as_deref())?;
+        let mut unreadable = Unreadable::default();
+
+        let mut pending = roots;
+        pending.reverse();
+        let (mut output, stopped_at_limit) = self.search_with_matcher(
+            &mut pending,
+            scope,
+            matcher,
+            &ignore,
+            &exclude,
+            &mut unreadable,
+            &mut matched_files,
+        )?;
+        pending.reverse();
+        unreadable.append_to(&mut output);
+
+        if !matched_files.is_empty() || !pending.is_empty() {
+            let id = std::iter::repeat_with(fastrand::alphanumeric)
+                .take(6)
+                .collect::<String>()
+                .to_lowercase();
+            if pending.is_empty() {
+                let matched = match self.files_without_match.unwrap_or_default() {
+                    true => "files without a match",
+                    false => "files matched",
+                };
+                output.push_str(&format!(
+                    "\n\nsearch_id: {id} ({} {matched}; pass as `refine` to search within \
+                     them)",
+                    matched_files.len()
+                ));
+            } else {
+                let refine = if matched_files.is_empty() {
+                    ""
+                } else {
+                    ", or as `refine` to search within them"
+                };
+                let stopped = match stopped_at_limit {
+                    true => "`max_results` was reached".to_string(),
+                    false => format!(
+                        "The time limit of {}ms was reached",
+                        self.time_limit().as_millis()
+                    ),
+                };
+                output.push_str(&format!(
+                    "\n\n{stopped} before searching everything, so these results are \
+                     partial.\nsearch_id: {id} ({} files matched so far; pass as `resume` with \
+                     the same pattern to continue{refine})",
+                    matched_files.len()
+                ));
+            }
+            state.record_search(SearchRecord {
+                id,
+                pattern: self.pattern.clone(),
+                files: matched_files,
+                pending,
+                options: self.matching_options(),
+            })?;
+        }
+
+        Ok(output)
+    }
+
+    /// Search the paths in `pending`, which is used as a stack (the last path is searched
+    /// first). If the time limit or `max_results` is reached, the paths left unsearched remain
+    /// in `pending`. Returns the output, and whether the search stopped at `max_results`
+    #[allow(clippy::too_many_arguments)]
+    fn search_with_matcher(
+        &self,
+        pending: &mut Vec<PathBuf>,
+        scope: &str,
+        matcher: impl Matcher,
+        ignore: &ProjectIgnore,
+        exclude: &Exclude,
+        unreadable: &mut Unreadable,
+        matched_files: &mut Vec<PathBuf>,
+    ) -> Result<(String, bool)> {
         let mut results = Vec::new();
         let mut total_matches = 0;
         let max_results = self.max_results();
+        let deadline = Instant::now() + self.time_limit();
+        let mut searched_any = false;
+        let count_all_matches = self.count_all_matches.unwrap_or_default();
+        let mut stopped_at_limit = false;
+        // Files that reached `max_results_per_file`, with how many of their matches were left out
+        let mut suppressed = Vec::new();
 
-        self.search_path(
-            search_path,
-            &matcher,
-            &mut results,
-            &mut total_matches,
-            max_results,
-        )?;
+        while let Some(path) = pending.pop() {
+            // Always make some progress, so that resuming can't get stuck
+            if searched_any && Instant::now() >= deadline {
+                pending.push(path);
+                break;
+            }
+            if !count_all_matches && total_matches > max_results {
+                pending.push(path);
+                stopped_at_limit = true;
+                break;
+            }
+            searched_any = true;
 
-        if results.is_empty() {
-            Ok(format!(
-                "No matches found for pattern \"{}\" in {}",
-                self.pattern,
-                search_path.display()
+            let suppressed_matches = self.search_path(
+                &path,
+                &matcher,
+                ignore,
+                exclude,
+                unreadable,
+                &mut results,
+                &mut total_matches,
+                max_results,
+                matched_files,
+                pending,
+            )?;
+            if suppressed_matches > 0 {
+                suppressed.push((path, suppressed_matches));
+            }
+        }
+
+        // Walk order depends on the platform and on how a search was refined or resumed, so
+        // results are sorted by path (component by component) and line number for stable output
+        results.sort_by(|a, b| {
+            Path::new(&a.file_path)
+                .cmp(Path::new(&b.file_path))
+                .then(a.line_number.cmp(&b.line_number))
+        });
+        suppressed.sort();
+        matched_files.sort();
+
+        if self.files_without_match.unwrap_or_default() {
+            Ok((self.format_files_without_match(matched_files, scope), false))
+        } else if results.is_empty() {
+            Ok((
+                format!(
+                    "No matches found for pattern \"{}\" in {scope}",
+                    self.pattern
+                ),
+                false,
             ))
         } else {
+            let group_by_file = self.group_by_file.unwrap_or_default();
+            let files = match results.chunk_by(|a, b| a.file_path == b.file_path).count() {
+                _ if !group_by_file => String::new(),
+                1 => " in 1 file".to_string(),
+                files => format!(" in {files} files"),
+            };
+            let matches = match self.invert_match.unwrap_or_default() {
+                true => "lines not matching",
+                false => "matches for",
+            };
             let mut output = format!(
-                "Found {} matches for pattern \"{}\":\n\n",
+                "Found {} {matches} pattern \"{}\"{files}:\n\n",
                 results.len(),
                 self.pattern
             );
 
-            let highlight_style = self.highlight_style();
-            let case_sensitive = self.case_sensitive();
-
-            for result in results {
-                let highlighted_content =
-                    highlight_style.highlight(&result.line_content, &self.pattern, case_sensitive);
-
-                // Add context before if available
-                for (i, context_line) in result.context_before.iter().enumerate() {
-                    let context_line_num =
-                        result.line_number - (result.context_before.len() - i) as u64;
-                    output.push_str(&format!(
-                        "{}:{}: {}\n",
-                        result.file_path,
-                        context_line_num,
-                        context_line.trim()
-                    ));
+            if group_by_file {
+                output.push_str(&self.format_grouped(&results));
+            } else {
+                output.push_str(&self.format_interleaved(&results));
+            }
+
+            if stopped_at_limit {
+                output.push_str(&format!(
+                    "\n... and at least {} more matches (limit {max_results}). The search stopped \
+                     at the limit; pass `count_all_matches: true` to count every match",
+                    total_matches - max_results,
+                ));
+            } else if total_matches > max_results {
+                output.push_str(&format!(
+                    "\n... and {} more matches (limit {})",
+                    total_matches - max_results,
+                    max_results
+                ));
+            }
+
+            if !suppressed.is_empty() {
+                let count = suppressed.iter().map(|(_, count)| count).sum::<usize>();
+                let files = match suppressed.len() {
+                    1 => "1 file".to_string(),
+                    files => format!("{files} files"),
+                };
+                output.push_str(&format!(
+                    "\n... and {count} more matches in {files} past `max_results_per_file` ({}):",
+                    self.max_results_per_file.unwrap_or_default()
+                ));
+                for (path, count) in &suppressed {
+                    output.push_str(&format!("\n  {}: {count} more", path.display()));
                 }
+            }
+
+            Ok((output, stopped_at_limit))
+        }
+    }
+
+    /// The files that had no match, up to `max_results` of them
+    fn format_files_without_match(&self, files: &[PathBuf], scope: &str) -> String {
+        if files.is_empty() {
+            return format!("Every file in {scope} matches pattern \"{}\"", self.pattern);
+        }
+        let count = match files.len() {
+            1 => "1 file".to_string(),
+            count => format!("{count} files"),
+        };
+        let mut output = format!(
+            "Found {count} without a match for pattern \"{}\":\n\n",
+            self.pattern
+        );
+        let max_results = self.max_results();
+        for file in files.iter().take(max_results) {
+            output.push_str(&format!("{}\n", file.display()));
+        }
+        if files.len() > max_results {
+            output.push_str(&format!(
+                "\n... and {} more files (limit {max_results})",
+                files.len() - max_results
+            ));
+        }
+        output
+    }
 
-                // Add the matched line
+    /// Each result as `path:line: text`, with its context lines before and after it
+    fn format_interleaved(&self, results: &[SearchResult]) -> String {
+        let mut output = String::new();
+        let highlight_style = self.highlight_style();
+        let case_sensitive = self.case_sensitive();
+        let pattern = self.regex();
+
+        for result in results {
+            let highlighted_content =
+                highlight_style.highlight(&result.line_content, &pattern, case_sensitive);
+
+            // Add context before if available
+            for (i, context_line) in result.context_before.iter().enumerate() {
+                let context_line_num =
+                    result.line_number - (result.context_before.len() - i) as u64;
                 output.push_str(&format!(
                     "{}:{}: {}\n",
                     result.file_path,
-                    result.line_number,
-                    highlighted_content.trim()
+                    context_line_num,
+                    context_line.trim()
                 ));
+            }
 
-                // Add context after if available
-                for (i, context_line) in result.context_after.iter().enumerate() {
-                    let context_line_num = result.line_number + (i + 1) as u64;
-                    output.push_str(&format!(
-                        "{}:{}: {}\n",
-                        result.file_path,
-                        context_line_num,
-                        context_line.trim()
-                    ));
-                }
+            // Add the matched lines, of which there are several for a multiline match
+            let mut columns = result.format_columns();
+            for (line_number, line) in (result.line_number..).zip(highlighted_content.lines()) {
+                output.push_str(&format!(
+                    "{}:{}{}: {}\n",
+                    result.file_path,
+                    line_number,
+                    std::mem::take(&mut columns),
+                    line.trim()
+                ));
+            }
 
-                // Add separator between results if context lines are shown
-                if self.context_lines() > 0
-                    && (!result.context_before.is_empty() || !result.context_after.is_empty())
-                {
-                    output.push_str("--\n");
+            // Add context after if available
+            for (i, context_line) in result.context_after.iter().enumerate() {
+                let context_line_num = result.last_line() + (i + 1) as u64;
+                output.push_str(&format!(
+                    "{}:{}: {}\n",
+                    result.file_path,
+                    context_line_num,
+                    context_line.trim()
+                ));
+            }
+
+            // Add separator between results if context lines are shown
+            if self.context_lines() > 0
+                && (!result.context_before.is_empty() || !result.context_after.is_empty())
+            {
+                output.push_str("--\n");
+            }
+        }
+
+        output
+    }
+
+    /// A section per file, headed by its path and match count, with each line shown once even
+    /// if it is context for several matches
+    fn format_grouped(&self, results: &[SearchResult]) -> String {
+        let highlight_style = self.highlight_style();
+        let case_sensitive = self.case_sensitive();
+        let pattern = self.regex();
+
+        // Results are in file order, so each file's results are together
+        let mut output = String::new();
+        for file_results in results.chunk_by(|a, b| a.file_path == b.file_path) {
+            // Line number to text, and whether it matched with the columns of its matches
+            let mut lines = BTreeMap::<u64, (String, Option<String>)>::new();
+            for result in file_results {
+                let before = result.line_number - result.context_before.len() as u64;
+                for (line_number, line) in (before..).zip(&result.context_before) {
+                    lines
+                        .entry(line_number)
+                        .or_insert_with(|| (line.clone(), None));
+                }
+                let highlighted =
+                    highlight_style.highlight(&result.line_content, &pattern, case_sensitive);
+                let mut columns = result.format_columns();
+                for (line_number, line) in (result.line_number..).zip(highlighted.lines()) {
+                    let columns = std::mem::take(&mut columns);
+                    lines.insert(line_number, (line.to_string(), Some(columns)));
+                }
+                for (line_number, line) in (result.last_line() + 1..).zip(&result.context_after) {
+                    lines
+                        .entry(line_number)
+                        .or_insert_with(|| (line.clone(), None));
                 }
             }
 
-            if total_matches > max_results {
+            let count = match file_results.len() {
+                1 => "1 match".to_string(),
+                count => format!("{count} matches"),
+            };
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            output.push_str(&format!("{} ({count}):\n", file_results[0].file_path));
+            let width = lines.keys().last().map_or(1, |last| last.to_string().len());
+            let mut previous = None;
+            for (line_number, (line, columns)) in &lines {
+                if previous.is_some_and(|previous| previous + 1 < *line_number) {
+                    output.push_str("  --\n");
+                }
+                let (columns, separator) = match columns {
+                    Some(columns) => (columns.as_str(), ':'),
+                    None => ("", '-'),
+                };
                 output.push_str(&format!(
-                    "\n... and {} more matches (limit {})",
-                    total_matches - max_results,
-                    max_results
+                    "  {line_number:>width$}{columns}{separator} {}\n",
+                    line.trim()
                 ));
+                previous = Some(*line_number);
             }
-
-            Ok(output)
         }
+        output
     }
 
+    /// Search a file, or queue the entries of a directory onto `pending` in sorted order.
+    /// Returns how many of a file's matches were left out by `max_results_per_file`
+    #[allow(clippy::too_many_arguments)]
     fn search_path(
         &self,
         path: &Path,
         matcher: &impl Matcher,
+        ignore: &ProjectIgnore,
+        exclude: &Exclude,
+        unreadable: &mut Unreadable,
         results: &mut Vec<SearchResult>,
         total_matches: &mut usize,
         max_results: usize,
-    ) -> Result<()> {
-        if *total_matches >= max_results {
-            return Ok(());
-        }
-
+        matched_files: &mut Vec<PathBuf>,
+        pending: &mut Vec<PathBuf>,
+    ) -> Result<usize> {
         if path.is_file() {
             if self.should_search_file(path) {
-                self.search_file(path, matcher, results, total_matches, max_results)?;
+                return self.search_file(
+                    path,
+                    matcher,
+                    unreadable,
+                    results,
+                    total_matches,
+                    max_results,
+                    matched_files,
+                );
             }
         } else if path.is_dir() {
-            let entries = std::fs::read_dir(path)
-                .with_context(|| format!("Failed to read directory: {}", path.display()))?;
-
-            for entry in entries {
-                let entry = entry?;
-                let entry_path = entry.path();
-
-                if self.should_exclude_path(&entry_path) {
-                    continue;
-                }
-
-                self.search_path(&entry_path, matcher, results, total_matches, max_results)?;
-
-                if *total_matches >= max_results {
-                    break;
+            let mut entry_paths = Vec::new();
+            for entry in Visibility::new(self.include_hidden, self.include_ignored).children(path) {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(error) => {
+                        unreadable.record_walk_error(&error);
+                        continue;
+                    }
+                };
+                let is_dir = entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_dir());
+                if !self.should_exclude_path(entry.path())
+                    && !ignore.is_ignored(entry.path(), is_dir)
+                    && !exclude.is_excluded(entry.path())
+                {
+                    entry_paths.push(entry.into_path());
                 }
             }
+
+            // Reverse sorted, so that entries are popped in order
+            entry_paths.sort_by(|a, b| b.cmp(a));
+            pending.extend(entry_paths);
         }
 
-        Ok(())
+        Ok(0)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn search_file(
         &self,
         file_path: &Path,
         matcher: &impl Matcher,
+        unreadable: &mut Unreadable,
         results: &mut Vec<SearchResult>,
         total_matches: &mut usize,
         max_results: usize,
-    ) -> Result<()> {
-        let content = std::fs::read_to_string(file_path)
-            .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
+        matched_files: &mut Vec<PathBuf>,
+    ) -> Result<usize> {
+        let content = match std::fs::read_to_string(file_path) {
+            Ok(content) => content,
+            // Not text, so there's nothing to search
+            Err(error) if error.kind() == std::io::ErrorKind::InvalidData => return Ok(0),
+            Err(error) => {
+                unreadable.record(file_path, error.kind());
+                return Ok(0);
+            }
+        };
 
         let lines: Vec<&str> = content.lines().collect();
         let context_lines = self.context_lines();
 
-        for (line_idx, line) in lines.iter().enumerate() {
-            if *total_matches >= max_results {
-                break;
+        let mut file_matched = false;
+        // Validated in execute to only be given for a single file
+        let line_range = self.line_range().ok().flatten();
+        let mut spans = if self.multiline.unwrap_or_default() {
+            Self::multiline_spans(&content, lines.len(), matcher)?
+        } else {
+            let invert_match = self.invert_match.unwrap_or_default();
+            let mut spans = vec![];
+            for (line_idx, line) in lines.iter().enumerate() {
+                let found = matcher
+                    .find(line.as_bytes())
+                    .map_err(|e| anyhow::anyhow!("Matcher error: {}", e))?
+                    .is_some();
+                if found != invert_match {
+                    spans.push((line_idx, line_idx));
+                }
             }
+            spans
+        };
+        spans.retain(|(line_idx, _)| {
+            line_range
+                .as_ref()
+                .is_none_or(|range| range.contains(&(*line_idx as u64 + 1)))
+        });
 
+        if self.files_without_match.unwrap_or_default() {
+            if spans.is_empty() {
+                matched_files.push(file_path.to_path_buf());
+            }
+            return Ok(0);
+        }
+
+        let max_results_per_file = self.max_results_per_file.unwrap_or(usize::MAX);
+        let mut suppressed = spans.len().saturating_sub(max_results_per_file);
+        for (line_idx, last_idx) in spans.into_iter().take(max_results_per_file) {
             let line_number = (line_idx + 1) as u64;
+            file_matched = true;
 
-            // Check if this line matches
-            let match_result = matcher.find(line.as_bytes());
-            if match_result
-                .map_err(|e| anyhow::anyhow!("Matcher error: {}", e))?
-                .is_some()
-            {
-                // Collect context before
-                let context_before = if context_lines > 0 {
-                    let start = line_idx.saturating_sub(context_lines);
-                    lines[start..line_idx]
-                        .iter()
-                        .map(|s| s.to_string())
-                        .collect()
-                } else {
-                    Vec::new()
-                };
+            // Past the limit, matches are only counted so the total and the set of
+            // matching files stay accurate for refinement
+            *total_matches += 1;
+            if *total_matches > max_results {
+                continue;
+            }
 
-                // Collect context after
-                let context_after = if context_lines > 0 {
-                    let end = (line_idx + 1 + context_lines).min(lines.len());
-                    lines[line_idx + 1..end]
-                        .iter()
-                        .map(|s| s.to_string())
-                        .collect()
-                } else {
-                    Vec::new()
-                };
+            // Collect context before
+            let context_before = if context_lines > 0 {
+                let start = line_idx.saturating_sub(context_lines);
+                lines[start..line_idx]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect()
+            } else {
+                Vec::new()
+            };
 
-                results.push(SearchResult {
-                    file_path: file_path.display().to_string(),
-                    line_number,
-                    line_content: line.to_string(),
-                    context_before,
-                    context_after,
-                });
+            // Collect context after
+            let context_after = if context_lines > 0 {
+                let end = (last_idx + 1 + context_lines).min(lines.len());
+                lines[last_idx + 1..end]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect()
+            } else {
+                Vec::new()
+            };
 
-                *total_matches += 1;
-            }
+            let line_content = lines[line_idx..=last_idx].join("\n");
+            let columns = match self.columns.unwrap_or_default() {
+                true => Self::match_columns(&line_content, matcher)?,
+                false => vec![],
+            };
+            results.push(SearchResult {
+                file_path: file_path.display().to_string(),
+                line_number,
+                line_content,
+                columns,
+                context_before,
+                context_after,
+            });
         }
 
-        Ok(())
+        if file_matched {
+            matched_files.push(file_path.to_path_buf());
+        }
+
+        // Once `max_results` is reached, matches left out of this file would be left out anyway,
+        // so they are reported with the rest of the matches past that limit
+        if *total_matches >= max_results {
+            *total_matches += suppressed;
+            suppressed = 0;
+        }
+        Ok(suppressed)
+    }
+
+    /// The first and last line, 0-based, of each match of `matcher` in the whole of `content`.
+    /// Matches starting on a line covered by the previous match are left out, so each line is
+    /// reported once
+    pub(crate) fn multiline_spans(
+        content: &str,
+        line_count: usize,
+        matcher: &impl Matcher,
+    ) -> Result<Vec<(usize, usize)>> {
+        let line_starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(index, _)| index + 1))
+            .collect::<Vec<_>>();
+        // A match at the very end of a file ending in a newline is on its last line
+        let line_of = |offset: usize| {
+            (line_starts.partition_point(|start| *start <= offset) - 1)
+                .min(line_count.saturating_sub(1))
+        };
+        if line_count == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut spans: Vec<(usize, usize)> = vec![];
+        matcher
+            .find_iter(content.as_bytes(), |found| {
+                let first = line_of(found.start());
+                // An empty match, or one ending with a newline, ends on the line it started
+                let last = line_of(found.end().saturating_sub(1).max(found.start()));
+                if spans.last().is_none_or(|(_, previous)| first > *previous) {
+                    spans.push((first, last));
+                }
+                true
+            })
+            .map_err(|e| anyhow::anyhow!("Matcher error: {}", e))?;
+        Ok(spans)
+    }
+
+    /// Where each match of `matcher` in `content` starts on its first line, as its 1-based
+    /// character column and 0-based byte offset. A multiline match's `content` is all of its
+    /// lines, so that the pattern still matches
+    pub(crate) fn match_columns(
+        content: &str,
+        matcher: &impl Matcher,
+    ) -> Result<Vec<(usize, usize)>> {
+        let first_line = content.find('\n').unwrap_or(content.len());
+        let mut columns = vec![];
+        matcher
+            .find_iter(content.as_bytes(), |found| {
+                if found.start() > first_line {
+                    return false;
+                }
+                let column = content[..found.start()].chars().count() + 1;
+                columns.push((column, found.start()));
+                true
+            })
+            .map_err(|e| anyhow::anyhow!("Matcher error: {}", e))?;
+        Ok(columns)
     }
 
     fn should_search_file(&self, path: &Path) -> bool {
@@ -435,13 +1576,14 @@ impl Search {
     }
 
     fn should_exclude_path(&self, path: &Path) -> bool {
-        // Default exclusions for common non-source directories
+        // Default exclusions for common non-source directories, for trees without ignore files.
+        // Version control directories are never searched
         let path_str = path.to_string_lossy();
+        let build_output = path_str.contains("/target/") || path_str.contains("/node_modules/");
         path_str.contains("/.git/")
-            || path_str.contains("/target/")
-            || path_str.contains("/node_modules/")
             || path_str.contains("/.svn/")
             || path_str.contains("/.hg/")
+            || (build_output && !self.include_ignored.unwrap_or_default())
     }
 }
 
@@ -449,7 +1591,30 @@ impl Search {
 struct SearchResult {
     file_path: String,
     line_number: u64,
+    /// The matched line, or lines joined with `\n` for a multiline match
     line_content: String,
+    /// The character column and byte offset of each match on the first line, with `columns`
+    columns: Vec<(usize, usize)>,
     context_before: Vec<String>,
     context_after: Vec<String>,
 }
+
+impl SearchResult {
+    /// The columns as shown after the line number, e.g. `:5[4],19[22]`
+    fn format_columns(&self) -> String {
+        let columns = self
+            .columns
+            .iter()
+            .map(|(column, byte)| format!("{column}[{byte}]"))
+            .collect::<Vec<_>>();
+        match columns.is_empty() {
+            true => String::new(),
+            false => format!(":{}", columns.join(",")),
+        }
+    }
+
+    /// The number of the last line of the match
+    fn last_line(&self) -> u64 {
+        self.line_number + self.line_content.lines().count().max(1) as u64 - 1
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{self_check, tools::FsTools};
+use anyhow::Result;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+
+/// Check that fs-mcp can work properly here: that its session files are writable and parse,
+/// and which filesystem features (symlinks, extended attributes, reflinks) the working
+/// directory supports. Use when tools fail in ways that suggest a broken setup. Read-only
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "self_check")]
+pub struct SelfCheck {}
+
+impl WithExamples for SelfCheck {
+    fn examples() -> Vec<Example<Self>> {
+        vec![Example {
+            description: "Checking the environment after unexpected failures",
+            item: Self {},
+        }]
+    }
+}
+
+impl Tool<FsTools> for SelfCheck {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        // Capabilities depend on the filesystem, so they are tried where the tools operate
+        let (probe_dir, probe_description) = match state.get_context(None)? {
+            Some(_) => (state.resolve_path(".", None)?, "the working directory"),
+            None => (state.data_dir().to_path_buf(), "the data directory"),
+        };
+        let checks = self_check::run(state, &probe_dir);
+
+        let mut output = format!(
+            "fs-mcp {} on {} ({}), session {}. Filesystem capabilities are for {probe_description}\n\n",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            state.default_session_id(),
+        );
+        for check in checks {
+            output.push_str(&format!("{check}\n"));
+        }
+        Ok(output)
+    }
+}
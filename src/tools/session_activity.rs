@@ -0,0 +1,89 @@
+use crate::{hints::Hinted, tools::FsTools};
+use anyhow::Result;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+
+/// Show the recent tool calls of a session, such as one created with create_child_session, so
+/// a supervisor can follow what a worker agent has been doing through its own fs-mcp. Read-only
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "session_activity")]
+pub struct SessionActivity {
+    /// Session to show. Defaults to the current session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+
+    /// Number of most recent calls to show
+    /// Default: 20
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+impl WithExamples for SessionActivity {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Checking on a sub-agent working in its own session",
+                item: Self {
+                    session: Some("docs-review".into()),
+                    limit: None,
+                },
+            },
+            Example {
+                description: "Reviewing the last few calls of the current session",
+                item: Self {
+                    session: None,
+                    limit: Some(5),
+                },
+            },
+        ]
+    }
+}
+
+impl Tool<FsTools> for SessionActivity {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let session = self
+            .session
+            .as_deref()
+            .unwrap_or_else(|| state.default_session_id());
+        let Some(activity) = state.activity(session)? else {
+            return Err(Hinted::new(
+                format!("There is no session named {session}"),
+                Some("check the name passed to create_child_session or FS_MCP_SESSION".into()),
+            )
+            .into());
+        };
+
+        if activity.is_empty() {
+            return Ok(format!("Session {session} has no recorded activity"));
+        }
+
+        let settings = state.settings(None)?;
+        let formatter = settings.timestamp_formatter();
+        let limit = self.limit.unwrap_or(20);
+        let shown = &activity[activity.len().saturating_sub(limit)..];
+
+        let mut output = format!(
+            "Last {} of {} recorded calls in session {session}, oldest first:\n",
+            shown.len(),
+            activity.len()
+        );
+        for entry in shown {
+            output.push_str(&format!(
+                "{}  {} {}",
+                formatter.format(entry.at),
+                entry.tool,
+                entry.arguments
+            ));
+            if let Some(error) = &entry.error {
+                output.push_str(&format!("\n    failed: {error}"));
+            }
+            output.push('\n');
+        }
+
+        Ok(output)
+    }
+}
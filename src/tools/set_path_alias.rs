@@ -0,0 +1,100 @@
+use crate::tools::FsTools;
+use anyhow::{Result, bail};
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+
+/// Define a named path variable for this session, usable as `$NAME` or `${NAME}` in any path
+/// argument (e.g. `$TESTS/fixtures/input.json`), or as `@NAME` at its start. Saves repeating
+/// long absolute paths, especially when working across several projects
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "set_path_alias")]
+pub struct SetPathAlias {
+    /// Variable name, made of letters, digits, and underscores (e.g. "SRC"). A leading `$` is
+    /// ignored
+    pub name: String,
+
+    /// Path the variable expands to. Omit to remove the alias
+    /// Can be absolute, or relative to session context path, and may itself use other aliases.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl WithExamples for SetPathAlias {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Naming the frontend of a second project",
+                item: Self {
+                    name: "WEB".into(),
+                    path: Some("/some/absolute/path/other-project/web/src".into()),
+                },
+            },
+            Example {
+                description: "Naming a directory relative to the session context",
+                item: Self {
+                    name: "TESTS".into(),
+                    path: Some("crates/core/tests".into()),
+                },
+            },
+            Example {
+                description: "Removing an alias",
+                item: Self {
+                    name: "WEB".into(),
+                    path: None,
+                },
+            },
+        ]
+    }
+}
+
+/// `name` without a leading `sigil`, if it is a valid alias name
+pub(crate) fn alias_name(name: &str, sigil: char) -> Result<&str> {
+    let name = name.trim_start_matches(sigil);
+    let valid = name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        bail!("Invalid alias name {name}: use letters, digits, and underscores");
+    }
+    Ok(name)
+}
+
+/// Define or remove an alias, describing the change and listing every alias using `sigil`,
+/// the syntax the calling tool documents
+pub(crate) fn update_alias(
+    state: &mut FsTools,
+    name: &str,
+    path: Option<&str>,
+    sigil: char,
+) -> Result<String> {
+    let name = alias_name(name, sigil)?;
+
+    let path = path
+        .map(|path| state.resolve_path(path, None))
+        .transpose()?;
+
+    let mut response = match &path {
+        Some(path) => format!("{sigil}{name} now refers to {}", path.display()),
+        None => format!("Removed {sigil}{name}"),
+    };
+
+    state.set_path_alias(None, name, path)?;
+
+    let aliases = state.path_aliases(None)?;
+    if !aliases.is_empty() {
+        response.push_str("\n\nAliases:\n");
+        for (name, path) in aliases {
+            response.push_str(&format!("{sigil}{name} = {}\n", path.display()));
+        }
+    }
+
+    Ok(response)
+}
+
+impl Tool<FsTools> for SetPathAlias {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        update_alias(state, &self.name, self.path.as_deref(), '$')
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{hints::Hint, settings::Verbosity, tools::FsTools};
+use anyhow::{Context, Result, anyhow, bail};
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs::Permissions, io::Read as _, path::Path};
+
+/// Change file permissions, using octal (`755`) or symbolic (`u+x`, `go-w`) notation.
+/// On Windows only the read-only attribute can be changed, based on the owner write bit
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "set_permissions")]
+pub struct SetPermissions {
+    /// Path to change
+    /// Can be absolute, or relative to session context path.
+    pub path: String,
+
+    /// New mode, either octal like "755" or "0644", or symbolic like "u+x", "go-w", or
+    /// "a=r,u+w" (as accepted by chmod)
+    pub mode: String,
+
+    /// Response detail: "quiet" reports only the resulting mode, "normal" reports the mode
+    /// before and after, and "detailed" adds hints, such as an executable script lacking a
+    /// shebang line
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub verbosity: Option<Verbosity>,
+}
+
+impl WithExamples for SetPermissions {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Making a newly written script executable",
+                item: Self {
+                    path: "scripts/release.sh".into(),
+                    mode: "u+x".into(),
+                    verbosity: None,
+                },
+            },
+            Example {
+                description: "Setting an exact mode by absolute path",
+                item: Self {
+                    path: "/some/absolute/path/bin/run".into(),
+                    mode: "755".into(),
+                    verbosity: None,
+                },
+            },
+        ]
+    }
+}
+
+impl SetPermissions {
+    /// Compute the mode that results from applying `spec` to `current`
+    pub(crate) fn apply_mode_spec(spec: &str, current: u32, is_dir: bool) -> Result<u32> {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            bail!("Mode must not be empty");
+        }
+
+        if spec.chars().all(|c| c.is_ascii_digit()) {
+            let mode =
+                u32::from_str_radix(spec, 8).map_err(|_| anyhow!("Invalid octal mode: {spec}"))?;
+            if mode > 0o7777 {
+                bail!("Octal mode out of range: {spec}");
+            }
+            return Ok(mode);
+        }
+
+        let mut mode = current & 0o7777;
+        for clause in spec.split(',') {
+            mode = apply_symbolic_clause(clause, mode, is_dir)
+                .with_context(|| format!("Invalid symbolic mode: {spec}"))?;
+        }
+        Ok(mode)
+    }
+}
+
+fn apply_symbolic_clause(clause: &str, mut mode: u32, is_dir: bool) -> Result<u32> {
+    let op_start = clause
+        .find(['+', '-', '='])
+        .ok_or_else(|| anyhow!("missing operator in \"{clause}\""))?;
+    let (who, mut rest) = clause.split_at(op_start);
+
+    let mut who_mask = 0;
+    for c in who.chars() {
+        who_mask |= match c {
+            'u' => 0o4700,
+            'g' => 0o2070,
+            'o' => 0o1007,
+            'a' => 0o7777,
+            _ => bail!("unknown user class '{c}'"),
+        };
+    }
+    if who_mask == 0 {
+        who_mask = 0o7777;
+    }
+
+    while let Some(op) = rest.chars().next() {
+        rest = &rest[1..];
+        let perms_end = rest.find(['+', '-', '=']).unwrap_or(rest.len());
+        let (perms, remaining) = rest.split_at(perms_end);
+        rest = remaining;
+
+        let executable = is_dir || mode & 0o111 != 0;
+        let mut bits = 0;
+        for c in perms.chars() {
+            bits |= match c {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' => 0o111,
+                'X' if executable => 0o111,
+                'X' => 0,
+                's' => 0o6000,
+                't' => 0o1000,
+                _ => bail!("unknown permission '{c}'"),
+            };
+        }
+        bits &= who_mask;
+
+        match op {
+            '+' => mode |= bits,
+            '-' => mode &= !bits,
+            '=' => mode = (mode & !who_mask) | bits,
+            _ => unreachable!(),
+        }
+    }
+
+    Ok(mode)
+}
+
+/// Render a mode as `0755 (rwxr-xr-x)`
+pub(crate) fn describe(mode: u32) -> String {
+    let mut symbolic = String::with_capacity(9);
+    for shift in [6, 3, 0] {
+        let bits = (mode >> shift) & 0o7;
+        symbolic.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        symbolic.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        symbolic.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    format!("{:04o} ({symbolic})", mode & 0o7777)
+}
+
+#[cfg(unix)]
+pub(crate) fn current_mode(permissions: &Permissions) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    permissions.mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+pub(crate) fn current_mode(permissions: &Permissions) -> u32 {
+    if permissions.readonly() { 0o444 } else { 0o666 }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mut permissions: Permissions, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    permissions.set_mode(mode);
+    std::fs::set_permissions(path, permissions).hint_path(path)
+}
+
+#[cfg(not(unix))]
+fn set_mode(path: &Path, mut permissions: Permissions, mode: u32) -> Result<()> {
+    permissions.set_readonly(mode & 0o200 == 0);
+    std::fs::set_permissions(path, permissions).hint_path(path)
+}
+
+impl Tool<FsTools> for SetPermissions {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let path = state.resolve_path(&self.path, None)?;
+        let metadata = std::fs::metadata(&path).hint_path(&path)?;
+
+        let permissions = metadata.permissions();
+        let before = current_mode(&permissions);
+        let after = Self::apply_mode_spec(&self.mode, before, metadata.is_dir())?;
+
+        set_mode(&path, permissions, after)?;
+
+        let after = current_mode(&std::fs::metadata(&path)?.permissions());
+
+        let verbosity = state.settings(None)?.verbosity(self.verbosity);
+        if verbosity == Verbosity::Quiet {
+            return Ok(format!("{}: {}", path.display(), describe(after)));
+        }
+
+        let mut result = format!(
+            "Changed permissions of {} from {} to {}",
+            path.display(),
+            describe(before),
+            describe(after)
+        );
+
+        if verbosity == Verbosity::Detailed && metadata.is_file() && after & 0o111 != 0 {
+            // The mode was already changed, so a file that can no longer be read just gets no
+            // hint, e.g. after a mode like "100"
+            let mut start = Vec::with_capacity(512);
+            let read = std::fs::File::open(&path)
+                .and_then(|file| std::io::Read::take(file, 512).read_to_end(&mut start))
+                .is_ok();
+            let is_text = read && !start.is_empty() && !start.contains(&0);
+            if is_text && !start.starts_with(b"#!") {
+                result.push_str(
+                    "\nHint: this file is executable but does not start with a shebang line \
+                     (e.g. #!/usr/bin/env bash), so running it directly may use the wrong interpreter",
+                );
+            }
+        }
+
+        Ok(result)
+    }
+}
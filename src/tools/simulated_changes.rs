@@ -0,0 +1,95 @@
+use crate::{hints::Hinted, simulation::SimulatedChange, tools::FsTools};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+
+/// List the files that tools have added, modified, or deleted while fs-mcp is serving with
+/// `--simulate`, where changes only happen in an overlay and the real tree is never touched.
+/// Read-only
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "simulated_changes")]
+pub struct SimulatedChanges {
+    /// Include a unified diff of every modified or added text file
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub diff: Option<bool>,
+}
+
+impl WithExamples for SimulatedChanges {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Listing what a simulated run would have changed",
+                item: Self { diff: None },
+            },
+            Example {
+                description: "Reviewing the would-be changes in full",
+                item: Self { diff: Some(true) },
+            },
+        ]
+    }
+}
+
+impl Tool<FsTools> for SimulatedChanges {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let Some(overlay) = state.simulation() else {
+            return Err(Hinted::new(
+                "fs-mcp is not running a simulation, so every change was made to the real tree",
+                Some("start the server with `fs-mcp serve --simulate` to simulate changes".into()),
+            )
+            .into());
+        };
+
+        let changes = overlay.changes()?;
+        if changes.is_empty() {
+            return Ok("The simulation has not changed any files".into());
+        }
+
+        let mut output = match changes.len() {
+            1 => "The simulation changed 1 file:\n".to_string(),
+            count => format!("The simulation changed {count} files:\n"),
+        };
+        let mut diffs = String::new();
+        for change in &changes {
+            let (label, path) = match change {
+                SimulatedChange::Added(path) => ("added", path),
+                SimulatedChange::Modified(path) => ("modified", path),
+                SimulatedChange::Deleted(path) => ("deleted", path),
+            };
+            output.push_str(&format!("{label} {}\n", path.display()));
+
+            if !self.diff.unwrap_or_default() || label == "deleted" {
+                continue;
+            }
+            let before = match change {
+                SimulatedChange::Modified(_) => std::fs::read(path)?,
+                _ => vec![],
+            };
+            let after = std::fs::read(overlay.simulated(path))?;
+            let (Ok(before), Ok(after)) = (String::from_utf8(before), String::from_utf8(after))
+            else {
+                diffs.push_str(&format!("Binary file {} differs\n", path.display()));
+                continue;
+            };
+            let path = path.display().to_string();
+            diffs.push_str(
+                &TextDiff::from_lines(&before, &after)
+                    .unified_diff()
+                    .header(&path, &path)
+                    .to_string(),
+            );
+        }
+
+        if !diffs.is_empty() {
+            output.push('\n');
+            output.push_str(&diffs);
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,329 @@
+use crate::{
+    hints::{ErrorCode, Hint, Hinted},
+    tools::FsTools,
+};
+use anyhow::{Context, Result};
+use clap::ArgAction;
+use jiff::{
+    civil::DateTime,
+    tz::{Offset, TimeZone},
+};
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{Read as _, Seek, SeekFrom},
+    sync::LazyLock,
+    time::SystemTime,
+};
+
+/// Show the last lines of a log file, optionally only those matching a regex or written since a
+/// time, like `tail -n 50 app.log | grep ERROR` in one call. The file is read backwards from its
+/// end, so only as much of a large log is read as the answer needs. Read-only
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "tail_log")]
+pub struct TailLog {
+    /// Log file to read
+    /// Can be absolute, or relative to session context path.
+    pub path: String,
+
+    /// How many lines to return, counting only lines that pass the filters
+    /// Default: 50
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub lines: Option<usize>,
+
+    /// Only return lines matching this regex, e.g. `ERROR|WARN`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub pattern: Option<String>,
+
+    /// Whether `pattern` is case sensitive
+    /// Default: true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::Set)]
+    pub case_sensitive: Option<bool>,
+
+    /// Only return lines logged at or after this time: a timestamp like `2024-05-01T12:00:00`,
+    /// or a duration before now like "15m" or "2h". Lines are dated by the first
+    /// `YYYY-MM-DD HH:MM:SS` timestamp in them, and lines without one (such as stack traces) by
+    /// the line above. Timestamps with an offset like `Z` or `+02:00` are read in it, and those
+    /// without one as local time. Reading stops at the first line older than this, so the
+    /// log has to be in chronological order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub since: Option<String>,
+}
+
+impl WithExamples for TailLog {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Getting the last 50 errors of a log",
+                item: Self {
+                    path: "logs/app.log".into(),
+                    lines: None,
+                    pattern: Some("ERROR".into()),
+                    case_sensitive: None,
+                    since: None,
+                },
+            },
+            Example {
+                description: "Seeing what a service logged in the last ten minutes",
+                item: Self {
+                    path: "/var/log/service.log".into(),
+                    lines: Some(200),
+                    pattern: None,
+                    case_sensitive: None,
+                    since: Some("10m".into()),
+                },
+            },
+            Example {
+                description: "Finding warnings and errors since a deploy",
+                item: Self {
+                    path: "logs/app.log".into(),
+                    lines: Some(20),
+                    pattern: Some("warn|error".into()),
+                    case_sensitive: Some(false),
+                    since: Some("2024-05-01T12:00:00".into()),
+                },
+            },
+        ]
+    }
+}
+
+/// The log is read backwards in chunks of this many bytes
+const CHUNK_BYTES: u64 = 64 * 1024;
+
+/// Returned lines are cut to this many characters
+const MAX_LINE_CHARS: usize = 500;
+
+static TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?<time>\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(\.\d+)?)(\s?(?<offset>Z|[+-]\d{2}:?\d{2}))?",
+    )
+    .expect("valid regex")
+});
+
+/// The time a timestamp matched by [`TIMESTAMP`] stands for, in its offset if it has one and in
+/// local time otherwise
+fn parse_timestamp(captures: &regex::Captures<'_>) -> Option<SystemTime> {
+    let time: DateTime = captures["time"].parse().ok()?;
+    let zone = match captures.name("offset").map(|offset| offset.as_str()) {
+        None => TimeZone::system(),
+        Some("Z") => TimeZone::UTC,
+        Some(offset) => {
+            let digits = offset[1..].replace(':', "");
+            let seconds =
+                digits[..2].parse::<i32>().ok()? * 3600 + digits[2..].parse::<i32>().ok()? * 60;
+            let seconds = if offset.starts_with('-') {
+                -seconds
+            } else {
+                seconds
+            };
+            TimeZone::fixed(Offset::from_seconds(seconds).ok()?)
+        }
+    };
+    Some(time.to_zoned(zone).ok()?.timestamp().into())
+}
+
+/// The time a log line was written, from the first timestamp in it
+fn timestamp(line: &str) -> Option<SystemTime> {
+    parse_timestamp(&TIMESTAMP.captures(line)?)
+}
+
+/// Filters lines visited from the end of the log, keeping the newest ones first
+struct Tail {
+    lines: usize,
+    pattern: Option<Regex>,
+    since: Option<SystemTime>,
+    /// Matching lines, newest first
+    kept: Vec<String>,
+    /// Matching lines without a timestamp, newest first, kept once the line above them shows
+    /// they are recent enough. Only as many as could still be returned are held
+    undated: Vec<String>,
+    /// Whether any line had a timestamp
+    dated: bool,
+}
+
+impl Tail {
+    /// Consider the line before the ones visited so far, returning whether to keep going
+    fn visit(&mut self, line: &[u8]) -> bool {
+        let line = String::from_utf8_lossy(line);
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+        let matches = self
+            .pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(line));
+        let Some(since) = self.since else {
+            if matches {
+                self.kept.push(line.to_string());
+            }
+            return self.kept.len() < self.lines;
+        };
+
+        match timestamp(line) {
+            Some(time) => {
+                self.dated = true;
+                if time < since {
+                    self.undated.clear();
+                    return false;
+                }
+                self.kept.append(&mut self.undated);
+                if matches {
+                    self.kept.push(line.to_string());
+                }
+            }
+            None if matches && self.kept.len() + self.undated.len() < self.lines => {
+                self.undated.push(line.to_string());
+            }
+            None => {}
+        }
+        self.kept.len() < self.lines
+    }
+
+    /// The kept lines, oldest first
+    fn finish(mut self) -> Vec<String> {
+        // Lines above the first timestamp of the log can't be dated, so they are kept
+        self.kept.append(&mut self.undated);
+        self.kept.truncate(self.lines);
+        self.kept.reverse();
+        self.kept
+    }
+}
+
+impl TailLog {
+    fn since(&self) -> Result<Option<SystemTime>> {
+        let Some(since) = &self.since else {
+            return Ok(None);
+        };
+        if let Ok(duration) = humantime::parse_duration(since) {
+            return Ok(SystemTime::now().checked_sub(duration));
+        }
+        TIMESTAMP
+            .captures(since)
+            .filter(|captures| captures[0].len() == since.len())
+            .and_then(|captures| parse_timestamp(&captures))
+            .map(Some)
+            .ok_or_else(|| {
+                Hinted::new(
+                format!("Invalid `since`: {since}"),
+                Some(
+                    "use a timestamp like \"2024-05-01T12:00:00\" or \"2024-05-01T12:00:00Z\", \
+                     or a duration like \"15m\""
+                        .into(),
+                ),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into()
+            })
+    }
+}
+
+impl Tool<FsTools> for TailLog {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let path = state.resolve_path(&self.path, None)?;
+        if path.is_dir() {
+            return Err(Hinted::new(
+                format!("{} is a directory", path.display()),
+                Some("use recent_files to find the newest log in it".into()),
+            )
+            .with_code(ErrorCode::IsADirectory)
+            .with_path(&path)
+            .into());
+        }
+        let pattern = self
+            .pattern
+            .as_deref()
+            .map(|pattern| {
+                RegexBuilder::new(pattern)
+                    .case_insensitive(!self.case_sensitive.unwrap_or(true))
+                    .build()
+                    .context("Invalid regex pattern")
+            })
+            .transpose()?;
+        let mut tail = Tail {
+            lines: self.lines.unwrap_or(50),
+            pattern,
+            since: self.since()?,
+            kept: vec![],
+            undated: vec![],
+            dated: false,
+        };
+
+        let mut file = File::open(&path).hint_path(&path)?;
+        let size = file.metadata().hint_path(&path)?.len();
+        // The end of the last line, leaving out the newline that ends the file
+        let mut end = size;
+        if size > 0 {
+            let mut last = [0];
+            file.seek(SeekFrom::Start(size - 1)).hint_path(&path)?;
+            file.read_exact(&mut last).hint_path(&path)?;
+            if last[0] == b'\n' {
+                end -= 1;
+            }
+        }
+
+        // The start of the line that continues past the chunk read last
+        let mut partial = vec![];
+        let mut position = end;
+        let reached_start = loop {
+            if position == 0 {
+                if end > 0 {
+                    tail.visit(&partial);
+                }
+                break true;
+            }
+            let start = position.saturating_sub(CHUNK_BYTES);
+            let mut chunk = vec![0; (position - start) as usize];
+            file.seek(SeekFrom::Start(start)).hint_path(&path)?;
+            file.read_exact(&mut chunk).hint_path(&path)?;
+            chunk.append(&mut partial);
+            position = start;
+
+            let mut lines = chunk.split(|byte| *byte == b'\n');
+            let first = lines.next().unwrap_or_default().to_vec();
+            if !lines.rev().all(|line| tail.visit(line)) {
+                break false;
+            }
+            partial = first;
+        };
+
+        let lines = tail.lines;
+        let dated = tail.dated;
+        let kept = tail.finish();
+        let settings = state.settings(None)?;
+        let mut output = format!("{}: the last {} lines", path.display(), kept.len());
+        if let Some(pattern) = &self.pattern {
+            output.push_str(&format!(" matching `{pattern}`"));
+        }
+        if let Some(since) = &self.since {
+            output.push_str(&format!(" since {since}"));
+        }
+        output.push_str(&format!(
+            ", from the last {} of {}",
+            settings.format_size(size - position),
+            settings.format_size(size)
+        ));
+        if reached_start && kept.len() < lines {
+            output.push_str(" (the whole file)");
+        }
+        output.push_str("\n\n");
+        for line in &kept {
+            match line.char_indices().nth(MAX_LINE_CHARS) {
+                Some((index, _)) => output.push_str(&format!("{}…\n", &line[..index])),
+                None => output.push_str(&format!("{line}\n")),
+            }
+        }
+        if self.since.is_some() && !dated {
+            output.push_str(
+                "\nNo line had a `YYYY-MM-DD HH:MM:SS` timestamp, so `since` filtered nothing out\n",
+            );
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{quota::QuotaUsage, settings::Verbosity, tools::FsTools};
+use anyhow::{Context, Result};
+use clap::ArgAction;
+use filetime::FileTime;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs::OpenOptions, time::SystemTime};
+
+/// Create an empty file, or update the access and modification times of an existing file
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "touch")]
+pub struct Touch {
+    /// Path to touch
+    /// Can be absolute, or relative to session context path.
+    pub path: String,
+
+    /// Explicit timestamp to set as both access and modification time, in RFC 3339 format
+    /// (e.g. "2024-01-15T09:30:00Z").
+    ///
+    /// Default: now
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub timestamp: Option<String>,
+
+    /// Create any directories leading up to this file if they don't already exist.
+    ///
+    /// Default: the session's `auto_create_directories` preference (true unless changed with
+    /// configure_session)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub create_directories: Option<bool>,
+
+    /// Response detail: "quiet" names the path, "normal" includes the timestamp that was set,
+    /// and "detailed" also includes the previous modification time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub verbosity: Option<Verbosity>,
+}
+
+impl WithExamples for Touch {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Creating an empty marker file relative to a session",
+                item: Self {
+                    path: "src/generated/.keep".into(),
+                    timestamp: None,
+                    create_directories: Some(true),
+                    verbosity: None,
+                },
+            },
+            Example {
+                description: "Backdating a file so a build system considers it stale",
+                item: Self {
+                    path: "/some/absolute/path/build.rs".into(),
+                    timestamp: Some("2020-01-01T00:00:00Z".into()),
+                    create_directories: None,
+                    verbosity: None,
+                },
+            },
+        ]
+    }
+}
+
+impl Touch {
+    fn timestamp(&self) -> Result<SystemTime> {
+        match &self.timestamp {
+            Some(timestamp) => humantime::parse_rfc3339_weak(timestamp)
+                .with_context(|| format!("Invalid RFC 3339 timestamp: {timestamp}")),
+            None => Ok(SystemTime::now()),
+        }
+    }
+}
+
+impl Tool<FsTools> for Touch {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let path = state.resolve_new_path(&self.path, None)?;
+        let time = self.timestamp()?;
+
+        let previous_modified = std::fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let created = previous_modified.is_none() && !path.exists();
+        if created {
+            state.charge_quota(QuotaUsage::created(1))?;
+            if state
+                .settings(None)?
+                .create_directories(self.create_directories)
+            {
+                if let Some(parent_dir) = path.parent() {
+                    std::fs::create_dir_all(parent_dir).with_context(|| {
+                        format!("Failed to create directories for {}", parent_dir.display())
+                    })?;
+                }
+            }
+
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+        }
+
+        let file_time = FileTime::from_system_time(time);
+        filetime::set_file_times(&path, file_time, file_time)
+            .with_context(|| format!("Failed to set times on {}", path.display()))?;
+
+        let settings = state.settings(None)?;
+        let verbosity = settings.verbosity(self.verbosity);
+        if verbosity == Verbosity::Quiet {
+            return Ok(format!("Touched {}", path.display()));
+        }
+
+        let timestamp = humantime::format_rfc3339_seconds(time);
+        let mut result = if created {
+            format!("Created {} with timestamp {timestamp}", path.display())
+        } else {
+            format!("Updated timestamps of {} to {timestamp}", path.display())
+        };
+
+        if verbosity == Verbosity::Detailed {
+            if let Some(previous_modified) = previous_modified {
+                result.push_str(&format!(
+                    " (previously modified {})",
+                    settings.timestamp_formatter().format(previous_modified)
+                ));
+            }
+        }
+
+        Ok(result)
+    }
+}
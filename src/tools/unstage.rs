@@ -0,0 +1,110 @@
+use super::delete::{describe_staged, select_staged};
+use crate::{
+    hints::{Hint, Hinted},
+    quota::QuotaUsage,
+    tools::FsTools,
+};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+
+/// Restore files staged by `delete` with `stage` to their original locations
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "unstage")]
+pub struct Unstage {
+    /// Ids of the staged files to restore, as reported by delete. Omit to list staged files
+    /// without restoring anything
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub ids: Option<Vec<String>>,
+
+    /// Replace a file that has since been created at the original location
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub overwrite: Option<bool>,
+}
+
+impl WithExamples for Unstage {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Reviewing which files are staged for deletion",
+                item: Self {
+                    ids: None,
+                    overwrite: None,
+                },
+            },
+            Example {
+                description: "Restoring a file that was staged by mistake",
+                item: Self {
+                    ids: Some(vec!["k3f9xq".into()]),
+                    overwrite: None,
+                },
+            },
+        ]
+    }
+}
+
+impl Tool<FsTools> for Unstage {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let settings = state.settings(None)?;
+        let staged = state.staged(None)?;
+
+        let Some(ids) = &self.ids else {
+            if staged.is_empty() {
+                return Ok("No files are staged".into());
+            }
+            let mut output = format!("{} staged files:\n", staged.len());
+            for entry in &staged {
+                output.push_str(&describe_staged(entry, &settings));
+                output.push('\n');
+            }
+            return Ok(output);
+        };
+
+        let selected = select_staged(&staged, Some(ids))?;
+        if !self.overwrite.unwrap_or_default() {
+            if let Some(entry) = selected.iter().find(|entry| entry.original.exists()) {
+                return Err(Hinted::new(
+                    format!(
+                        "{} exists again, so {} was not restored. Nothing has been restored",
+                        entry.original.display(),
+                        entry.id
+                    ),
+                    Some("use `overwrite` to replace it with the staged file".into()),
+                )
+                .into());
+            }
+        }
+
+        let mut usage = QuotaUsage::default();
+        for entry in &selected {
+            usage.bytes_written += super::r#move::relocation_bytes(&entry.staged, &entry.original)
+                .hint_path(&entry.staged)?;
+            usage.files_created += u64::from(!entry.original.exists());
+        }
+        state.charge_quota(usage)?;
+
+        let mut output = String::new();
+        for entry in &selected {
+            if let Some(parent) = entry.original.parent() {
+                std::fs::create_dir_all(parent).hint_path(parent)?;
+            }
+            super::r#move::relocate(&entry.staged, &entry.original).hint_path(&entry.staged)?;
+            if let Some(staging_dir) = entry.staged.parent() {
+                let _ = std::fs::remove_dir(staging_dir);
+            }
+            state.update_staged(None, |staged| staged.retain(|e| e.id != entry.id))?;
+            output.push_str(&format!("Restored {}\n", entry.original.display()));
+        }
+
+        let remaining = state.staged(None)?.len();
+        output.push_str(&format!("{remaining} files remain staged"));
+        Ok(output)
+    }
+}
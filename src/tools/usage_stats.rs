@@ -0,0 +1,118 @@
+use crate::{hints::Hinted, tools::FsTools};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+
+/// Responses are estimated at this many bytes per token, which is close for English text and
+/// code
+const BYTES_PER_TOKEN: u64 = 4;
+
+/// Show how often each tool has been called in a session and how much response text it has
+/// returned, with an estimate in tokens, to find the calls that use up the most context. Large
+/// totals can often be brought down with options like `max_results` or `verbosity: "quiet"`
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "usage_stats")]
+pub struct UsageStats {
+    /// Session to show. Defaults to the current session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+
+    /// Clear the statistics after showing them, to measure from here on
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub reset: Option<bool>,
+}
+
+impl WithExamples for UsageStats {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Seeing which tools have returned the most text",
+                item: Self {
+                    session: None,
+                    reset: None,
+                },
+            },
+            Example {
+                description: "Starting a fresh measurement before trying smaller defaults",
+                item: Self {
+                    session: None,
+                    reset: Some(true),
+                },
+            },
+        ]
+    }
+}
+
+impl Tool<FsTools> for UsageStats {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let session = self
+            .session
+            .as_deref()
+            .unwrap_or_else(|| state.default_session_id());
+        let Some(usage) = state.usage(session)? else {
+            return Err(Hinted::new(
+                format!("There is no session named {session}"),
+                Some("check the name passed to create_child_session or FS_MCP_SESSION".into()),
+            )
+            .into());
+        };
+
+        if self.reset.unwrap_or_default() {
+            state.reset_usage(session)?;
+        }
+
+        if usage.is_empty() {
+            return Ok(format!("Session {session} has no recorded tool calls"));
+        }
+
+        let settings = state.settings(None)?;
+        let mut usage = usage.into_iter().collect::<Vec<_>>();
+        usage.sort_by(|(a_name, a), (b_name, b)| {
+            b.response_bytes
+                .cmp(&a.response_bytes)
+                .then_with(|| a_name.cmp(b_name))
+        });
+
+        let total_bytes = usage
+            .iter()
+            .map(|(_, usage)| usage.response_bytes)
+            .sum::<u64>();
+        let total_calls = usage.iter().map(|(_, usage)| usage.calls).sum::<u64>();
+        let calls = |count: u64| match count {
+            1 => "1 call".to_string(),
+            count => format!("{count} calls"),
+        };
+        let mut output = format!(
+            "{} in session {session} returned {} (~{} tokens), largest first:\n",
+            calls(total_calls),
+            settings.format_size(total_bytes),
+            total_bytes / BYTES_PER_TOKEN
+        );
+        for (tool, usage) in &usage {
+            output.push_str(&format!(
+                "{tool}: {}, {} (~{} tokens), {} on average, {} at most",
+                calls(usage.calls),
+                settings.format_size(usage.response_bytes),
+                usage.response_bytes / BYTES_PER_TOKEN,
+                settings.format_size(usage.response_bytes / usage.calls.max(1)),
+                settings.format_size(usage.largest_response),
+            ));
+            if usage.errors > 0 {
+                output.push_str(&format!(", {} failed", usage.errors));
+            }
+            output.push('\n');
+        }
+
+        if self.reset.unwrap_or_default() {
+            output.push_str("\nThe statistics have been reset");
+        }
+
+        Ok(output)
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{git, hints, tools::FsTools};
+use anyhow::Result;
+use clap::ArgAction;
+use mcplease::{
+    traits::{Tool, WithExamples},
+    types::Example,
+};
+use serde::{Deserialize, Serialize};
+
+/// Show the git status of the repository containing the working directory: the staged, unstaged,
+/// untracked, and conflicted files, relative to the working directory. Read-only
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(rename = "vcs_status")]
+pub struct VcsStatus {
+    /// Only report files under this path
+    /// Can be absolute, or relative to session context path.
+    /// Defaults to the whole repository if not provided
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// Leave out untracked files
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub exclude_untracked: Option<bool>,
+}
+
+impl WithExamples for VcsStatus {
+    fn examples() -> Vec<Example<Self>> {
+        vec![
+            Example {
+                description: "Seeing everything changed so far in the current repository",
+                item: Self {
+                    path: None,
+                    exclude_untracked: None,
+                },
+            },
+            Example {
+                description: "Checking only the tracked files changed in one directory",
+                item: Self {
+                    path: Some("src/tools".into()),
+                    exclude_untracked: Some(true),
+                },
+            },
+        ]
+    }
+}
+
+/// One path reported by `git status --porcelain`
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct StatusEntry {
+    pub staged: char,
+    pub unstaged: char,
+    pub path: String,
+    /// The previous path of a rename or copy
+    pub from: Option<String>,
+}
+
+impl StatusEntry {
+    fn is_untracked(&self) -> bool {
+        self.staged == '?'
+    }
+
+    fn is_conflicted(&self) -> bool {
+        self.staged == 'U'
+            || self.unstaged == 'U'
+            || (self.staged, self.unstaged) == ('A', 'A')
+            || (self.staged, self.unstaged) == ('D', 'D')
+    }
+
+    fn describe(code: char) -> &'static str {
+        match code {
+            'M' => "modified",
+            'A' => "added",
+            'D' => "deleted",
+            'R' => "renamed",
+            'C' => "copied",
+            'T' => "type changed",
+            _ => "changed",
+        }
+    }
+}
+
+impl VcsStatus {
+    /// The branch line and entries of `git status --porcelain=v1 --branch -z`
+    pub(crate) fn parse(porcelain: &str) -> (Option<String>, Vec<StatusEntry>) {
+        let mut branch = None;
+        let mut entries = vec![];
+        let mut records = porcelain.split('\0').filter(|record| !record.is_empty());
+        while let Some(record) = records.next() {
+            if let Some(rest) = record.strip_prefix("## ") {
+                branch = Some(rest.to_string());
+                continue;
+            }
+            let mut chars = record.chars();
+            let (Some(staged), Some(unstaged)) = (chars.next(), chars.next()) else {
+                continue;
+            };
+            let path = record.get(3..).unwrap_or_default().to_string();
+            let from = if matches!(staged, 'R' | 'C') || matches!(unstaged, 'R' | 'C') {
+                records.next().map(String::from)
+            } else {
+                None
+            };
+            entries.push(StatusEntry {
+                staged,
+                unstaged,
+                path,
+                from,
+            });
+        }
+        (branch, entries)
+    }
+}
+
+impl Tool<FsTools> for VcsStatus {
+    fn execute(self, state: &mut FsTools) -> Result<String> {
+        let scope = match &self.path {
+            Some(path) => Some(state.resolve_path(path, None)?),
+            None => None,
+        };
+        if let Some(scope) = &scope {
+            if !scope.exists() {
+                return Err(hints::not_found(scope));
+            }
+        }
+        let base = match (&scope, state.resolve_path(".", None)) {
+            (_, Ok(working_directory)) => working_directory,
+            (Some(scope), Err(_)) => git::directory_for(scope).to_path_buf(),
+            (None, Err(error)) => return Err(error),
+        };
+        let directory = git::directory_for(scope.as_deref().unwrap_or(&base));
+        let root = git::toplevel(directory)?;
+
+        let mut args = vec!["status", "--porcelain=v1", "--branch", "-z"];
+        if self.exclude_untracked.unwrap_or_default() {
+            args.push("--untracked-files=no");
+        }
+        let scope_arg = scope
+            .as_ref()
+            .map(|scope| scope.to_string_lossy().into_owned());
+        if let Some(scope_arg) = &scope_arg {
+            args.extend(["--", scope_arg]);
+        }
+        let (branch, entries) = Self::parse(&git::run(directory, &args)?);
+
+        let display = |path: &str| {
+            let full = root.join(path);
+            let relative = pathdiff::diff_paths(&full, &base).unwrap_or(full);
+            relative.display().to_string()
+        };
+        let line = |code: char, entry: &StatusEntry| match &entry.from {
+            Some(from) => format!(
+                "  {:<12} {} -> {}\n",
+                StatusEntry::describe(code),
+                display(from),
+                display(&entry.path)
+            ),
+            None => format!(
+                "  {:<12} {}\n",
+                StatusEntry::describe(code),
+                display(&entry.path)
+            ),
+        };
+
+        let mut conflicted = String::new();
+        let mut staged = String::new();
+        let mut unstaged = String::new();
+        let mut untracked = String::new();
+        let mut counts = [0; 4];
+        for entry in &entries {
+            if entry.is_untracked() {
+                untracked.push_str(&format!("  {}\n", display(&entry.path)));
+                counts[3] += 1;
+            } else if entry.is_conflicted() {
+                conflicted.push_str(&format!("  {}\n", display(&entry.path)));
+                counts[0] += 1;
+            } else {
+                if !matches!(entry.staged, ' ' | '!') {
+                    staged.push_str(&line(entry.staged, entry));
+                    counts[1] += 1;
+                }
+                if !matches!(entry.unstaged, ' ' | '!') {
+                    unstaged.push_str(&line(entry.unstaged, entry));
+                    counts[2] += 1;
+                }
+            }
+        }
+
+        let mut output = String::new();
+        if let Some(branch) = branch {
+            output.push_str(&format!("Branch: {branch}\n"));
+        }
+        output.push_str(&format!(
+            "Repository: {}, paths relative to {}\n",
+            root.display(),
+            base.display()
+        ));
+
+        if entries.is_empty() {
+            let under = match &scope {
+                Some(scope) => format!(" under {}", scope.display()),
+                None => String::new(),
+            };
+            output.push_str(&format!("\nNo changes{under}\n"));
+            return Ok(output);
+        }
+
+        let sections = [
+            ("Conflicted", conflicted),
+            ("Staged", staged),
+            ("Not staged", unstaged),
+            ("Untracked", untracked),
+        ];
+        for ((heading, section), count) in sections.into_iter().zip(counts) {
+            if count > 0 {
+                output.push_str(&format!("\n{heading} ({count}):\n{section}"));
+            }
+        }
+        Ok(output)
+    }
+}
@@ -1,18 +1,26 @@
-use crate::tools::FsTools;
-use anyhow::{Context, Result, bail};
+use crate::{
+    config::Config,
+    hints::{self, ErrorCode, Hint, Hinted},
+    quota::QuotaUsage,
+    settings::Verbosity,
+    tools::FsTools,
+};
+use anyhow::{Context, Result};
 use clap::ArgAction;
 use mcplease::{
     traits::{Tool, WithExamples},
     types::Example,
 };
 use serde::{Deserialize, Serialize};
-use size::Size;
+use similar::TextDiff;
 use std::{
     fs::{self, OpenOptions},
     io::{ErrorKind, Write as _},
+    path::{Path, PathBuf},
 };
 
-/// Write contents to a file, optionally creating any directories needed
+/// Write contents to a file, optionally creating any directories needed. Binary files can be
+/// written from base64 with `content_encoding: "base64"`
 ///
 /// Usage recommendation: For very large files, you may want to use multiple append operations
 /// if you encounter interruption issues.
@@ -27,6 +35,14 @@ pub struct Write {
     /// next chunk to write.
     pub contents: String,
 
+    /// How `contents` is encoded: "utf8" text, or "base64" for binary files such as images and
+    /// compiled fixtures, which would be corrupted as utf8. Line-based options (`insert_at_line`,
+    /// `extract_code_fence`, `dedent`, `strip_prefix`, `tidy`) only apply to utf8
+    /// Default: utf8
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub content_encoding: Option<ContentEncoding>,
+
     /// Overwrite file if it exists
     ///
     /// Only use if you have recently read the file and intend to replace it.
@@ -52,12 +68,161 @@ pub struct Write {
     /// This allows you to write files at arbitrary paths without needing a distinct interface or
     /// tool call for creating empty directories.
     ///
-    /// Default: true
+    /// Default: the session's `auto_create_directories` preference (true unless changed with
+    /// configure_session)
     #[serde(skip_serializing_if = "Option::is_none")]
     #[arg(long, action = ArgAction::SetTrue)]
     pub create_directories: Option<bool>,
+
+    /// Insert `contents` into an existing file so that it begins at this 1-based line number,
+    /// shifting the existing line and everything after it down. Use the file's line count plus
+    /// one to insert after the last line. Mutually exclusive with `overwrite` and `append`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub insert_at_line: Option<usize>,
+
+    /// When appending, rotate the file first if the append would take it past this many bytes.
+    /// The existing file is renamed with a numeric suffix (`out.log` to `out.log.1`, shifting
+    /// older rotations to `.2`, `.3`, ...) and the contents are written to a fresh file.
+    /// Only valid with `append`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub rotate_at_bytes: Option<u64>,
+
+    /// With `rotate_at_bytes`, how many rotated files to keep. Older ones are deleted when the
+    /// file rotates, so that a long-running log stays within about `keep_rotated + 1` times
+    /// `rotate_at_bytes`
+    /// Default: all of them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub keep_rotated: Option<usize>,
+
+    /// If `contents` is entirely wrapped in a Markdown code fence (```` ```rust ... ``` ````),
+    /// remove the fence and language tag before writing
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub extract_code_fence: Option<bool>,
+
+    /// Remove indentation common to every non-blank line of `contents` before writing, for
+    /// contents that were indented inside surrounding markup
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub dedent: Option<bool>,
+
+    /// A sentinel prefix (e.g. "| ") to strip from the start of each line of `contents` that
+    /// begins with it, applied after `dedent`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub strip_prefix: Option<String>,
+
+    /// Remove a byte order mark (U+FEFF) from the start of `contents`. BOMs are invisible, so
+    /// one copied from another file would otherwise end up in this one unnoticed
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub strip_bom: Option<bool>,
+
+    /// End `contents` with a newline if it doesn't already, to avoid "no newline at end of
+    /// file" diffs
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub ensure_trailing_newline: Option<bool>,
+
+    /// Trim trailing whitespace from every line of `contents` and end it with exactly one
+    /// newline, so that edits don't introduce whitespace-only diff noise
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub tidy: Option<bool>,
+
+    /// Line endings for `contents`: "preserve" converts them to match the existing file when
+    /// appending, inserting, or overwriting, so that e.g. a Windows project keeps its CRLF
+    /// files consistent; "lf" or "crlf" convert them to that style regardless, which can also
+    /// be used with `overwrite` to convert a whole file. Only applies to utf8
+    /// Default: preserve
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub line_endings: Option<LineEndings>,
+
+    /// Allow writing to a file that matches one of the session's generated file patterns
+    /// (lockfiles and generated code, see configure_session). These are almost always meant to
+    /// be regenerated by their tool rather than edited by hand
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub allow_generated: Option<bool>,
+
+    /// Write even if the path goes through a symlink that points outside of the working
+    /// directory. Without it, such writes are refused, so that files outside of the project
+    /// aren't changed unknowingly
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub follow_symlinks: Option<bool>,
+
+    /// Skip the formatter that the fs-mcp config file runs after writes to files with this
+    /// extension, e.g. to write deliberately unformatted test fixtures
+    /// Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub skip_format: Option<bool>,
+
+    /// Response detail: "quiet" confirms the path and byte count, "normal" adds the total file
+    /// size and, when appending, the lines around the append point, and "detailed" adds a
+    /// unified diff when overwriting and the resulting line count
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub verbosity: Option<Verbosity>,
+}
+
+/// How the `contents` of a write are encoded
+#[derive(
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    schemars::JsonSchema,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentEncoding {
+    #[default]
+    Utf8,
+    Base64,
 }
 
+/// How the line endings of a write's `contents` are treated
+#[derive(
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    schemars::JsonSchema,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEndings {
+    /// Match the existing file, if there is one with line breaks
+    #[default]
+    Preserve,
+    Lf,
+    Crlf,
+}
+
+/// How much of an existing file is read to detect its line endings
+const LINE_ENDING_SAMPLE_BYTES: u64 = 64 * 1024;
+
 impl WithExamples for Write {
     fn examples() -> Vec<Example<Self>> {
         vec![
@@ -66,9 +231,24 @@ impl WithExamples for Write {
                 item: Self {
                     path: "src/main.rs".into(),
                     contents: "#[main]\nfn main() {\n  todo!()\n }".into(),
+                    content_encoding: None,
                     overwrite: None,
                     create_directories: None,
                     append: None,
+                    insert_at_line: None,
+                    rotate_at_bytes: None,
+                    keep_rotated: None,
+                    extract_code_fence: None,
+                    dedent: None,
+                    strip_prefix: None,
+                    strip_bom: None,
+                    ensure_trailing_newline: None,
+                    tidy: None,
+                    line_endings: None,
+                    allow_generated: None,
+                    follow_symlinks: None,
+                    skip_format: None,
+                    verbosity: None,
                 },
             },
             Example {
@@ -76,9 +256,24 @@ impl WithExamples for Write {
                 item: Self {
                     path: "/some/absolute/path/src/main.rs".into(),
                     contents: "#[main]\nfn main() {\n  todo!()\n }".into(),
+                    content_encoding: None,
                     overwrite: Some(true),
                     create_directories: Some(false),
                     append: None,
+                    insert_at_line: None,
+                    rotate_at_bytes: None,
+                    keep_rotated: None,
+                    extract_code_fence: None,
+                    dedent: None,
+                    strip_prefix: None,
+                    strip_bom: None,
+                    ensure_trailing_newline: None,
+                    tidy: None,
+                    line_endings: None,
+                    allow_generated: None,
+                    follow_symlinks: None,
+                    skip_format: None,
+                    verbosity: None,
                 },
             },
             Example {
@@ -86,9 +281,125 @@ impl WithExamples for Write {
                 item: Self {
                     path: "/some/absolute/path/tests/tests.rs".into(),
                     contents: "\n\n#[test]\nfn another_test() {\n  assert!(true)\n }".into(),
+                    content_encoding: None,
+                    overwrite: None,
+                    create_directories: None,
+                    append: Some(true),
+                    insert_at_line: None,
+                    rotate_at_bytes: None,
+                    keep_rotated: None,
+                    extract_code_fence: None,
+                    dedent: None,
+                    strip_prefix: None,
+                    strip_bom: None,
+                    ensure_trailing_newline: None,
+                    tidy: None,
+                    line_endings: None,
+                    allow_generated: None,
+                    follow_symlinks: None,
+                    skip_format: None,
+                    verbosity: None,
+                },
+            },
+            Example {
+                description: "Adding an import as the third line of a file",
+                item: Self {
+                    path: "src/lib.rs".into(),
+                    contents: "use std::collections::HashMap;\n".into(),
+                    content_encoding: None,
+                    overwrite: None,
+                    create_directories: None,
+                    append: None,
+                    insert_at_line: Some(3),
+                    rotate_at_bytes: None,
+                    keep_rotated: None,
+                    extract_code_fence: None,
+                    dedent: None,
+                    strip_prefix: None,
+                    strip_bom: None,
+                    ensure_trailing_newline: None,
+                    tidy: None,
+                    line_endings: None,
+                    allow_generated: None,
+                    follow_symlinks: None,
+                    skip_format: None,
+                    verbosity: None,
+                },
+            },
+            Example {
+                description: "Accumulating long-running output, rolling over at 1MiB and keeping \
+                              the last five rotations",
+                item: Self {
+                    path: "logs/build.log".into(),
+                    contents: "step 3 of 10 complete\n".into(),
+                    content_encoding: None,
                     overwrite: None,
                     create_directories: None,
                     append: Some(true),
+                    insert_at_line: None,
+                    rotate_at_bytes: Some(1024 * 1024),
+                    keep_rotated: Some(5),
+                    extract_code_fence: None,
+                    dedent: None,
+                    strip_prefix: None,
+                    strip_bom: None,
+                    ensure_trailing_newline: None,
+                    tidy: None,
+                    line_endings: None,
+                    allow_generated: None,
+                    follow_symlinks: None,
+                    skip_format: None,
+                    verbosity: None,
+                },
+            },
+            Example {
+                description: "Writing contents that were indented inside a Markdown list",
+                item: Self {
+                    path: "config/settings.toml".into(),
+                    contents: "    [server]\n    port = 8080\n".into(),
+                    content_encoding: None,
+                    overwrite: None,
+                    create_directories: None,
+                    append: None,
+                    insert_at_line: None,
+                    rotate_at_bytes: None,
+                    keep_rotated: None,
+                    extract_code_fence: None,
+                    dedent: Some(true),
+                    strip_prefix: None,
+                    strip_bom: None,
+                    ensure_trailing_newline: None,
+                    tidy: None,
+                    line_endings: None,
+                    allow_generated: None,
+                    follow_symlinks: None,
+                    skip_format: None,
+                    verbosity: None,
+                },
+            },
+            Example {
+                description: "Creating a binary file from base64",
+                item: Self {
+                    path: "tests/fixtures/pixel.gif".into(),
+                    contents: "R0lGODlhAQABAIAAAAAAAP///yH5BAEAAAAALAAAAAABAAEAAAIBRAA7".into(),
+                    content_encoding: Some(ContentEncoding::Base64),
+                    overwrite: None,
+                    create_directories: None,
+                    append: None,
+                    insert_at_line: None,
+                    rotate_at_bytes: None,
+                    keep_rotated: None,
+                    extract_code_fence: None,
+                    dedent: None,
+                    strip_prefix: None,
+                    strip_bom: None,
+                    ensure_trailing_newline: None,
+                    tidy: None,
+                    line_endings: None,
+                    allow_generated: None,
+                    follow_symlinks: None,
+                    skip_format: None,
+                    verbosity: None,
                 },
             },
         ]
@@ -104,11 +415,401 @@ impl Write {
         self.append.unwrap_or_default()
     }
 
-    fn create_directories(&self) -> bool {
-        self.create_directories.unwrap_or(true)
+    fn base64(&self) -> bool {
+        self.content_encoding.unwrap_or_default() == ContentEncoding::Base64
+    }
+
+    /// Decode base64 `contents`, ignoring whitespace so that wrapped lines are accepted
+    pub(crate) fn decode_base64(contents: &str) -> Result<Vec<u8>> {
+        use base64::Engine;
+        let compact = contents
+            .chars()
+            .filter(|c| !c.is_ascii_whitespace())
+            .collect::<String>();
+        base64::engine::general_purpose::STANDARD
+            .decode(compact)
+            .map_err(|error| {
+                Hinted::new(
+                    format!("contents are not valid base64: {error}"),
+                    Some(
+                        "use standard base64 with padding, or `content_encoding: \"utf8\"` for \
+                         text"
+                            .into(),
+                    ),
+                )
+                .with_code(ErrorCode::InvalidArgument)
+                .into()
+            })
+    }
+
+    fn extract_code_fence(&self) -> bool {
+        self.extract_code_fence.unwrap_or_default()
+    }
+
+    /// If `contents` is wrapped in a Markdown code fence, return what is inside it along with
+    /// the language tag, if any
+    pub(crate) fn strip_code_fence(contents: &str) -> Option<(String, Option<String>)> {
+        let trimmed = contents.trim();
+        let fence_char = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+        let fence_len = trimmed.chars().take_while(|c| *c == fence_char).count();
+        if fence_len < 3 {
+            return None;
+        }
+        let fence = &trimmed[..fence_len];
+
+        let (opening, rest) = trimmed.split_once('\n')?;
+        let inner = rest.trim_end().strip_suffix(fence)?;
+        if !inner.is_empty() && !inner.ends_with('\n') {
+            return None;
+        }
+
+        let language = opening[fence_len..].trim();
+        if language.contains(fence_char) {
+            return None;
+        }
+
+        Some((
+            inner.to_string(),
+            (!language.is_empty()).then(|| language.to_string()),
+        ))
+    }
+
+    fn tidy(&self) -> bool {
+        self.tidy.unwrap_or_default()
+    }
+
+    /// Trim trailing spaces and tabs from each line and end with exactly one newline, keeping
+    /// `\r\n` line endings intact. Returns the tidied text and the number of lines changed
+    pub(crate) fn tidy_contents(contents: &str) -> (String, usize) {
+        if contents.is_empty() {
+            return (String::new(), 0);
+        }
+
+        let mut adjusted = 0;
+        let mut tidied = String::with_capacity(contents.len() + 1);
+        let mut last_ending = "\n";
+        for line in contents.split_inclusive('\n') {
+            let (text, ending) = if let Some(text) = line.strip_suffix("\r\n") {
+                (text, "\r\n")
+            } else if let Some(text) = line.strip_suffix('\n') {
+                (text, "\n")
+            } else {
+                (line, "")
+            };
+            let trimmed = text.trim_end_matches([' ', '\t']);
+            if trimmed.len() != text.len() {
+                adjusted += 1;
+            }
+            if !ending.is_empty() {
+                last_ending = ending;
+            }
+            tidied.push_str(trimmed);
+            tidied.push_str(ending);
+        }
+
+        let content_end = tidied.trim_end_matches(['\r', '\n']).len();
+        let final_newline_changed = tidied[content_end..] != *last_ending;
+        tidied.truncate(content_end);
+        tidied.push_str(last_ending);
+        if final_newline_changed {
+            adjusted += 1;
+        }
+
+        (tidied, adjusted)
+    }
+
+    fn dedent(&self) -> bool {
+        self.dedent.unwrap_or_default()
+    }
+
+    /// Remove the longest run of leading whitespace shared by every non-blank line. Returns the
+    /// dedented text and the number of characters removed from each line
+    pub(crate) fn dedent_contents(contents: &str) -> (String, usize) {
+        let common = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| &line[..line.len() - line.trim_start().len()])
+            .reduce(|common, indent| {
+                let shared = common
+                    .char_indices()
+                    .zip(indent.chars())
+                    .find(|((_, a), b)| a != b)
+                    .map_or(common.len().min(indent.len()), |((index, _), _)| index);
+                &common[..shared]
+            })
+            .unwrap_or_default();
+
+        if common.is_empty() {
+            return (contents.to_string(), 0);
+        }
+
+        let dedented = contents
+            .split_inclusive('\n')
+            .map(|line| {
+                line.strip_prefix(common)
+                    .unwrap_or(line.trim_start_matches([' ', '\t']))
+            })
+            .collect();
+        (dedented, common.chars().count())
     }
 
-    fn read_file_tail(path: &std::path::Path, lines: usize) -> Result<String> {
+    /// Apply `strip_bom`, `extract_code_fence`, `dedent`, `strip_prefix`,
+    /// `ensure_trailing_newline`, and `tidy` to the contents, returning a note for each change
+    /// made
+    fn normalize_contents(&mut self) -> Vec<String> {
+        let mut notes = vec![];
+
+        if self.strip_bom.unwrap_or_default() {
+            if let Some(stripped) = self.contents.strip_prefix('\u{feff}') {
+                self.contents = stripped.to_string();
+                notes.push("Removed a byte order mark".to_string());
+            }
+        }
+
+        if self.extract_code_fence() {
+            if let Some((inner, language)) = Self::strip_code_fence(&self.contents) {
+                self.contents = inner;
+                notes.push(match language {
+                    Some(language) => format!("Removed a Markdown code fence ({language})"),
+                    None => "Removed a Markdown code fence".to_string(),
+                });
+            }
+        }
+
+        if self.dedent() {
+            let (dedented, removed) = Self::dedent_contents(&self.contents);
+            if removed > 0 {
+                self.contents = dedented;
+                notes.push(format!(
+                    "Removed {removed} characters of common indentation from each line"
+                ));
+            }
+        }
+
+        if let Some(prefix) = self
+            .strip_prefix
+            .as_deref()
+            .filter(|prefix| !prefix.is_empty())
+        {
+            let mut stripped = 0;
+            self.contents = self
+                .contents
+                .split_inclusive('\n')
+                .map(|line| match line.strip_prefix(prefix) {
+                    Some(line) => {
+                        stripped += 1;
+                        line
+                    }
+                    None => line,
+                })
+                .collect();
+            if stripped > 0 {
+                notes.push(format!("Stripped {prefix:?} from {stripped} lines"));
+            }
+        }
+
+        if self.ensure_trailing_newline.unwrap_or_default()
+            && !self.contents.is_empty()
+            && !self.contents.ends_with('\n')
+        {
+            self.contents.push('\n');
+            notes.push("Added a final newline".to_string());
+        }
+
+        if self.tidy() {
+            let (tidied, adjusted) = Self::tidy_contents(&self.contents);
+            if adjusted > 0 {
+                self.contents = tidied;
+                notes.push(format!(
+                    "Tidied {adjusted} lines (trailing whitespace and final newline)"
+                ));
+            }
+        }
+
+        notes
+    }
+
+    /// The line ending that most lines of `text` use, or None if it has no line breaks
+    pub(crate) fn detect_line_ending(text: &str) -> Option<&'static str> {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        match (crlf, lf) {
+            (0, 0) => None,
+            (crlf, lf) if crlf > lf => Some("\r\n"),
+            _ => Some("\n"),
+        }
+    }
+
+    /// Convert every line break in `contents` to `ending`, returning the converted text and the
+    /// number of line breaks that changed
+    pub(crate) fn convert_line_endings(contents: &str, ending: &str) -> (String, usize) {
+        let crlf = contents.matches("\r\n").count();
+        let lf = contents.matches('\n').count() - crlf;
+        let changed = if ending == "\r\n" { lf } else { crlf };
+        if changed == 0 {
+            return (contents.to_string(), 0);
+        }
+        let unified = contents.replace("\r\n", "\n");
+        let converted = if ending == "\r\n" {
+            unified.replace('\n', "\r\n")
+        } else {
+            unified
+        };
+        (converted, changed)
+    }
+
+    /// The line ending to write `contents` with: the one requested, or, when preserving, the
+    /// one the existing file at `path` uses if this write keeps any of it
+    fn target_line_ending(&self, path: &Path) -> Option<&'static str> {
+        match self.line_endings.unwrap_or_default() {
+            LineEndings::Lf => Some("\n"),
+            LineEndings::Crlf => Some("\r\n"),
+            LineEndings::Preserve
+                if self.append() || self.overwrite() || self.insert_at_line.is_some() =>
+            {
+                let mut sample = vec![];
+                fs::File::open(path)
+                    .and_then(|file| {
+                        std::io::Read::read_to_end(
+                            &mut std::io::Read::take(file, LINE_ENDING_SAMPLE_BYTES),
+                            &mut sample,
+                        )
+                    })
+                    .ok()?;
+                Self::detect_line_ending(&String::from_utf8_lossy(&sample))
+            }
+            LineEndings::Preserve => None,
+        }
+    }
+
+    /// Rename `path` to `path.1`, first shifting any existing `path.N` to `path.N+1`, and
+    /// deleting those that would end up numbered above `keep`. Returns the path the file was
+    /// moved to
+    pub(crate) fn rotate(path: &Path, keep: Option<usize>) -> Result<PathBuf> {
+        let numbered = |n: usize| {
+            let mut numbered = path.as_os_str().to_owned();
+            numbered.push(format!(".{n}"));
+            PathBuf::from(numbered)
+        };
+
+        let mut highest = 0;
+        while numbered(highest + 1).exists() {
+            highest += 1;
+        }
+
+        for n in (1..=highest).rev() {
+            if keep.is_some_and(|keep| n + 1 > keep) {
+                fs::remove_file(numbered(n)).hint_path(&numbered(n))?;
+            } else {
+                fs::rename(numbered(n), numbered(n + 1)).hint_path(&numbered(n))?;
+            }
+        }
+
+        let rotated = numbered(1);
+        fs::rename(path, &rotated).hint_path(path)?;
+        Ok(rotated)
+    }
+
+    /// Insert `contents` into `existing` so that it begins at 1-based `line`
+    pub(crate) fn insert_lines(existing: &str, contents: &str, line: usize) -> Result<String> {
+        let line_count = existing.lines().count();
+        if line == 0 || line > line_count + 1 {
+            return Err(Hinted::new(
+                format!("Cannot insert at line {line} of a file with {line_count} lines"),
+                Some(format!(
+                    "use a line number from 1 to {}, where {} inserts after the last line",
+                    line_count + 1,
+                    line_count + 1
+                )),
+            )
+            .with_code(ErrorCode::InvalidArgument)
+            .into());
+        }
+
+        let offset = existing
+            .split_inclusive('\n')
+            .take(line - 1)
+            .map(str::len)
+            .sum::<usize>();
+        let (before, after) = existing.split_at(offset);
+
+        let mut result = String::with_capacity(existing.len() + contents.len() + 2);
+        result.push_str(before);
+        if !before.is_empty() && !before.ends_with('\n') {
+            result.push('\n');
+        }
+        result.push_str(contents);
+        if !after.is_empty() && !contents.ends_with('\n') {
+            result.push('\n');
+        }
+        result.push_str(after);
+        Ok(result)
+    }
+
+    fn insert(
+        &self,
+        path: &Path,
+        line: usize,
+        verbosity: Verbosity,
+        notes: &str,
+    ) -> Result<String> {
+        if !path.is_file() {
+            return Err(hints::not_found(path));
+        }
+
+        let existing = fs::read_to_string(path).hint_path(path)?;
+        let updated = Self::insert_lines(&existing, &self.contents, line)?;
+        fs::write(path, &updated).hint_path(path)?;
+
+        let inserted = self.contents.lines().count();
+        let mut result = format!(
+            "Inserted {inserted} lines at line {line} of {}{notes}",
+            path.display()
+        );
+        if verbosity == Verbosity::Quiet {
+            return Ok(result);
+        }
+
+        // Show the inserted lines with a line of surrounding context on either side
+        let first = line.saturating_sub(1).max(1);
+        let last = line + inserted;
+        result.push_str("\n\n");
+        for (number, text) in updated.lines().enumerate().map(|(i, text)| (i + 1, text)) {
+            if (first..=last).contains(&number) {
+                let marker = if (line..line + inserted).contains(&number) {
+                    '+'
+                } else {
+                    ' '
+                };
+                result.push_str(&format!("{marker}{number:>5}\t{text}\n"));
+            }
+        }
+
+        if verbosity == Verbosity::Detailed {
+            result.push_str(&format!("\nFile now has {} lines", updated.lines().count()));
+        }
+
+        Ok(result)
+    }
+
+    /// Run the formatter the config declares for `path`, if any, returning a note for the
+    /// response
+    fn format(&self, state: &mut FsTools, path: &Path, verbosity: Verbosity) -> String {
+        if self.skip_format.unwrap_or_default() || self.base64() {
+            return String::new();
+        }
+        let Some(hook) = Config::global().format_hook(path) else {
+            return String::new();
+        };
+        // Without a working directory, formatters still find project config next to the file
+        let working_directory = match state.resolve_path(".", None) {
+            Ok(working_directory) => working_directory,
+            Err(_) => path.parent().unwrap_or(path).to_path_buf(),
+        };
+        hook.run(path, &working_directory, verbosity != Verbosity::Quiet)
+    }
+
+    fn read_file_tail(path: &Path, lines: usize) -> Result<String> {
         if !path.exists() {
             return Ok(String::new());
         }
@@ -157,11 +858,98 @@ impl Write {
 }
 
 impl Tool<FsTools> for Write {
-    fn execute(self, state: &mut FsTools) -> Result<String> {
-        let path = state
-            .resolve_path(&self.path, None)
-            .with_context(|| format!("Failed to resolve {}", self.path))?;
-        if self.create_directories() {
+    fn execute(mut self, state: &mut FsTools) -> Result<String> {
+        if self.append() && self.overwrite() {
+            return Ok("`overwrite` and `append` are mutually exclusive. No filesystem operation has been performed".into());
+        }
+
+        if self.insert_at_line.is_some() && (self.append() || self.overwrite()) {
+            return Ok("`insert_at_line` is mutually exclusive with `overwrite` and `append`. No filesystem operation has been performed".into());
+        }
+
+        if self.rotate_at_bytes.is_some() && !self.append() {
+            return Ok("`rotate_at_bytes` is only valid with `append`. No filesystem operation has been performed".into());
+        }
+
+        if self.keep_rotated.is_some() && self.rotate_at_bytes.is_none() {
+            return Ok("`keep_rotated` is only valid with `rotate_at_bytes`. No filesystem operation has been performed".into());
+        }
+
+        if self.keep_rotated == Some(0) {
+            return Ok("`keep_rotated` must be at least 1; leave out `rotate_at_bytes` to never rotate. No filesystem operation has been performed".into());
+        }
+
+        if self.insert_at_line.is_some() && self.base64() {
+            return Ok("`insert_at_line` is only valid with utf8 contents. No filesystem operation has been performed".into());
+        }
+
+        // Only a file being overwritten has to exist already
+        let path = match self.overwrite.unwrap_or_default() {
+            true => state.resolve_path(&self.path, None),
+            false => state.resolve_new_path(&self.path, None),
+        }
+        .with_context(|| format!("Failed to resolve {}", self.path))?;
+
+        // Held until the write is done, so that appends and inserts don't interleave with
+        // another process's change to the same file
+        let _lock = state.lock_paths(&[&path])?;
+
+        if !self.follow_symlinks.unwrap_or_default() {
+            state.check_symlinks(&path, true)?;
+        }
+
+        if !self.allow_generated.unwrap_or_default() {
+            if let Some(pattern) = state.settings(None)?.generated_pattern(&path) {
+                return Err(Hinted::new(
+                    format!(
+                        "{} matches the generated file pattern \"{pattern}\" and was not written",
+                        path.display()
+                    ),
+                    Some(
+                        "regenerate it with the tool that owns it (e.g. cargo update or npm \
+                         install), or pass `allow_generated: true` if editing it by hand is \
+                         really intended"
+                            .into(),
+                    ),
+                )
+                .into());
+            }
+        }
+
+        let (bytes, notes) = if self.base64() {
+            (Self::decode_base64(&self.contents)?, String::new())
+        } else {
+            let mut notes = self.normalize_contents();
+            if let Some(ending) = self.target_line_ending(&path) {
+                let (converted, changed) = Self::convert_line_endings(&self.contents, ending);
+                if changed > 0 {
+                    self.contents = converted;
+                    let style = if ending == "\r\n" { "CRLF" } else { "LF" };
+                    notes.push(match self.line_endings.unwrap_or_default() {
+                        LineEndings::Preserve => {
+                            format!("Converted {changed} line endings to {style} to match the file")
+                        }
+                        _ => format!("Converted {changed} line endings to {style}"),
+                    });
+                }
+            }
+            let notes = notes
+                .into_iter()
+                .map(|note| format!("\n{note}"))
+                .collect::<String>();
+            (self.contents.as_bytes().to_vec(), notes)
+        };
+
+        // Writing without `overwrite` or `append` to an existing file is refused below
+        let exists = path.exists();
+        if !exists || self.append() || self.overwrite() || self.insert_at_line.is_some() {
+            state.charge_quota(QuotaUsage::written(bytes.len() as u64, !exists))?;
+        }
+
+        if state
+            .settings(None)?
+            .create_directories(self.create_directories)
+        {
             if let Some(parent_dir) = path.parent() {
                 fs::create_dir_all(parent_dir).with_context(|| {
                     format!("Failed to create directories for {}", parent_dir.display())
@@ -169,17 +957,46 @@ impl Tool<FsTools> for Write {
             }
         }
 
-        if self.append() && self.overwrite() {
-            return Ok("`overwrite` and `append` are mutually exclusive. No filesystem operation has been performed".into());
+        if let Some(line) = self.insert_at_line {
+            let verbosity = state.settings(None)?.verbosity(self.verbosity);
+            let mut result = self.insert(&path, line, verbosity, &notes)?;
+            result.push_str(&self.format(state, &path, verbosity));
+            return Ok(result);
         }
 
+        let rotated = match (self.rotate_at_bytes, fs::metadata(&path)) {
+            (Some(limit), Ok(metadata))
+                if metadata.len() > 0 && metadata.len() + bytes.len() as u64 > limit =>
+            {
+                Some(Self::rotate(&path, self.keep_rotated)?)
+            }
+            _ => None,
+        };
+        let rotation_note = rotated
+            .as_ref()
+            .map(|rotated| format!(" (rotated previous contents to {})", rotated.display()))
+            .unwrap_or_default();
+
+        let settings = state.settings(None)?;
+        let verbosity = settings.verbosity(self.verbosity);
+
         // For append operations, read the tail before writing for seam display
-        let tail_content = if self.append() {
+        let tail_content = if self.append() && !self.base64() {
             Self::read_file_tail(&path, 3).unwrap_or_default()
         } else {
             String::new()
         };
 
+        // For detailed overwrites, keep the previous contents to diff against
+        let previous_contents =
+            if self.overwrite() && verbosity == Verbosity::Detailed && !self.base64() {
+                fs::read(&path)
+                    .ok()
+                    .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            } else {
+                None
+            };
+
         let mut open_options = OpenOptions::new();
         if self.append() {
             open_options.create(true).append(true);
@@ -199,29 +1016,76 @@ impl Tool<FsTools> for Write {
                     ));
                 }
 
-                Err(e) => bail!("Failed to open {} for writing: {e}", path.display()),
+                Err(e)
+                    if e.kind() == ErrorKind::NotFound
+                        && path.parent().is_some_and(|parent| !parent.exists()) =>
+                {
+                    return Err(Hinted::new(
+                        format!("The parent directory of {} does not exist", path.display()),
+                        Some("pass `create_directories: true` to create it".into()),
+                    )
+                    .with_code(ErrorCode::NotFound)
+                    .with_path(&path)
+                    .into());
+                }
+
+                Err(e) => return Err(hints::io_error(e, &path)),
                 Ok(file) => file,
             };
 
-            file.write_all(self.contents.as_bytes())
+            file.write_all(&bytes)
                 .with_context(|| format!("Failed to write to {}", path.display()))?;
         }
 
+        let format_note = self.format(state, &path, verbosity);
+        if verbosity == Verbosity::Quiet {
+            return Ok(format!(
+                "Wrote {} bytes to {}{rotation_note}{notes}{format_note}",
+                bytes.len(),
+                path.display()
+            ));
+        }
+
         let metadata = fs::metadata(&path)
             .with_context(|| format!("Failed to get metadata for {}", path.display()))?;
-        let size = Size::from_bytes(metadata.len());
+        let size = settings.format_size(metadata.len());
 
         let mut result = format!(
-            "Successfully wrote {} bytes to {} (total: {size})",
-            self.contents.len(),
+            "Successfully wrote {} bytes to {} (total: {size}){rotation_note}{notes}",
+            bytes.len(),
             path.display()
         );
 
         // Add seam display for append operations
-        if self.append() && (!tail_content.is_empty() || !self.contents.is_empty()) {
+        if self.append()
+            && !self.base64()
+            && (!tail_content.is_empty() || !self.contents.is_empty())
+        {
             result.push_str(&Self::format_seam_display(&tail_content, &self.contents, 3));
         }
 
+        if verbosity == Verbosity::Detailed && !self.base64() {
+            if let Ok(contents) = fs::read(&path) {
+                let line_count = String::from_utf8_lossy(&contents).lines().count();
+                result.push_str(&format!("\nFile now has {line_count} lines"));
+            }
+
+            if let Some(previous_contents) = previous_contents {
+                let diff = TextDiff::from_lines(&previous_contents, &self.contents);
+                let diff = diff
+                    .unified_diff()
+                    .context_radius(3)
+                    .header("before", "after")
+                    .to_string();
+                if diff.is_empty() {
+                    result.push_str("\nContents are unchanged");
+                } else {
+                    result.push_str(&format!("\n\n{diff}"));
+                }
+            }
+        }
+        result.push_str(&format_note);
+
         Ok(result)
     }
 }
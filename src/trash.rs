@@ -0,0 +1,22 @@
+//! Moving deleted files to the operating system's trash, where the user can restore them with
+//! their file manager, instead of removing them outright.
+//!
+//! The `trash` crate does the work: the freedesktop trash specification on Linux and other
+//! unix systems, including the per-mount trash directories of other filesystems, the Finder on
+//! macOS, and the Recycle Bin on Windows.
+
+use crate::hints::Hinted;
+use anyhow::Result;
+use std::path::Path;
+
+/// Move `path`, a file or directory, to the trash
+pub fn trash(path: &Path) -> Result<()> {
+    ::trash::delete(path).map_err(|error| {
+        Hinted::new(
+            format!("Could not move {} to the trash: {error}", path.display()),
+            Some("pass `permanent: true` to delete it instead".into()),
+        )
+        .with_path(path)
+        .into()
+    })
+}
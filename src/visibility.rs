@@ -0,0 +1,50 @@
+//! Which hidden and ignored paths the tools that walk directories see.
+//!
+//! `list`, `search`, `extract_matches`, and `recent_files` all skip hidden paths and paths
+//! ignored by `.gitignore`, `.ignore`, and git's excludes, unless asked with `include_hidden`
+//! or `include_ignored`. `.fs-mcp-ignore` is separate, see [`crate::project_ignore`].
+
+use ignore::{DirEntry, WalkBuilder};
+use std::path::Path;
+
+/// The `include_hidden` and `include_ignored` arguments of a call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Visibility {
+    pub include_hidden: bool,
+    pub include_ignored: bool,
+}
+
+impl Visibility {
+    pub fn new(include_hidden: Option<bool>, include_ignored: Option<bool>) -> Self {
+        Self {
+            include_hidden: include_hidden.unwrap_or_default(),
+            include_ignored: include_ignored.unwrap_or_default(),
+        }
+    }
+
+    /// A walk of `path` that skips what this visibility leaves out
+    pub fn walk_builder(self, path: &Path) -> WalkBuilder {
+        let ignored = !self.include_ignored;
+        let mut builder = WalkBuilder::new(path);
+        builder
+            .hidden(!self.include_hidden)
+            .parents(ignored)
+            .ignore(ignored)
+            .git_ignore(ignored)
+            .git_global(ignored)
+            .git_exclude(ignored);
+        builder
+    }
+
+    /// The visible entries directly inside `directory`, for walks that manage their own
+    /// traversal. Ignore files in parent directories still apply
+    pub fn children(
+        self,
+        directory: &Path,
+    ) -> impl Iterator<Item = Result<DirEntry, ignore::Error>> {
+        self.walk_builder(directory)
+            .max_depth(Some(1))
+            .build()
+            .filter(|entry| entry.as_ref().map_or(true, |entry| entry.depth() > 0))
+    }
+}